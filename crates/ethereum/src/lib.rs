@@ -1,12 +1,14 @@
 use std::collections::BTreeMap;
 use std::future::Future;
+use std::num::NonZeroUsize;
 use std::time::Duration;
 
 use alloy::eips::{BlockId, BlockNumberOrTag};
 use alloy::primitives::{Address, TxHash};
-use alloy::providers::{Provider, ProviderBuilder, WsConnect};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder, WsConnect};
 use alloy::rpc::types::{FilteredParams, Log};
 use anyhow::Context;
+use futures::future::join_all;
 use pathfinder_common::prelude::*;
 use pathfinder_common::transaction::L1HandlerTransaction;
 use pathfinder_common::{EthereumChain, L1BlockNumber, L1TransactionHash};
@@ -19,9 +21,15 @@ use tokio::time::MissedTickBehavior;
 
 use crate::utils::*;
 
+#[cfg(test)]
+mod mock;
 mod starknet;
 mod utils;
 
+/// The maximum number of L1 blocks whose logs are requested at once when
+/// polling for state updates.
+const MAX_LOG_BLOCK_RANGE: u64 = 1000;
+
 /// Starknet core contract addresses
 pub mod core_addr {
     use const_decoder::Decoder;
@@ -47,16 +55,32 @@ pub struct EthereumStateUpdate {
     pub block_hash: BlockHash,
 }
 
+/// A message sent from L1 to L2, as emitted by a `LogMessageToL2` event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L1ToL2MessageLog {
+    pub l1_block_number: L1BlockNumber,
+    pub l1_transaction_hash: L1TransactionHash,
+    /// The L1 handler transaction which consumes the message on L2
+    pub transaction: L1HandlerTransaction,
+}
+
 /// Ethereum API trait
 #[async_trait::async_trait]
 pub trait EthereumApi {
     async fn get_starknet_state(&self, address: &H160) -> anyhow::Result<EthereumStateUpdate>;
     async fn get_chain(&self) -> anyhow::Result<EthereumChain>;
+    async fn get_finalized_block_number(&self) -> anyhow::Result<L1BlockNumber>;
     async fn get_l1_handler_txs(
         &self,
         address: &H160,
         tx_hash: &L1TransactionHash,
     ) -> anyhow::Result<Vec<L1HandlerTransaction>>;
+    async fn get_messages_to_l2(
+        &self,
+        address: &H160,
+        from: L1BlockNumber,
+        to: L1BlockNumber,
+    ) -> anyhow::Result<Vec<L1ToL2MessageLog>>;
     async fn sync_and_listen<F, Fut>(
         &mut self,
         address: &H160,
@@ -69,19 +93,23 @@ pub trait EthereumApi {
 }
 
 /// Ethereum client
+///
+/// Endpoints are connected to over WebSocket or HTTP depending on the URL
+/// scheme. Queries fail over between the configured endpoints in order. With a
+/// quorum larger than one, the Starknet state is only accepted once that many
+/// endpoints agree on it, so that a single lagging or misbehaving endpoint
+/// can't stall or mislead L1 acceptance.
 #[derive(Clone, Debug)]
 pub struct EthereumClient {
-    url: Url,
+    urls: Vec<Url>,
+    quorum: NonZeroUsize,
     pending_state_updates: BTreeMap<L1BlockNumber, EthereumStateUpdate>,
 }
 
 impl EthereumClient {
     /// Creates a new [EthereumClient]
     pub fn new<U: IntoUrl>(url: U) -> anyhow::Result<Self> {
-        Ok(Self {
-            url: url.into_url()?,
-            pending_state_updates: BTreeMap::new(),
-        })
+        Self::with_urls(vec![url.into_url()?], NonZeroUsize::MIN)
     }
 
     /// Creates a new password-protected [EthereumClient]
@@ -92,18 +120,341 @@ impl EthereumClient {
         Self::new(url)
     }
 
-    /// Returns the block number of the last finalized block
-    async fn get_finalized_block_number(&self) -> anyhow::Result<L1BlockNumber> {
-        // Create a WebSocket connection
-        let ws = WsConnect::new(self.url.clone());
-        let provider = ProviderBuilder::new().on_ws(ws).await?;
-        // Fetch the finalized block number
-        provider
-            .get_block_by_number(BlockNumberOrTag::Finalized)
-            .await?
-            .map(|block| L1BlockNumber::new_or_panic(block.header.number))
-            .context("Failed to fetch finalized block hash")
+    /// Creates a new [EthereumClient] which requires `quorum` of the endpoints
+    /// to agree on the Starknet state.
+    pub fn with_urls(urls: Vec<Url>, quorum: NonZeroUsize) -> anyhow::Result<Self> {
+        anyhow::ensure!(!urls.is_empty(), "At least one Ethereum URL is required");
+        anyhow::ensure!(
+            quorum.get() <= urls.len(),
+            "The quorum of {quorum} exceeds the number of Ethereum URLs ({})",
+            urls.len()
+        );
+
+        Ok(Self {
+            urls,
+            quorum,
+            pending_state_updates: BTreeMap::new(),
+        })
+    }
+
+    /// Runs `f` against each endpoint in turn until it succeeds, and returns
+    /// the last error if all of them fail.
+    async fn with_failover<T, F, Fut>(&self, mut f: F) -> anyhow::Result<T>
+    where
+        F: FnMut(Url) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut last_error = None;
+        for url in &self.urls {
+            match f(url.clone()).await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    tracing::debug!(endpoint=%endpoint_label(url), error=%e, "Ethereum endpoint failed");
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.expect("There is at least one endpoint"))
     }
+
+    /// Returns the Starknet state which at least `quorum` endpoints agree on,
+    /// as of the latest L1 block that at least `quorum` of them have
+    /// finalized.
+    async fn get_agreed_starknet_state(
+        &self,
+        address: &H160,
+    ) -> anyhow::Result<EthereumStateUpdate> {
+        let quorum = self.quorum.get();
+
+        let finalized = join_all(self.urls.iter().cloned().map(get_finalized_block_number)).await;
+        let finalized: Vec<(&Url, L1BlockNumber)> = self
+            .urls
+            .iter()
+            .zip(finalized)
+            .filter_map(|(url, result)| match result {
+                Ok(block_number) => Some((url, block_number)),
+                Err(e) => {
+                    tracing::debug!(endpoint=%endpoint_label(url), error=%e, "Fetching L1 finalized block failed");
+                    None
+                }
+            })
+            .collect();
+        let block_number = quorum_block_number(finalized.iter().map(|(_, n)| *n).collect(), quorum)
+            .with_context(|| {
+                format!(
+                    "Only {} of the {quorum} required Ethereum endpoints are reachable",
+                    finalized.len()
+                )
+            })?;
+
+        // Endpoints which haven't finalized the block yet can't vote on it.
+        let voters: Vec<&Url> = finalized
+            .into_iter()
+            .filter(|(_, n)| *n >= block_number)
+            .map(|(url, _)| url)
+            .collect();
+        let states = join_all(
+            voters
+                .iter()
+                .map(|url| get_starknet_state_at((*url).clone(), address, block_number)),
+        )
+        .await;
+        let states: Vec<(&Url, EthereumStateUpdate)> = voters
+            .into_iter()
+            .zip(states)
+            .filter_map(|(url, result)| match result {
+                Ok(state_update) => Some((url, state_update)),
+                Err(e) => {
+                    tracing::debug!(endpoint=%endpoint_label(url), error=%e, "Fetching Starknet state failed");
+                    None
+                }
+            })
+            .collect();
+
+        let agreed =
+            quorum_state_update(&states.iter().map(|(_, s)| *s).collect::<Vec<_>>(), quorum)
+                .with_context(|| {
+                    format!(
+                        "Fewer than {quorum} Ethereum endpoints agree on the Starknet state at L1 \
+                         block {block_number}"
+                    )
+                })?;
+
+        for (url, state_update) in &states {
+            if *state_update != agreed {
+                tracing::warn!(endpoint=%endpoint_label(url), ?state_update, ?agreed, "Ethereum endpoint disagrees with the quorum on the Starknet state");
+            }
+        }
+
+        Ok(agreed)
+    }
+
+    /// Polls the endpoints for the Starknet state they agree on and emits it
+    /// whenever it advances.
+    async fn poll_agreed_starknet_state<F, Fut>(
+        &self,
+        address: &H160,
+        poll_interval: Duration,
+        callback: F,
+    ) -> anyhow::Result<()>
+    where
+        F: Fn(EthereumStateUpdate) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut interval = tokio::time::interval(poll_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut latest: Option<BlockNumber> = None;
+
+        loop {
+            interval.tick().await;
+
+            match self.get_agreed_starknet_state(address).await {
+                // A different set of endpoints may reach the quorum on an older
+                // state, which must not be emitted again.
+                Ok(state_update)
+                    if latest.map_or(true, |latest| state_update.block_number > latest) =>
+                {
+                    latest = Some(state_update.block_number);
+                    callback(state_update).await;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!(error=%e, "No quorum on the Starknet state on Ethereum");
+                }
+            }
+        }
+    }
+
+    /// Polls for `LogStateUpdate` events in finalized L1 blocks, which is used
+    /// when log subscriptions aren't available. The next block to scan is
+    /// only advanced once the events before it were emitted, so failed polls
+    /// are repeated on the next tick.
+    async fn poll_state_update_logs<F, Fut>(
+        &self,
+        address: &H160,
+        poll_interval: Duration,
+        callback: F,
+    ) -> anyhow::Result<()>
+    where
+        F: Fn(EthereumStateUpdate) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        // Fetch the current Starknet state from Ethereum
+        let (finalized_block_number, state_update) = self
+            .with_failover(|url| async move {
+                let finalized_block_number = get_finalized_block_number(url.clone()).await?;
+                let state_update =
+                    get_starknet_state_at(url, address, finalized_block_number).await?;
+                Ok((finalized_block_number, state_update))
+            })
+            .await?;
+        callback(state_update).await;
+
+        let mut next_block = finalized_block_number + 1;
+        let mut interval = tokio::time::interval(poll_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+
+            let finalized_block_number = match self.with_failover(get_finalized_block_number).await
+            {
+                Ok(block_number) => block_number,
+                Err(e) => {
+                    tracing::warn!(error=%e, "Error fetching L1 finalized block");
+                    continue;
+                }
+            };
+
+            while next_block <= finalized_block_number {
+                let to = L1BlockNumber::new_or_panic(
+                    (next_block.get() + MAX_LOG_BLOCK_RANGE - 1).min(finalized_block_number.get()),
+                );
+                tracing::trace!(from=%next_block, %to, "Polling for LogStateUpdate events");
+
+                match self
+                    .with_failover(|url| get_state_update_logs(url, address, next_block, to))
+                    .await
+                {
+                    Ok(state_updates) => {
+                        for state_update in state_updates {
+                            callback(state_update).await;
+                        }
+                        next_block = to + 1;
+                    }
+                    Err(e) => {
+                        tracing::warn!(error=%e, "Error fetching LogStateUpdate events");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Creates a WebSocket or HTTP connection, depending on the URL scheme
+async fn connect(url: Url) -> anyhow::Result<DynProvider> {
+    let provider = if is_websocket(&url) {
+        let ws = WsConnect::new(url);
+        ProviderBuilder::new().on_ws(ws).await?.erased()
+    } else {
+        ProviderBuilder::new().on_http(url).erased()
+    };
+
+    Ok(provider)
+}
+
+/// Log subscriptions are only available over WebSocket.
+fn is_websocket(url: &Url) -> bool {
+    matches!(url.scheme(), "ws" | "wss")
+}
+
+/// Returns the `LogStateUpdate` events emitted in the given range of L1 blocks
+async fn get_state_update_logs(
+    url: Url,
+    address: &H160,
+    from: L1BlockNumber,
+    to: L1BlockNumber,
+) -> anyhow::Result<Vec<EthereumStateUpdate>> {
+    let provider = connect(url).await?;
+
+    let core_address = Address::new((*address).into());
+    let core_contract = StarknetCoreContract::new(core_address, provider.clone());
+    let filter = core_contract
+        .LogStateUpdate_filter()
+        .filter
+        .from_block(from.get())
+        .to_block(to.get());
+
+    provider
+        .get_logs(&filter)
+        .await?
+        .into_iter()
+        .map(|log| {
+            let log: Log<StarknetCoreContract::LogStateUpdate> = log.log_decode()?;
+            Ok(EthereumStateUpdate {
+                block_number: get_block_number(log.inner.blockNumber),
+                block_hash: get_block_hash(log.inner.blockHash),
+                state_root: get_state_root(log.inner.globalRoot),
+            })
+        })
+        .collect()
+}
+
+/// Returns the block number of the last finalized block
+async fn get_finalized_block_number(url: Url) -> anyhow::Result<L1BlockNumber> {
+    let provider = connect(url).await?;
+    // Fetch the finalized block number
+    provider
+        .get_block_by_number(BlockNumberOrTag::Finalized)
+        .await?
+        .map(|block| L1BlockNumber::new_or_panic(block.header.number))
+        .context("Failed to fetch finalized block hash")
+}
+
+/// Returns the Starknet state as of the given L1 block
+async fn get_starknet_state_at(
+    url: Url,
+    address: &H160,
+    block_number: L1BlockNumber,
+) -> anyhow::Result<EthereumStateUpdate> {
+    let provider = connect(url).await?;
+
+    // Create the StarknetCoreContract instance
+    let address = Address::new((*address).into());
+    let contract = StarknetCoreContract::new(address, provider);
+    let block_id = BlockId::Number(BlockNumberOrTag::Number(block_number.get()));
+
+    // Call the contract methods
+    let state_root = contract.stateRoot().block(block_id).call().await?;
+    let block_hash = contract.stateBlockHash().block(block_id).call().await?;
+    let block_number = contract.stateBlockNumber().block(block_id).call().await?;
+
+    // Return the state update
+    Ok(EthereumStateUpdate {
+        state_root: get_state_root(state_root._0),
+        block_hash: get_block_hash(block_hash._0),
+        block_number: get_block_number(block_number._0),
+    })
+}
+
+/// Returns the chain the endpoint is connected to
+async fn get_chain(url: Url) -> anyhow::Result<EthereumChain> {
+    let provider = connect(url).await?;
+
+    // Get the chain ID
+    let chain_id = provider.get_chain_id().await?;
+    let chain_id = U256::from(chain_id);
+
+    // Map the chain ID to the corresponding Ethereum chain
+    Ok(match chain_id {
+        x if x == U256::from(1u32) => EthereumChain::Mainnet,
+        x if x == U256::from(11155111u32) => EthereumChain::Sepolia,
+        x => EthereumChain::Other(x),
+    })
+}
+
+/// The latest L1 block that at least `quorum` endpoints have finalized.
+fn quorum_block_number(mut finalized: Vec<L1BlockNumber>, quorum: usize) -> Option<L1BlockNumber> {
+    finalized.sort_unstable_by(|a, b| b.cmp(a));
+    finalized.get(quorum - 1).copied()
+}
+
+/// The state update reported by at least `quorum` endpoints.
+fn quorum_state_update(
+    state_updates: &[EthereumStateUpdate],
+    quorum: usize,
+) -> Option<EthereumStateUpdate> {
+    state_updates
+        .iter()
+        .find(|a| state_updates.iter().filter(|b| b == a).count() >= quorum)
+        .copied()
+}
+
+/// The endpoint's origin, which unlike the URL can't contain credentials.
+fn endpoint_label(url: &Url) -> String {
+    url.origin().ascii_serialization()
 }
 
 #[async_trait::async_trait]
@@ -111,6 +462,9 @@ impl EthereumApi for EthereumClient {
     /// Listens for Ethereum events and notifies the caller using the provided
     /// callback. State updates will only be emitted once they belong to a
     /// finalized block.
+    ///
+    /// With a quorum larger than one the endpoints are polled instead, and
+    /// only the state they agree on is emitted.
     async fn sync_and_listen<F, Fut>(
         &mut self,
         address: &H160,
@@ -121,9 +475,20 @@ impl EthereumApi for EthereumClient {
         F: Fn(EthereumStateUpdate) -> Fut + Send + 'static,
         Fut: Future<Output = ()> + Send + 'static,
     {
+        if self.quorum.get() > 1 {
+            return self
+                .poll_agreed_starknet_state(address, poll_interval, callback)
+                .await;
+        }
+
+        if !self.urls.iter().all(is_websocket) {
+            return self
+                .poll_state_update_logs(address, poll_interval, callback)
+                .await;
+        }
+
         // Create a WebSocket connection
-        let ws = WsConnect::new(self.url.clone());
-        let provider = ProviderBuilder::new().on_ws(ws).await?;
+        let provider = self.with_failover(connect).await?;
 
         // Fetch the current Starknet state from Ethereum
         let state_update = self.get_starknet_state(address).await?;
@@ -236,111 +601,268 @@ impl EthereumApi for EthereumClient {
         address: &H160,
         tx_hash: &L1TransactionHash,
     ) -> anyhow::Result<Vec<L1HandlerTransaction>> {
-        // Create a WebSocket connection
-        let ws = WsConnect::new(self.url.clone());
-        let provider = ProviderBuilder::new().on_ws(ws).await?;
+        self.with_failover(|url| get_l1_handler_txs(url, address, tx_hash))
+            .await
+    }
 
-        let core_address = Address::new((*address).into());
-        let core_contract = StarknetCoreContract::new(core_address, provider.clone());
-        let filter = FilteredParams::new(Some(core_contract.LogMessageToL2_filter().filter));
+    /// Get the messages sent to L2 in the given range of L1 blocks
+    async fn get_messages_to_l2(
+        &self,
+        address: &H160,
+        from: L1BlockNumber,
+        to: L1BlockNumber,
+    ) -> anyhow::Result<Vec<L1ToL2MessageLog>> {
+        let mut messages = Vec::new();
+        let mut next_block = from;
+
+        while next_block <= to {
+            let chunk_end = L1BlockNumber::new_or_panic(
+                (next_block.get() + MAX_LOG_BLOCK_RANGE - 1).min(to.get()),
+            );
+            messages.extend(
+                self.with_failover(|url| get_messages_to_l2(url, address, next_block, chunk_end))
+                    .await?,
+            );
+            next_block = chunk_end + 1;
+        }
 
-        let tx_hash = TxHash::from_slice(tx_hash.as_bytes());
-        if let Some(receipt) = provider.get_transaction_receipt(tx_hash).await? {
-            let logs: Vec<Log<StarknetCoreContract::LogMessageToL2>> = receipt
-                .inner
-                .logs()
-                .iter()
-                .filter(|log| {
-                    filter.filter_address(&log.address()) && filter.filter_topics(log.topics())
-                })
-                .filter_map(|log| {
-                    log.log_decode::<StarknetCoreContract::LogMessageToL2>()
-                        .ok()
-                })
-                .collect();
-
-            let mut l1_handler_txs = Vec::new();
-            for log in logs {
-                let nonce: [u8; 32] = log.inner.nonce.to_be_bytes();
-                let to_addr: [u8; 32] = log.inner.toAddress.to_be_bytes();
-                let from_addr: [u8; 20] = log.inner.fromAddress.0.into();
-                let selector: [u8; 32] = log.inner.selector.to_be_bytes();
-
-                let felt_nonce = Felt::from(nonce);
-                let felt_to_addr = Felt::from(to_addr);
-                let felt_from_addr = Felt::from_be_slice(&from_addr)?;
-                let felt_selector = Felt::from(selector);
-
-                let payload: Vec<CallParam> = log
-                    .inner
-                    .payload
-                    .iter()
-                    .map(|p| p.to_be_bytes::<32>())
-                    .map(Felt::from)
-                    .map(CallParam)
-                    .collect();
-
-                let mut call_data: Vec<CallParam> = vec![CallParam(felt_from_addr)];
-                call_data.extend(payload);
-
-                // Create the L1HandlerTransaction
-                let tx = L1HandlerTransaction {
-                    contract_address: ContractAddress(felt_to_addr),
-                    entry_point_selector: EntryPoint(felt_selector),
-                    nonce: TransactionNonce(felt_nonce),
-                    calldata: call_data,
-                };
-                l1_handler_txs.push(tx);
-            }
+        Ok(messages)
+    }
 
-            Ok(l1_handler_txs)
-        } else {
-            Err(anyhow::anyhow!("Transaction not found"))
-        }
+    /// Get the number of the last finalized L1 block
+    async fn get_finalized_block_number(&self) -> anyhow::Result<L1BlockNumber> {
+        self.with_failover(get_finalized_block_number).await
     }
 
     /// Get the Starknet state
     async fn get_starknet_state(&self, address: &H160) -> anyhow::Result<EthereumStateUpdate> {
-        // Create a WebSocket connection
-        let ws = WsConnect::new(self.url.clone());
-        let provider = ProviderBuilder::new().on_ws(ws).await?;
+        if self.quorum.get() > 1 {
+            return self.get_agreed_starknet_state(address).await;
+        }
 
-        // Create the StarknetCoreContract instance
-        let address = Address::new((*address).into());
-        let contract = StarknetCoreContract::new(address, provider);
-
-        // Get the finalized block hash
-        let finalized_block_number = self.get_finalized_block_number().await?;
-        let block_id = BlockId::Number(BlockNumberOrTag::Number(finalized_block_number.get()));
-
-        // Call the contract methods
-        let state_root = contract.stateRoot().block(block_id).call().await?;
-        let block_hash = contract.stateBlockHash().block(block_id).call().await?;
-        let block_number = contract.stateBlockNumber().block(block_id).call().await?;
-
-        // Return the state update
-        Ok(EthereumStateUpdate {
-            state_root: get_state_root(state_root._0),
-            block_hash: get_block_hash(block_hash._0),
-            block_number: get_block_number(block_number._0),
+        self.with_failover(|url| async move {
+            let finalized_block_number = get_finalized_block_number(url.clone()).await?;
+            get_starknet_state_at(url, address, finalized_block_number).await
         })
+        .await
     }
 
-    /// Get the Ethereum chain
+    /// Get the Ethereum chain, which all reachable endpoints have to agree on
     async fn get_chain(&self) -> anyhow::Result<EthereumChain> {
-        // Create a WebSocket connection
-        let ws = WsConnect::new(self.url.clone());
-        let provider = ProviderBuilder::new().on_ws(ws).await?;
-
-        // Get the chain ID
-        let chain_id = provider.get_chain_id().await?;
-        let chain_id = U256::from(chain_id);
-
-        // Map the chain ID to the corresponding Ethereum chain
-        Ok(match chain_id {
-            x if x == U256::from(1u32) => EthereumChain::Mainnet,
-            x if x == U256::from(11155111u32) => EthereumChain::Sepolia,
-            x => EthereumChain::Other(x),
+        let chains = join_all(self.urls.iter().cloned().map(get_chain)).await;
+
+        let mut agreed = None;
+        let mut last_error = None;
+        for (url, result) in self.urls.iter().zip(chains) {
+            match (result, agreed) {
+                (Ok(chain), None) => agreed = Some(chain),
+                (Ok(chain), Some(other)) => anyhow::ensure!(
+                    chain == other,
+                    "Ethereum endpoint {} is connected to {chain:?} instead of {other:?}",
+                    endpoint_label(url)
+                ),
+                (Err(e), _) => {
+                    tracing::warn!(endpoint=%endpoint_label(url), error=%e, "Determining Ethereum chain failed");
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match agreed {
+            Some(chain) => Ok(chain),
+            None => Err(last_error.expect("There is at least one endpoint")),
+        }
+    }
+}
+
+async fn get_l1_handler_txs(
+    url: Url,
+    address: &H160,
+    tx_hash: &L1TransactionHash,
+) -> anyhow::Result<Vec<L1HandlerTransaction>> {
+    // Create a WebSocket connection
+    let provider = connect(url).await?;
+
+    let core_address = Address::new((*address).into());
+    let core_contract = StarknetCoreContract::new(core_address, provider.clone());
+    let filter = FilteredParams::new(Some(core_contract.LogMessageToL2_filter().filter));
+
+    let tx_hash = TxHash::from_slice(tx_hash.as_bytes());
+    if let Some(receipt) = provider.get_transaction_receipt(tx_hash).await? {
+        let logs: Vec<Log<StarknetCoreContract::LogMessageToL2>> = receipt
+            .inner
+            .logs()
+            .iter()
+            .filter(|log| {
+                filter.filter_address(&log.address()) && filter.filter_topics(log.topics())
+            })
+            .filter_map(|log| {
+                log.log_decode::<StarknetCoreContract::LogMessageToL2>()
+                    .ok()
+            })
+            .collect();
+
+        logs.iter().map(|log| l1_handler_tx(&log.inner)).collect()
+    } else {
+        Err(anyhow::anyhow!("Transaction not found"))
+    }
+}
+
+/// Returns the messages sent to L2 in the given range of L1 blocks
+async fn get_messages_to_l2(
+    url: Url,
+    address: &H160,
+    from: L1BlockNumber,
+    to: L1BlockNumber,
+) -> anyhow::Result<Vec<L1ToL2MessageLog>> {
+    let provider = connect(url).await?;
+
+    let core_address = Address::new((*address).into());
+    let core_contract = StarknetCoreContract::new(core_address, provider.clone());
+    let filter = core_contract
+        .LogMessageToL2_filter()
+        .filter
+        .from_block(from.get())
+        .to_block(to.get());
+
+    provider
+        .get_logs(&filter)
+        .await?
+        .into_iter()
+        .map(|log| {
+            let l1_block_number = log
+                .block_number
+                .map(L1BlockNumber::new_or_panic)
+                .context("Missing L1 block number")?;
+            let l1_transaction_hash = log
+                .transaction_hash
+                .map(|hash| L1TransactionHash::from_slice(hash.as_slice()))
+                .context("Missing L1 transaction hash")?;
+            let log: Log<StarknetCoreContract::LogMessageToL2> = log.log_decode()?;
+
+            Ok(L1ToL2MessageLog {
+                l1_block_number,
+                l1_transaction_hash,
+                transaction: l1_handler_tx(&log.inner)?,
+            })
         })
+        .collect()
+}
+
+/// Creates the L1 handler transaction which consumes the message on L2
+fn l1_handler_tx(
+    log: &StarknetCoreContract::LogMessageToL2,
+) -> anyhow::Result<L1HandlerTransaction> {
+    let nonce: [u8; 32] = log.nonce.to_be_bytes();
+    let to_addr: [u8; 32] = log.toAddress.to_be_bytes();
+    let from_addr: [u8; 20] = log.fromAddress.0.into();
+    let selector: [u8; 32] = log.selector.to_be_bytes();
+
+    let felt_nonce = Felt::from(nonce);
+    let felt_to_addr = Felt::from(to_addr);
+    let felt_from_addr = Felt::from_be_slice(&from_addr)?;
+    let felt_selector = Felt::from(selector);
+
+    let payload: Vec<CallParam> = log
+        .payload
+        .iter()
+        .map(|p| p.to_be_bytes::<32>())
+        .map(Felt::from)
+        .map(CallParam)
+        .collect();
+
+    let mut call_data: Vec<CallParam> = vec![CallParam(felt_from_addr)];
+    call_data.extend(payload);
+
+    // Create the L1HandlerTransaction
+    Ok(L1HandlerTransaction {
+        contract_address: ContractAddress(felt_to_addr),
+        entry_point_selector: EntryPoint(felt_selector),
+        nonce: TransactionNonce(felt_nonce),
+        calldata: call_data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn quorum_block_number_is_finalized_by_a_quorum() {
+        let finalized = || {
+            vec![
+                L1BlockNumber::new_or_panic(10),
+                L1BlockNumber::new_or_panic(12),
+                L1BlockNumber::new_or_panic(7),
+            ]
+        };
+
+        assert_eq!(
+            quorum_block_number(finalized(), 1),
+            Some(L1BlockNumber::new_or_panic(12))
+        );
+        assert_eq!(
+            quorum_block_number(finalized(), 2),
+            Some(L1BlockNumber::new_or_panic(10))
+        );
+        assert_eq!(quorum_block_number(finalized()[..1].to_vec(), 2), None);
+    }
+
+    #[test]
+    fn quorum_state_update_requires_agreement() {
+        let state_update = |state_root| EthereumStateUpdate {
+            state_root,
+            block_number: BlockNumber::new_or_panic(1),
+            block_hash: block_hash!("0x1"),
+        };
+        let good = state_update(state_commitment!("0x1"));
+        let bad = state_update(state_commitment!("0x2"));
+
+        assert_eq!(quorum_state_update(&[bad, good, good], 2), Some(good));
+        assert_eq!(quorum_state_update(&[bad, good], 2), None);
+        assert_eq!(quorum_state_update(&[], 1), None);
+    }
+
+    #[tokio::test]
+    async fn sync_and_listen_over_http() {
+        let address = H160::from_low_u64_be(1);
+        let mock = mock::MockEthereum::spawn(address).await;
+        let state_update = |block| EthereumStateUpdate {
+            state_root: state_commitment!("0x1"),
+            block_number: BlockNumber::new_or_panic(block),
+            block_hash: block_hash!("0x1"),
+        };
+        mock.add_state_update(1, state_update(10));
+        mock.finalize(2);
+
+        let mut client = EthereumClient::new(mock.url()).unwrap();
+        assert_eq!(client.get_chain().await.unwrap(), EthereumChain::Mainnet);
+        assert_eq!(
+            client.get_starknet_state(&address).await.unwrap(),
+            state_update(10)
+        );
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            client
+                .sync_and_listen(&address, Duration::from_millis(10), move |state_update| {
+                    let _ = tx.send(state_update);
+                    std::future::ready(())
+                })
+                .await
+        });
+        assert_eq!(rx.recv().await, Some(state_update(10)));
+
+        // Only events in finalized blocks are emitted.
+        mock.add_state_update(3, state_update(11));
+        mock.add_state_update(1500, state_update(12));
+        mock.finalize(3);
+        assert_eq!(rx.recv().await, Some(state_update(11)));
+
+        // Ranges larger than the maximum are polled in chunks.
+        mock.finalize(2000);
+        assert_eq!(rx.recv().await, Some(state_update(12)));
     }
 }
@@ -0,0 +1,154 @@
+//! A stand-in for an Ethereum JSON-RPC endpoint served over HTTP.
+//!
+//! It only knows about the Starknet core contract: `LogStateUpdate` events
+//! are added with [MockEthereum::add_state_update], and the contract's state
+//! getters return the latest state update as of the requested L1 block.
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use alloy::primitives::{Address, I256, U256};
+use alloy::sol_types::{SolCall, SolEvent};
+use axum::extract::State;
+use axum::Json;
+use pathfinder_common::L1BlockNumber;
+use primitive_types::H160;
+use reqwest::Url;
+use serde_json::{json, Value};
+
+use crate::starknet::StarknetCoreContract;
+use crate::EthereumStateUpdate;
+
+#[derive(Clone)]
+pub(crate) struct MockEthereum {
+    url: Url,
+    chain: Arc<Mutex<Chain>>,
+}
+
+struct Chain {
+    core_address: Address,
+    finalized: L1BlockNumber,
+    /// The state updates and the L1 blocks they were emitted in, in order.
+    state_updates: Vec<(L1BlockNumber, EthereumStateUpdate)>,
+}
+
+impl MockEthereum {
+    pub(crate) async fn spawn(core_address: H160) -> Self {
+        let chain = Arc::new(Mutex::new(Chain {
+            core_address: Address::new(core_address.into()),
+            finalized: L1BlockNumber::new_or_panic(0),
+            state_updates: Vec::new(),
+        }));
+
+        let router = axum::Router::new()
+            .route("/", axum::routing::post(handle))
+            .with_state(chain.clone());
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        Self { url, chain }
+    }
+
+    pub(crate) fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    pub(crate) fn add_state_update(&self, l1_block: u64, state_update: EthereumStateUpdate) {
+        self.chain
+            .lock()
+            .unwrap()
+            .state_updates
+            .push((L1BlockNumber::new_or_panic(l1_block), state_update));
+    }
+
+    pub(crate) fn finalize(&self, l1_block: u64) {
+        self.chain.lock().unwrap().finalized = L1BlockNumber::new_or_panic(l1_block);
+    }
+}
+
+async fn handle(State(chain): State<Arc<Mutex<Chain>>>, Json(request): Json<Value>) -> Json<Value> {
+    let chain = chain.lock().unwrap();
+    let params = &request["params"];
+
+    let result = match request["method"].as_str().unwrap_or_default() {
+        "eth_chainId" => json!("0x1"),
+        "eth_getBlockByNumber" => {
+            let mut block = alloy::rpc::types::Block::<alloy::rpc::types::Transaction>::default();
+            block.header.inner.number = chain.finalized.get();
+            serde_json::to_value(block).unwrap()
+        }
+        "eth_getLogs" => {
+            let from = block_number(&params[0]["fromBlock"], &chain);
+            let to = block_number(&params[0]["toBlock"], &chain);
+            let logs: Vec<_> = chain
+                .state_updates
+                .iter()
+                .filter(|(l1_block, _)| (from..=to).contains(l1_block))
+                .map(|(l1_block, state_update)| alloy::rpc::types::Log {
+                    inner: alloy::primitives::Log {
+                        address: chain.core_address,
+                        data: StarknetCoreContract::LogStateUpdate {
+                            globalRoot: U256::from_be_bytes(
+                                state_update.state_root.0.to_be_bytes(),
+                            ),
+                            blockNumber: I256::try_from(state_update.block_number.get()).unwrap(),
+                            blockHash: U256::from_be_bytes(state_update.block_hash.0.to_be_bytes()),
+                        }
+                        .encode_log_data(),
+                    },
+                    block_number: Some(l1_block.get()),
+                    ..Default::default()
+                })
+                .collect();
+            serde_json::to_value(logs).unwrap()
+        }
+        "eth_call" => {
+            let block = block_number(&params[1], &chain);
+            let state_update = chain
+                .state_updates
+                .iter()
+                .rev()
+                .find(|(l1_block, _)| *l1_block <= block)
+                .map(|(_, state_update)| *state_update)
+                .unwrap_or_default();
+
+            let input = params[0]["input"]
+                .as_str()
+                .or(params[0]["data"].as_str())
+                .unwrap_or_default();
+            let selector = hex::decode(input.trim_start_matches("0x")).unwrap_or_default();
+            let word = match selector.get(..4) {
+                Some(s) if s == StarknetCoreContract::stateRootCall::SELECTOR => {
+                    state_update.state_root.0.to_be_bytes()
+                }
+                Some(s) if s == StarknetCoreContract::stateBlockHashCall::SELECTOR => {
+                    state_update.block_hash.0.to_be_bytes()
+                }
+                Some(s) if s == StarknetCoreContract::stateBlockNumberCall::SELECTOR => {
+                    U256::from(state_update.block_number.get()).to_be_bytes()
+                }
+                _ => [0; 32],
+            };
+            json!(format!("0x{}", hex::encode(word)))
+        }
+        method => unimplemented!("Mock Ethereum method {method}"),
+    };
+
+    Json(json!({
+        "jsonrpc": "2.0",
+        "id": request["id"],
+        "result": result,
+    }))
+}
+
+/// Parses a block number or tag, treating all tags as the finalized block.
+fn block_number(value: &Value, chain: &Chain) -> L1BlockNumber {
+    value
+        .as_str()
+        .and_then(|s| s.strip_prefix("0x"))
+        .and_then(|s| u64::from_str_radix(s, 16).ok())
+        .map(L1BlockNumber::new_or_panic)
+        .unwrap_or(chain.finalized)
+}
@@ -26,6 +26,26 @@ pub fn call(
     contract_address: ContractAddress,
     entry_point_selector: EntryPoint,
     calldata: Vec<CallParam>,
+) -> Result<Vec<CallResultValue>, CallError> {
+    let deadline = execution_state.deadline();
+    let result = execute_call(
+        execution_state,
+        contract_address,
+        entry_point_selector,
+        calldata,
+    );
+    // State reads fail and the step limit runs out once the deadline has
+    // passed, which surfaces as an arbitrary execution error.
+    deadline.check_outcome(result.is_err())?;
+
+    result
+}
+
+fn execute_call(
+    execution_state: ExecutionState<'_>,
+    contract_address: ContractAddress,
+    entry_point_selector: EntryPoint,
+    calldata: Vec<CallParam>,
 ) -> Result<Vec<CallResultValue>, CallError> {
     let (mut state, block_context) = execution_state.starknet_state()?;
 
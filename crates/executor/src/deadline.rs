@@ -0,0 +1,156 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use blockifier::state::errors::StateError;
+use blockifier::versioned_constants::VersionedConstants;
+
+use crate::error::{CallError, TransactionExecutionError};
+
+/// A lower bound on the number of Cairo steps the VM executes per second, even
+/// on slow hardware and with state reads going to disk.
+const MIN_STEPS_PER_SECOND: u64 = 1_000_000;
+
+/// The point in time after which an execution is aborted.
+///
+/// The VM can't be interrupted, so the deadline is turned into a step limit
+/// before the execution starts, and checked on every state read and between
+/// transactions. Once it has passed, the execution's outcome is replaced by a
+/// [DeadlineExceeded] error.
+///
+/// Classes executed natively aren't bound by the step limit, so a native
+/// execution only notices the deadline on its next state read.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Deadline {
+    deadline: Option<Instant>,
+    /// Set once the step limits were lowered to meet the deadline.
+    limits_steps: Arc<AtomicBool>,
+}
+
+impl Deadline {
+    pub(crate) fn new(deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            limits_steps: Default::default(),
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DeadlineExceeded> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(DeadlineExceeded),
+            _ => Ok(()),
+        }
+    }
+
+    /// Like [check](Self::check), but also fails if an execution `failed` while
+    /// its steps were limited by the deadline, as it might have run out of
+    /// steps before the deadline.
+    pub(crate) fn check_outcome(&self, failed: bool) -> Result<(), DeadlineExceeded> {
+        if failed && self.limits_steps.load(Ordering::Relaxed) {
+            return Err(DeadlineExceeded);
+        }
+        self.check()
+    }
+
+    /// Caps the step limits of `constants` to the steps the VM is guaranteed to
+    /// execute before the deadline, as per [MIN_STEPS_PER_SECOND].
+    pub(crate) fn limit_steps<'a>(
+        &self,
+        mut constants: Cow<'a, VersionedConstants>,
+    ) -> Cow<'a, VersionedConstants> {
+        let Some(deadline) = self.deadline else {
+            return constants;
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let max_steps = (remaining.as_millis() as u64).saturating_mul(MIN_STEPS_PER_SECOND) / 1000;
+        let max_steps = u32::try_from(max_steps).unwrap_or(u32::MAX);
+
+        if constants.invoke_tx_max_n_steps > max_steps || constants.validate_max_n_steps > max_steps
+        {
+            let constants = constants.to_mut();
+            constants.invoke_tx_max_n_steps = constants.invoke_tx_max_n_steps.min(max_steps);
+            constants.validate_max_n_steps = constants.validate_max_n_steps.min(max_steps);
+            self.limits_steps.store(true, Ordering::Relaxed);
+        }
+
+        constants
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct DeadlineExceeded;
+
+impl std::fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Execution deadline exceeded")
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+impl From<DeadlineExceeded> for StateError {
+    fn from(error: DeadlineExceeded) -> Self {
+        StateError::StateReadError(error.to_string())
+    }
+}
+
+impl From<DeadlineExceeded> for CallError {
+    fn from(_: DeadlineExceeded) -> Self {
+        Self::DeadlineExceeded
+    }
+}
+
+impl From<DeadlineExceeded> for TransactionExecutionError {
+    fn from(_: DeadlineExceeded) -> Self {
+        Self::DeadlineExceeded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn check() {
+        Deadline::default().check().unwrap();
+        Deadline::new(Instant::now() + Duration::from_secs(60))
+            .check()
+            .unwrap();
+        Deadline::new(Instant::now()).check().unwrap_err();
+    }
+
+    #[test]
+    fn check_outcome() {
+        let constants = VersionedConstants::latest_constants();
+
+        let distant = Deadline::new(Instant::now() + Duration::from_secs(3600));
+        distant.limit_steps(Cow::Borrowed(constants));
+        distant.check_outcome(true).unwrap();
+
+        // The step limit is lowered, but the deadline hasn't passed yet.
+        let close = Deadline::new(Instant::now() + Duration::from_secs(1));
+        close.limit_steps(Cow::Borrowed(constants));
+        close.check_outcome(false).unwrap();
+        close.check_outcome(true).unwrap_err();
+    }
+
+    #[test]
+    fn limit_steps() {
+        let constants = VersionedConstants::latest_constants();
+
+        let unlimited = Deadline::default().limit_steps(Cow::Borrowed(constants));
+        assert!(matches!(unlimited, Cow::Borrowed(_)));
+
+        let distant = Deadline::new(Instant::now() + Duration::from_secs(3600))
+            .limit_steps(Cow::Borrowed(constants));
+        assert!(matches!(distant, Cow::Borrowed(_)));
+
+        let passed = Deadline::new(Instant::now()).limit_steps(Cow::Borrowed(constants));
+        assert_eq!(passed.invoke_tx_max_n_steps, 0);
+        assert_eq!(passed.validate_max_n_steps, 0);
+    }
+}
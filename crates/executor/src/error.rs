@@ -1,3 +1,4 @@
+use blockifier::blockifier::stateful_validator::StatefulValidatorError;
 use blockifier::execution::errors::{
     ConstructorEntryPointExecutionError,
     EntryPointExecutionError as BlockifierEntryPointExecutionError,
@@ -5,7 +6,11 @@ use blockifier::execution::errors::{
 };
 use blockifier::execution::stack_trace::gen_tx_execution_error_trace;
 use blockifier::state::errors::StateError;
-use blockifier::transaction::errors::TransactionExecutionError as BlockifierTransactionExecutionError;
+use blockifier::transaction::errors::{
+    TransactionExecutionError as BlockifierTransactionExecutionError,
+    TransactionFeeError,
+    TransactionPreValidationError,
+};
 
 use crate::error_stack::ErrorStack;
 
@@ -14,6 +19,8 @@ pub enum CallError {
     ContractNotFound,
     InvalidMessageSelector,
     ContractError(anyhow::Error, ErrorStack),
+    /// The execution was aborted because its deadline passed.
+    DeadlineExceeded,
     Internal(anyhow::Error),
     Custom(anyhow::Error),
 }
@@ -107,6 +114,8 @@ pub enum TransactionExecutionError {
         error: String,
         error_stack: ErrorStack,
     },
+    /// The execution was aborted because its deadline passed.
+    DeadlineExceeded,
     Internal(anyhow::Error),
     Custom(anyhow::Error),
 }
@@ -144,6 +153,88 @@ impl TransactionExecutionError {
     }
 }
 
+/// The reasons a transaction would be rejected by the sequencer before being
+/// included in a block.
+#[derive(Debug)]
+pub enum TransactionValidationError {
+    InvalidNonce,
+    InsufficientResourcesForValidate,
+    InsufficientAccountBalance,
+    ValidationFailure(String),
+    NonAccount,
+    Internal(anyhow::Error),
+    Custom(anyhow::Error),
+}
+
+impl From<BlockifierTransactionExecutionError> for TransactionValidationError {
+    fn from(value: BlockifierTransactionExecutionError) -> Self {
+        match value {
+            BlockifierTransactionExecutionError::TransactionPreValidationError(
+                TransactionPreValidationError::InvalidNonce { .. },
+            ) => Self::InvalidNonce,
+            BlockifierTransactionExecutionError::TransactionPreValidationError(
+                TransactionPreValidationError::TransactionFeeError(error),
+            ) => Self::from_fee_error(&error),
+            BlockifierTransactionExecutionError::TransactionFeeError(error) => {
+                Self::from_fee_error(&error)
+            }
+            BlockifierTransactionExecutionError::ValidateTransactionError {
+                error:
+                    BlockifierEntryPointExecutionError::PreExecutionError(
+                        PreExecutionError::EntryPointNotFound(_)
+                        | PreExecutionError::NoEntryPointOfTypeFound(_),
+                    ),
+                ..
+            } => Self::NonAccount,
+            error @ BlockifierTransactionExecutionError::ValidateTransactionError { .. } => {
+                Self::ValidationFailure(error.to_string())
+            }
+            error => Self::Custom(error.into()),
+        }
+    }
+}
+
+impl From<StatefulValidatorError> for TransactionValidationError {
+    fn from(value: StatefulValidatorError) -> Self {
+        match value {
+            StatefulValidatorError::StateError(error) => error.into(),
+            StatefulValidatorError::TransactionExecutionError(error) => error.into(),
+            StatefulValidatorError::TransactionPreValidationError(error) => {
+                BlockifierTransactionExecutionError::TransactionPreValidationError(error).into()
+            }
+            error => Self::Custom(error.into()),
+        }
+    }
+}
+
+impl TransactionValidationError {
+    fn from_fee_error(error: &TransactionFeeError) -> Self {
+        match error {
+            TransactionFeeError::MaxFeeExceedsBalance { .. }
+            | TransactionFeeError::GasBoundsExceedBalance { .. }
+            | TransactionFeeError::ResourcesBoundsExceedBalance { .. } => {
+                Self::InsufficientAccountBalance
+            }
+            _ => Self::InsufficientResourcesForValidate,
+        }
+    }
+}
+
+impl From<StateError> for TransactionValidationError {
+    fn from(e: StateError) -> Self {
+        match e {
+            StateError::StateReadError(_) => Self::Internal(e.into()),
+            _ => Self::Custom(anyhow::anyhow!("State error: {}", e)),
+        }
+    }
+}
+
+impl From<anyhow::Error> for TransactionValidationError {
+    fn from(value: anyhow::Error) -> Self {
+        Self::Internal(value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
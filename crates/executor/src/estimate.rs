@@ -22,13 +22,16 @@ pub fn estimate(
     epsilon: Percentage,
 ) -> Result<Vec<FeeEstimate>, TransactionExecutionError> {
     let block_number = execution_state.header.number;
+    let deadline = execution_state.deadline();
 
     let (mut state, block_context) = execution_state.starknet_state()?;
 
-    transactions
+    let estimates = transactions
         .into_iter()
         .enumerate()
         .map(|(tx_index, mut tx)| {
+            deadline.check()?;
+
             let _span = tracing::debug_span!(
                 "estimate",
                 block_number = %block_number,
@@ -75,7 +78,12 @@ pub fn estimate(
                 &block_context,
             ))
         })
-        .collect()
+        .collect();
+    // State reads fail and the step limit runs out once the deadline has
+    // passed, which surfaces as an arbitrary execution error.
+    deadline.check_outcome(estimates.is_err())?;
+
+    estimates
 }
 
 impl FeeEstimate {
@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Context;
 use blockifier::blockifier::block::pre_process_block;
@@ -15,6 +16,7 @@ use starknet_api::core::PatriciaKey;
 
 use super::pending::PendingStateReader;
 use super::state_reader::PathfinderStateReader;
+use crate::deadline::Deadline;
 use crate::state_reader::NativeClassCache;
 use crate::IntoStarkFelt;
 
@@ -193,27 +195,68 @@ impl Default for VersionedConstantsMap {
     }
 }
 
+/// The state of a block which is no longer stored in the database, given as the
+/// state of a later block that still is, with the values written after the
+/// block reverted.
+#[derive(Debug, Clone)]
+pub struct StateOverlay {
+    /// The block whose state is read from the database.
+    pub base: BlockNumber,
+    /// The values at the executed block of everything written after it, up to
+    /// and including `base`.
+    pub reverted: Arc<StateUpdate>,
+    /// The hash of the block ten blocks before the executed one, which is made
+    /// available to contracts when executing on top of it.
+    pub old_block_hash: Option<BlockHash>,
+}
+
 pub struct ExecutionState<'tx> {
     transaction: &'tx pathfinder_storage::Transaction<'tx>,
     pub chain_id: ChainId,
     pub header: BlockHeader,
     execute_on_parent_state: bool,
     pending_state: Option<Arc<StateUpdate>>,
+    state_overlay: Option<StateOverlay>,
     allow_use_kzg_data: bool,
     versioned_constants_map: VersionedConstantsMap,
     eth_fee_address: ContractAddress,
     strk_fee_address: ContractAddress,
     native_class_cache: Option<NativeClassCache>,
+    deadline: Deadline,
 }
 
 impl<'tx> ExecutionState<'tx> {
+    /// Aborts the execution once the deadline has passed, in which case it
+    /// fails with a `DeadlineExceeded` error.
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        Self {
+            deadline: Deadline::new(deadline),
+            ..self
+        }
+    }
+
+    /// Reads the state of the header's block through `overlay`, for blocks
+    /// whose state is no longer stored.
+    pub fn with_state_overlay(self, overlay: StateOverlay) -> Self {
+        Self {
+            state_overlay: Some(overlay),
+            ..self
+        }
+    }
+
+    pub(super) fn deadline(&self) -> Deadline {
+        self.deadline.clone()
+    }
+
     pub(super) fn starknet_state(
         self,
     ) -> anyhow::Result<(
         CachedState<PendingStateReader<PathfinderStateReader<'tx>>>,
         BlockContext,
     )> {
-        let block_number = if self.execute_on_parent_state {
+        let block_number = if let Some(overlay) = &self.state_overlay {
+            Some(overlay.base)
+        } else if self.execute_on_parent_state {
             self.header.number.parent()
         } else {
             Some(self.header.number)
@@ -227,8 +270,13 @@ impl<'tx> ExecutionState<'tx> {
             block_number,
             self.pending_state.is_some(),
             self.native_class_cache,
+            self.deadline.clone(),
         );
-        let pending_state_reader = PendingStateReader::new(raw_reader, self.pending_state.clone());
+        let overlay = match &self.state_overlay {
+            Some(overlay) => Some(overlay.reverted.clone()),
+            None => self.pending_state.clone(),
+        };
+        let pending_state_reader = PendingStateReader::new(raw_reader, overlay);
         let mut cached_state = CachedState::new(pending_state_reader);
 
         // Perform system contract updates if we are executing ontop of a parent block.
@@ -236,10 +284,13 @@ impl<'tx> ExecutionState<'tx> {
         let old_block_number_and_hash = if self.header.number.get() >= 10 {
             let block_number_whose_hash_becomes_available =
                 pathfinder_common::BlockNumber::new_or_panic(self.header.number.get() - 10);
-            let block_hash = self
-                .transaction
-                .block_hash(block_number_whose_hash_becomes_available.into())?
-                .context("Getting historical block hash")?;
+            let block_hash = match &self.state_overlay {
+                Some(overlay) => overlay.old_block_hash,
+                None => self
+                    .transaction
+                    .block_hash(block_number_whose_hash_becomes_available.into())?,
+            }
+            .context("Getting historical block hash")?;
 
             tracing::trace!(%block_number_whose_hash_becomes_available, %block_hash, "Setting historical block hash");
 
@@ -253,9 +304,10 @@ impl<'tx> ExecutionState<'tx> {
             None
         };
 
-        let versioned_constants = self
-            .versioned_constants_map
-            .for_version(&self.header.starknet_version);
+        let versioned_constants = self.deadline.limit_steps(
+            self.versioned_constants_map
+                .for_version(&self.header.starknet_version),
+        );
 
         pre_process_block(
             &mut cached_state,
@@ -398,12 +450,14 @@ impl<'tx> ExecutionState<'tx> {
             chain_id,
             header,
             pending_state,
+            state_overlay: None,
             execute_on_parent_state: true,
             allow_use_kzg_data: true,
             versioned_constants_map,
             eth_fee_address,
             strk_fee_address,
             native_class_cache,
+            deadline: Deadline::default(),
         }
     }
 
@@ -424,12 +478,14 @@ impl<'tx> ExecutionState<'tx> {
             chain_id,
             header,
             pending_state,
+            state_overlay: None,
             execute_on_parent_state: false,
             allow_use_kzg_data: l1_blob_data_availability == L1BlobDataAvailability::Enabled,
             versioned_constants_map,
             eth_fee_address,
             strk_fee_address,
             native_class_cache,
+            deadline: Deadline::default(),
         }
     }
 }
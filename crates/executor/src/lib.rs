@@ -1,5 +1,6 @@
 pub(crate) mod call;
 pub(crate) mod class;
+pub(crate) mod deadline;
 pub(crate) mod error;
 pub(crate) mod error_stack;
 pub(crate) mod estimate;
@@ -11,6 +12,7 @@ pub(crate) mod simulate;
 pub(crate) mod state_reader;
 pub(crate) mod transaction;
 pub mod types;
+pub(crate) mod validate;
 
 // re-export blockifier transaction type since it's exposed on our API
 pub use blockifier::transaction::account_transaction::{
@@ -21,11 +23,17 @@ pub use blockifier::transaction::transaction_execution::Transaction;
 pub use blockifier::versioned_constants::VersionedConstants;
 pub use call::call;
 pub use class::{parse_casm_definition, parse_deprecated_class_definition};
-pub use error::{CallError, TransactionExecutionError};
+pub use error::{CallError, TransactionExecutionError, TransactionValidationError};
 pub use error_stack::{CallFrame, ErrorStack, Frame};
 pub use estimate::estimate;
-pub use execution_state::{ExecutionState, L1BlobDataAvailability, VersionedConstantsMap};
+pub use execution_state::{
+    ExecutionState,
+    L1BlobDataAvailability,
+    StateOverlay,
+    VersionedConstantsMap,
+};
 pub use felt::{IntoFelt, IntoStarkFelt};
-pub use simulate::{simulate, trace, TraceCache};
+pub use simulate::{simulate, store_traces, trace, TraceCache};
 pub use starknet_api::contract_class::ClassInfo;
 pub use state_reader::NativeClassCache;
+pub use validate::{transaction_hash, validate};
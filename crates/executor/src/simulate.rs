@@ -1,4 +1,5 @@
 use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
 use std::sync::{Arc, Mutex};
 
 use anyhow::Context;
@@ -9,6 +10,7 @@ use blockifier::transaction::transactions::ExecutableTransaction;
 use blockifier::versioned_constants::VersionedConstants;
 use cached::{Cached, SizedCache};
 use pathfinder_common::prelude::*;
+use pathfinder_storage::Storage;
 use starknet_api::transaction::fields::GasVectorComputationMode;
 use util::percentage::Percentage;
 
@@ -41,11 +43,28 @@ use crate::IntoFelt;
 
 #[derive(Debug)]
 enum CacheItem {
-    Inflight(tokio::sync::broadcast::Receiver<Result<Traces, ExecutionError>>),
+    Inflight(tokio::sync::broadcast::Receiver<Result<Traces, InflightError>>),
     CachedOk(Traces),
     CachedErr(ExecutionError),
 }
 
+/// Why an inflight trace failed, as told to the requests waiting for it.
+#[derive(Debug, Clone)]
+enum InflightError {
+    Execution(ExecutionError),
+    /// The deadline of the request tracing the block passed.
+    DeadlineExceeded,
+}
+
+impl From<InflightError> for TransactionExecutionError {
+    fn from(value: InflightError) -> Self {
+        match value {
+            InflightError::Execution(error) => error.into(),
+            InflightError::DeadlineExceeded => Self::DeadlineExceeded,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct ExecutionError {
     transaction_index: usize,
@@ -63,30 +82,86 @@ impl From<ExecutionError> for TransactionExecutionError {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct TraceCache(Arc<Mutex<SizedCache<BlockHash, CacheItem>>>);
+/// Caches the traces of recently traced blocks in memory, optionally backed by
+/// a persistent [store](TraceCache::with_store) which survives restarts.
+#[derive(Clone)]
+pub struct TraceCache {
+    cache: Arc<Mutex<SizedCache<BlockHash, CacheItem>>>,
+    store: Option<Storage>,
+}
 
 type Traces = Vec<(TransactionHash, TransactionTrace)>;
 
 impl Default for TraceCache {
     fn default() -> Self {
-        Self(Arc::new(Mutex::new(SizedCache::with_size(128))))
+        Self::new(NonZeroUsize::new(128).unwrap())
     }
 }
 
+impl TraceCache {
+    /// Creates a cache which keeps the traces of up to `size` blocks in memory.
+    pub fn new(size: NonZeroUsize) -> Self {
+        Self {
+            cache: Arc::new(Mutex::new(SizedCache::with_size(size.get()))),
+            store: None,
+        }
+    }
+
+    /// Looks up traces missing from memory in the database, where they are
+    /// persisted by [store_traces].
+    pub fn with_store(self, storage: Storage) -> Self {
+        Self {
+            store: Some(storage),
+            ..self
+        }
+    }
+}
+
+/// Persists the traces of a block, keeping those of up to `max_blocks` blocks.
+///
+/// This is meant for traces of blocks executed during sync, so that tracing
+/// requests don't write to the database.
+pub fn store_traces(
+    db: &pathfinder_storage::Transaction<'_>,
+    block_number: BlockNumber,
+    block_hash: BlockHash,
+    traces: &[(TransactionHash, TransactionTrace)],
+    max_blocks: NonZeroUsize,
+) -> anyhow::Result<()> {
+    let traces = serde_json::to_vec(traces).context("Serializing block traces")?;
+    db.insert_block_traces(block_number, block_hash, &traces, max_blocks)
+}
+
+fn stored_traces(storage: &Storage, block_hash: BlockHash) -> anyhow::Result<Option<Traces>> {
+    let mut db = storage
+        .connection()
+        .context("Creating database connection")?;
+    let db = db.transaction().context("Creating database transaction")?;
+
+    let Some(traces) = db.block_traces(block_hash)? else {
+        return Ok(None);
+    };
+    let traces = serde_json::from_slice(&traces).context("Deserializing block traces")?;
+
+    Ok(Some(traces))
+}
+
 pub fn simulate(
     execution_state: ExecutionState<'_>,
     transactions: Vec<Transaction>,
     epsilon: Percentage,
 ) -> Result<Vec<TransactionSimulation>, TransactionExecutionError> {
     let block_number = execution_state.header.number;
+    let deadline = execution_state.deadline();
 
     let (mut state, block_context) = execution_state.starknet_state()?;
 
-    transactions
+    let simulations = transactions
         .into_iter()
         .enumerate()
         .map(|(tx_index, mut tx)| {
+            deadline.check()?;
+
             let _span = tracing::debug_span!(
                 "simulate",
                 block_number = %block_number,
@@ -114,6 +189,8 @@ pub fn simulate(
             } else {
                 execute_transaction(&tx, tx_index, &mut tx_state, &block_context, &ExecutionBehaviorOnRevert::Continue)?
             };
+            // Running out of steps reverts the transaction instead of failing it.
+            deadline.check_outcome(tx_info.revert_error.is_some())?;
             let state_diff = to_state_diff(&mut tx_state, transaction_declared_deprecated_class(&tx))?;
             tx_state.commit();
 
@@ -135,7 +212,12 @@ pub fn simulate(
                 ),
             })
         })
-        .collect()
+        .collect();
+    // State reads fail and the step limit runs out once the deadline has
+    // passed, which surfaces as an arbitrary execution error.
+    deadline.check_outcome(simulations.is_err())?;
+
+    simulations
 }
 
 pub fn trace(
@@ -144,10 +226,11 @@ pub fn trace(
     block_hash: BlockHash,
     transactions: Vec<Transaction>,
 ) -> Result<Vec<(TransactionHash, TransactionTrace)>, TransactionExecutionError> {
+    let deadline = execution_state.deadline();
     let (mut state, block_context) = execution_state.starknet_state()?;
 
     let sender = {
-        let mut cache = cache.0.lock().unwrap();
+        let mut cache = cache.cache.lock().unwrap();
         match cache.cache_get(&block_hash) {
             Some(CacheItem::CachedOk(cached)) => {
                 tracing::trace!(block=%block_hash, "trace cache hit: ok");
@@ -174,6 +257,22 @@ pub fn trace(
         }
     };
 
+    if let Some(store) = &cache.store {
+        match stored_traces(store, block_hash) {
+            Ok(Some(traces)) => {
+                tracing::trace!(block=%block_hash, "trace store hit");
+                let mut cache = cache.cache.lock().unwrap();
+                let _ = sender.send(Ok(traces.clone()));
+                cache.cache_set(block_hash, CacheItem::CachedOk(traces.clone()));
+                return Ok(traces);
+            }
+            Ok(None) => {}
+            Err(error) => {
+                tracing::warn!(block=%block_hash, ?error, "Failed to read trace store");
+            }
+        }
+    }
+
     let mut traces = Vec::with_capacity(transactions.len());
     for (transaction_idx, tx) in transactions.into_iter().enumerate() {
         let hash = TransactionHash(Transaction::tx_hash(&tx).0.into_felt());
@@ -185,7 +284,21 @@ pub fn trace(
         let gas_vector_computation_mode = super::transaction::gas_vector_computation_mode(&tx);
 
         let mut tx_state = CachedState::<_>::create_transactional(&mut state);
-        let tx_info = tx.execute(&mut tx_state, &block_context).map_err(|e| {
+        let tx_info = tx.execute(&mut tx_state, &block_context);
+        // An execution aborted by the deadline says nothing about the block, so
+        // neither its outcome nor the error is cached.
+        let failed = tx_info
+            .as_ref()
+            .map_or(true, |tx_info| tx_info.revert_error.is_some());
+        if let Err(error) = deadline.check_outcome(failed) {
+            // Remove the cache entry so it's no longer inflight, and let the
+            // requests waiting for it know why.
+            let mut cache = cache.cache.lock().unwrap();
+            let _ = sender.send(Err(InflightError::DeadlineExceeded));
+            cache.cache_remove(&block_hash);
+            return Err(error.into());
+        }
+        let tx_info = tx_info.map_err(|e| {
             // Update the cache with the error. Lock the cache before sending to avoid
             // race conditions between senders and receivers.
             let err = ExecutionError {
@@ -193,15 +306,15 @@ pub fn trace(
                 error: e.to_string(),
                 error_stack: e.into(),
             };
-            let mut cache = cache.0.lock().unwrap();
-            let _ = sender.send(Err(err.clone()));
+            let mut cache = cache.cache.lock().unwrap();
+            let _ = sender.send(Err(InflightError::Execution(err.clone())));
             cache.cache_set(block_hash, CacheItem::CachedErr(err.clone()));
             err
         })?;
         let state_diff = to_state_diff(&mut tx_state, tx_declared_deprecated_class_hash)
             .inspect_err(|_| {
                 // Remove the cache entry so it's no longer inflight.
-                let mut cache = cache.0.lock().unwrap();
+                let mut cache = cache.cache.lock().unwrap();
                 cache.cache_remove(&block_hash);
             })?;
         tx_state.commit();
@@ -220,7 +333,7 @@ pub fn trace(
 
     // Lock the cache before sending to avoid race conditions between senders and
     // receivers.
-    let mut cache = cache.0.lock().unwrap();
+    let mut cache = cache.cache.lock().unwrap();
     let _ = sender.send(Ok(traces.clone()));
     cache.cache_set(block_hash, CacheItem::CachedOk(traces.clone()));
     Ok(traces)
@@ -8,6 +8,7 @@ use starknet_api::StarknetApiError;
 use starknet_types_core::felt::Felt as CoreFelt;
 
 use super::felt::{IntoFelt, IntoStarkFelt};
+use crate::deadline::Deadline;
 use crate::lru_cache::GLOBAL_CACHE;
 
 #[cfg(feature = "cairo-native")]
@@ -35,6 +36,7 @@ pub(super) struct PathfinderStateReader<'tx> {
     ignore_block_number_for_classes: bool,
     #[allow(unused)]
     native_class_cache: Option<NativeClassCache>,
+    deadline: Deadline,
 }
 
 impl<'tx> PathfinderStateReader<'tx> {
@@ -43,12 +45,14 @@ impl<'tx> PathfinderStateReader<'tx> {
         block_number: Option<BlockNumber>,
         ignore_block_number_for_classes: bool,
         native_class_cache: Option<NativeClassCache>,
+        deadline: Deadline,
     ) -> Self {
         Self {
             transaction,
             block_number,
             ignore_block_number_for_classes,
             native_class_cache,
+            deadline,
         }
     }
 
@@ -214,6 +218,8 @@ impl StateReader for PathfinderStateReader<'_> {
 
         tracing::trace!("Getting storage value");
 
+        self.deadline.check()?;
+
         let Some(block_id) = self.state_block_id() else {
             return Ok(Felt::ZERO.into_starkfelt());
         };
@@ -242,6 +248,8 @@ impl StateReader for PathfinderStateReader<'_> {
 
         tracing::trace!("Getting nonce for contract");
 
+        self.deadline.check()?;
+
         let Some(block_id) = self.state_block_id() else {
             return Ok(starknet_api::core::Nonce(
                 pathfinder_common::ContractNonce::ZERO.0.into_starkfelt(),
@@ -268,6 +276,8 @@ impl StateReader for PathfinderStateReader<'_> {
 
         tracing::trace!("Getting class hash at contract");
 
+        self.deadline.check()?;
+
         let Some(block_id) = self.state_block_id() else {
             return Ok(starknet_api::core::ClassHash(
                 ClassHash::ZERO.0.into_starkfelt(),
@@ -298,6 +308,8 @@ impl StateReader for PathfinderStateReader<'_> {
             tracing::trace_span!("get_compiled_contract_class", class_hash=%pathfinder_class_hash)
                 .entered();
 
+        self.deadline.check()?;
+
         if let Some(entry) = GLOBAL_CACHE.get(&class_hash) {
             if let Some(reader_block_number) = self.block_number {
                 if entry.height <= reader_block_number {
@@ -325,6 +337,8 @@ impl StateReader for PathfinderStateReader<'_> {
 
         tracing::trace!(%class_hash, "Getting compiled class hash");
 
+        self.deadline.check()?;
+
         let block_id = self.state_block_id().ok_or_else(|| {
             StateError::UndeclaredClassHash(starknet_api::core::ClassHash(
                 class_hash.0.into_starkfelt(),
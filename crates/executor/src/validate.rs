@@ -0,0 +1,49 @@
+use blockifier::blockifier::stateful_validator::StatefulValidator;
+use blockifier::transaction::transaction_execution::Transaction;
+use pathfinder_common::TransactionHash;
+
+use super::error::TransactionValidationError;
+use super::execution_state::ExecutionState;
+use crate::IntoFelt;
+
+/// Checks whether `transaction` would be admitted by the sequencer on top of
+/// the given state: nonce, resource bounds, fee token balance and the account's
+/// `__validate__` entry point.
+///
+/// The nonce check is not strict, the same way the gateway checks it: a nonce
+/// ahead of the account's current nonce is accepted, so that an account can
+/// submit several transactions before the first of them is included in a
+/// block. Only the `__validate__` phase is executed, with the exception of
+/// deploy account transactions whose constructor has to run first.
+pub fn validate(
+    execution_state: ExecutionState<'_>,
+    transaction: Transaction,
+) -> Result<(), TransactionValidationError> {
+    let block_number = execution_state.header.number;
+
+    let transaction_hash = transaction_hash(&transaction);
+    let Transaction::Account(transaction) = transaction else {
+        return Err(TransactionValidationError::Custom(anyhow::anyhow!(
+            "L1 handler transactions cannot be submitted"
+        )));
+    };
+
+    let (state, block_context) = execution_state.starknet_state()?;
+
+    let _span = tracing::debug_span!(
+        "validate",
+        block_number = %block_number,
+        transaction_hash = %transaction_hash,
+    )
+    .entered();
+
+    let mut validator = StatefulValidator::create(state, block_context);
+    validator.perform_validations(transaction).map_err(|error| {
+        tracing::debug!(%error, "Transaction validation failed");
+        error.into()
+    })
+}
+
+pub fn transaction_hash(transaction: &Transaction) -> TransactionHash {
+    TransactionHash(Transaction::tx_hash(transaction).0.into_felt())
+}
@@ -15,6 +15,7 @@
 use pathfinder_common::{BlockId, ClassHash, TransactionHash};
 use starknet_gateway_types::error::SequencerError;
 
+use crate::endpoints::Endpoints;
 use crate::metrics::{with_metrics, BlockTag, RequestMetadata};
 
 const X_THROTTLING_BYPASS: &str = "X-Throttling-Bypass";
@@ -22,7 +23,9 @@ const X_THROTTLING_BYPASS: &str = "X-Throttling-Bypass";
 /// A Sequencer Request builder.
 pub struct Request<'a, S: RequestState> {
     state: S,
-    url: reqwest::Url,
+    endpoints: &'a Endpoints,
+    /// Query parameters, which are appended to the selected endpoint's URL.
+    params: Vec<(String, String)>,
     api_key: Option<String>,
     client: &'a reqwest::Client,
 }
@@ -77,11 +80,12 @@ impl<'a> Request<'a, stage::Init> {
     /// Initialize a [Request] builder.
     pub fn builder(
         client: &'a reqwest::Client,
-        url: reqwest::Url,
+        endpoints: &'a Endpoints,
         api_key: Option<String>,
     ) -> Request<'a, stage::Method> {
         Request {
-            url,
+            endpoints,
+            params: Vec::new(),
             client,
             api_key,
             state: stage::Method,
@@ -151,15 +155,11 @@ impl<'a> Request<'a, stage::Method> {
         get_public_key,
     );
 
-    /// Appends the given method to the request url.
-    fn method(mut self, method: &'static str) -> Request<'a, stage::Params> {
-        self.url
-            .path_segments_mut()
-            .expect("Base URL is valid")
-            .push(method);
-
+    /// Selects the method, which is appended to the endpoint's URL.
+    fn method(self, method: &'static str) -> Request<'a, stage::Params> {
         Request {
-            url: self.url,
+            endpoints: self.endpoints,
+            params: self.params,
             client: self.client,
             api_key: self.api_key,
             state: stage::Params {
@@ -205,7 +205,7 @@ impl<'a> Request<'a, stage::Params> {
     }
 
     pub fn param(mut self, name: &str, value: &str) -> Self {
-        self.url.query_pairs_mut().append_pair(name, value);
+        self.params.push((name.to_owned(), value.to_owned()));
         self
     }
 
@@ -217,7 +217,8 @@ impl<'a> Request<'a, stage::Params> {
     /// Sets the request retry behavior.
     pub fn retry(self, retry: bool) -> Request<'a, stage::Final> {
         Request {
-            url: self.url,
+            endpoints: self.endpoints,
+            params: self.params,
             client: self.client,
             api_key: self.api_key,
             state: stage::Final {
@@ -229,6 +230,17 @@ impl<'a> Request<'a, stage::Params> {
 }
 
 impl Request<'_, stage::Final> {
+    /// The request's URL on the endpoint with the given base URL.
+    fn url(&self, mut base: reqwest::Url) -> reqwest::Url {
+        base.path_segments_mut()
+            .expect("Base URL is valid")
+            .push(self.state.meta.method);
+        if !self.params.is_empty() {
+            base.query_pairs_mut().extend_pairs(&self.params);
+        }
+        base
+    }
+
     /// Sends the Sequencer request as a REST `GET` operation and parses the
     /// response into `T`.
     pub async fn get<T>(self) -> Result<T, SequencerError>
@@ -254,19 +266,20 @@ impl Request<'_, stage::Final> {
             .await
         }
 
-        match self.state.retry {
-            false => send_request(self.url, self.api_key, self.client, self.state.meta).await,
-            true => {
-                retry0(
-                    || async {
-                        let url = self.url.clone();
-                        let api_key = self.api_key.clone();
-                        send_request(url, api_key, self.client, self.state.meta).await
-                    },
-                    retry_condition,
+        let send = || {
+            self.endpoints.send(|base| {
+                send_request(
+                    self.url(base),
+                    self.api_key.clone(),
+                    self.client,
+                    self.state.meta,
                 )
-                .await
-            }
+            })
+        };
+
+        match self.state.retry {
+            false => send().await,
+            true => retry0(send, retry_condition).await,
         }
     }
 
@@ -294,19 +307,20 @@ impl Request<'_, stage::Final> {
             .await
         }
 
-        match self.state.retry {
-            false => get_as_bytes_inner(self.url, self.api_key, self.client, self.state.meta).await,
-            true => {
-                retry0(
-                    || async {
-                        let url = self.url.clone();
-                        let api_key = self.api_key.clone();
-                        get_as_bytes_inner(url, api_key, self.client, self.state.meta).await
-                    },
-                    retry_condition,
+        let send = || {
+            self.endpoints.send(|base| {
+                get_as_bytes_inner(
+                    self.url(base),
+                    self.api_key.clone(),
+                    self.client,
+                    self.state.meta,
                 )
-                .await
-            }
+            })
+        };
+
+        match self.state.retry {
+            false => send().await,
+            true => retry0(send, retry_condition).await,
         }
     }
 
@@ -352,38 +366,24 @@ impl Request<'_, stage::Final> {
             .await
         }
 
-        match self.state.retry {
-            false => {
+        let send = || {
+            self.endpoints.send(|base| {
+                let url = self.url(base);
+                tracing::trace!(%url, "Posting data to gateway");
                 post_with_json_inner(
-                    self.url,
-                    self.api_key,
+                    url,
+                    self.api_key.clone(),
                     self.client,
                     self.state.meta,
                     json,
                     timeout,
                 )
-                .await
-            }
-            true => {
-                retry0(
-                    || async {
-                        tracing::trace!(url=%self.url, "Posting data to gateway");
-                        let url = self.url.clone();
-                        let api_key = self.api_key.clone();
-                        post_with_json_inner(
-                            url,
-                            api_key,
-                            self.client,
-                            self.state.meta,
-                            json,
-                            timeout,
-                        )
-                        .await
-                    },
-                    retry_condition,
-                )
-                .await
-            }
+            })
+        };
+
+        match self.state.retry {
+            false => send().await,
+            true => retry0(send, retry_condition).await,
         }
     }
 }
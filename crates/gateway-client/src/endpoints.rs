@@ -0,0 +1,349 @@
+//! Failover between interchangeable gateway endpoints.
+//!
+//! Every request goes to the best available endpoint: primary endpoints are
+//! preferred over fallbacks, and among those the one with the lowest observed
+//! latency is chosen. An endpoint which fails to respond is backed off for an
+//! exponentially growing period and the request is immediately repeated on the
+//! next available endpoint. Starknet errors are proper responses and don't
+//! count as failures. As endpoints might lag behind each other, a block which
+//! isn't found is requested from the remaining primary endpoints as well.
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::Url;
+use starknet_gateway_types::error::{KnownStarknetErrorCode, SequencerError, StarknetError};
+
+use crate::metrics::{METRIC_ENDPOINT_FAILOVERS, METRIC_ENDPOINT_FAILURES};
+
+/// How long an endpoint is backed off after its first consecutive failure.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+/// The backoff doubles on each consecutive failure up to this limit.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// The weight of the latest measurement in an endpoint's latency estimate.
+const LATENCY_WEIGHT: f64 = 0.2;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Endpoints(Arc<[Endpoint]>);
+
+#[derive(Debug)]
+struct Endpoint {
+    url: Url,
+    /// Fallbacks are only used while all primary endpoints are backed off.
+    fallback: bool,
+    /// The endpoint's origin, which unlike the URL can't contain credentials
+    /// and is used as a metrics label.
+    label: String,
+    health: Mutex<Health>,
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    consecutive_failures: u32,
+    backed_off_until: Option<Instant>,
+    /// Exponentially weighted moving average of the response time.
+    latency: Option<Duration>,
+}
+
+impl Endpoints {
+    pub(crate) fn new(primary: Vec<Url>) -> anyhow::Result<Self> {
+        anyhow::ensure!(!primary.is_empty(), "At least one URL is required");
+
+        Ok(Self(
+            primary
+                .into_iter()
+                .map(|url| Endpoint::new(url, false))
+                .collect(),
+        ))
+    }
+
+    pub(crate) fn single(url: Url) -> Self {
+        Self(Arc::new([Endpoint::new(url, false)]))
+    }
+
+    /// Adds an endpoint which is only used while the others are backed off.
+    pub(crate) fn with_fallback(&self, url: Url) -> Self {
+        let endpoints = self
+            .0
+            .iter()
+            .map(|endpoint| Endpoint::new(endpoint.url.clone(), endpoint.fallback))
+            .chain(std::iter::once(Endpoint::new(url, true)))
+            .collect();
+
+        Self(endpoints)
+    }
+
+    /// Sends a request with `send`, which is given the base URL of the
+    /// selected endpoint. Endpoint failures and blocks which aren't found are
+    /// retried on the remaining available endpoints, and the last result is
+    /// returned if none of them succeeds.
+    pub(crate) async fn send<T, F, Fut>(&self, mut send: F) -> Result<T, SequencerError>
+    where
+        F: FnMut(Url) -> Fut,
+        Fut: futures::Future<Output = Result<T, SequencerError>>,
+    {
+        let mut tried = Vec::with_capacity(self.0.len());
+        let mut index = self.select_first(Instant::now());
+
+        loop {
+            let endpoint = &self.0[index];
+            let started = Instant::now();
+            let result = send(endpoint.url.clone()).await;
+            let failed = matches!(&result, Err(e) if is_endpoint_failure(e));
+
+            endpoint.record(failed, started.elapsed(), Instant::now());
+            tried.push(index);
+            let lagging = matches!(&result, Err(e) if is_block_not_found(e));
+            if !failed && !lagging {
+                return result;
+            }
+
+            match self.select_next(&tried, Instant::now()) {
+                Some(next) if failed => {
+                    tracing::debug!(from=%endpoint.label, to=%self.0[next].label, "Gateway endpoint failed, failing over");
+                    metrics::increment_counter!(METRIC_ENDPOINT_FAILOVERS, "endpoint" => endpoint.label.clone());
+                    index = next;
+                }
+                Some(next) => {
+                    tracing::debug!(from=%endpoint.label, to=%self.0[next].label, "Block not found on gateway endpoint, trying the next one");
+                    index = next;
+                }
+                None => return result,
+            }
+        }
+    }
+
+    /// The best available endpoint, or the one that is available again
+    /// soonest if all of them are backed off.
+    fn select_first(&self, now: Instant) -> usize {
+        self.select_next(&[], now).unwrap_or_else(|| {
+            self.0
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, endpoint)| endpoint.health.lock().unwrap().backed_off_until)
+                .map(|(index, _)| index)
+                .expect("There is at least one endpoint")
+        })
+    }
+
+    /// The best available endpoint that wasn't tried yet. Fallbacks are only
+    /// selected while all primary endpoints are backed off.
+    fn select_next(&self, tried: &[usize], now: Instant) -> Option<usize> {
+        let primaries_backed_off = self
+            .0
+            .iter()
+            .filter(|endpoint| !endpoint.fallback)
+            .all(|endpoint| !endpoint.is_available(now));
+
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(index, endpoint)| {
+                !tried.contains(index) && (!endpoint.fallback || primaries_backed_off)
+            })
+            .filter_map(|(index, endpoint)| {
+                let health = endpoint.health.lock().unwrap();
+                let available = health.backed_off_until.map_or(true, |until| until <= now);
+                // Endpoints without measurements are tried first to measure them.
+                available.then_some((index, endpoint.fallback, health.latency.unwrap_or_default()))
+            })
+            .min_by_key(|&(_, fallback, latency)| (fallback, latency))
+            .map(|(index, ..)| index)
+    }
+}
+
+impl Endpoint {
+    fn new(url: Url, fallback: bool) -> Self {
+        Self {
+            label: url.origin().ascii_serialization(),
+            url,
+            fallback,
+            health: Default::default(),
+        }
+    }
+
+    fn is_available(&self, now: Instant) -> bool {
+        self.health
+            .lock()
+            .unwrap()
+            .backed_off_until
+            .map_or(true, |until| until <= now)
+    }
+
+    fn record(&self, failed: bool, latency: Duration, now: Instant) {
+        let mut health = self.health.lock().unwrap();
+
+        if failed {
+            health.consecutive_failures += 1;
+            let backoff = MIN_BACKOFF
+                .saturating_mul(2u32.saturating_pow(health.consecutive_failures - 1))
+                .min(MAX_BACKOFF);
+            health.backed_off_until = Some(now + backoff);
+
+            if health.consecutive_failures == 1 {
+                tracing::warn!(endpoint=%self.label, "Gateway endpoint is failing, backing off");
+            }
+            metrics::increment_counter!(METRIC_ENDPOINT_FAILURES, "endpoint" => self.label.clone());
+            return;
+        }
+
+        if health.consecutive_failures > 0 {
+            tracing::info!(endpoint=%self.label, "Gateway endpoint recovered");
+        }
+        health.consecutive_failures = 0;
+        health.backed_off_until = None;
+        health.latency = Some(match health.latency {
+            Some(average) => {
+                average.mul_f64(1.0 - LATENCY_WEIGHT) + latency.mul_f64(LATENCY_WEIGHT)
+            }
+            None => latency,
+        });
+    }
+}
+
+/// Starknet errors are proper responses, everything else means that the
+/// endpoint couldn't serve the request.
+fn is_endpoint_failure(error: &SequencerError) -> bool {
+    !matches!(error, SequencerError::StarknetError(_))
+}
+
+/// The endpoint might be behind the others.
+fn is_block_not_found(error: &SequencerError) -> bool {
+    matches!(
+        error,
+        SequencerError::StarknetError(StarknetError { code, .. })
+            if *code == KnownStarknetErrorCode::BlockNotFound.into()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(host: &str) -> Url {
+        Url::parse(&format!("https://{host}/feeder_gateway")).unwrap()
+    }
+
+    #[test]
+    fn selection_prefers_fast_primary_endpoints() {
+        let endpoints = Endpoints::new(vec![url("a"), url("b")])
+            .unwrap()
+            .with_fallback(url("c"));
+        let now = Instant::now();
+
+        endpoints.0[0].record(false, Duration::from_millis(200), now);
+        endpoints.0[1].record(false, Duration::from_millis(100), now);
+        endpoints.0[2].record(false, Duration::from_millis(10), now);
+        assert_eq!(endpoints.select_first(now), 1);
+
+        // Failing endpoints are backed off, fallbacks are used last.
+        endpoints.0[1].record(true, Duration::ZERO, now);
+        assert_eq!(endpoints.select_first(now), 0);
+        endpoints.0[0].record(true, Duration::ZERO, now);
+        assert_eq!(endpoints.select_first(now), 2);
+
+        // Backoff expires.
+        assert_eq!(endpoints.select_first(now + MIN_BACKOFF), 1);
+    }
+
+    #[test]
+    fn backoff_grows_with_consecutive_failures() {
+        let endpoints = Endpoints::new(vec![url("a"), url("b")]).unwrap();
+        let now = Instant::now();
+
+        endpoints.0[0].record(true, Duration::ZERO, now);
+        endpoints.0[0].record(true, Duration::ZERO, now);
+        endpoints.0[1].record(true, Duration::ZERO, now);
+
+        // All endpoints are backed off, so the one available soonest is used.
+        assert_eq!(endpoints.select_first(now), 1);
+        assert_eq!(endpoints.select_next(&[], now + MIN_BACKOFF), Some(1));
+        assert_eq!(endpoints.select_next(&[], now + 2 * MIN_BACKOFF), Some(0));
+    }
+
+    #[tokio::test]
+    async fn failover() {
+        let endpoints = Endpoints::new(vec![url("a"), url("b")]).unwrap();
+
+        let response = endpoints
+            .send(|url| async move {
+                match url.host_str() {
+                    Some("a") => Err(SequencerError::InvalidStarknetErrorVariant),
+                    _ => Ok(url),
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(response, url("b"));
+
+        // Starknet errors don't cause a failover.
+        let error = endpoints
+            .send(|_| async {
+                Err::<(), _>(SequencerError::StarknetError(StarknetError {
+                    code: KnownStarknetErrorCode::UndeclaredClass.into(),
+                    message: String::new(),
+                }))
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(error, SequencerError::StarknetError(_)));
+        assert_eq!(
+            endpoints.0[1].health.lock().unwrap().consecutive_failures,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_blocks_are_requested_from_other_endpoints() {
+        let endpoints = Endpoints::new(vec![url("a"), url("b")]).unwrap();
+        let block_not_found = || {
+            SequencerError::StarknetError(StarknetError {
+                code: KnownStarknetErrorCode::BlockNotFound.into(),
+                message: String::new(),
+            })
+        };
+
+        // The first endpoint lags behind the second one.
+        let response = endpoints
+            .send(|url| async move {
+                match url.host_str() {
+                    Some("a") => Err(block_not_found()),
+                    _ => Ok(url),
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(response, url("b"));
+        assert_eq!(
+            endpoints.0[0].health.lock().unwrap().consecutive_failures,
+            0
+        );
+
+        // The block is unknown to all endpoints.
+        let error = endpoints
+            .send(|_| async { Err::<(), _>(block_not_found()) })
+            .await
+            .unwrap_err();
+        assert!(is_block_not_found(&error));
+    }
+
+    #[tokio::test]
+    async fn missing_blocks_are_not_requested_from_fallbacks() {
+        let endpoints = Endpoints::new(vec![url("a"), url("b")])
+            .unwrap()
+            .with_fallback(url("c"));
+
+        let error = endpoints
+            .send(|url| async move {
+                match url.host_str() {
+                    Some("c") => panic!("Fallback must not be called"),
+                    _ => Err::<(), _>(SequencerError::StarknetError(StarknetError {
+                        code: KnownStarknetErrorCode::BlockNotFound.into(),
+                        message: String::new(),
+                    })),
+                }
+            })
+            .await
+            .unwrap_err();
+        assert!(is_block_not_found(&error));
+    }
+}
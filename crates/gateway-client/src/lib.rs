@@ -3,8 +3,9 @@ use std::fmt::Debug;
 use std::result::Result;
 use std::time::Duration;
 
+use anyhow::Context;
 use pathfinder_common::prelude::*;
-use pathfinder_common::BlockId;
+use pathfinder_common::{BlockId, Chain};
 use reqwest::Url;
 use starknet_gateway_types::error::SequencerError;
 use starknet_gateway_types::reply::PendingBlock;
@@ -12,6 +13,7 @@ use starknet_gateway_types::trace::{BlockTrace, TransactionTrace};
 use starknet_gateway_types::{reply, request};
 
 mod builder;
+mod endpoints;
 mod metrics;
 
 #[allow(unused_variables)]
@@ -207,14 +209,17 @@ impl<T: GatewayApi + Sync + Send> GatewayApi for std::sync::Arc<T> {
 /// `backoff [secs] = min((2 ^ N) * 15, 600) [secs]`
 ///
 /// where `N` is the consecutive retry iteration number `{1, 2, ...}`.
+///
+/// Multiple feeder gateway URLs can be given, in which case each request goes
+/// to the fastest one which is up, failing over to the others when it isn't.
 #[derive(Debug, Clone)]
 pub struct Client {
     /// This client is internally refcounted
     inner: reqwest::Client,
     /// Starknet gateway URL.
-    gateway: Url,
-    /// Starknet feeder gateway URL.
-    feeder_gateway: Url,
+    gateway: endpoints::Endpoints,
+    /// Starknet feeder gateway URLs.
+    feeder_gateway: endpoints::Endpoints,
     /// Whether __read only__ requests should be retried, defaults to __true__
     /// for production.
     /// Use [disable_retry_for_tests](Client::disable_retry_for_tests) to
@@ -228,29 +233,17 @@ pub struct Client {
 impl Client {
     /// Creates a [Client] for [pathfinder_common::Chain::Mainnet].
     pub fn mainnet(timeout: Duration) -> Self {
-        Self::with_base_url(
-            Url::parse("https://alpha-mainnet.starknet.io/").unwrap(),
-            timeout,
-        )
-        .unwrap()
+        Self::with_base_url(public_base_url(Chain::Mainnet).unwrap(), timeout).unwrap()
     }
 
     /// Creates a [Client] for [pathfinder_common::Chain::SepoliaTestnet].
     pub fn sepolia_testnet(timeout: Duration) -> Self {
-        Self::with_base_url(
-            Url::parse("https://alpha-sepolia.starknet.io/").unwrap(),
-            timeout,
-        )
-        .unwrap()
+        Self::with_base_url(public_base_url(Chain::SepoliaTestnet).unwrap(), timeout).unwrap()
     }
 
     /// Creates a [Client] for [pathfinder_common::Chain::SepoliaIntegration].
     pub fn sepolia_integration(timeout: Duration) -> Self {
-        Self::with_base_url(
-            Url::parse("https://integration-sepolia.starknet.io/").unwrap(),
-            timeout,
-        )
-        .unwrap()
+        Self::with_base_url(public_base_url(Chain::SepoliaIntegration).unwrap(), timeout).unwrap()
     }
 
     /// Creates a [Client] with a shared feeder gateway and gateway base url.
@@ -262,6 +255,16 @@ impl Client {
 
     /// Create a Sequencer client for the given [Url]s.
     pub fn with_urls(gateway: Url, feeder_gateway: Url, timeout: Duration) -> anyhow::Result<Self> {
+        Self::with_feeder_gateways(gateway, vec![feeder_gateway], timeout)
+    }
+
+    /// Create a Sequencer client which fails over between the given feeder
+    /// gateway [Url]s. The first one is preferred while latencies are unknown.
+    pub fn with_feeder_gateways(
+        gateway: Url,
+        feeder_gateways: Vec<Url>,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
         metrics::register();
 
         Ok(Self {
@@ -269,13 +272,25 @@ impl Client {
                 .timeout(timeout)
                 .user_agent(pathfinder_version::USER_AGENT)
                 .build()?,
-            gateway,
-            feeder_gateway,
+            gateway: endpoints::Endpoints::single(gateway),
+            feeder_gateway: endpoints::Endpoints::new(feeder_gateways)
+                .context("Configuring feeder gateways")?,
             retry: true,
             api_key: None,
         })
     }
 
+    /// Adds the public feeder gateway of `chain` as a fallback, which is only
+    /// used while all other feeder gateways are failing. Has no effect for
+    /// custom networks.
+    pub fn with_public_fallback(mut self, chain: Chain) -> Self {
+        if let Some(base) = public_base_url(chain) {
+            let feeder_gateway = base.join("feeder_gateway").expect("Valid URL");
+            self.feeder_gateway = self.feeder_gateway.with_fallback(feeder_gateway);
+        }
+        self
+    }
+
     /// Sets the api key to be used for each request as a value for
     /// 'X-Throttling-Bypass' header.
     pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
@@ -293,18 +308,26 @@ impl Client {
     }
 
     fn gateway_request(&self) -> builder::Request<'_, builder::stage::Method> {
-        builder::Request::builder(&self.inner, self.gateway.clone(), self.api_key.clone())
+        builder::Request::builder(&self.inner, &self.gateway, self.api_key.clone())
     }
 
     fn feeder_gateway_request(&self) -> builder::Request<'_, builder::stage::Method> {
-        builder::Request::builder(
-            &self.inner,
-            self.feeder_gateway.clone(),
-            self.api_key.clone(),
-        )
+        builder::Request::builder(&self.inner, &self.feeder_gateway, self.api_key.clone())
     }
 }
 
+/// The base URL of the public gateways of a known network.
+fn public_base_url(chain: Chain) -> Option<Url> {
+    let url = match chain {
+        Chain::Mainnet => "https://alpha-mainnet.starknet.io/",
+        Chain::SepoliaTestnet => "https://alpha-sepolia.starknet.io/",
+        Chain::SepoliaIntegration => "https://integration-sepolia.starknet.io/",
+        Chain::Custom => return None,
+    };
+
+    Some(Url::parse(url).expect("Valid URL"))
+}
+
 #[async_trait::async_trait]
 impl GatewayApi for Client {
     #[tracing::instrument(skip(self))]
@@ -9,6 +9,10 @@ use super::SequencerError;
 const METRIC_REQUESTS: &str = "gateway_requests_total";
 const METRIC_FAILED_REQUESTS: &str = "gateway_requests_failed_total";
 const METRIC_REQUESTS_LATENCY: &str = "gateway_request_duration_seconds";
+/// Requests an endpoint failed to serve, labeled by the endpoint's origin.
+pub(crate) const METRIC_ENDPOINT_FAILURES: &str = "gateway_endpoint_failures_total";
+/// Requests repeated on another endpoint after the labeled endpoint failed.
+pub(crate) const METRIC_ENDPOINT_FAILOVERS: &str = "gateway_endpoint_failovers_total";
 const METRICS: [&str; 2] = [METRIC_REQUESTS, METRIC_FAILED_REQUESTS];
 const TAG_LATEST: &str = "latest";
 const TAG_PENDING: &str = "pending";
@@ -2132,7 +2132,7 @@ pub mod state_update {
 
 /// Used to deserialize replies to Starknet Ethereum contract requests.
 #[serde_as]
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct EthContractAddresses {
     #[serde(rename = "Starknet")]
     #[serde_as(as = "EthereumAddressAsHexStr")]
@@ -47,6 +47,12 @@ impl<'tx> ClassCommitmentTree<'tx> {
         self
     }
 
+    /// Recalculates the hashes of all nodes and checks them against the stored
+    /// hashes, see [MerkleTree::verify].
+    pub fn verify(&self) -> anyhow::Result<ClassCommitment> {
+        self.tree.verify(&self.storage).map(ClassCommitment)
+    }
+
     /// Adds a leaf node for a Sierra -> CASM commitment.
     ///
     /// Note that the leaf value is _not_ the Cairo hash, but a hashed value
@@ -110,6 +110,12 @@ impl<'tx> ContractsStorageTree<'tx> {
         MerkleTree::<PedersenHash, 251>::get_proofs(root, &storage, &keys)
     }
 
+    /// Recalculates the hashes of all nodes and checks them against the stored
+    /// hashes, see [MerkleTree::verify].
+    pub fn verify(&self) -> anyhow::Result<ContractRoot> {
+        self.tree.verify(&self.storage).map(ContractRoot)
+    }
+
     pub fn set(&mut self, address: StorageAddress, value: StorageValue) -> anyhow::Result<()> {
         let key = address.view_bits().to_owned();
         self.tree.set(&self.storage, key, value.0)
@@ -175,6 +181,32 @@ impl<'tx> StorageCommitmentTree<'tx> {
         self
     }
 
+    /// Recalculates the hashes of all nodes and checks them against the stored
+    /// hashes, see [MerkleTree::verify].
+    pub fn verify(&self) -> anyhow::Result<StorageCommitment> {
+        self.tree.verify(&self.storage).map(StorageCommitment)
+    }
+
+    /// Like [StorageCommitmentTree::verify] but also calls `verify_contract`
+    /// with the state hash of every contract, so that it can be checked
+    /// against the contract's storage trie, class hash and nonce.
+    pub fn verify_with_contracts<F>(
+        &self,
+        mut verify_contract: F,
+    ) -> anyhow::Result<StorageCommitment>
+    where
+        F: FnMut(ContractAddress, ContractStateHash) -> anyhow::Result<()>,
+    {
+        self.tree
+            .verify_with_leaves(&self.storage, |path, value| {
+                let contract = ContractAddress(
+                    Felt::from_bits(path).context("Mapping leaf path to contract address")?,
+                );
+                verify_contract(contract, ContractStateHash(value))
+            })
+            .map(StorageCommitment)
+    }
+
     pub fn set(
         &mut self,
         address: ContractAddress,
@@ -907,6 +907,119 @@ impl<H: FeltHash, const HEIGHT: usize> MerkleTree<H, HEIGHT> {
 
         Ok(None)
     }
+
+    /// Recalculates the hash of every node from the leaves up and checks it
+    /// against the stored hash. Returns the recalculated root hash.
+    ///
+    /// Only unmodified trees can be verified. Note that this reads the entire
+    /// tree from storage.
+    pub fn verify(&self, storage: &impl Storage) -> anyhow::Result<Felt> {
+        self.verify_with_leaves(storage, |_, _| Ok(()))
+    }
+
+    /// Like [MerkleTree::verify] but also calls `on_leaf` with the key and
+    /// value of every leaf, which lets callers verify the leaf values as well.
+    pub fn verify_with_leaves<F>(
+        &self,
+        storage: &impl Storage,
+        mut on_leaf: F,
+    ) -> anyhow::Result<Felt>
+    where
+        F: FnMut(&BitSlice<u8, Msb0>, Felt) -> anyhow::Result<()>,
+    {
+        let Some(root) = self.root.as_ref() else {
+            return Ok(Felt::ZERO);
+        };
+
+        let root = match &*root.borrow() {
+            InternalNode::Unresolved(idx) => *idx,
+            _ => anyhow::bail!("Cannot verify a modified tree"),
+        };
+
+        self.verify_subtree(storage, root, BitVec::new(), &mut on_leaf)
+    }
+
+    fn verify_subtree<F>(
+        &self,
+        storage: &impl Storage,
+        index: TrieStorageIndex,
+        path: BitVec<u8, Msb0>,
+        on_leaf: &mut F,
+    ) -> anyhow::Result<Felt>
+    where
+        F: FnMut(&BitSlice<u8, Msb0>, Felt) -> anyhow::Result<()>,
+    {
+        anyhow::ensure!(
+            path.len() < HEIGHT,
+            "Node {index} at height {} exceeds the tree height {HEIGHT}",
+            path.len()
+        );
+
+        let node = storage
+            .get(index)?
+            .with_context(|| format!("Node {index} at height {} is missing", path.len()))?;
+
+        let child_path = |suffix: &BitSlice<u8, Msb0>| {
+            let mut child_path = path.clone();
+            child_path.extend_from_bitslice(suffix);
+            child_path
+        };
+        let leaf = |path: BitVec<u8, Msb0>, on_leaf: &mut F| -> anyhow::Result<Felt> {
+            anyhow::ensure!(
+                path.len() == HEIGHT,
+                "Leaf at height {} does not match the tree height {HEIGHT}",
+                path.len()
+            );
+            let value = storage
+                .leaf(&path)?
+                .with_context(|| format!("Leaf value for node {index} is missing"))?;
+            on_leaf(&path, value)?;
+            Ok(value)
+        };
+        let child_in_direction = |direction: Direction| {
+            let mut child_path = path.clone();
+            child_path.push(direction.into());
+            child_path
+        };
+        let left = || child_in_direction(Direction::Left);
+        let right = || child_in_direction(Direction::Right);
+
+        let hash = match node {
+            StoredNode::Binary {
+                left: left_index,
+                right: right_index,
+            } => {
+                let left_hash = self.verify_subtree(storage, left_index, left(), on_leaf)?;
+                let right_hash = self.verify_subtree(storage, right_index, right(), on_leaf)?;
+                BinaryNode::calculate_hash::<H>(left_hash, right_hash)
+            }
+            StoredNode::Edge {
+                child,
+                path: edge_path,
+            } => {
+                let child_hash =
+                    self.verify_subtree(storage, child, child_path(&edge_path), on_leaf)?;
+                EdgeNode::calculate_hash::<H>(child_hash, &edge_path)
+            }
+            StoredNode::LeafBinary => {
+                BinaryNode::calculate_hash::<H>(leaf(left(), on_leaf)?, leaf(right(), on_leaf)?)
+            }
+            StoredNode::LeafEdge { path: edge_path } => {
+                EdgeNode::calculate_hash::<H>(leaf(child_path(&edge_path), on_leaf)?, &edge_path)
+            }
+        };
+
+        let stored_hash = storage
+            .hash(index)?
+            .with_context(|| format!("Hash of node {index} is missing"))?;
+        anyhow::ensure!(
+            hash == stored_hash,
+            "Hash mismatch for node {index} at height {}: stored {stored_hash}, calculated {hash}",
+            path.len()
+        );
+
+        Ok(hash)
+    }
 }
 
 pub type TrieNodeWithHash = (TrieNode, Felt);
@@ -1401,6 +1514,127 @@ mod tests {
         }
     }
 
+    mod verify {
+        use super::*;
+
+        fn populated_storage() -> (TestStorage, Felt, TrieStorageIndex) {
+            let mut tree = TestTree::empty();
+            let mut storage = TestStorage::default();
+
+            for (key, value) in [
+                (felt!("0x1"), felt!("0x11")),
+                (felt!("0x2"), felt!("0x22")),
+                (felt!("0x3"), felt!("0x33")),
+                (felt!("0x99"), felt!("0x1")),
+            ] {
+                tree.set(&storage, key.view_bits().to_bitvec(), value)
+                    .unwrap();
+            }
+            let (root_hash, root_index) = commit_and_persist_without_pruning(tree, &mut storage);
+
+            (storage, root_hash, root_index)
+        }
+
+        #[test]
+        fn empty_tree() {
+            let storage = TestStorage::default();
+            assert_eq!(TestTree::empty().verify(&storage).unwrap(), Felt::ZERO);
+        }
+
+        #[test]
+        fn intact_tree() {
+            let (storage, root_hash, root_index) = populated_storage();
+            assert_eq!(
+                TestTree::new(root_index).verify(&storage).unwrap(),
+                root_hash
+            );
+        }
+
+        #[test]
+        fn corrupted_node_hash() {
+            let (mut storage, _, root_index) = populated_storage();
+            let (_, (hash, _)) = storage
+                .nodes
+                .iter_mut()
+                .find(|(idx, _)| **idx != root_index)
+                .unwrap();
+            *hash = felt!("0xdead");
+
+            TestTree::new(root_index).verify(&storage).unwrap_err();
+        }
+
+        #[test]
+        fn visits_every_leaf() {
+            let (storage, _, root_index) = populated_storage();
+
+            let mut leaves = Vec::new();
+            TestTree::new(root_index)
+                .verify_with_leaves(&storage, |key, value| {
+                    leaves.push((Felt::from_bits(key).unwrap(), value));
+                    Ok(())
+                })
+                .unwrap();
+            leaves.sort();
+
+            assert_eq!(
+                leaves,
+                vec![
+                    (felt!("0x1"), felt!("0x11")),
+                    (felt!("0x2"), felt!("0x22")),
+                    (felt!("0x3"), felt!("0x33")),
+                    (felt!("0x99"), felt!("0x1")),
+                ]
+            );
+        }
+
+        #[test]
+        fn rejected_leaf() {
+            let (storage, _, root_index) = populated_storage();
+
+            TestTree::new(root_index)
+                .verify_with_leaves(&storage, |_, value| {
+                    anyhow::ensure!(value != felt!("0x22"), "Bad leaf");
+                    Ok(())
+                })
+                .unwrap_err();
+        }
+
+        #[test]
+        fn corrupted_leaf() {
+            let (mut storage, _, root_index) = populated_storage();
+            storage.leaves.insert(felt!("0x2"), felt!("0xdead"));
+
+            TestTree::new(root_index).verify(&storage).unwrap_err();
+        }
+
+        #[test]
+        fn missing_node() {
+            let (mut storage, _, root_index) = populated_storage();
+            let idx = *storage
+                .nodes
+                .keys()
+                .find(|idx| **idx != root_index)
+                .unwrap();
+            storage.nodes.remove(&idx);
+
+            TestTree::new(root_index).verify(&storage).unwrap_err();
+        }
+
+        #[test]
+        fn modified_tree() {
+            let (storage, _, root_index) = populated_storage();
+            let mut tree = TestTree::new(root_index);
+            tree.set(
+                &storage,
+                felt!("0x4").view_bits().to_bitvec(),
+                felt!("0x44"),
+            )
+            .unwrap();
+
+            tree.verify(&storage).unwrap_err();
+        }
+    }
+
     mod persistence {
         use super::*;
 
@@ -1,17 +1,89 @@
 //! Consensus behaviour and other related utilities for the consensus p2p
 //! network.
+use std::time::Duration;
+
+use libp2p::PeerId;
+use p2p_proto::consensus::{ProposalPart, Vote};
+use tokio::sync::oneshot;
+
 mod behaviour;
+pub mod client;
+
+#[cfg(test)]
+mod tests;
 
-pub use behaviour::Behaviour;
+pub use behaviour::{Behaviour, PROPOSALS_TOPIC, VOTES_TOPIC};
+pub use client::Client;
 
 /// Commands for the consensus behaviour.
-pub enum Command {}
+#[derive(Debug)]
+pub enum Command {
+    /// Gossip a proposal part to the network.
+    BroadcastProposalPart {
+        part: ProposalPart,
+        sender: oneshot::Sender<anyhow::Result<()>>,
+    },
+    /// Gossip a vote to the network.
+    BroadcastVote {
+        vote: Vote,
+        sender: oneshot::Sender<anyhow::Result<()>>,
+    },
+}
 
 /// Events emitted by the consensus behaviour.
-pub enum Event {}
+///
+/// Only messages that passed validation are emitted, invalid messages are
+/// dropped and penalize the peer that propagated them.
+#[derive(Debug)]
+pub enum Event {
+    /// A proposal part was received.
+    ProposalPart {
+        /// The peer that published the message, which is not necessarily the
+        /// peer we received it from.
+        from: PeerId,
+        part: ProposalPart,
+    },
+    /// A vote was received.
+    Vote {
+        /// The peer that published the message, which is not necessarily the
+        /// peer we received it from.
+        from: PeerId,
+        vote: Vote,
+    },
+}
 
 /// State of the consensus behaviour.
+#[derive(Debug, Default)]
 pub struct State {}
 
 /// Configuration for the consensus P2P network.
-pub struct Config {}
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Interval between gossipsub heartbeats, which maintain the mesh and
+    /// emit gossip.
+    pub heartbeat_interval: Duration,
+    /// Maximum size of a single gossiped message, in bytes.
+    pub max_message_size: usize,
+    /// How long message IDs are remembered to discard duplicates.
+    pub duplicate_cache_time: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(1),
+            max_message_size: 1024 * 1024,
+            duplicate_cache_time: Duration::from_secs(60),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Config {
+    pub fn for_test() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_millis(100),
+            ..Default::default()
+        }
+    }
+}
@@ -1,30 +1,205 @@
-use libp2p::gossipsub;
+use libp2p::gossipsub::{self, IdentTopic, MessageAcceptance};
+use libp2p::identity::Keypair;
 use libp2p::swarm::NetworkBehaviour;
+use p2p_proto::consensus::{ProposalPart, Vote};
+use p2p_proto::{proto, ToProtobuf, TryFromProtobuf};
+use prost::Message;
 use tokio::sync::mpsc;
 
+use crate::consensus::Config;
 use crate::{consensus, ApplicationBehaviour};
 
+/// Gossipsub topic for proposal parts.
+pub const PROPOSALS_TOPIC: &str = "consensus_proposals";
+/// Gossipsub topic for prevotes and precommits.
+pub const VOTES_TOPIC: &str = "consensus_votes";
+
 /// The consensus P2P network behaviour.
 #[derive(NetworkBehaviour)]
 pub struct Behaviour {
     gossipsub: gossipsub::Behaviour,
 }
 
+impl Behaviour {
+    /// Creates the behaviour and subscribes to the consensus topics.
+    ///
+    /// Messages are signed with `keypair`.
+    pub fn new(keypair: Keypair, config: Config) -> Self {
+        let gossipsub_config = gossipsub::ConfigBuilder::default()
+            .heartbeat_interval(config.heartbeat_interval)
+            .max_transmit_size(config.max_message_size)
+            .duplicate_cache_time(config.duplicate_cache_time)
+            .validation_mode(gossipsub::ValidationMode::Strict)
+            // Messages are only forwarded after they pass our own validation, see
+            // `handle_event`
+            .validate_messages()
+            .message_id_fn(message_id)
+            .build()
+            .expect("Valid gossipsub config");
+
+        let mut gossipsub = gossipsub::Behaviour::new(
+            gossipsub::MessageAuthenticity::Signed(keypair),
+            gossipsub_config,
+        )
+        .expect("Valid gossipsub behaviour");
+
+        gossipsub
+            .with_peer_score(peer_score_params(), gossipsub::PeerScoreThresholds::default())
+            .expect("Valid peer score params");
+
+        for topic in [PROPOSALS_TOPIC, VOTES_TOPIC] {
+            gossipsub
+                .subscribe(&IdentTopic::new(topic))
+                .expect("Subscribing to consensus topic");
+        }
+
+        Self { gossipsub }
+    }
+
+    fn publish(&mut self, topic: &'static str, data: Vec<u8>) -> anyhow::Result<()> {
+        self.gossipsub
+            .publish(IdentTopic::new(topic), data)
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!("Publishing to {topic} failed: {e}"))
+    }
+}
+
 impl ApplicationBehaviour for Behaviour {
     type Command = consensus::Command;
     type Event = consensus::Event;
     type State = consensus::State;
 
-    async fn handle_command(&mut self, _command: Self::Command, _state: &mut Self::State) {
-        todo!()
+    async fn handle_command(&mut self, command: Self::Command, _state: &mut Self::State) {
+        use consensus::Command::*;
+        match command {
+            BroadcastProposalPart { part, sender } => {
+                tracing::debug!(?part, "Broadcasting proposal part");
+                let data = part.to_protobuf().encode_to_vec();
+                let _ = sender.send(self.publish(PROPOSALS_TOPIC, data));
+            }
+            BroadcastVote { vote, sender } => {
+                tracing::debug!(?vote, "Broadcasting vote");
+                let data = vote.to_protobuf().encode_to_vec();
+                let _ = sender.send(self.publish(VOTES_TOPIC, data));
+            }
+        }
     }
 
     async fn handle_event(
         &mut self,
-        _event: <Self as NetworkBehaviour>::ToSwarm,
+        event: BehaviourEvent,
         _state: &mut Self::State,
-        _event_sender: mpsc::Sender<Self::Event>,
+        event_sender: mpsc::Sender<Self::Event>,
     ) {
-        todo!()
+        match event {
+            BehaviourEvent::Gossipsub(gossipsub::Event::Message {
+                propagation_source,
+                message_id,
+                message,
+            }) => {
+                // Signed messages always carry their author
+                let from = message.source.unwrap_or(propagation_source);
+
+                let result = if message.topic == IdentTopic::new(PROPOSALS_TOPIC).hash() {
+                    decode_proposal_part(&message.data)
+                        .map(|part| consensus::Event::ProposalPart { from, part })
+                } else if message.topic == IdentTopic::new(VOTES_TOPIC).hash() {
+                    decode_vote(&message.data).map(|vote| consensus::Event::Vote { from, vote })
+                } else {
+                    Err(anyhow::anyhow!("Unknown topic {}", message.topic))
+                };
+
+                match result {
+                    Ok(event) => {
+                        tracing::debug!(%propagation_source, %message_id, ?event, "Received consensus message");
+                        let _ = self.gossipsub.report_message_validation_result(
+                            &message_id,
+                            &propagation_source,
+                            MessageAcceptance::Accept,
+                        );
+                        event_sender
+                            .send(event)
+                            .await
+                            .expect("Event receiver not to be dropped");
+                    }
+                    Err(error) => {
+                        tracing::debug!(%propagation_source, %message_id, %error, "Rejecting invalid consensus message");
+                        // Rejecting penalizes the propagation source, see `peer_score_params`
+                        let _ = self.gossipsub.report_message_validation_result(
+                            &message_id,
+                            &propagation_source,
+                            MessageAcceptance::Reject,
+                        );
+                    }
+                }
+            }
+            BehaviourEvent::Gossipsub(gossipsub::Event::Subscribed { peer_id, topic }) => {
+                tracing::debug!(%peer_id, %topic, "Peer subscribed to consensus topic");
+            }
+            BehaviourEvent::Gossipsub(gossipsub::Event::Unsubscribed { peer_id, topic }) => {
+                tracing::debug!(%peer_id, %topic, "Peer unsubscribed from consensus topic");
+            }
+            BehaviourEvent::Gossipsub(event) => {
+                tracing::trace!(?event, "Ignoring gossipsub event");
+            }
+        }
     }
 }
+
+/// Message IDs are derived from the content so that the same proposal part or
+/// vote is not propagated twice, even if it was published more than once.
+fn message_id(message: &gossipsub::Message) -> gossipsub::MessageId {
+    use sha3::{Digest, Sha3_256};
+    gossipsub::MessageId::from(Sha3_256::digest(&message.data).to_vec())
+}
+
+/// Penalizes peers that propagate messages failing validation. A few invalid
+/// messages are enough for a peer to be graylisted, after which its messages
+/// are ignored until the penalty decays.
+fn peer_score_params() -> gossipsub::PeerScoreParams {
+    let topic_params = gossipsub::TopicScoreParams {
+        topic_weight: 1.0,
+        // Consensus messages are bursty, so low delivery rates are not penalized.
+        mesh_message_deliveries_weight: 0.0,
+        mesh_failure_penalty_weight: 0.0,
+        invalid_message_deliveries_weight: -10.0,
+        invalid_message_deliveries_decay: 0.9,
+        ..Default::default()
+    };
+
+    let mut params = gossipsub::PeerScoreParams::default();
+    for topic in [PROPOSALS_TOPIC, VOTES_TOPIC] {
+        params
+            .topics
+            .insert(IdentTopic::new(topic).hash(), topic_params.clone());
+    }
+    params
+}
+
+pub(crate) fn decode_proposal_part(data: &[u8]) -> anyhow::Result<ProposalPart> {
+    let part = proto::consensus::ProposalPart::decode(data)?;
+    let part = ProposalPart::try_from_protobuf(part, "ProposalPart")?;
+
+    match &part {
+        ProposalPart::Init(init) => {
+            if let Some(valid_round) = init.valid_round {
+                anyhow::ensure!(
+                    valid_round < init.round,
+                    "Valid round {valid_round} is not lower than round {}",
+                    init.round
+                );
+            }
+        }
+        ProposalPart::TransactionBatch(batch) => {
+            anyhow::ensure!(!batch.transactions.is_empty(), "Empty transaction batch");
+        }
+        ProposalPart::Fin(_) => {}
+    }
+
+    Ok(part)
+}
+
+pub(crate) fn decode_vote(data: &[u8]) -> anyhow::Result<Vote> {
+    let vote = proto::consensus::Vote::decode(data)?;
+    Ok(Vote::try_from_protobuf(vote, "Vote")?)
+}
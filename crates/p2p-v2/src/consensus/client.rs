@@ -0,0 +1,58 @@
+use libp2p::PeerId;
+use p2p_proto::consensus::{ProposalPart, Vote};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::consensus::Command;
+use crate::core;
+
+/// A handle to the consensus p2p network.
+#[derive(Clone, Debug)]
+pub struct Client {
+    sender: mpsc::Sender<core::Command<Command>>,
+    local_peer_id: PeerId,
+}
+
+impl From<(PeerId, mpsc::Sender<core::Command<Command>>)> for Client {
+    fn from((peer_id, sender): (PeerId, mpsc::Sender<core::Command<Command>>)) -> Self {
+        Self {
+            sender,
+            local_peer_id: peer_id,
+        }
+    }
+}
+
+impl Client {
+    pub fn local_peer_id(&self) -> &PeerId {
+        &self.local_peer_id
+    }
+
+    /// Gossips a proposal part to the peers subscribed to the proposals topic.
+    ///
+    /// Fails if there are no such peers.
+    pub async fn broadcast_proposal_part(&self, part: ProposalPart) -> anyhow::Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(core::Command::Application(Command::BroadcastProposalPart {
+                part,
+                sender,
+            }))
+            .await
+            .expect("Command receiver not to be dropped");
+        receiver.await.expect("Sender not to be dropped")
+    }
+
+    /// Gossips a vote to the peers subscribed to the votes topic.
+    ///
+    /// Fails if there are no such peers.
+    pub async fn broadcast_vote(&self, vote: Vote) -> anyhow::Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(core::Command::Application(Command::BroadcastVote {
+                vote,
+                sender,
+            }))
+            .await
+            .expect("Command receiver not to be dropped");
+        receiver.await.expect("Sender not to be dropped")
+    }
+}
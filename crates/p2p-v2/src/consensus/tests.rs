@@ -0,0 +1,123 @@
+use fake::{Fake, Faker};
+use p2p_proto::consensus::{ProposalInit, ProposalPart, TransactionBatch, Vote};
+use p2p_proto::{proto, ToProtobuf};
+use prost::Message;
+
+use crate::consensus::behaviour::{decode_proposal_part, decode_vote, Behaviour};
+use crate::consensus::{Client, Config, Event};
+use crate::test_utils::peer::TestPeerBuilder;
+use crate::test_utils::{retry_until_published, wait_for_event};
+
+type ConsensusTestPeer = crate::test_utils::peer::TestPeer<Behaviour>;
+
+fn create_peer() -> ConsensusTestPeer {
+    let builder = TestPeerBuilder::new();
+    let keypair = builder.keypair.clone();
+    builder
+        .app_behaviour(Behaviour::new(keypair, Config::for_test()))
+        .build(crate::core::Config::for_test())
+}
+
+async fn create_peers() -> (ConsensusTestPeer, ConsensusTestPeer) {
+    let mut peer1 = create_peer();
+    let peer2 = create_peer();
+
+    let addr1 = peer1.start_listening().await.unwrap();
+    tracing::info!(%peer1.peer_id, %addr1);
+
+    peer2.client.dial(peer1.peer_id, addr1).await.unwrap();
+
+    (peer1, peer2)
+}
+
+fn client(peer: &ConsensusTestPeer) -> Client {
+    peer.client.as_pair().into()
+}
+
+#[test_log::test(tokio::test)]
+async fn proposal_part_is_gossiped() {
+    let (mut peer1, peer2) = create_peers().await;
+    let expected = ProposalPart::Init(ProposalInit {
+        valid_round: None,
+        ..Faker.fake()
+    });
+
+    let client2 = client(&peer2);
+    retry_until_published(|| client2.broadcast_proposal_part(expected.clone())).await;
+
+    let (from, part) = wait_for_event(&mut peer1.app_event_receiver, |event| match event {
+        Event::ProposalPart { from, part } => Some((from, part)),
+        _ => None,
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(from, peer2.peer_id);
+    assert_eq!(part, expected);
+}
+
+#[test_log::test(tokio::test)]
+async fn vote_is_gossiped() {
+    let (peer1, mut peer2) = create_peers().await;
+    let expected = Faker.fake::<Vote>();
+
+    let client1 = client(&peer1);
+    retry_until_published(|| client1.broadcast_vote(expected.clone())).await;
+
+    let (from, vote) = wait_for_event(&mut peer2.app_event_receiver, |event| match event {
+        Event::Vote { from, vote } => Some((from, vote)),
+        _ => None,
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(from, peer1.peer_id);
+    assert_eq!(vote, expected);
+}
+
+#[test]
+fn invalid_messages_are_rejected() {
+    assert!(decode_vote(b"definitely not a vote").is_err());
+    assert!(decode_proposal_part(&[]).is_err());
+
+    let init = ProposalPart::Init(ProposalInit {
+        round: 1,
+        valid_round: Some(1),
+        ..Faker.fake()
+    });
+    assert!(decode_proposal_part(&init.to_protobuf().encode_to_vec()).is_err());
+
+    let empty_batch = ProposalPart::TransactionBatch(TransactionBatch {
+        transactions: vec![],
+    });
+    assert!(decode_proposal_part(&empty_batch.to_protobuf().encode_to_vec()).is_err());
+
+    // Required fields must be present
+    let fin = proto::consensus::ProposalPart {
+        messages: Some(proto::consensus::proposal_part::Messages::Fin(
+            proto::consensus::ProposalFin {
+                proposal_commitment: None,
+            },
+        )),
+    };
+    assert!(decode_proposal_part(&fin.encode_to_vec()).is_err());
+}
+
+#[test]
+fn valid_messages_round_trip() {
+    let vote = Faker.fake::<Vote>();
+    assert_eq!(
+        decode_vote(&vote.clone().to_protobuf().encode_to_vec()).unwrap(),
+        vote
+    );
+
+    let init = ProposalPart::Init(ProposalInit {
+        round: 2,
+        valid_round: Some(1),
+        ..Faker.fake()
+    });
+    assert_eq!(
+        decode_proposal_part(&init.clone().to_protobuf().encode_to_vec()).unwrap(),
+        init
+    );
+}
@@ -13,7 +13,7 @@ mod tests;
 pub use behaviour::{Behaviour, Builder, Event};
 pub use client::Client;
 
-use crate::peers::Peer;
+use crate::peers::{Peer, Penalty};
 use crate::EmptyResultSender;
 
 /// Commands that can be sent to the p2p behaviour.
@@ -45,6 +45,17 @@ pub enum Command<ApplicationCommand> {
         peer_id: PeerId,
         sender: oneshot::Sender<()>,
     },
+    /// Punish a misbehaving peer, responding with `true` if the peer got
+    /// banned.
+    Punish {
+        peer_id: PeerId,
+        penalty: Penalty,
+        sender: oneshot::Sender<bool>,
+    },
+    /// Get the peers currently connected to us.
+    GetConnectedPeers {
+        sender: oneshot::Sender<HashMap<PeerId, Peer>>,
+    },
     /// Application-specific command.
     Application(ApplicationCommand),
     /// For testing purposes only
@@ -41,7 +41,7 @@ mod builder;
 pub use builder::Builder;
 
 use crate::core::Config;
-use crate::peers::{Connectivity, Direction, KeyedNetworkGroup, Peer, PeerSet};
+use crate::peers::{Connectivity, Direction, KeyedNetworkGroup, Peer, PeerSet, Penalty};
 use crate::secret::Secret;
 
 /// The default kademlia protocol name for a given Starknet chain.
@@ -132,6 +132,7 @@ where
 
         self.check_duplicate_connection(peer)?;
         self.prevent_evicted_peer_reconnections(peer)?;
+        self.prevent_banned_peer_connections(peer)?;
 
         // Is the peer connecting over a relay?
         let is_relayed = remote_addr.iter().any(|p| p == Protocol::P2pCircuit);
@@ -175,6 +176,7 @@ where
 
         self.check_duplicate_connection(peer)?;
         self.prevent_evicted_peer_reconnections(peer)?;
+        self.prevent_banned_peer_connections(peer)?;
 
         self.inner.handle_established_outbound_connection(
             connection_id,
@@ -452,6 +454,7 @@ where
                 // hole-punching.
 
                 self.prevent_evicted_peer_reconnections(peer_id)?;
+                self.prevent_banned_peer_connections(peer_id)?;
 
                 if self.outbound_peers().count() >= self.cfg.max_outbound_peers {
                     self.evict_outbound_peer()?;
@@ -720,6 +723,15 @@ where
         }
     }
 
+    /// Prevent banned peers from connecting.
+    fn prevent_banned_peer_connections(&self, peer_id: PeerId) -> Result<(), ConnectionDenied> {
+        if self.is_banned(peer_id) {
+            tracing::debug!(%peer_id, "Banned peer attempting to connect, disconnecting");
+            return Err(ConnectionDenied::new("peer is banned"));
+        }
+        Ok(())
+    }
+
     /// Get the IP address from a multiaddr, or disconnect the peer if it
     /// doesn't have one.
     fn get_ip(addr: &Multiaddr) -> Result<IpAddr, ConnectionDenied> {
@@ -754,6 +766,40 @@ where
         });
     }
 
+    /// Punish a misbehaving peer, see [`PeerSet::punish`]. Banned peers are
+    /// disconnected and removed from the DHT. Returns `true` if the peer got
+    /// banned by this penalty.
+    pub fn punish(&mut self, peer_id: PeerId, penalty: Penalty) -> bool {
+        if !self.peers.punish(peer_id, penalty, Instant::now()) {
+            return false;
+        }
+
+        tracing::debug!(%peer_id, ?penalty, "Banning peer");
+        if let Some(kad) = self.inner.kademlia.as_mut() {
+            kad.remove_peer(&peer_id);
+        }
+        if self
+            .peers
+            .get(peer_id)
+            .is_some_and(|peer| peer.is_connected())
+        {
+            self.peers.update(peer_id, |peer| {
+                peer.connectivity = Connectivity::Disconnecting {
+                    connected_at: peer.connected_at(),
+                };
+            });
+            self.pending_events.push_back(ToSwarm::CloseConnection {
+                peer_id,
+                connection: CloseConnection::All,
+            });
+        }
+        true
+    }
+
+    pub fn is_banned(&self, peer_id: PeerId) -> bool {
+        self.peers.is_banned(peer_id, Instant::now())
+    }
+
     pub fn peers(&self) -> impl Iterator<Item = (PeerId, &Peer)> {
         self.peers.iter()
     }
@@ -1,10 +1,11 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Context;
 use libp2p::{Multiaddr, PeerId};
 use tokio::sync::{mpsc, oneshot};
 
 use crate::core::Command;
+use crate::peers::{Peer, Penalty};
 #[cfg(test)]
 use crate::test_utils;
 
@@ -94,6 +95,34 @@ impl<C> Client<C> {
         receiver.await.expect("Sender not to be dropped")
     }
 
+    /// Punish a misbehaving peer.
+    ///
+    /// Peers which repeatedly misbehave or serve provably invalid data get
+    /// banned: they are disconnected and can't connect again while the ban
+    /// lasts. Returns `true` if the peer got banned by this penalty.
+    pub async fn punish(&self, peer_id: PeerId, penalty: Penalty) -> bool {
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(Command::Punish {
+                peer_id,
+                penalty,
+                sender,
+            })
+            .await
+            .expect("Command receiver not to be dropped");
+        receiver.await.expect("Sender not to be dropped")
+    }
+
+    /// The peers currently connected to us.
+    pub async fn connected_peers(&self) -> HashMap<PeerId, Peer> {
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(Command::GetConnectedPeers { sender })
+            .await
+            .expect("Command receiver not to be dropped");
+        receiver.await.expect("Sender not to be dropped")
+    }
+
     #[cfg(test)]
     pub(crate) fn for_test(&self) -> test_utils::core::Client<C> {
         test_utils::core::Client::new(self.sender.clone())
@@ -7,6 +7,7 @@ use libp2p::multiaddr::Protocol;
 use super::TestEvent;
 use crate::core::config::RateLimit;
 use crate::core::Config;
+use crate::peers::Penalty;
 use crate::test_utils::peer::TestPeer;
 use crate::test_utils::{consume_accumulated_events, consume_all_events_forever, wait_for_event};
 
@@ -546,6 +547,51 @@ async fn evicted_peer_reconnection() {
     .await;
 }
 
+/// Ensure that banned peers are disconnected and can't reconnect.
+#[test_log::test(tokio::test)]
+async fn banned_peer() {
+    let mut peer1 = TestPeer::default();
+    let mut peer2 = TestPeer::default();
+
+    let addr1 = peer1.start_listening().await.unwrap();
+    tracing::info!(%peer1.peer_id, %addr1);
+    let addr2 = peer2.start_listening().await.unwrap();
+    tracing::info!(%peer2.peer_id, %addr2);
+
+    peer1
+        .client
+        .dial(peer2.peer_id, addr2.clone())
+        .await
+        .unwrap();
+    consume_accumulated_events(&mut peer1.test_event_receiver).await;
+
+    // Minor penalties only ban after repeated offenses.
+    assert!(!peer1.client.punish(peer2.peer_id, Penalty::Minor).await);
+    assert!(peer1.connected().await.contains_key(&peer2.peer_id));
+
+    assert!(peer1.client.punish(peer2.peer_id, Penalty::Invalid).await);
+    wait_for_event(&mut peer1.test_event_receiver, |event| match event {
+        TestEvent::ConnectionClosed { remote } if remote == peer2.peer_id => Some(()),
+        _ => None,
+    })
+    .await;
+
+    // Banned peers can't be dialed.
+    let result = peer1.client.dial(peer2.peer_id, addr2).await;
+    assert!(result.is_err());
+
+    consume_accumulated_events(&mut peer2.test_event_receiver).await;
+
+    // Banned peers can't connect either.
+    peer2.client.dial(peer1.peer_id, addr1).await.unwrap();
+    wait_for_event(&mut peer2.test_event_receiver, |event| match event {
+        TestEvent::ConnectionClosed { remote } if remote == peer1.peer_id => Some(()),
+        _ => None,
+    })
+    .await;
+    assert!(peer1.connected().await.is_empty());
+}
+
 /// Test that peers can only connect if they are whitelisted.
 #[test_log::test(tokio::test)]
 async fn ip_whitelist() {
@@ -25,6 +25,7 @@ mod transport;
 use builder::Builder;
 pub use libp2p;
 pub use peer_data::PeerData;
+pub use peers::{Direction, Peer, Penalty};
 
 /// Creates a new sync P2P network.
 pub fn new_sync(
@@ -37,8 +38,8 @@ pub fn new_sync(
     mpsc::Receiver<sync::Event>,
     main_loop::MainLoop<sync::Behaviour>,
 ) {
-    Builder::new(keypair, core_config, chain_id)
-        .app_behaviour(sync::Behaviour::new(sync_config))
+    Builder::new(keypair.clone(), core_config, chain_id)
+        .app_behaviour(sync::Behaviour::new(keypair, chain_id, sync_config))
         .build()
 }
 
@@ -46,17 +47,15 @@ pub fn new_sync(
 pub fn new_consensus(
     keypair: Keypair,
     core_config: core::Config,
-    _consensus_config: consensus::Config,
+    consensus_config: consensus::Config,
     chain_id: ChainId,
 ) -> (
-    core::Client<sync::Command>,
-    mpsc::Receiver<sync::Event>,
-    main_loop::MainLoop<sync::Behaviour>,
+    core::Client<consensus::Command>,
+    mpsc::Receiver<consensus::Event>,
+    main_loop::MainLoop<consensus::Behaviour>,
 ) {
-    // TODO remove allow when behaviour is built properly
-    #[allow(unreachable_code)]
-    Builder::new(keypair, core_config, chain_id)
-        .app_behaviour(todo!())
+    Builder::new(keypair.clone(), core_config, chain_id)
+        .app_behaviour(consensus::Behaviour::new(keypair, consensus_config))
         .build()
 }
 
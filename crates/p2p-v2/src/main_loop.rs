@@ -341,9 +341,14 @@ where
                                 use libp2p::kad::GetClosestPeersOk;
 
                                 let result = match result {
-                                    Ok(GetClosestPeersOk { peers, .. }) => {
-                                        Ok(peers.into_iter().map(|p| p.peer_id).collect())
-                                    }
+                                    Ok(GetClosestPeersOk { peers, .. }) => Ok(peers
+                                        .into_iter()
+                                        .map(|p| p.peer_id)
+                                        // Other peers don't know whom we banned.
+                                        .filter(|&peer_id| {
+                                            !self.swarm.behaviour().is_banned(peer_id)
+                                        })
+                                        .collect()),
                                     Err(e) => Err(e.into()),
                                 };
 
@@ -501,6 +506,26 @@ where
                 self.swarm.behaviour_mut().not_useful(peer_id);
                 let _ = sender.send(());
             }
+            // Punishes a misbehaving peer.
+            Command::Punish {
+                peer_id,
+                penalty,
+                sender,
+            } => {
+                let banned = self.swarm.behaviour_mut().punish(peer_id, penalty);
+                let _ = sender.send(banned);
+            }
+            // Lists the peers currently connected to the swarm.
+            Command::GetConnectedPeers { sender } => {
+                let peers = self
+                    .swarm
+                    .behaviour()
+                    .peers()
+                    .filter(|(_, peer)| peer.is_connected())
+                    .map(|(peer_id, peer)| (peer_id, peer.clone()))
+                    .collect();
+                let _ = sender.send(peers);
+            }
             // Application-specific commands.
             Command::Application(application_command) => {
                 self.swarm
@@ -8,6 +8,18 @@ use sha3::{Digest, Sha3_256};
 
 use crate::secret::Secret;
 
+/// A peer's negative score halves over this period, so peers recover from
+/// occasional misbehaviour.
+const SCORE_HALF_LIFE: Duration = Duration::from_secs(10 * 60);
+/// How much a [`Penalty::Minor`] lowers the score of a peer.
+const MINOR_PENALTY: f64 = -25.0;
+/// Peers whose score drops to this value get temporarily banned.
+const BAN_THRESHOLD: f64 = -100.0;
+/// How long a temporary ban lasts.
+const TEMPORARY_BAN_DURATION: Duration = Duration::from_secs(60 * 60);
+/// Scores above this value are considered recovered and are forgotten.
+const FORGET_THRESHOLD: f64 = -1.0;
+
 #[derive(Debug, Clone)]
 pub struct Peer {
     pub connectivity: Connectivity,
@@ -86,11 +98,66 @@ pub enum Direction {
     Outbound,
 }
 
+/// How severely a peer is punished for misbehaving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Penalty {
+    /// The peer served data which is wrong, but not provably so, e.g. it is
+    /// incomplete or inconsistent with data from other peers. Repeated
+    /// offenses lead to a temporary ban.
+    Minor,
+    /// The peer served data which is provably invalid, e.g. a block with a
+    /// bad hash or signature. The peer is banned permanently.
+    Invalid,
+}
+
+/// The reputation of a peer, which is kept even after the peer is dropped
+/// from the [`PeerSet`].
+#[derive(Debug, Clone, Copy)]
+struct Reputation {
+    /// Non-positive score which decays towards zero over time.
+    score: f64,
+    /// When the score was last updated.
+    updated_at: Instant,
+    ban: Option<Ban>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ban {
+    Temporary { until: Instant },
+    Permanent,
+}
+
+impl Reputation {
+    fn new(now: Instant) -> Self {
+        Self {
+            score: 0.0,
+            updated_at: now,
+            ban: None,
+        }
+    }
+
+    fn score(&self, now: Instant) -> f64 {
+        let half_lives = now.saturating_duration_since(self.updated_at).as_secs_f64()
+            / SCORE_HALF_LIFE.as_secs_f64();
+        self.score * 0.5f64.powf(half_lives)
+    }
+
+    fn is_banned(&self, now: Instant) -> bool {
+        match self.ban {
+            Some(Ban::Temporary { until }) => now < until,
+            Some(Ban::Permanent) => true,
+            None => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct PeerSet {
     peers: HashMap<PeerId, Peer>,
     /// How long to keep disconnected peers in the set.
     retention_period: Duration,
+    /// Reputations of misbehaving peers, whether they are in the set or not.
+    reputations: HashMap<PeerId, Reputation>,
 }
 
 impl PeerSet {
@@ -98,6 +165,7 @@ impl PeerSet {
         Self {
             peers: HashMap::new(),
             retention_period,
+            reputations: HashMap::new(),
         }
     }
 
@@ -143,6 +211,49 @@ impl PeerSet {
             }
         })
     }
+
+    /// Lower the score of a misbehaving peer, banning it if the score drops
+    /// too low. Returns `true` if the peer got banned by this penalty.
+    pub fn punish(&mut self, peer_id: PeerId, penalty: Penalty, now: Instant) -> bool {
+        // Forget peers that have recovered from their past misbehaviour.
+        self.reputations.retain(|_, reputation| {
+            reputation.is_banned(now) || reputation.score(now) < FORGET_THRESHOLD
+        });
+
+        let reputation = self
+            .reputations
+            .entry(peer_id)
+            .or_insert_with(|| Reputation::new(now));
+        let was_banned = reputation.is_banned(now);
+
+        match penalty {
+            Penalty::Invalid => {
+                // Temporary bans are upgraded as well.
+                reputation.ban = Some(Ban::Permanent);
+                !was_banned
+            }
+            Penalty::Minor if was_banned => false,
+            Penalty::Minor => {
+                reputation.score = reputation.score(now) + MINOR_PENALTY;
+                reputation.updated_at = now;
+                if reputation.score > BAN_THRESHOLD {
+                    return false;
+                }
+                // The peer starts with a clean slate once the ban expires.
+                reputation.score = 0.0;
+                reputation.ban = Some(Ban::Temporary {
+                    until: now + TEMPORARY_BAN_DURATION,
+                });
+                true
+            }
+        }
+    }
+
+    pub fn is_banned(&self, peer_id: PeerId, now: Instant) -> bool {
+        self.reputations
+            .get(&peer_id)
+            .is_some_and(|reputation| reputation.is_banned(now))
+    }
 }
 
 /// A network group that is keyed by a secret, calculated as SHA3(secret || 16
@@ -165,3 +276,58 @@ impl KeyedNetworkGroup {
         Self(hasher.finalize().into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minor_penalties_decay_and_lead_to_temporary_ban() {
+        let mut peers = PeerSet::new(Duration::from_secs(60));
+        let peer_id = PeerId::random();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(!peers.punish(peer_id, Penalty::Minor, now));
+        }
+        let reputation = peers.reputations[&peer_id];
+        assert_eq!(reputation.score(now), -75.0);
+        assert_eq!(reputation.score(now + SCORE_HALF_LIFE), -37.5);
+
+        // The score has recovered enough to take two more penalties, but not three.
+        let later = now + SCORE_HALF_LIFE;
+        assert!(!peers.punish(peer_id, Penalty::Minor, later));
+        assert!(!peers.punish(peer_id, Penalty::Minor, later));
+        assert!(!peers.is_banned(peer_id, later));
+        assert!(peers.punish(peer_id, Penalty::Minor, later));
+        assert!(peers.is_banned(peer_id, later));
+        assert!(peers.is_banned(peer_id, later + TEMPORARY_BAN_DURATION / 2));
+        assert!(!peers.is_banned(peer_id, later + TEMPORARY_BAN_DURATION));
+        assert_eq!(
+            peers.reputations[&peer_id].score(later + TEMPORARY_BAN_DURATION),
+            0.0
+        );
+    }
+
+    #[test]
+    fn invalid_data_leads_to_permanent_ban() {
+        let mut peers = PeerSet::new(Duration::from_secs(60));
+        let peer_id = PeerId::random();
+        let now = Instant::now();
+
+        assert!(peers.punish(peer_id, Penalty::Invalid, now));
+        assert!(peers.is_banned(peer_id, now + 1000 * TEMPORARY_BAN_DURATION));
+
+        // A temporary ban is upgraded to a permanent one.
+        let peer_id = PeerId::random();
+        for _ in 0..4 {
+            peers.punish(peer_id, Penalty::Minor, now);
+        }
+        assert!(peers.is_banned(peer_id, now));
+        assert!(!peers.punish(peer_id, Penalty::Invalid, now));
+        assert!(peers.is_banned(peer_id, now + TEMPORARY_BAN_DURATION));
+
+        // Unrelated peers are not affected.
+        assert!(!peers.is_banned(PeerId::random(), now));
+    }
+}
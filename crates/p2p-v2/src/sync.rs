@@ -7,6 +7,7 @@ use libp2p::PeerId;
 use p2p_proto::class::{ClassesRequest, ClassesResponse};
 use p2p_proto::event::{EventsRequest, EventsResponse};
 use p2p_proto::header::{BlockHeadersRequest, BlockHeadersResponse};
+use p2p_proto::pending::{NewPendingBlock, PendingBlockRequest, PendingBlockResponse};
 use p2p_proto::state::{StateDiffsRequest, StateDiffsResponse};
 use p2p_proto::transaction::{TransactionsRequest, TransactionsResponse};
 use p2p_stream::OutboundRequestId;
@@ -19,7 +20,7 @@ pub(crate) mod protocol;
 #[cfg(test)]
 mod tests;
 
-pub use behaviour::{Behaviour, Builder};
+pub use behaviour::{pending_blocks_topic, Behaviour, Builder};
 pub use client::Client;
 
 /// Commands for the sync behaviour.
@@ -60,6 +61,19 @@ pub enum Command {
         request: EventsRequest,
         sender: oneshot::Sender<anyhow::Result<ResponseReceiver<std::io::Result<EventsResponse>>>>,
     },
+    /// Request the pending block from a peer.
+    SendPendingBlockRequest {
+        peer_id: PeerId,
+        request: PendingBlockRequest,
+        sender: oneshot::Sender<
+            anyhow::Result<ResponseReceiver<std::io::Result<PendingBlockResponse>>>,
+        >,
+    },
+    /// Gossip that the pending block changed.
+    BroadcastNewPendingBlock {
+        announcement: NewPendingBlock,
+        sender: oneshot::Sender<anyhow::Result<()>>,
+    },
 }
 
 /// Events emitted by the sync behaviour.
@@ -90,6 +104,18 @@ pub enum Event {
         request: EventsRequest,
         channel: ResponseSender<EventsResponse>,
     },
+    InboundPendingBlockRequest {
+        from: PeerId,
+        request: PendingBlockRequest,
+        channel: ResponseSender<PendingBlockResponse>,
+    },
+    /// A peer announced that its pending block changed.
+    NewPendingBlock {
+        /// The peer that published the announcement, which is not
+        /// necessarily the peer we received it from.
+        from: PeerId,
+        announcement: NewPendingBlock,
+    },
 }
 
 /// State of the sync behaviour.
@@ -122,6 +148,10 @@ pub struct PendingRequests {
         OutboundRequestId,
         oneshot::Sender<anyhow::Result<ResponseReceiver<std::io::Result<EventsResponse>>>>,
     >,
+    pub pending_blocks: HashMap<
+        OutboundRequestId,
+        oneshot::Sender<anyhow::Result<ResponseReceiver<std::io::Result<PendingBlockResponse>>>>,
+    >,
 }
 
 /// Configuration for the sync P2P network.
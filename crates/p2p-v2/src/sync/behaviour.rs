@@ -1,4 +1,10 @@
+use libp2p::gossipsub::{self, IdentTopic, MessageAcceptance};
+use libp2p::identity::Keypair;
 use libp2p::swarm::NetworkBehaviour;
+use p2p_proto::pending::NewPendingBlock;
+use p2p_proto::{proto, ToProtobuf, TryFromProtobuf};
+use pathfinder_common::ChainId;
+use prost::Message;
 use tokio::sync::mpsc;
 
 use super::protocol::codec;
@@ -9,6 +15,11 @@ mod builder;
 
 pub use builder::Builder;
 
+/// Gossipsub topic for pending block announcements on a given Starknet chain.
+pub fn pending_blocks_topic(chain_id: ChainId) -> IdentTopic {
+    IdentTopic::new(format!("pending_blocks/{}", chain_id.as_str()))
+}
+
 /// The sync P2P network behaviour.
 #[derive(NetworkBehaviour)]
 pub struct Behaviour {
@@ -17,15 +28,19 @@ pub struct Behaviour {
     state_diff_sync: p2p_stream::Behaviour<codec::StateDiffs>,
     transaction_sync: p2p_stream::Behaviour<codec::Transactions>,
     event_sync: p2p_stream::Behaviour<codec::Events>,
+    pending_block_sync: p2p_stream::Behaviour<codec::PendingBlock>,
+    pending_block_gossip: gossipsub::Behaviour,
 }
 
 impl Behaviour {
-    pub fn new(config: Config) -> Self {
-        Builder::new(config).build()
+    /// Pending block announcements are signed with `keypair`, and only
+    /// exchanged with peers on `chain_id`.
+    pub fn new(keypair: Keypair, chain_id: ChainId, config: Config) -> Self {
+        Builder::new(keypair, chain_id, config).build()
     }
 
-    pub fn builder(config: Config) -> Builder {
-        Builder::new(config)
+    pub fn builder(keypair: Keypair, chain_id: ChainId, config: Config) -> Builder {
+        Builder::new(keypair, chain_id, config)
     }
 }
 
@@ -88,6 +103,38 @@ impl ApplicationBehaviour for Behaviour {
                 let request_id = self.event_sync.send_request(&peer_id, request);
                 state.pending_requests.events.insert(request_id, sender);
             }
+            SendPendingBlockRequest {
+                peer_id,
+                request,
+                sender,
+            } => {
+                tracing::debug!(?request, "Sending pending block sync request");
+                let request_id = self.pending_block_sync.send_request(&peer_id, request);
+                state
+                    .pending_requests
+                    .pending_blocks
+                    .insert(request_id, sender);
+            }
+            BroadcastNewPendingBlock {
+                announcement,
+                sender,
+            } => {
+                tracing::debug!(?announcement, "Broadcasting new pending block");
+                let data = announcement.to_protobuf().encode_to_vec();
+                // The only subscription is the pending blocks topic of our chain
+                let topic = self
+                    .pending_block_gossip
+                    .topics()
+                    .next()
+                    .cloned()
+                    .expect("Subscribed to the pending blocks topic");
+                let result = self
+                    .pending_block_gossip
+                    .publish(topic.clone(), data)
+                    .map(|_| ())
+                    .map_err(|e| anyhow::anyhow!("Publishing to {topic} failed: {e}"));
+                let _ = sender.send(result);
+            }
         }
     }
 
@@ -248,6 +295,71 @@ impl ApplicationBehaviour for Behaviour {
                     .expect("Event sync request still to be pending")
                     .send(Ok(channel));
             }
+            BehaviourEvent::PendingBlockSync(P2PStreamEvent::InboundRequest {
+                request_id,
+                request,
+                peer,
+                channel,
+            }) => {
+                tracing::debug!(?request, %peer, %request_id, "Received pending block sync request");
+                event_sender
+                    .send(sync::Event::InboundPendingBlockRequest {
+                        from: peer,
+                        request,
+                        channel,
+                    })
+                    .await
+                    .expect("Event receiver not to be dropped");
+            }
+            BehaviourEvent::PendingBlockSync(
+                P2PStreamEvent::OutboundRequestSentAwaitingResponses {
+                    request_id,
+                    peer,
+                    channel,
+                },
+            ) => {
+                tracing::debug!(%peer, %request_id, "Pending block sync request sent");
+                let _ = state
+                    .pending_requests
+                    .pending_blocks
+                    .remove(&request_id)
+                    .expect("Pending block sync request still to be pending")
+                    .send(Ok(channel));
+            }
+            BehaviourEvent::PendingBlockGossip(gossipsub::Event::Message {
+                propagation_source,
+                message_id,
+                message,
+            }) => {
+                // Signed messages always carry their author
+                let from = message.source.unwrap_or(propagation_source);
+
+                match decode_new_pending_block(&message.data) {
+                    Ok(announcement) => {
+                        tracing::debug!(%from, %message_id, ?announcement, "Received new pending block");
+                        let _ = self.pending_block_gossip.report_message_validation_result(
+                            &message_id,
+                            &propagation_source,
+                            MessageAcceptance::Accept,
+                        );
+                        event_sender
+                            .send(sync::Event::NewPendingBlock { from, announcement })
+                            .await
+                            .expect("Event receiver not to be dropped");
+                    }
+                    Err(error) => {
+                        tracing::debug!(%propagation_source, %message_id, %error, "Rejecting invalid pending block announcement");
+                        let _ = self.pending_block_gossip.report_message_validation_result(
+                            &message_id,
+                            &propagation_source,
+                            MessageAcceptance::Reject,
+                        );
+                    }
+                }
+            }
+            BehaviourEvent::PendingBlockGossip(event) => {
+                tracing::trace!(?event, "Ignoring gossipsub event");
+            }
             BehaviourEvent::HeaderSync(P2PStreamEvent::OutboundFailure {
                 request_id,
                 error,
@@ -303,9 +415,31 @@ impl ApplicationBehaviour for Behaviour {
                     let _ = sender.send(Err(error.into()));
                 }
             }
+            BehaviourEvent::PendingBlockSync(P2PStreamEvent::OutboundFailure {
+                request_id,
+                error,
+                ..
+            }) => {
+                tracing::warn!(
+                    ?request_id,
+                    ?error,
+                    "Outbound pending block sync request failed"
+                );
+                if let Some(sender) = state.pending_requests.pending_blocks.remove(&request_id) {
+                    let _ = sender.send(Err(error.into()));
+                }
+            }
             _ => {
                 tracing::warn!("Unhandled event: {:?}", event);
             }
         }
     }
 }
+
+pub(crate) fn decode_new_pending_block(data: &[u8]) -> anyhow::Result<NewPendingBlock> {
+    let announcement = proto::pending::NewPendingBlock::decode(data)?;
+    Ok(NewPendingBlock::try_from_protobuf(
+        announcement,
+        "NewPendingBlock",
+    )?)
+}
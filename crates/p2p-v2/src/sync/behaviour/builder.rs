@@ -1,26 +1,36 @@
-use crate::sync::behaviour::Behaviour;
+use libp2p::gossipsub;
+use libp2p::identity::Keypair;
+use pathfinder_common::ChainId;
+
+use crate::sync::behaviour::{pending_blocks_topic, Behaviour};
 use crate::sync::protocol::codec;
 use crate::sync::Config;
 
 /// Builder for the sync P2P network behaviour.
 pub struct Builder {
+    keypair: Keypair,
+    chain_id: ChainId,
     cfg: Config,
     header_sync: Option<p2p_stream::Behaviour<codec::Headers>>,
     class_sync: Option<p2p_stream::Behaviour<codec::Classes>>,
     state_diff_sync: Option<p2p_stream::Behaviour<codec::StateDiffs>>,
     transaction_sync: Option<p2p_stream::Behaviour<codec::Transactions>>,
     event_sync: Option<p2p_stream::Behaviour<codec::Events>>,
+    pending_block_sync: Option<p2p_stream::Behaviour<codec::PendingBlock>>,
 }
 
 impl Builder {
-    pub fn new(cfg: Config) -> Self {
+    pub fn new(keypair: Keypair, chain_id: ChainId, cfg: Config) -> Self {
         Self {
+            keypair,
+            chain_id,
             cfg,
             header_sync: None,
             class_sync: None,
             state_diff_sync: None,
             transaction_sync: None,
             event_sync: None,
+            pending_block_sync: None,
         }
     }
 
@@ -61,14 +71,25 @@ impl Builder {
         self
     }
 
+    pub fn pending_block_sync_behaviour(
+        mut self,
+        behaviour: p2p_stream::Behaviour<codec::PendingBlock>,
+    ) -> Self {
+        self.pending_block_sync = Some(behaviour);
+        self
+    }
+
     pub fn build(self) -> Behaviour {
         let Self {
+            keypair,
+            chain_id,
             cfg,
             header_sync,
             class_sync,
             state_diff_sync,
             transaction_sync,
             event_sync,
+            pending_block_sync,
         } = self;
 
         let p2p_stream_cfg = p2p_stream::Config::default()
@@ -86,6 +107,9 @@ impl Builder {
             .unwrap_or_else(|| p2p_stream::Behaviour::<codec::Transactions>::new(p2p_stream_cfg));
         let event_sync = event_sync
             .unwrap_or_else(|| p2p_stream::Behaviour::<codec::Events>::new(p2p_stream_cfg));
+        let pending_block_sync = pending_block_sync
+            .unwrap_or_else(|| p2p_stream::Behaviour::<codec::PendingBlock>::new(p2p_stream_cfg));
+        let pending_block_gossip = pending_block_gossip(keypair, chain_id);
 
         Behaviour {
             header_sync,
@@ -93,6 +117,35 @@ impl Builder {
             state_diff_sync,
             transaction_sync,
             event_sync,
+            pending_block_sync,
+            pending_block_gossip,
         }
     }
 }
+
+fn pending_block_gossip(keypair: Keypair, chain_id: ChainId) -> gossipsub::Behaviour {
+    let config = gossipsub::ConfigBuilder::default()
+        .validation_mode(gossipsub::ValidationMode::Strict)
+        // Announcements are only forwarded after they pass our own validation, see
+        // `Behaviour::handle_event`
+        .validate_messages()
+        // Every node re-announces the pending block it got from its peers, so the
+        // message ID is derived from the content to stop identical announcements
+        // from flooding the network.
+        .message_id_fn(|message| {
+            use sha3::{Digest, Sha3_256};
+            gossipsub::MessageId::from(Sha3_256::digest(&message.data).to_vec())
+        })
+        .build()
+        .expect("Valid gossipsub config");
+
+    let mut gossipsub =
+        gossipsub::Behaviour::new(gossipsub::MessageAuthenticity::Signed(keypair), config)
+            .expect("Valid gossipsub behaviour");
+
+    gossipsub
+        .subscribe(&pending_blocks_topic(chain_id))
+        .expect("Subscribing to pending blocks topic");
+
+    gossipsub
+}
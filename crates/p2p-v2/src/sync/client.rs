@@ -3,6 +3,7 @@ use libp2p::PeerId;
 use p2p_proto::class::{ClassesRequest, ClassesResponse};
 use p2p_proto::event::{EventsRequest, EventsResponse};
 use p2p_proto::header::{BlockHeadersRequest, BlockHeadersResponse};
+use p2p_proto::pending::{NewPendingBlock, PendingBlockRequest, PendingBlockResponse};
 use p2p_proto::state::{StateDiffsRequest, StateDiffsResponse};
 use p2p_proto::transaction::{TransactionsRequest, TransactionsResponse};
 use tokio::sync::{mpsc, oneshot};
@@ -89,4 +90,33 @@ impl Client {
         EventsRequest,
         EventsResponse
     );
+
+    impl_send!(
+        send_pending_block_request,
+        SendPendingBlockRequest,
+        PendingBlockRequest,
+        PendingBlockResponse
+    );
+
+    /// Gossips that the pending block changed to the peers subscribed to the
+    /// pending blocks topic.
+    ///
+    /// Fails if there are no such peers or if the same announcement was
+    /// already seen.
+    pub async fn broadcast_new_pending_block(
+        &self,
+        announcement: NewPendingBlock,
+    ) -> anyhow::Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(core::Command::Application(
+                Command::BroadcastNewPendingBlock {
+                    announcement,
+                    sender,
+                },
+            ))
+            .await
+            .expect("Command receiver not to be dropped");
+        receiver.await.expect("Sender not to be dropped")
+    }
 }
@@ -1,17 +1,17 @@
 //! _High level_ client for p2p interaction.
 //! Frees the caller from managing peers manually.
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use futures::channel::mpsc as fmpsc;
 use futures::{Stream, StreamExt, TryStreamExt};
 use libp2p::PeerId;
 use p2p_proto::class::{ClassesRequest, ClassesResponse};
 use p2p_proto::common::{Direction, Iteration};
 use p2p_proto::event::{EventsRequest, EventsResponse};
 use p2p_proto::header::{BlockHeadersRequest, BlockHeadersResponse};
+use p2p_proto::pending::{PendingBlockRequest, PendingBlockResponse};
 use p2p_proto::state::{
     ContractDiff,
     ContractStoredValue,
@@ -31,6 +31,7 @@ use tokio::sync::{mpsc, RwLock};
 mod fixtures;
 #[cfg(test)]
 mod tests;
+mod throughput;
 pub mod traits;
 
 use traits::{
@@ -38,18 +39,23 @@ use traits::{
     ClassStream,
     EventStream,
     HeaderStream,
+    PeerReputation,
+    PendingBlockClient,
     StateDiffStream,
     StreamItem,
     TransactionStream,
 };
 
 use crate::peer_data::PeerData;
+use crate::peers::{Peer, Penalty};
 use crate::sync::client::conv::{CairoDefinition, FromDto, SierraDefinition, TryFromDto};
+use crate::sync::client::peer_agnostic::throughput::{Protocol, Throughput};
 use crate::sync::client::types::{
     ClassDefinition,
     ClassDefinitionsError,
     EventsForBlockByTransaction,
     EventsResponseStreamFailure,
+    PendingBlock,
     Receipt,
     StateDiffsError,
     TransactionData,
@@ -60,6 +66,7 @@ use crate::{core, sync};
 pub struct Client {
     inner: sync::Client,
     peers: Arc<RwLock<Decaying<HashSet<PeerId>>>>,
+    throughput: Throughput,
 }
 
 impl Client {
@@ -67,9 +74,15 @@ impl Client {
         Self {
             inner,
             peers: Default::default(),
+            throughput: Default::default(),
         }
     }
 
+    /// The peers currently connected to us.
+    pub async fn connected_peers(&self) -> HashMap<PeerId, Peer> {
+        self.core_client().connected_peers().await
+    }
+
     fn core_client(&self) -> core::Client<sync::Command> {
         core::Client::new(self.inner.sender.clone(), self.inner.local_peer_id)
     }
@@ -126,6 +139,15 @@ impl Client {
 
         peers
     }
+
+    /// Random peers ordered by their `protocol` [throughput](Throughput::rank),
+    /// so that concurrent range requests are spread over the fastest peers.
+    async fn get_ranked_peers(&self, protocol: Protocol) -> Vec<PeerId> {
+        let mut peers = self.get_random_peers().await;
+        let connected = self.connected_peers().await;
+        self.throughput.rank(protocol, &mut peers, &connected);
+        peers
+    }
 }
 
 impl HeaderStream for Client {
@@ -136,6 +158,7 @@ impl HeaderStream for Client {
         reverse: bool,
     ) -> impl Stream<Item = PeerData<SignedBlockHeader>> {
         let inner = self.inner.clone();
+        let throughput = self.throughput.clone();
         let outer = self;
         header_stream::make(
             start,
@@ -143,11 +166,20 @@ impl HeaderStream for Client {
             reverse,
             move || {
                 let outer = outer.clone();
-                async move { outer.get_random_peers().await }
+                async move { outer.get_ranked_peers(Protocol::Headers).await }
             },
             move |peer, request| {
                 let inner = inner.clone();
-                async move { inner.send_headers_request(peer, request).await }
+                let throughput = throughput.clone();
+                async move {
+                    throughput
+                        .measure(
+                            peer,
+                            Protocol::Headers,
+                            inner.send_headers_request(peer, request),
+                        )
+                        .await
+                }
             },
         )
     }
@@ -161,6 +193,7 @@ impl TransactionStream for Client {
         transaction_count_stream: impl Stream<Item = anyhow::Result<usize>> + Send + 'static,
     ) -> impl Stream<Item = StreamItem<(TransactionData, BlockNumber)>> {
         let inner = self.inner.clone();
+        let throughput = self.throughput.clone();
         let outer = self;
         transaction_stream::make(
             start,
@@ -168,11 +201,20 @@ impl TransactionStream for Client {
             transaction_count_stream,
             move || {
                 let outer = outer.clone();
-                async move { outer.get_random_peers().await }
+                async move { outer.get_ranked_peers(Protocol::Transactions).await }
             },
             move |peer, request| {
                 let inner = inner.clone();
-                async move { inner.send_transactions_request(peer, request).await }
+                let throughput = throughput.clone();
+                async move {
+                    throughput
+                        .measure(
+                            peer,
+                            Protocol::Transactions,
+                            inner.send_transactions_request(peer, request),
+                        )
+                        .await
+                }
             },
         )
     }
@@ -191,6 +233,7 @@ impl StateDiffStream for Client {
         state_diff_length_stream: impl Stream<Item = anyhow::Result<usize>> + Send + 'static,
     ) -> impl Stream<Item = StreamItem<(StateUpdateData, BlockNumber)>> {
         let inner = self.inner.clone();
+        let throughput = self.throughput.clone();
         let outer = self;
         state_diff_stream::make(
             start,
@@ -198,11 +241,20 @@ impl StateDiffStream for Client {
             state_diff_length_stream,
             move || {
                 let outer = outer.clone();
-                async move { outer.get_random_peers().await }
+                async move { outer.get_ranked_peers(Protocol::StateDiffs).await }
             },
             move |peer, request| {
                 let inner = inner.clone();
-                async move { inner.send_state_diffs_request(peer, request).await }
+                let throughput = throughput.clone();
+                async move {
+                    throughput
+                        .measure(
+                            peer,
+                            Protocol::StateDiffs,
+                            inner.send_state_diffs_request(peer, request),
+                        )
+                        .await
+                }
             },
         )
     }
@@ -216,6 +268,7 @@ impl ClassStream for Client {
         declared_class_counts_stream: impl Stream<Item = anyhow::Result<usize>> + Send + 'static,
     ) -> impl Stream<Item = StreamItem<ClassDefinition>> {
         let inner = self.inner.clone();
+        let throughput = self.throughput.clone();
         let outer = self;
         class_definition_stream::make(
             start,
@@ -223,11 +276,20 @@ impl ClassStream for Client {
             declared_class_counts_stream,
             move || {
                 let outer = outer.clone();
-                async move { outer.get_random_peers().await }
+                async move { outer.get_ranked_peers(Protocol::Classes).await }
             },
             move |peer, request| {
                 let inner = inner.clone();
-                async move { inner.send_classes_request(peer, request).await }
+                let throughput = throughput.clone();
+                async move {
+                    throughput
+                        .measure(
+                            peer,
+                            Protocol::Classes,
+                            inner.send_classes_request(peer, request),
+                        )
+                        .await
+                }
             },
         )
     }
@@ -248,6 +310,7 @@ impl EventStream for Client {
         event_counts_stream: impl Stream<Item = anyhow::Result<usize>> + Send + 'static,
     ) -> impl Stream<Item = StreamItem<EventsForBlockByTransaction>> {
         let inner = self.inner.clone();
+        let throughput = self.throughput.clone();
         let outer = self;
         event_stream::make(
             start,
@@ -255,16 +318,36 @@ impl EventStream for Client {
             event_counts_stream,
             move || {
                 let outer = outer.clone();
-                async move { outer.get_random_peers().await }
+                async move { outer.get_ranked_peers(Protocol::Events).await }
             },
             move |peer, request| {
                 let inner = inner.clone();
-                async move { inner.send_events_request(peer, request).await }
+                let throughput = throughput.clone();
+                async move {
+                    throughput
+                        .measure(
+                            peer,
+                            Protocol::Events,
+                            inner.send_events_request(peer, request),
+                        )
+                        .await
+                }
             },
         )
     }
 }
 
+impl PeerReputation for Client {
+    async fn punish(&self, peer: PeerId, penalty: Penalty) {
+        if self.core_client().punish(peer, penalty).await {
+            // Don't wait for the known peers to be refreshed to stop selecting the peer.
+            if let Some(peers) = self.peers.write().await.get_mut() {
+                peers.remove(&peer);
+            }
+        }
+    }
+}
+
 impl BlockClient for Client {
     async fn transactions_for_block(
         self,
@@ -588,15 +671,238 @@ impl BlockClient for Client {
 
         None
     }
+
+    async fn headers_from(
+        self,
+        start: BlockNumber,
+        limit: u64,
+    ) -> Option<(PeerId, Vec<SignedBlockHeader>)> {
+        let request = BlockHeadersRequest {
+            iteration: Iteration {
+                start: start.get().into(),
+                direction: Direction::Forward,
+                limit,
+                step: 1.into(),
+            },
+        };
+
+        let peers = self.get_random_peers().await;
+
+        for peer in peers {
+            let Ok(mut stream) = self
+                .inner
+                .send_headers_request(peer, request)
+                .await
+                .inspect_err(|error| tracing::debug!(%peer, %error, "Headers request failed"))
+            else {
+                continue;
+            };
+
+            let mut headers = Vec::new();
+
+            while let Some(response) = stream.next().await {
+                let header = match response {
+                    Ok(BlockHeadersResponse::Header(header)) => {
+                        SignedBlockHeader::try_from_dto(*header)
+                    }
+                    Ok(BlockHeadersResponse::Fin) => break,
+                    Err(error) => Err(error.into()),
+                };
+
+                match header {
+                    Ok(header) if (headers.len() as u64) < limit => headers.push(header),
+                    Ok(_) => {
+                        tracing::debug!(%peer, "Header stream Fin missing, got extra header instead");
+                        break;
+                    }
+                    Err(error) => {
+                        tracing::debug!(%peer, %error, "Header response stream failed");
+                        break;
+                    }
+                }
+            }
+
+            if !headers.is_empty() {
+                return Some((peer, headers));
+            }
+        }
+
+        None
+    }
+}
+
+impl PendingBlockClient for Client {
+    async fn pending_block(
+        self,
+        parent_hash: BlockHash,
+        peer: Option<PeerId>,
+    ) -> Option<(PeerId, PendingBlock)> {
+        let request = PendingBlockRequest {
+            parent_hash: p2p_proto::common::Hash(parent_hash.0),
+        };
+
+        let mut peers = self.get_random_peers().await;
+        if let Some(peer) = peer {
+            peers.retain(|p| *p != peer);
+            peers.insert(0, peer);
+        }
+
+        for peer in peers {
+            let Ok(stream) = self
+                .inner
+                .send_pending_block_request(peer, request)
+                .await
+                .inspect_err(
+                    |error| tracing::debug!(%peer, %error, "Pending block request failed"),
+                )
+            else {
+                continue;
+            };
+
+            match pending_block::collect(stream).await {
+                Ok(Some(block)) if block.header.parent_hash == parent_hash => {
+                    return Some((peer, block))
+                }
+                Ok(Some(block)) => {
+                    tracing::debug!(%peer, expected=%parent_hash, actual=%block.header.parent_hash, "Pending block has the wrong parent");
+                }
+                Ok(None) => {
+                    tracing::trace!(%peer, %parent_hash, "Peer has no pending block on top of parent");
+                }
+                Err(error) => {
+                    tracing::debug!(%peer, %error, "Pending block response stream failed");
+                }
+            }
+        }
+
+        None
+    }
 }
 
 /// Maximum number of blocks to request in a single request
 const MAX_BLOCKS_COUNT: u64 = 500;
 
+mod pending_block {
+    use anyhow::Context;
+
+    use super::*;
+
+    /// Collects the pending block from the response stream. Returns `None` if
+    /// the peer has no pending block on top of the requested parent.
+    pub(super) async fn collect(
+        mut stream: impl Stream<Item = std::io::Result<PendingBlockResponse>> + Unpin,
+    ) -> anyhow::Result<Option<PendingBlock>> {
+        let header = match next(&mut stream).await? {
+            PendingBlockResponse::Header(header) => BlockHeader::try_from_dto(header)?,
+            PendingBlockResponse::Fin => return Ok(None),
+            _ => anyhow::bail!("Pending block stream does not start with a header"),
+        };
+
+        let mut transactions: Vec<(Transaction, Receipt, Vec<Event>)> = Vec::new();
+        let mut state_diff = StateUpdateData::default();
+
+        loop {
+            match next(&mut stream).await? {
+                PendingBlockResponse::Header(_) => {
+                    anyhow::bail!("Unexpected pending block header")
+                }
+                PendingBlockResponse::TransactionWithReceipt(TransactionWithReceipt {
+                    transaction,
+                    receipt,
+                }) => {
+                    anyhow::ensure!(
+                        state_diff.is_empty(),
+                        "Pending transaction sent after the state diff"
+                    );
+                    let index = TransactionIndex::new(transactions.len().try_into()?)
+                        .context("Invalid transaction index")?;
+                    transactions.push((
+                        Transaction::try_from_dto(transaction)?,
+                        Receipt::try_from((receipt, index))?,
+                        Vec::new(),
+                    ));
+                }
+                PendingBlockResponse::Event(event) => {
+                    let (transaction, _, events) = transactions
+                        .last_mut()
+                        .context("Pending event sent before any transaction")?;
+                    anyhow::ensure!(
+                        transaction.hash.0 == event.transaction_hash.0,
+                        "Pending event does not belong to the preceding transaction"
+                    );
+                    events.push(Event::from_dto(event));
+                }
+                PendingBlockResponse::ContractDiff(ContractDiff {
+                    address,
+                    nonce,
+                    class_hash,
+                    values,
+                    domain: _,
+                }) => {
+                    let address = ContractAddress(address.0);
+                    let storage = values
+                        .into_iter()
+                        .map(|ContractStoredValue { key, value }| {
+                            (StorageAddress(key), StorageValue(value))
+                        });
+                    if address.is_system_contract() {
+                        state_diff
+                            .system_contract_updates
+                            .entry(address)
+                            .or_default()
+                            .storage
+                            .extend(storage);
+                    } else {
+                        let update = state_diff.contract_updates.entry(address).or_default();
+                        update.storage.extend(storage);
+                        if let Some(nonce) = nonce {
+                            update.nonce = Some(ContractNonce(nonce));
+                        }
+                        if let Some(class_hash) = class_hash {
+                            update.class =
+                                Some(ContractClassUpdate::Deploy(ClassHash(class_hash.0)));
+                        }
+                    }
+                }
+                PendingBlockResponse::DeclaredClass(DeclaredClass {
+                    class_hash,
+                    compiled_class_hash,
+                }) => {
+                    if let Some(compiled_class_hash) = compiled_class_hash {
+                        state_diff
+                            .declared_sierra_classes
+                            .insert(SierraHash(class_hash.0), CasmHash(compiled_class_hash.0));
+                    } else {
+                        state_diff
+                            .declared_cairo_classes
+                            .insert(ClassHash(class_hash.0));
+                    }
+                }
+                PendingBlockResponse::Fin => {
+                    return Ok(Some(PendingBlock {
+                        header,
+                        transactions,
+                        state_diff,
+                    }))
+                }
+            }
+        }
+    }
+
+    async fn next(
+        stream: &mut (impl Stream<Item = std::io::Result<PendingBlockResponse>> + Unpin),
+    ) -> anyhow::Result<PendingBlockResponse> {
+        Ok(stream
+            .next()
+            .await
+            .context("Pending block stream ended without Fin")??)
+    }
+}
+
 mod header_stream {
     use super::*;
 
-    pub fn make<PF, RF>(
+    pub fn make<PF, RF, S>(
         start: BlockNumber,
         stop: BlockNumber,
         reverse: bool,
@@ -605,8 +911,8 @@ mod header_stream {
     ) -> impl Stream<Item = PeerData<SignedBlockHeader>>
     where
         PF: Future<Output = Vec<PeerId>> + Send,
-        RF: Future<Output = anyhow::Result<fmpsc::Receiver<std::io::Result<BlockHeadersResponse>>>>
-            + Send,
+        RF: Future<Output = anyhow::Result<S>> + Send,
+        S: Stream<Item = std::io::Result<BlockHeadersResponse>> + Unpin + Send + 'static,
     {
         let start: i64 = start.get().try_into().expect("block number <= i64::MAX");
         let stop: i64 = stop.get().try_into().expect("block number <= i64::MAX");
@@ -738,7 +1044,7 @@ mod header_stream {
 mod transaction_stream {
     use super::*;
 
-    pub fn make<PF, RF>(
+    pub fn make<PF, RF, S>(
         mut start: BlockNumber,
         stop: BlockNumber,
         counts_stream: impl Stream<Item = anyhow::Result<usize>> + Send + 'static,
@@ -747,8 +1053,8 @@ mod transaction_stream {
     ) -> impl Stream<Item = StreamItem<(TransactionData, BlockNumber)>>
     where
         PF: Future<Output = Vec<PeerId>> + Send,
-        RF: Future<Output = anyhow::Result<fmpsc::Receiver<std::io::Result<TransactionsResponse>>>>
-            + Send,
+        RF: Future<Output = anyhow::Result<S>> + Send,
+        S: Stream<Item = std::io::Result<TransactionsResponse>> + Unpin + Send + 'static,
     {
         tracing::trace!(?start, ?stop, "Streaming Transactions");
 
@@ -921,7 +1227,7 @@ mod transaction_stream {
 mod state_diff_stream {
     use super::*;
 
-    pub fn make<PF, RF>(
+    pub fn make<PF, RF, S>(
         mut start: BlockNumber,
         stop: BlockNumber,
         length_stream: impl Stream<Item = anyhow::Result<usize>> + Send + 'static,
@@ -930,8 +1236,8 @@ mod state_diff_stream {
     ) -> impl Stream<Item = StreamItem<(StateUpdateData, BlockNumber)>>
     where
         PF: Future<Output = Vec<PeerId>> + Send,
-        RF: Future<Output = anyhow::Result<fmpsc::Receiver<std::io::Result<StateDiffsResponse>>>>
-            + Send,
+        RF: Future<Output = anyhow::Result<S>> + Send,
+        S: Stream<Item = std::io::Result<StateDiffsResponse>> + Unpin + Send + 'static,
     {
         tracing::trace!(?start, ?stop, "Streaming state diffs");
 
@@ -1145,7 +1451,7 @@ mod state_diff_stream {
 mod class_definition_stream {
     use super::*;
 
-    pub fn make<PF, RF>(
+    pub fn make<PF, RF, S>(
         mut start: BlockNumber,
         stop: BlockNumber,
         counts_stream: impl Stream<Item = anyhow::Result<usize>> + Send + 'static,
@@ -1154,8 +1460,8 @@ mod class_definition_stream {
     ) -> impl Stream<Item = StreamItem<ClassDefinition>>
     where
         PF: Future<Output = Vec<PeerId>> + Send,
-        RF: Future<Output = anyhow::Result<fmpsc::Receiver<std::io::Result<ClassesResponse>>>>
-            + Send,
+        RF: Future<Output = anyhow::Result<S>> + Send,
+        S: Stream<Item = std::io::Result<ClassesResponse>> + Unpin + Send + 'static,
     {
         tracing::trace!(?start, ?stop, "Streaming classes");
 
@@ -1344,7 +1650,7 @@ mod class_definition_stream {
 mod event_stream {
     use super::*;
 
-    pub fn make<PF, RF>(
+    pub fn make<PF, RF, S>(
         mut start: BlockNumber,
         stop: BlockNumber,
         counts_stream: impl Stream<Item = anyhow::Result<usize>> + Send + 'static,
@@ -1353,8 +1659,8 @@ mod event_stream {
     ) -> impl Stream<Item = StreamItem<EventsForBlockByTransaction>>
     where
         PF: Future<Output = Vec<PeerId>> + Send,
-        RF: Future<Output = anyhow::Result<fmpsc::Receiver<std::io::Result<EventsResponse>>>>
-            + Send,
+        RF: Future<Output = anyhow::Result<S>> + Send,
+        S: Stream<Item = std::io::Result<EventsResponse>> + Unpin + Send + 'static,
     {
         tracing::trace!(?start, ?stop, "Streaming events");
 
@@ -1608,6 +1914,15 @@ impl<T: Default> Decaying<T> {
         }
     }
 
+    /// Mutable counterpart of [`Self::get`].
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if self.last_update.elapsed() > self.timeout {
+            None
+        } else {
+            Some(&mut self.data)
+        }
+    }
+
     pub fn update(&mut self, data: T) {
         self.last_update = Instant::now();
         self.data = data;
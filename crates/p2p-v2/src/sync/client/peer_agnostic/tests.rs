@@ -938,3 +938,290 @@ async fn make_event_stream(
 
     pretty_assertions_sorted::assert_eq!(actual, expected_stream);
 }
+
+mod pending_block {
+    use fake::{Fake, Faker};
+    use p2p_proto::pending::PendingBlockHeader;
+
+    use super::*;
+
+    fn header() -> PendingBlockResponse {
+        PendingBlockResponse::Header(PendingBlockHeader {
+            number: 1,
+            time: 2,
+            protocol_version: "0.13.2".to_owned(),
+            ..Faker.fake()
+        })
+    }
+
+    fn transaction(tag: i32) -> PendingBlockResponse {
+        match txn_resp(tag, 0) {
+            TransactionsResponse::TransactionWithReceipt(x) => {
+                PendingBlockResponse::TransactionWithReceipt(x)
+            }
+            TxnFin => unreachable!(),
+        }
+    }
+
+    fn event_for(transaction: &PendingBlockResponse) -> PendingBlockResponse {
+        let PendingBlockResponse::TransactionWithReceipt(x) = transaction else {
+            unreachable!()
+        };
+        PendingBlockResponse::Event(p2p_proto::event::Event {
+            transaction_hash: x.transaction.transaction_hash,
+            ..Faker.fake()
+        })
+    }
+
+    fn diff(tag: i32) -> PendingBlockResponse {
+        match contract_diff(tag) {
+            StateDiffsResponse::ContractDiff(x) => PendingBlockResponse::ContractDiff(x),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn collect(responses: Vec<PendingBlockResponse>) -> anyhow::Result<Option<PendingBlock>> {
+        crate::sync::client::peer_agnostic::pending_block::collect(stream::iter(
+            responses.into_iter().map(Ok),
+        ))
+        .await
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn no_pending_block() {
+        assert_eq!(
+            collect(vec![PendingBlockResponse::Fin]).await.unwrap(),
+            None
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn full_pending_block() {
+        let txn0 = transaction(0);
+        let txn1 = transaction(1);
+        let responses = vec![
+            header(),
+            txn0.clone(),
+            event_for(&txn0),
+            event_for(&txn0),
+            txn1.clone(),
+            diff(2),
+            PendingBlockResponse::Fin,
+        ];
+
+        let block = collect(responses).await.unwrap().unwrap();
+
+        assert_eq!(block.header.number, BlockNumber::new_or_panic(1));
+        assert_eq!(block.header.hash, BlockHash::ZERO);
+        let events_per_txn = block
+            .transactions
+            .iter()
+            .map(|(_, r, e)| (r.transaction_index.get(), e.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(events_per_txn, vec![(0, 2), (1, 0)]);
+        assert!(!block.state_diff.is_empty());
+    }
+
+    #[rstest]
+    #[case::no_header(vec![transaction(0), PendingBlockResponse::Fin])]
+    #[case::event_before_transaction(vec![header(), event_for(&transaction(0)), PendingBlockResponse::Fin])]
+    #[case::event_for_another_transaction(vec![header(), transaction(0), event_for(&transaction(1)), PendingBlockResponse::Fin])]
+    #[case::transaction_after_state_diff(vec![header(), diff(0), transaction(0), PendingBlockResponse::Fin])]
+    #[case::missing_fin(vec![header(), transaction(0)])]
+    #[test_log::test(tokio::test)]
+    async fn malformed_pending_block(#[case] responses: Vec<PendingBlockResponse>) {
+        collect(responses).await.unwrap_err();
+    }
+}
+
+mod throughput {
+    use std::time::Instant;
+
+    use futures::{stream, FutureExt};
+
+    use super::*;
+    use crate::peers::{Connectivity, Direction as PeerDirection};
+    use crate::sync::client::peer_agnostic::throughput::{Protocol, Throughput};
+
+    fn connected(min_ping: Option<Duration>) -> Peer {
+        Peer {
+            connectivity: Connectivity::Connected {
+                connected_at: Instant::now(),
+            },
+            direction: PeerDirection::Outbound,
+            addr: None,
+            keyed_network_group: None,
+            min_ping,
+            evicted: false,
+            useful: true,
+        }
+    }
+
+    fn all_connected(peers: &[PeerId]) -> HashMap<PeerId, Peer> {
+        peers.iter().map(|peer| (*peer, connected(None))).collect()
+    }
+
+    /// Serves `responses` to a `protocol` request instantly from `peer` and
+    /// consumes them.
+    async fn serve(throughput: &Throughput, protocol: Protocol, peer: PeerId, responses: usize) {
+        throughput
+            .measure(peer, protocol, async {
+                Ok(stream::iter(vec![(); responses]))
+            })
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+    }
+
+    #[test]
+    fn unmeasured_peers_are_ranked_by_ping() {
+        let [fast, slow, unknown] = [0, 1, 2].map(|_| PeerId::random());
+        let connected_peers = HashMap::from([
+            (fast, connected(Some(Duration::from_millis(10)))),
+            (slow, connected(Some(Duration::from_millis(100)))),
+            (unknown, connected(None)),
+        ]);
+
+        let mut peers = vec![unknown, slow, fast];
+        Throughput::default().rank(Protocol::Headers, &mut peers, &connected_peers);
+
+        assert_eq!(peers, vec![fast, slow, unknown]);
+    }
+
+    #[tokio::test]
+    async fn measured_peers_are_ranked_by_throughput() {
+        let [fast, slow, unmeasured] = [0, 1, 2].map(|_| PeerId::random());
+        let throughput = Throughput::default();
+        serve(&throughput, Protocol::Headers, slow, 1).await;
+        serve(&throughput, Protocol::Headers, fast, 1000).await;
+
+        let mut peers = vec![slow, fast, unmeasured];
+        throughput.rank(Protocol::Headers, &mut peers, &all_connected(&peers));
+
+        assert_eq!(peers, vec![unmeasured, fast, slow]);
+    }
+
+    #[tokio::test]
+    async fn throughput_is_measured_per_protocol() {
+        let [headers, classes] = [0, 1].map(|_| PeerId::random());
+        let measured = move || async move {
+            let throughput = Throughput::default();
+            serve(&throughput, Protocol::Headers, headers, 1000).await;
+            serve(&throughput, Protocol::Headers, classes, 1).await;
+            serve(&throughput, Protocol::Classes, headers, 1).await;
+            serve(&throughput, Protocol::Classes, classes, 1000).await;
+            throughput
+        };
+
+        let mut peers = vec![classes, headers];
+        measured()
+            .await
+            .rank(Protocol::Headers, &mut peers, &all_connected(&peers));
+        assert_eq!(peers, vec![headers, classes]);
+
+        measured()
+            .await
+            .rank(Protocol::Classes, &mut peers, &all_connected(&peers));
+        assert_eq!(peers, vec![classes, headers]);
+    }
+
+    #[test]
+    fn ranked_peers_are_reserved() {
+        let [fast, slow] = [0, 1].map(|_| PeerId::random());
+        let connected_peers = HashMap::from([
+            (fast, connected(Some(Duration::from_millis(10)))),
+            (slow, connected(Some(Duration::from_millis(100)))),
+        ]);
+        let throughput = Throughput::default();
+
+        let mut peers = vec![fast, slow];
+        throughput.rank(Protocol::Headers, &mut peers, &connected_peers);
+        assert_eq!(peers, vec![fast, slow]);
+
+        // The request to the first peer wasn't sent yet.
+        throughput.rank(Protocol::Headers, &mut peers, &connected_peers);
+        assert_eq!(peers, vec![slow, fast]);
+    }
+
+    #[tokio::test]
+    async fn disconnected_peers_are_forgotten() {
+        let [fast, slow] = [0, 1].map(|_| PeerId::random());
+        let throughput = Throughput::default();
+        serve(&throughput, Protocol::Headers, slow, 1).await;
+        serve(&throughput, Protocol::Headers, fast, 1000).await;
+
+        // Once forgotten, the slow peer is ranked first as it isn't measured.
+        let mut peers = vec![fast, slow];
+        throughput.rank(Protocol::Headers, &mut peers, &all_connected(&[fast]));
+
+        assert_eq!(peers, vec![slow, fast]);
+    }
+
+    #[tokio::test]
+    async fn cancelled_requests_are_not_in_flight() {
+        let [fast, slow] = [0, 1].map(|_| PeerId::random());
+        let throughput = Throughput::default();
+        serve(&throughput, Protocol::Headers, slow, 1).await;
+        serve(&throughput, Protocol::Headers, fast, 1000).await;
+
+        // Polls the request once and drops it while it's pending.
+        let cancelled = throughput
+            .measure(
+                slow,
+                Protocol::Headers,
+                std::future::pending::<anyhow::Result<stream::Empty<()>>>(),
+            )
+            .now_or_never();
+        assert!(cancelled.is_none());
+
+        // A peer with a request in flight would not be forgotten.
+        let mut peers = vec![fast, slow];
+        throughput.rank(Protocol::Headers, &mut peers, &all_connected(&[fast]));
+
+        assert_eq!(peers, vec![slow, fast]);
+    }
+
+    #[tokio::test]
+    async fn busy_peers_are_ranked_last() {
+        let [fast, slow] = [0, 1].map(|_| PeerId::random());
+        let throughput = Throughput::default();
+        serve(&throughput, Protocol::Headers, slow, 1).await;
+        serve(&throughput, Protocol::Headers, fast, 1000).await;
+
+        let responses = throughput
+            .measure(fast, Protocol::Headers, async {
+                Ok(stream::pending::<()>())
+            })
+            .await
+            .unwrap();
+
+        let mut peers = vec![fast, slow];
+        let connected_peers = all_connected(&peers);
+        throughput.rank(Protocol::Headers, &mut peers, &connected_peers);
+        assert_eq!(peers, vec![slow, fast]);
+
+        // The slow peer is reserved by the previous ranking.
+        drop(responses);
+        throughput.rank(Protocol::Headers, &mut peers, &connected_peers);
+        assert_eq!(peers, vec![fast, slow]);
+    }
+
+    #[tokio::test]
+    async fn failed_requests_are_ranked_last() {
+        let [failed, working] = [0, 1].map(|_| PeerId::random());
+        let throughput = Throughput::default();
+        serve(&throughput, Protocol::Headers, working, 1).await;
+        throughput
+            .measure(failed, Protocol::Headers, async {
+                Err::<stream::Empty<()>, _>(anyhow::anyhow!("Failed"))
+            })
+            .await
+            .unwrap_err();
+
+        let mut peers = vec![failed, working];
+        throughput.rank(Protocol::Headers, &mut peers, &all_connected(&peers));
+
+        assert_eq!(peers, vec![working, failed]);
+    }
+}
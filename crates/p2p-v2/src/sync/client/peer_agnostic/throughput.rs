@@ -0,0 +1,201 @@
+//! Tracks how fast peers serve sync responses, so that concurrent requests are
+//! spread over different peers, preferring the fastest ones.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::Stream;
+use libp2p::PeerId;
+
+use crate::peers::Peer;
+
+/// Weight of the latest measurement in the throughput estimate of a peer.
+const SMOOTHING: f64 = 0.3;
+
+/// How long a peer stays reserved for a request which is never sent.
+const RESERVATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The sync protocols, whose throughput is measured separately as their
+/// responses differ in size by orders of magnitude.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum Protocol {
+    Headers,
+    Transactions,
+    StateDiffs,
+    Classes,
+    Events,
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    /// Requests to the peer whose responses are still being received.
+    in_flight: usize,
+    /// When the peer was ranked first for requests which weren't sent yet.
+    reserved: Vec<Instant>,
+    /// Smoothed number of responses per second for each protocol, unknown
+    /// until the first request to the peer for the protocol completed.
+    responses_per_second: HashMap<Protocol, f64>,
+}
+
+impl Stats {
+    fn busy(&self) -> usize {
+        self.in_flight + self.reserved.len()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(super) struct Throughput(Arc<Mutex<HashMap<PeerId, Stats>>>);
+
+impl Throughput {
+    /// Orders `peers` by how soon they are expected to serve a `protocol`
+    /// request, and reserves the first peer for the request, so that concurrent
+    /// requests are ranked knowing about each other.
+    ///
+    /// The least busy peers come first, so that concurrent requests go to
+    /// different peers. Among equally busy peers, peers which weren't measured
+    /// yet are tried first, lowest ping first, followed by the measured peers
+    /// from the fastest to the slowest. Ties keep their order in `peers`.
+    ///
+    /// Measurements of peers which are no longer `connected` are dropped.
+    pub fn rank(
+        &self,
+        protocol: Protocol,
+        peers: &mut [PeerId],
+        connected: &HashMap<PeerId, Peer>,
+    ) {
+        let mut stats = self.0.lock().unwrap();
+        stats.retain(|peer, stats| {
+            stats
+                .reserved
+                .retain(|reserved_at| reserved_at.elapsed() < RESERVATION_TIMEOUT);
+            stats.in_flight > 0 || connected.contains_key(peer)
+        });
+
+        let key = |peer: &PeerId| {
+            let (busy, throughput) = stats
+                .get(peer)
+                .map(|stats| {
+                    (
+                        stats.busy(),
+                        stats.responses_per_second.get(&protocol).copied(),
+                    )
+                })
+                .unwrap_or_default();
+            let min_ping = connected
+                .get(peer)
+                .and_then(|peer| peer.min_ping)
+                .unwrap_or(Duration::MAX);
+            (busy, throughput, min_ping)
+        };
+
+        peers.sort_by(|a, b| {
+            let (a_busy, a_throughput, a_ping) = key(a);
+            let (b_busy, b_throughput, b_ping) = key(b);
+            a_busy
+                .cmp(&b_busy)
+                .then_with(|| match (a_throughput, b_throughput) {
+                    (None, None) => a_ping.cmp(&b_ping),
+                    (None, Some(_)) => Ordering::Less,
+                    (Some(_), None) => Ordering::Greater,
+                    (Some(a), Some(b)) => b.total_cmp(&a),
+                })
+        });
+
+        if let Some(first) = peers.first() {
+            stats
+                .entry(*first)
+                .or_default()
+                .reserved
+                .push(Instant::now());
+        }
+    }
+
+    /// Sends a `protocol` request to `peer` and measures how fast the peer
+    /// serves the responses. A failed or cancelled request counts as a peer
+    /// serving nothing.
+    pub async fn measure<S>(
+        &self,
+        peer: PeerId,
+        protocol: Protocol,
+        request: impl Future<Output = anyhow::Result<S>>,
+    ) -> anyhow::Result<Metered<S>> {
+        // Created before awaiting the request, so that the request is no longer
+        // in flight once it's dropped.
+        let in_flight = InFlight {
+            peer,
+            protocol,
+            sent_at: Instant::now(),
+            count: 0,
+            throughput: self.clone(),
+        };
+        {
+            let mut stats = self.0.lock().unwrap();
+            let stats = stats.entry(peer).or_default();
+            // The request takes the place of the reservation made by ranking the peer
+            // first, if any.
+            stats.reserved.pop();
+            stats.in_flight += 1;
+        }
+
+        let responses = request.await?;
+
+        Ok(Metered {
+            responses,
+            in_flight,
+        })
+    }
+
+    fn record(&self, peer: PeerId, protocol: Protocol, responses: u64, elapsed: Duration) {
+        // Don't divide by zero for responses which arrived instantly.
+        let measured = responses as f64 / elapsed.as_secs_f64().max(1e-3);
+
+        let mut stats = self.0.lock().unwrap();
+        let stats = stats.entry(peer).or_default();
+        stats.in_flight = stats.in_flight.saturating_sub(1);
+        stats
+            .responses_per_second
+            .entry(protocol)
+            .and_modify(|previous| *previous = SMOOTHING * measured + (1.0 - SMOOTHING) * *previous)
+            .or_insert(measured);
+    }
+}
+
+/// The responses to a request, which update the throughput of the peer once
+/// they are dropped.
+pub(super) struct Metered<S> {
+    responses: S,
+    in_flight: InFlight,
+}
+
+/// A request which updates the throughput of the peer once it's dropped.
+struct InFlight {
+    peer: PeerId,
+    protocol: Protocol,
+    sent_at: Instant,
+    /// The number of responses received so far.
+    count: u64,
+    throughput: Throughput,
+}
+
+impl<S: Stream + Unpin> Stream for Metered<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.responses).poll_next(cx);
+        if let Poll::Ready(Some(_)) = poll {
+            self.in_flight.count += 1;
+        }
+        poll
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.throughput
+            .record(self.peer, self.protocol, self.count, self.sent_at.elapsed());
+    }
+}
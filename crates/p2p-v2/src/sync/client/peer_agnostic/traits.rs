@@ -3,18 +3,19 @@ use libp2p::PeerId;
 use pathfinder_common::event::Event;
 use pathfinder_common::state_update::StateUpdateData;
 use pathfinder_common::transaction::Transaction;
-use pathfinder_common::{BlockNumber, SignedBlockHeader, TransactionHash};
+use pathfinder_common::{BlockHash, BlockNumber, SignedBlockHeader, TransactionHash};
 
 use crate::sync::client::types::{
     ClassDefinition,
     ClassDefinitionsError,
     EventsForBlockByTransaction,
     EventsResponseStreamFailure,
+    PendingBlock,
     Receipt,
     StateDiffsError,
     TransactionData,
 };
-use crate::PeerData;
+use crate::{PeerData, Penalty};
 
 pub type StreamItem<T> = Result<PeerData<T>, anyhow::Error>;
 
@@ -72,7 +73,7 @@ pub trait EventStream {
         start: BlockNumber,
         stop: BlockNumber,
         event_count_stream: impl Stream<Item = anyhow::Result<usize>> + Send + 'static,
-    ) -> impl Stream<Item = StreamItem<EventsForBlockByTransaction>>;
+    ) -> impl Stream<Item = StreamItem<EventsForBlockByTransaction>> + Send;
 }
 
 pub trait BlockClient {
@@ -108,4 +109,31 @@ pub trait BlockClient {
             impl Stream<Item = Result<(TransactionHash, Event), EventsResponseStreamFailure>> + Send,
         )>,
     > + Send;
+
+    /// Requests up to `limit` consecutive headers starting at block `start`.
+    /// Returns the headers served by the first peer which has any, so an
+    /// empty response from one peer doesn't mean that the others are not
+    /// ahead.
+    fn headers_from(
+        self,
+        start: BlockNumber,
+        limit: u64,
+    ) -> impl Future<Output = Option<(PeerId, Vec<SignedBlockHeader>)>> + Send;
+}
+
+pub trait PendingBlockClient {
+    /// Requests the pending block built on top of `parent_hash`, from `peer`
+    /// first if given. Returns the pending block served by the first peer which
+    /// has one.
+    fn pending_block(
+        self,
+        parent_hash: BlockHash,
+        peer: Option<PeerId>,
+    ) -> impl Future<Output = Option<(PeerId, PendingBlock)>> + Send;
+}
+
+pub trait PeerReputation {
+    /// Punish a peer which served bad data, so that it stops being selected
+    /// once it gets banned.
+    fn punish(&self, peer: PeerId, penalty: Penalty) -> impl Future<Output = ()> + Send;
 }
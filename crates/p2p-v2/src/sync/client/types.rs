@@ -4,6 +4,7 @@ use libp2p::PeerId;
 use pathfinder_common::event::Event;
 use pathfinder_common::prelude::*;
 use pathfinder_common::receipt::{ExecutionResources, ExecutionStatus, L2ToL1Message};
+use pathfinder_common::state_update::StateUpdateData;
 use pathfinder_common::transaction::Transaction;
 use pathfinder_tagged::Tagged;
 use pathfinder_tagged_debug_derive::TaggedDebug;
@@ -61,6 +62,22 @@ pub type TransactionData = Vec<(Transaction, Receipt)>;
 
 pub type EventsForBlockByTransaction = (BlockNumber, Vec<(TransactionHash, Vec<Event>)>);
 
+/// The pending block and its state diff as served by a peer.
+///
+/// Pending data is not signed, so it is only as trustworthy as the peer which
+/// served it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingBlock {
+    /// The hash and commitments of the pending block are not known until it
+    /// is closed, so they are left at their defaults.
+    pub header: BlockHeader,
+    pub transactions: Vec<(Transaction, Receipt, Vec<Event>)>,
+    /// Contract class updates are set to `ContractClassUpdate::Deploy` but
+    /// __the caller is responsible for determining if the class was really
+    /// deployed or replaced__.
+    pub state_diff: StateUpdateData,
+}
+
 impl TryFromDto<p2p_proto::header::SignedBlockHeader> for SignedBlockHeader {
     fn try_from_dto(dto: p2p_proto::header::SignedBlockHeader) -> anyhow::Result<Self> {
         anyhow::ensure!(dto.signatures.len() == 1, "expected exactly one signature");
@@ -102,6 +119,26 @@ impl TryFromDto<p2p_proto::header::SignedBlockHeader> for SignedBlockHeader {
     }
 }
 
+impl TryFromDto<p2p_proto::pending::PendingBlockHeader> for BlockHeader {
+    fn try_from_dto(dto: p2p_proto::pending::PendingBlockHeader) -> anyhow::Result<Self> {
+        Ok(BlockHeader {
+            parent_hash: BlockHash(dto.parent_hash.0),
+            number: BlockNumber::new(dto.number).context("block number > i64::MAX")?,
+            timestamp: BlockTimestamp::new(dto.time).context("block timestamp > i64::MAX")?,
+            eth_l1_gas_price: GasPrice(dto.gas_price_wei),
+            strk_l1_gas_price: GasPrice(dto.gas_price_fri),
+            eth_l1_data_gas_price: GasPrice(dto.data_gas_price_wei),
+            strk_l1_data_gas_price: GasPrice(dto.data_gas_price_fri),
+            eth_l2_gas_price: GasPrice(dto.l2_gas_price_wei),
+            strk_l2_gas_price: GasPrice(dto.l2_gas_price_fri),
+            sequencer_address: SequencerAddress(dto.sequencer_address.0),
+            starknet_version: dto.protocol_version.parse()?,
+            l1_da_mode: TryFromDto::try_from_dto(dto.l1_data_availability_mode)?,
+            ..Default::default()
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum StateDiffsError {
     IncorrectStateDiffCount(PeerId),
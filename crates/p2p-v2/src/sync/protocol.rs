@@ -23,6 +23,7 @@ pub mod name {
     define_protocol!(Classes, "/starknet/classes/0.1.0-rc.0");
     define_protocol!(Transactions, "/starknet/transactions/0.1.0-rc.0");
     define_protocol!(Events, "/starknet/events/0.1.0-rc.0");
+    define_protocol!(PendingBlock, "/starknet/pending_block/0.1.0-rc.0");
 }
 
 pub(crate) mod codec {
@@ -30,7 +31,17 @@ pub(crate) mod codec {
 
     use async_trait::async_trait;
     use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
-    use p2p_proto::{class, event, header, proto, state, transaction, ToProtobuf, TryFromProtobuf};
+    use p2p_proto::{
+        class,
+        event,
+        header,
+        pending,
+        proto,
+        state,
+        transaction,
+        ToProtobuf,
+        TryFromProtobuf,
+    };
     use p2p_stream::Codec;
 
     use super::name;
@@ -83,6 +94,15 @@ pub(crate) mod codec {
         ONE_MIB,
     >;
 
+    pub type PendingBlock = SyncCodec<
+        name::PendingBlock,
+        pending::PendingBlockRequest,
+        pending::PendingBlockResponse,
+        proto::pending::PendingBlockRequest,
+        proto::pending::PendingBlockResponse,
+        ONE_MIB,
+    >;
+
     #[derive(Clone)]
     pub struct ProdCodec<Protocol, Req, Resp, ProstReq, ProstResp, const RESPONSE_SIZE_LIMIT: usize>(
         PhantomData<(Protocol, Req, Resp, ProstReq, ProstResp)>,
@@ -3,8 +3,10 @@ use futures::{FutureExt, SinkExt, StreamExt};
 use p2p_proto::class::{ClassesRequest, ClassesResponse};
 use p2p_proto::event::{EventsRequest, EventsResponse};
 use p2p_proto::header::{BlockHeadersRequest, BlockHeadersResponse};
+use p2p_proto::pending::{NewPendingBlock, PendingBlockRequest, PendingBlockResponse};
 use p2p_proto::state::{StateDiffsRequest, StateDiffsResponse};
 use p2p_proto::transaction::{TransactionsRequest, TransactionsResponse};
+use pathfinder_common::ChainId;
 use rstest::rstest;
 
 use crate::sync::behaviour::Behaviour;
@@ -12,13 +14,24 @@ use crate::sync::client::Client;
 use crate::sync::protocol::codec;
 use crate::sync::{Config, Event};
 use crate::test_utils::peer::TestPeerBuilder;
-use crate::test_utils::{consume_all_events_forever, filter_events};
+use crate::test_utils::{
+    consume_all_events_forever,
+    filter_events,
+    retry_until_published,
+    wait_for_event,
+};
 
 type SyncTestPeer = crate::test_utils::peer::TestPeer<Behaviour>;
 
 fn create_peer() -> SyncTestPeer {
-    TestPeerBuilder::new()
-        .app_behaviour(Behaviour::new(Config::for_test()))
+    let builder = TestPeerBuilder::new();
+    let keypair = builder.keypair.clone();
+    builder
+        .app_behaviour(Behaviour::new(
+            keypair,
+            ChainId::SEPOLIA_TESTNET,
+            Config::for_test(),
+        ))
         .build(crate::core::Config::for_test())
 }
 
@@ -172,6 +185,33 @@ mod successful_sync {
         InboundEventsRequest,
         send_events_request
     );
+
+    define_test!(
+        sync_pending_block,
+        PendingBlockRequest,
+        PendingBlockResponse,
+        InboundPendingBlockRequest,
+        send_pending_block_request
+    );
+}
+
+#[test_log::test(tokio::test)]
+async fn new_pending_block_is_gossiped() {
+    let (mut peer1, peer2) = create_peers().await;
+    let expected = Faker.fake::<NewPendingBlock>();
+
+    let client2 = Client::from(peer2.client.as_pair());
+    retry_until_published(|| client2.broadcast_new_pending_block(expected)).await;
+
+    let (from, announcement) = wait_for_event(&mut peer1.app_event_receiver, |event| match event {
+        Event::NewPendingBlock { from, announcement } => Some((from, announcement)),
+        _ => None,
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(from, peer2.peer_id);
+    assert_eq!(announcement, expected);
 }
 
 mod propagate_codec_errors_to_caller {
@@ -189,6 +229,7 @@ mod propagate_codec_errors_to_caller {
         StateDiffs,
         Classes,
         Events,
+        PendingBlock,
     }
 
     fn error_factory<T>() -> TypeErasedReadFactory<T> {
@@ -208,7 +249,12 @@ mod propagate_codec_errors_to_caller {
     async fn create_peers(bad_peer: BadPeer, bad_codec: BadCodec) -> (SyncTestPeer, SyncTestPeer) {
         let good = create_peer();
 
-        let sync_behaviour_builder = Behaviour::builder(Config::for_test());
+        let bad_builder = SyncTestPeer::builder();
+        let sync_behaviour_builder = Behaviour::builder(
+            bad_builder.keypair.clone(),
+            ChainId::SEPOLIA_TESTNET,
+            Config::for_test(),
+        );
         let sync_behaviour_builder = match bad_codec {
             BadCodec::Headers => {
                 sync_behaviour_builder.header_sync_behaviour(p2p_stream::Behaviour::with_codec(
@@ -240,9 +286,15 @@ mod propagate_codec_errors_to_caller {
                     Default::default(),
                 ))
             }
+            BadCodec::PendingBlock => sync_behaviour_builder.pending_block_sync_behaviour(
+                p2p_stream::Behaviour::with_codec(
+                    codec::PendingBlock::for_test().set_read_response_factory(error_factory()),
+                    Default::default(),
+                ),
+            ),
         };
 
-        let bad = SyncTestPeer::builder()
+        let bad = bad_builder
             .app_behaviour(sync_behaviour_builder.build())
             .build(crate::core::Config::for_test());
 
@@ -399,4 +451,13 @@ mod propagate_codec_errors_to_caller {
         send_events_request,
         BadCodec::Events
     );
+
+    define_test!(
+        sync_pending_block,
+        PendingBlockRequest,
+        PendingBlockResponse,
+        InboundPendingBlockRequest,
+        send_pending_block_request,
+        BadCodec::PendingBlock
+    );
 }
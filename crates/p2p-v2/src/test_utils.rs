@@ -4,6 +4,7 @@ pub mod peer;
 pub mod sync;
 
 use std::fmt::Debug;
+use std::time::Duration;
 
 use tokio::sync::mpsc;
 
@@ -51,6 +52,24 @@ where
     None
 }
 
+/// Retries gossiping a message with `f`. Publishing fails until the remote
+/// peer's subscriptions are known, which happens shortly after the connection
+/// is established.
+pub(crate) async fn retry_until_published<F, Fut>(f: F)
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<()>>,
+{
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while let Err(error) = f().await {
+            tracing::debug!(%error, "Retrying publish");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("Message published before timeout");
+}
+
 /// Consume all events that have accumulated for the peer so far. You don't care
 /// about any of those events in the queue __right now__, but later you may do
 /// something that triggers new events for this peer, which you may care for.
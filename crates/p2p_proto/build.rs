@@ -5,8 +5,10 @@ fn main() -> Result<()> {
         &[
             "proto/class.proto",
             "proto/common.proto",
+            "proto/consensus.proto",
             "proto/event.proto",
             "proto/header.proto",
+            "proto/pending.proto",
             "proto/receipt.proto",
             "proto/state.proto",
             "proto/transaction.proto",
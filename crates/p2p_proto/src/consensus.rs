@@ -0,0 +1,108 @@
+use fake::Dummy;
+
+use crate::common::{Address, Hash};
+use crate::transaction::Transaction;
+use crate::{proto, proto_field, ToProtobuf, TryFromProtobuf};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Dummy)]
+pub enum VoteType {
+    Prevote,
+    Precommit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, ToProtobuf, TryFromProtobuf, Dummy)]
+#[protobuf(name = "crate::proto::consensus::Vote")]
+pub struct Vote {
+    pub vote_type: VoteType,
+    pub height: u64,
+    pub round: u32,
+    // Not present if the vote is NIL
+    #[optional]
+    pub block_hash: Option<Hash>,
+    pub voter: Address,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, ToProtobuf, TryFromProtobuf, Dummy)]
+#[protobuf(name = "crate::proto::consensus::ProposalInit")]
+pub struct ProposalInit {
+    pub height: u64,
+    pub round: u32,
+    #[optional]
+    pub valid_round: Option<u32>,
+    pub proposer: Address,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, ToProtobuf, TryFromProtobuf, Dummy)]
+#[protobuf(name = "crate::proto::consensus::TransactionBatch")]
+pub struct TransactionBatch {
+    pub transactions: Vec<Transaction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, ToProtobuf, TryFromProtobuf, Dummy)]
+#[protobuf(name = "crate::proto::consensus::ProposalFin")]
+pub struct ProposalFin {
+    pub proposal_commitment: Hash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Dummy)]
+pub enum ProposalPart {
+    Init(ProposalInit),
+    Fin(ProposalFin),
+    TransactionBatch(TransactionBatch),
+}
+
+impl ToProtobuf<i32> for VoteType {
+    fn to_protobuf(self) -> i32 {
+        use proto::consensus::vote::VoteType::{Precommit, Prevote};
+        match self {
+            VoteType::Prevote => Prevote as i32,
+            VoteType::Precommit => Precommit as i32,
+        }
+    }
+}
+
+impl TryFromProtobuf<i32> for VoteType {
+    fn try_from_protobuf(input: i32, field_name: &'static str) -> Result<Self, std::io::Error> {
+        use proto::consensus::vote::VoteType::{Precommit, Prevote};
+        Ok(
+            match TryFrom::try_from(input).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Invalid vote type field element {field_name} enum value: {e}"),
+                )
+            })? {
+                Prevote => VoteType::Prevote,
+                Precommit => VoteType::Precommit,
+            },
+        )
+    }
+}
+
+impl ToProtobuf<proto::consensus::ProposalPart> for ProposalPart {
+    fn to_protobuf(self) -> proto::consensus::ProposalPart {
+        use proto::consensus::proposal_part::Messages::{Fin, Init, Transactions};
+        proto::consensus::ProposalPart {
+            messages: Some(match self {
+                Self::Init(init) => Init(init.to_protobuf()),
+                Self::Fin(fin) => Fin(fin.to_protobuf()),
+                Self::TransactionBatch(batch) => Transactions(batch.to_protobuf()),
+            }),
+        }
+    }
+}
+
+impl TryFromProtobuf<proto::consensus::ProposalPart> for ProposalPart {
+    fn try_from_protobuf(
+        input: proto::consensus::ProposalPart,
+        field_name: &'static str,
+    ) -> Result<Self, std::io::Error> {
+        use proto::consensus::proposal_part::Messages::{Fin, Init, Transactions};
+        Ok(match proto_field(input.messages, field_name)? {
+            Init(init) => Self::Init(TryFromProtobuf::try_from_protobuf(init, field_name)?),
+            Fin(fin) => Self::Fin(TryFromProtobuf::try_from_protobuf(fin, field_name)?),
+            Transactions(batch) => {
+                Self::TransactionBatch(TryFromProtobuf::try_from_protobuf(batch, field_name)?)
+            }
+        })
+    }
+}
@@ -7,6 +7,9 @@ pub mod proto {
     pub mod common {
         include!(concat!(env!("OUT_DIR"), "/starknet.common.rs"));
     }
+    pub mod consensus {
+        include!(concat!(env!("OUT_DIR"), "/starknet.consensus.rs"));
+    }
     pub mod event {
         include!(concat!(env!("OUT_DIR"), "/starknet.event.rs"));
     }
@@ -15,6 +18,10 @@ pub mod proto {
         include!(concat!(env!("OUT_DIR"), "/starknet.header.rs"));
     }
     #[allow(clippy::large_enum_variant)]
+    pub mod pending {
+        include!(concat!(env!("OUT_DIR"), "/starknet.pending.rs"));
+    }
+    #[allow(clippy::large_enum_variant)]
     pub mod receipt {
         include!(concat!(env!("OUT_DIR"), "/starknet.receipt.rs"));
     }
@@ -167,8 +174,10 @@ fn proto_field<T>(input: Option<T>, field_name: &'static str) -> Result<T, std::
 use p2p_proto_derive::*;
 pub mod class;
 pub mod common;
+pub mod consensus;
 pub mod event;
 pub mod header;
+pub mod pending;
 pub mod receipt;
 pub mod state;
 pub mod transaction;
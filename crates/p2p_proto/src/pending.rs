@@ -0,0 +1,104 @@
+use fake::Dummy;
+
+use crate::common::{Address, Hash, L1DataAvailabilityMode};
+use crate::event::Event;
+use crate::state::{ContractDiff, DeclaredClass};
+use crate::transaction::TransactionWithReceipt;
+use crate::{proto, proto_field, ToProtobuf, TryFromProtobuf};
+
+#[derive(Debug, Clone, PartialEq, Eq, ToProtobuf, TryFromProtobuf, Dummy)]
+#[protobuf(name = "crate::proto::pending::PendingBlockHeader")]
+pub struct PendingBlockHeader {
+    pub parent_hash: Hash,
+    pub number: u64,
+    pub time: u64,
+    pub sequencer_address: Address,
+    pub protocol_version: String,
+    pub gas_price_fri: u128,
+    pub gas_price_wei: u128,
+    pub data_gas_price_fri: u128,
+    pub data_gas_price_wei: u128,
+    pub l2_gas_price_fri: u128,
+    pub l2_gas_price_wei: u128,
+    pub l1_data_availability_mode: L1DataAvailabilityMode,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ToProtobuf, TryFromProtobuf, Dummy)]
+#[protobuf(name = "crate::proto::pending::PendingBlockRequest")]
+pub struct PendingBlockRequest {
+    pub parent_hash: Hash,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ToProtobuf, TryFromProtobuf, Dummy)]
+#[protobuf(name = "crate::proto::pending::NewPendingBlock")]
+pub struct NewPendingBlock {
+    pub parent_hash: Hash,
+    pub transaction_count: u64,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Dummy)]
+pub enum PendingBlockResponse {
+    Header(PendingBlockHeader),
+    TransactionWithReceipt(TransactionWithReceipt),
+    Event(Event),
+    ContractDiff(ContractDiff),
+    DeclaredClass(DeclaredClass),
+    #[default]
+    Fin,
+}
+
+impl ToProtobuf<proto::pending::PendingBlockResponse> for PendingBlockResponse {
+    fn to_protobuf(self) -> proto::pending::PendingBlockResponse {
+        use proto::pending::pending_block_response::PendingBlockMessage::{
+            ContractDiff,
+            DeclaredClass,
+            Event,
+            Fin,
+            Header,
+            TransactionWithReceipt,
+        };
+        proto::pending::PendingBlockResponse {
+            pending_block_message: Some(match self {
+                Self::Header(header) => Header(header.to_protobuf()),
+                Self::TransactionWithReceipt(transaction) => {
+                    TransactionWithReceipt(transaction.to_protobuf())
+                }
+                Self::Event(event) => Event(event.to_protobuf()),
+                Self::ContractDiff(contract_diff) => ContractDiff(contract_diff.to_protobuf()),
+                Self::DeclaredClass(declared_class) => DeclaredClass(declared_class.to_protobuf()),
+                Self::Fin => Fin(proto::common::Fin {}),
+            }),
+        }
+    }
+}
+
+impl TryFromProtobuf<proto::pending::PendingBlockResponse> for PendingBlockResponse {
+    fn try_from_protobuf(
+        input: proto::pending::PendingBlockResponse,
+        field_name: &'static str,
+    ) -> Result<Self, std::io::Error> {
+        use proto::pending::pending_block_response::PendingBlockMessage::{
+            ContractDiff,
+            DeclaredClass,
+            Event,
+            Fin,
+            Header,
+            TransactionWithReceipt,
+        };
+        match proto_field(input.pending_block_message, field_name)? {
+            Header(x) => TryFromProtobuf::try_from_protobuf(x, field_name).map(Self::Header),
+            TransactionWithReceipt(x) => {
+                TryFromProtobuf::try_from_protobuf(x, field_name).map(Self::TransactionWithReceipt)
+            }
+            Event(x) => TryFromProtobuf::try_from_protobuf(x, field_name).map(Self::Event),
+            ContractDiff(x) => {
+                TryFromProtobuf::try_from_protobuf(x, field_name).map(Self::ContractDiff)
+            }
+            DeclaredClass(x) => {
+                TryFromProtobuf::try_from_protobuf(x, field_name).map(Self::DeclaredClass)
+            }
+            Fin(_) => Ok(Self::Fin),
+        }
+    }
+}
@@ -16,11 +16,14 @@
 ///     --data-directory /tmp
 /// ```
 ///
+/// The node serves the same endpoints from its own database when started with
+/// `--feeder-gateway.address`. Use this tool to serve an existing database
+/// without running a node, or to test how pathfinder handles reorgs.
+///
 /// Optionally this tool can simulate reorgs. To have the tool return data so
 /// that pathfinder reorgs from block 50 to 40 use the following command line:
 /// `cargo run --release -p pathfinder --example feeder_gateway
 /// ./testnet-sepolia.sqlite --reorg-at-block 50 --reorg-to-block 40`
-use std::collections::HashMap;
 use std::convert::Infallible;
 use std::num::NonZeroU32;
 use std::path::PathBuf;
@@ -30,19 +33,11 @@ use std::sync::Arc;
 use anyhow::Context;
 use clap::{Args, Parser};
 use pathfinder_common::prelude::*;
-use pathfinder_common::state_update::ContractClassUpdate;
 use pathfinder_common::Chain;
-use pathfinder_lib::state::block_hash::calculate_receipt_commitment;
+use pathfinder_lib::feeder_gateway;
 use pathfinder_storage::BlockId;
 use primitive_types::H160;
 use serde::{Deserialize, Serialize};
-use starknet_gateway_types::reply::state_update::{
-    DeclaredSierraClass,
-    DeployedContract,
-    ReplacedClass,
-    StorageDiff,
-};
-use starknet_gateway_types::reply::{GasPrices, Status};
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, EnvFilter};
 use warp::Filter;
@@ -408,67 +403,7 @@ fn resolve_block(
     tx: &pathfinder_storage::Transaction<'_>,
     block_id: BlockId,
 ) -> anyhow::Result<starknet_gateway_types::reply::Block> {
-    let header = tx
-        .block_header(block_id)
-        .context("Fetching block header")?
-        .context("Block header missing")?;
-
-    let transactions_receipts = tx
-        .transaction_data_for_block(header.number.into())
-        .context("Reading transactions from database")?
-        .context("Transaction data missing")?;
-
-    let receipts = transactions_receipts
-        .iter()
-        .map(|(_, r, _)| r.clone())
-        .collect::<Vec<_>>();
-
-    let receipt_commitment = calculate_receipt_commitment(&receipts)?;
-
-    let (transactions, transaction_receipts): (Vec<_>, Vec<_>) = transactions_receipts
-        .into_iter()
-        .map(|(tx, rx, ev)| (tx, (rx, ev)))
-        .unzip();
-
-    let block_status = tx
-        .block_is_l1_accepted(header.number.into())
-        .context("Querying block status")?;
-    let block_status = if block_status {
-        Status::AcceptedOnL1
-    } else {
-        Status::AcceptedOnL2
-    };
-
-    Ok(starknet_gateway_types::reply::Block {
-        block_hash: header.hash,
-        block_number: header.number,
-        l1_gas_price: GasPrices {
-            price_in_wei: header.eth_l1_gas_price,
-            price_in_fri: header.strk_l1_gas_price,
-        },
-        l1_data_gas_price: GasPrices {
-            price_in_wei: header.eth_l1_data_gas_price,
-            price_in_fri: header.strk_l1_data_gas_price,
-        },
-        l2_gas_price: Some(GasPrices {
-            price_in_wei: header.eth_l2_gas_price,
-            price_in_fri: header.strk_l2_gas_price,
-        }),
-        parent_block_hash: header.parent_hash,
-        sequencer_address: Some(header.sequencer_address),
-        state_commitment: header.state_commitment,
-        status: block_status,
-        timestamp: header.timestamp,
-        transaction_receipts,
-        transactions,
-        starknet_version: header.starknet_version,
-        l1_da_mode: header.l1_da_mode.into(),
-        transaction_commitment: header.transaction_commitment,
-        event_commitment: header.event_commitment,
-        receipt_commitment: Some(receipt_commitment),
-        state_diff_commitment: Some(header.state_diff_commitment),
-        state_diff_length: Some(header.state_diff_length),
-    })
+    feeder_gateway::block(tx, block_id)?.context("Block missing")
 }
 
 #[tracing::instrument(level = "trace", skip(tx))]
@@ -476,24 +411,7 @@ fn resolve_signature(
     tx: &pathfinder_storage::Transaction<'_>,
     block_id: BlockId,
 ) -> anyhow::Result<starknet_gateway_types::reply::BlockSignature> {
-    let header = tx
-        .block_header(block_id)
-        .context("Fetching block header")?
-        .context("Block header missing")?;
-
-    let signature = tx
-        .signature(block_id)
-        .context("Fetching signature")?
-        // fall back to zero since we might have missing signatures in old DBs
-        .unwrap_or(BlockCommitmentSignature {
-            r: BlockCommitmentSignatureElem::ZERO,
-            s: BlockCommitmentSignatureElem::ZERO,
-        });
-
-    Ok(starknet_gateway_types::reply::BlockSignature {
-        block_hash: header.hash,
-        signature: [signature.r, signature.s],
-    })
+    feeder_gateway::signature(tx, block_id)?.context("Block missing")
 }
 
 #[tracing::instrument(level = "trace", skip(tx))]
@@ -536,10 +454,7 @@ fn resolve_state_update(
         block
     };
 
-    tx.state_update(block)
-        .context("Fetching state update")?
-        .context("State update missing")
-        .map(storage_to_gateway)
+    feeder_gateway::state_update(tx, block)?.context("State update missing")
 }
 
 #[tracing::instrument(level = "trace", skip(tx))]
@@ -554,77 +469,3 @@ fn resolve_class(
 
     Ok(definition)
 }
-
-fn storage_to_gateway(
-    state_update: pathfinder_common::StateUpdate,
-) -> starknet_gateway_types::reply::StateUpdate {
-    let mut storage_diffs = HashMap::new();
-    let mut deployed_contracts = Vec::new();
-    let mut nonces = HashMap::new();
-    let mut replaced_classes = Vec::new();
-
-    for (address, update) in state_update.contract_updates {
-        if let Some(nonce) = update.nonce {
-            nonces.insert(address, nonce);
-        }
-
-        match update.class {
-            Some(ContractClassUpdate::Deploy(class_hash)) => {
-                deployed_contracts.push(DeployedContract {
-                    address,
-                    class_hash,
-                })
-            }
-            Some(ContractClassUpdate::Replace(class_hash)) => {
-                replaced_classes.push(ReplacedClass {
-                    address,
-                    class_hash,
-                })
-            }
-            None => {}
-        }
-
-        let storage = update
-            .storage
-            .into_iter()
-            .map(|(key, value)| StorageDiff { key, value })
-            .collect();
-
-        storage_diffs.insert(address, storage);
-    }
-
-    for (address, update) in state_update.system_contract_updates {
-        let storage = update
-            .storage
-            .into_iter()
-            .map(|(key, value)| StorageDiff { key, value })
-            .collect();
-
-        storage_diffs.insert(address, storage);
-    }
-
-    let declared_classes = state_update
-        .declared_sierra_classes
-        .into_iter()
-        .map(|(class_hash, compiled_class_hash)| DeclaredSierraClass {
-            class_hash,
-            compiled_class_hash,
-        })
-        .collect();
-
-    let state_diff = starknet_gateway_types::reply::state_update::StateDiff {
-        storage_diffs,
-        deployed_contracts,
-        old_declared_contracts: state_update.declared_cairo_classes,
-        declared_classes,
-        nonces,
-        replaced_classes,
-    };
-
-    starknet_gateway_types::reply::StateUpdate {
-        block_hash: state_update.block_hash,
-        new_root: state_update.state_commitment,
-        old_root: state_update.parent_state_commitment,
-        state_diff,
-    }
-}
@@ -9,6 +9,8 @@ use std::time::Duration;
 use clap::{ArgAction, CommandFactory, Parser};
 use pathfinder_common::{AllowedOrigins, StarknetVersion};
 use pathfinder_executor::VersionedConstantsMap;
+use pathfinder_rpc::method_filter::{MethodFilter, MethodFilters};
+use pathfinder_rpc::middleware::auth::AuthConfig;
 use pathfinder_storage::JournalMode;
 use reqwest::Url;
 use util::percentage::Percentage;
@@ -26,7 +28,11 @@ use p2p::{P2PConsensusConfig, P2PSyncConfig};
 #[command(
     about = "A Starknet node implemented by Equilibrium Labs. Submit bug reports and issues at https://github.com/eqlabs/pathfinder."
 )]
+#[command(subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(
         long,
         value_name = "DIR", 
@@ -39,7 +45,7 @@ struct Cli {
 
     #[arg(
         long = "ethereum.password",
-        long_help = "The optional password to use for the Ethereum API",
+        long_help = "The optional password to use for the Ethereum API. It is used for all Ethereum URLs which don't contain a password themselves.",
         value_name = None,
         env = "PATHFINDER_ETHEREUM_API_PASSWORD", 
     )]
@@ -47,16 +53,31 @@ struct Cli {
 
     #[arg(
         long = "ethereum.url",
-        long_help = r"This should point to the WS RPC endpoint of your Ethereum entry-point, typically a local Ethereum client or a hosted gateway service such as Infura, Alchemy or Cloudflare.
+        long_help = r"This should point to the WS or HTTP RPC endpoint of your Ethereum entry-point, typically a local Ethereum client or a hosted gateway service such as Infura, Alchemy or Cloudflare. Over HTTP, new Starknet state updates are polled for with `eth_getLogs` instead of being subscribed to.
 
 Examples:
     alchemy: wss://eth-mainnet.g.alchemy.com/v2/<PROJECT_ID>
-    geth:    wss://localhost:8545",
+    geth:    wss://localhost:8545
+
+Multiple comma separated URLs of independent providers can be given, in which case requests fail over between them and `--ethereum.quorum` of them have to agree on the Starknet state.",
         value_name = "HTTP(s) URL",
         value_hint = clap::ValueHint::Url,
+        value_delimiter = ',',
         env = "PATHFINDER_ETHEREUM_API_URL", 
+        required = true
     )]
-    ethereum_url: Url,
+    ethereum_url: Vec<Url>,
+
+    #[arg(
+        long = "ethereum.quorum",
+        long_help = "The number of Ethereum URLs which have to agree on the finalized Starknet \
+                     state before it is accepted as the L1 state. Must not exceed the number of \
+                     URLs.",
+        value_name = "NUMBER",
+        env = "PATHFINDER_ETHEREUM_QUORUM",
+        default_value = "1"
+    )]
+    ethereum_quorum: NonZeroUsize,
 
     #[arg(
         long = "http-rpc",
@@ -99,6 +120,28 @@ Examples:
     )]
     execution_concurrency: Option<NonZeroU32>,
 
+    #[arg(
+        long = "rpc.request-max-size",
+        value_name = "Bytes",
+        long_help = "The maximum size of JSON-RPC request bodies and websocket messages",
+        default_value = "10485760",
+        env = "PATHFINDER_RPC_REQUEST_MAX_SIZE"
+    )]
+    rpc_request_max_size: NonZeroUsize,
+
+    #[arg(
+        long = "rpc.request-timeout",
+        value_name = "Seconds",
+        long_help = "How long serving a JSON-RPC request may take before it is answered with `408 \
+                     Request Timeout`",
+        default_value = "120",
+        env = "PATHFINDER_RPC_REQUEST_TIMEOUT"
+    )]
+    rpc_request_timeout: std::num::NonZeroU64,
+
+    #[clap(flatten)]
+    execution_deadlines: ExecutionDeadlinesCli,
+
     #[arg(
         long = "monitor-address",
         long_help = "The address at which pathfinder will serve monitoring related information",
@@ -107,6 +150,17 @@ Examples:
     )]
     monitor_address: Option<SocketAddr>,
 
+    #[arg(
+        long = "feeder-gateway.address",
+        long_help = "Serve the feeder gateway REST API from the local database at this address, \
+                     so that other nodes can use this node as their feeder gateway with \
+                     `--feeder-gateway-url http://<IP:PORT>/feeder_gateway`. Only the \
+                     endpoints required for syncing are served, pending data is not.",
+        value_name = "IP:PORT",
+        env = "PATHFINDER_FEEDER_GATEWAY_ADDRESS"
+    )]
+    feeder_gateway_address: Option<SocketAddr>,
+
     #[clap(flatten)]
     network: NetworkCli,
 
@@ -205,6 +259,30 @@ Examples:
     #[clap(flatten)]
     websocket: WebsocketConfig,
 
+    #[clap(flatten)]
+    mempool: MempoolConfig,
+
+    #[clap(flatten)]
+    historical_state: HistoricalStateConfig,
+
+    #[clap(flatten)]
+    trace_cache: TraceCacheConfig,
+
+    #[clap(flatten)]
+    response_cache: ResponseCacheConfig,
+
+    #[clap(flatten)]
+    admin: AdminConfig,
+
+    #[clap(flatten)]
+    rate_limit: RateLimitConfig,
+
+    #[clap(flatten)]
+    auth: AuthCli,
+
+    #[clap(flatten)]
+    method_filters: MethodFiltersCli,
+
     #[arg(
         long = "sync.verify_tree_node_data",
         long_help = r"When enabled, state tree node hashes are verified when loaded from disk.
@@ -219,6 +297,18 @@ This should only be enabled for debugging purposes as it adds substantial proces
     )]
     verify_tree_node_data: bool,
 
+    #[arg(
+        long = "sync.re-execution-verifier.enabled",
+        long_help = r"When enabled, every newly synced block is re-executed in the background and the results are compared against the block's receipts and state update.
+
+Mismatches are logged and exported as the `re_execution_mismatches_total` metric. This is useful for catching execution divergences, but adds the cost of executing every block.
+",
+        default_value = "false",
+        env = "PATHFINDER_SYNC_RE_EXECUTION_VERIFIER_ENABLED",
+        value_name = "BOOL"
+    )]
+    re_execution_verifier: bool,
+
     #[arg(
         long = "rpc.batch-concurrency-limit",
         long_help = "Sets the concurrency limit for request batch processing. May lower the \
@@ -313,6 +403,8 @@ This should only be enabled for debugging purposes as it adds substantial proces
         long = "storage.blockchain-history",
         long_help = "When set to `archive` all historical blockchain data is preserved. When set to an integer N, only the last N+1 blocks of the blockchain are kept in the database. \
             This can be used to reduce the disk space usage at the cost of only being able to provide information for the latest N+1 blocks (the state for the latest block is always stored). \
+            N can be changed on an existing database, and an archive database can be converted to a pruned one; reducing the history removes the excess blocks in the background. \
+            A pruned database cannot be converted back to `archive`. \
             Defaults to `archive` if not specified.",
         env = "PATHFINDER_STORAGE_BLOCKCHAIN_HISTORY",
         value_name = "archive | N",
@@ -331,6 +423,28 @@ This should only be enabled for debugging purposes as it adds substantial proces
     )]
     state_tries: Option<StateTries>,
 
+    #[arg(
+        long = "storage.restore-from-snapshot",
+        long_help = "Create the database from a zstd-compressed snapshot, e.g. one created with \
+                     `pathfinder snapshot`. The snapshot's checksum, schema revision and the \
+                     state commitment of its latest block are verified first, and the node \
+                     refuses to start if any of them are inconsistent. Ignored if the database \
+                     already exists.",
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        env = "PATHFINDER_STORAGE_RESTORE_FROM_SNAPSHOT"
+    )]
+    restore_from_snapshot: Option<PathBuf>,
+
+    #[arg(
+        long = "storage.restore-from-snapshot.allow-missing-manifest",
+        long_help = "Restore a snapshot which has no `.manifest.json` file next to it. The \
+                     snapshot's checksum cannot be verified in this case.",
+        default_value = "false",
+        env = "PATHFINDER_STORAGE_RESTORE_FROM_SNAPSHOT_ALLOW_MISSING_MANIFEST"
+    )]
+    restore_allow_missing_manifest: bool,
+
     #[arg(
         long = "rpc.custom-versioned-constants-json-path",
         long_help = "Path to a JSON file referencing sequencer versioned constants. The file maps \
@@ -478,11 +592,25 @@ Note that 'custom' requires also setting the --gateway-url and --feeder-gateway-
         long = "feeder-gateway-url",
         value_name = "URL",
         value_hint = clap::ValueHint::Url,
-        long_help = "Specify a custom Starknet feeder gateway url. Can be used to run pathfinder on a custom Starknet network, or to use a gateway proxy. Requires '--network custom'.",
-        env = "PATHFINDER_FEEDER_GATEWAY_URL", 
+        long_help = "Specify a custom Starknet feeder gateway url. Can be used to run pathfinder on a custom Starknet network, or to use a gateway proxy. Requires '--network custom'.
+
+Multiple comma separated URLs may be given, e.g. several mirrors of the same network. Requests go to the fastest URL which is up, and fail over to the others when it is down.",
+        env = "PATHFINDER_FEEDER_GATEWAY_URL",
+        value_delimiter = ',',
         required_if_eq("network", Network::Custom),
     )]
-    feeder_gateway: Option<Url>,
+    feeder_gateway: Vec<Url>,
+
+    #[arg(
+        long = "feeder-gateway.public-fallback",
+        long_help = "If the custom feeder gateways are proxies of a known network, fall back to \
+                     the network's public feeder gateway while all of them are down.",
+        action = clap::ArgAction::Set,
+        default_value = "false",
+        requires = "feeder_gateway",
+        env = "PATHFINDER_FEEDER_GATEWAY_PUBLIC_FALLBACK"
+    )]
+    feeder_gateway_public_fallback: bool,
 
     #[arg(
         long = "gateway-url",
@@ -605,6 +733,24 @@ fn parse_cors(inputs: Vec<String>) -> Result<Option<AllowedOrigins>, RpcCorsDoma
     )))
 }
 
+pub fn parse_ethereum_quorum_or_exit(quorum: NonZeroUsize, urls: &[Url]) -> NonZeroUsize {
+    use clap::error::ErrorKind;
+
+    if quorum.get() > urls.len() {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "The Ethereum quorum of {quorum} exceeds the number of Ethereum URLs ({}).",
+                    urls.len()
+                ),
+            )
+            .exit()
+    }
+
+    quorum
+}
+
 pub fn parse_cors_or_exit(input: Vec<String>) -> Option<AllowedOrigins> {
     use clap::error::ErrorKind;
 
@@ -627,6 +773,127 @@ enum RpcCorsDomainsParseError {
     WildcardAmongOtherValues,
 }
 
+fn parse_auth(cli: AuthCli) -> Result<Option<AuthConfig>, AuthParseError> {
+    if cli.routes.is_empty() {
+        return Ok(None);
+    }
+
+    if let Some(route) = cli.routes.iter().find(|route| !route.starts_with('/')) {
+        return Err(AuthParseError::InvalidRoute(route.clone()));
+    }
+
+    let jwt_secret = match cli.jwt_secret_path {
+        Some(path) => {
+            let secret = std::fs::read_to_string(path)?;
+            let secret = secret.trim();
+            let secret = secret.strip_prefix("0x").unwrap_or(secret);
+            let secret = hex::decode(secret).map_err(|_| AuthParseError::InvalidJwtSecret)?;
+            if secret.len() != 32 {
+                return Err(AuthParseError::InvalidJwtSecret);
+            }
+            Some(secret)
+        }
+        None => None,
+    };
+
+    if cli.api_keys.is_empty() && jwt_secret.is_none() {
+        return Err(AuthParseError::MissingCredentials);
+    }
+
+    Ok(Some(AuthConfig {
+        routes: cli.routes.into_iter().collect(),
+        api_keys: cli.api_keys.into_iter().collect(),
+        jwt_secret,
+    }))
+}
+
+pub fn parse_auth_or_exit(cli: AuthCli) -> Option<AuthConfig> {
+    use clap::error::ErrorKind;
+
+    match parse_auth(cli) {
+        Ok(parsed) => parsed,
+        Err(error) => Cli::command()
+            .error(ErrorKind::ValueValidation, error)
+            .exit(),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum AuthParseError {
+    #[error("Authenticated RPC routes must be paths starting with '/': {0}.")]
+    InvalidRoute(String),
+    #[error("IO error while reading the JWT secret: {0}.")]
+    Io(#[from] std::io::Error),
+    #[error("The JWT secret must be 32 hex encoded bytes.")]
+    InvalidJwtSecret,
+    #[error("Authenticated RPC routes require API keys or a JWT secret.")]
+    MissingCredentials,
+}
+
+fn parse_method_filters(cli: MethodFiltersCli) -> Result<MethodFilters, MethodFilterParseError> {
+    let mut filters = MethodFilters::default();
+
+    for pattern in cli.allow {
+        add_method_pattern(&mut filters, pattern, |filter| &mut filter.allow)?;
+    }
+    for pattern in cli.deny {
+        add_method_pattern(&mut filters, pattern, |filter| &mut filter.deny)?;
+    }
+
+    Ok(filters)
+}
+
+/// Adds the pattern to the list selected from the filter of its endpoint, or
+/// from all filters if it isn't prefixed with an endpoint.
+fn add_method_pattern(
+    filters: &mut MethodFilters,
+    pattern: String,
+    select: fn(&mut MethodFilter) -> &mut Vec<String>,
+) -> Result<(), MethodFilterParseError> {
+    let Some((endpoint, method_pattern)) = pattern.split_once(':') else {
+        for filter in [
+            &mut filters.v06,
+            &mut filters.v07,
+            &mut filters.v08,
+            &mut filters.pathfinder,
+        ] {
+            select(filter).push(pattern.clone());
+        }
+        return Ok(());
+    };
+
+    let filter = match endpoint {
+        "v06" => &mut filters.v06,
+        "v07" => &mut filters.v07,
+        "v08" => &mut filters.v08,
+        "pathfinder" => &mut filters.pathfinder,
+        _ => return Err(MethodFilterParseError::UnknownEndpoint(pattern.clone())),
+    };
+    select(filter).push(method_pattern.to_owned());
+
+    Ok(())
+}
+
+pub fn parse_method_filters_or_exit(cli: MethodFiltersCli) -> MethodFilters {
+    use clap::error::ErrorKind;
+
+    match parse_method_filters(cli) {
+        Ok(parsed) => parsed,
+        Err(error) => Cli::command()
+            .error(ErrorKind::ValueValidation, error)
+            .exit(),
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+enum MethodFilterParseError {
+    #[error(
+        "Invalid method pattern: {0}. Only the v06, v07, v08 and pathfinder endpoints can be \
+         selected."
+    )]
+    UnknownEndpoint(String),
+}
+
 fn parse_versioned_constants(
     path: PathBuf,
 ) -> Result<VersionedConstantsMap, ParseVersionedConstantsError> {
@@ -700,9 +967,21 @@ pub struct Config {
     pub rpc_cors_domains: Option<AllowedOrigins>,
     pub rpc_root_version: RootRpcVersion,
     pub websocket: WebsocketConfig,
+    pub mempool: MempoolConfig,
+    pub historical_state: HistoricalStateConfig,
+    pub response_cache: ResponseCacheConfig,
+    pub trace_cache: TraceCacheConfig,
+    pub admin: AdminConfig,
+    pub rate_limit: RateLimitConfig,
+    pub auth: Option<AuthConfig>,
+    pub method_filters: MethodFilters,
     pub monitor_address: Option<SocketAddr>,
+    pub feeder_gateway_address: Option<SocketAddr>,
     pub network: Option<NetworkConfig>,
     pub execution_concurrency: Option<std::num::NonZeroU32>,
+    pub rpc_request_max_size: NonZeroUsize,
+    pub rpc_request_timeout: Duration,
+    pub execution_deadlines: pathfinder_rpc::context::ExecutionDeadlines,
     pub sqlite_wal: JournalMode,
     pub max_rpc_connections: std::num::NonZeroUsize,
     pub poll_interval: Duration,
@@ -714,6 +993,7 @@ pub struct Config {
     pub consensus_p2p: P2PConsensusConfig,
     pub debug: DebugConfig,
     pub verify_tree_hashes: bool,
+    pub re_execution_verifier: bool,
     pub rpc_batch_concurrency_limit: NonZeroUsize,
     pub is_sync_enabled: bool,
     pub is_rpc_enabled: bool,
@@ -724,6 +1004,8 @@ pub struct Config {
     pub get_events_max_uncached_event_filters_to_load: NonZeroUsize,
     pub blockchain_history: Option<BlockchainHistory>,
     pub state_tries: Option<StateTries>,
+    pub restore_from_snapshot: Option<PathBuf>,
+    pub restore_allow_missing_manifest: bool,
     pub versioned_constants_map: VersionedConstantsMap,
     pub feeder_gateway_fetch_concurrency: NonZeroUsize,
     pub fetch_casm_from_fgw: bool,
@@ -733,8 +1015,9 @@ pub struct Config {
 }
 
 pub struct Ethereum {
-    pub url: Url,
+    pub urls: Vec<Url>,
     pub password: Option<String>,
+    pub quorum: NonZeroUsize,
 }
 
 #[derive(Clone)]
@@ -744,7 +1027,8 @@ pub enum NetworkConfig {
     SepoliaIntegration,
     Custom {
         gateway: Url,
-        feeder_gateway: Url,
+        feeder_gateways: Vec<Url>,
+        public_fallback: bool,
         chain_id: String,
     },
 }
@@ -771,14 +1055,15 @@ impl NetworkConfig {
         let cfg = match (
             args.network,
             args.gateway,
-            args.feeder_gateway,
+            (!args.feeder_gateway.is_empty()).then_some(args.feeder_gateway),
             args.chain_id,
         ) {
             (None, None, None, None) => return None,
-            (Some(Custom), Some(gateway), Some(feeder_gateway), Some(chain_id)) => {
+            (Some(Custom), Some(gateway), Some(feeder_gateways), Some(chain_id)) => {
                 NetworkConfig::Custom {
                     gateway,
-                    feeder_gateway,
+                    feeder_gateways,
+                    public_fallback: args.feeder_gateway_public_fallback,
                     chain_id,
                 }
             }
@@ -866,26 +1151,56 @@ impl NativeExecutionConfig {
     }
 }
 
-impl Config {
-    #[cfg_attr(not(feature = "cairo-native"), allow(clippy::unit_arg))]
+/// What pathfinder was invoked to do.
+pub enum Mode {
+    /// Run the node.
+    Node(Box<Config>),
+    /// Run a subcommand and exit.
+    Command(Command),
+}
+
+impl Mode {
     pub fn parse() -> Self {
         let cli = Cli::parse();
 
+        match cli.command {
+            Some(command) => Mode::Command(command),
+            None => Mode::Node(Box::new(Config::from_cli(cli))),
+        }
+    }
+}
+
+impl Config {
+    #[cfg_attr(not(feature = "cairo-native"), allow(clippy::unit_arg))]
+    fn from_cli(cli: Cli) -> Self {
         let network = NetworkConfig::from_components(cli.network);
 
         Config {
             data_directory: cli.data_directory,
             ethereum: Ethereum {
                 password: cli.ethereum_password,
-                url: cli.ethereum_url,
+                quorum: parse_ethereum_quorum_or_exit(cli.ethereum_quorum, &cli.ethereum_url),
+                urls: cli.ethereum_url,
             },
             rpc_address: cli.rpc_address,
             rpc_cors_domains: parse_cors_or_exit(cli.rpc_cors_domains),
             rpc_root_version: cli.rpc_root_version,
             websocket: cli.websocket,
+            mempool: cli.mempool,
+            historical_state: cli.historical_state,
+            response_cache: cli.response_cache,
+            admin: cli.admin,
+            rate_limit: cli.rate_limit,
+            auth: parse_auth_or_exit(cli.auth),
+            method_filters: parse_method_filters_or_exit(cli.method_filters),
+            trace_cache: cli.trace_cache,
             monitor_address: cli.monitor_address,
+            feeder_gateway_address: cli.feeder_gateway_address,
             network,
             execution_concurrency: cli.execution_concurrency,
+            rpc_request_max_size: cli.rpc_request_max_size,
+            rpc_request_timeout: Duration::from_secs(cli.rpc_request_timeout.get()),
+            execution_deadlines: cli.execution_deadlines.into(),
             sqlite_wal: match cli.sqlite_wal {
                 true => JournalMode::WAL,
                 false => JournalMode::Rollback,
@@ -900,6 +1215,7 @@ impl Config {
             consensus_p2p: P2PConsensusConfig::parse_or_exit(cli.p2p_consensus),
             debug: DebugConfig::parse(cli.debug),
             verify_tree_hashes: cli.verify_tree_node_data,
+            re_execution_verifier: cli.re_execution_verifier,
             rpc_batch_concurrency_limit: cli.rpc_batch_concurrency_limit,
             is_sync_enabled: cli.is_sync_enabled,
             is_rpc_enabled: cli.is_rpc_enabled,
@@ -912,6 +1228,8 @@ impl Config {
             feeder_gateway_fetch_concurrency: cli.feeder_gateway_fetch_concurrency,
             blockchain_history: cli.blockchain_history,
             state_tries: cli.state_tries,
+            restore_from_snapshot: cli.restore_from_snapshot,
+            restore_allow_missing_manifest: cli.restore_allow_missing_manifest,
             versioned_constants_map: cli
                 .custom_versioned_constants_path
                 .map(parse_versioned_constants_or_exit)
@@ -924,6 +1242,42 @@ impl Config {
     }
 }
 
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Create a zstd-compressed snapshot of the database while the node keeps
+    /// running.
+    Snapshot(SnapshotConfig),
+}
+
+#[derive(clap::Args, Clone)]
+pub struct SnapshotConfig {
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "The database file to create a snapshot of, e.g. \
+                     `<data-directory>/mainnet.sqlite`. The node using the database can keep \
+                     running while the snapshot is created."
+    )]
+    pub database: PathBuf,
+    #[arg(
+        long = "output-directory",
+        value_name = "DIR",
+        value_hint = clap::ValueHint::DirPath,
+        long_help = "Directory in which the compressed database and its manifest are created",
+        default_value_os_t = (&std::path::Component::CurDir).into()
+    )]
+    pub output_directory: PathBuf,
+    #[arg(
+        long,
+        value_name = "BLOCK_NUMBER",
+        long_help = "The latest block of the snapshot. The copy of the database is reverted to \
+                     this block, which requires the state tries of the block to be available. \
+                     Defaults to the latest block of the database."
+    )]
+    pub block: Option<u64>,
+}
+
 #[derive(clap::Args, Clone)]
 pub struct WebsocketConfig {
     #[arg(
@@ -956,6 +1310,315 @@ pub struct WebsocketConfig {
     pub topic_sender_capacity: NonZeroUsize,
 }
 
+#[derive(clap::Args, Clone)]
+pub struct MempoolConfig {
+    #[arg(
+        long = "rpc.mempool.enabled",
+        long_help = "Validate submitted transactions against the latest state before relaying \
+                     them to the gateway. Invalid and duplicate transactions are rejected \
+                     locally.",
+        default_value = "false",
+        env = "PATHFINDER_RPC_MEMPOOL_ENABLED"
+    )]
+    pub enabled: bool,
+    #[arg(
+        long = "rpc.mempool.capacity",
+        long_help = "The maximum number of relayed transaction hashes remembered for \
+                     deduplication",
+        value_name = "CAPACITY",
+        default_value = "10000",
+        env = "PATHFINDER_RPC_MEMPOOL_CAPACITY"
+    )]
+    pub capacity: NonZeroUsize,
+    #[arg(
+        long = "rpc.mempool.ttl",
+        long_help = "How long relayed transaction hashes are remembered for deduplication",
+        value_name = "Seconds",
+        default_value = "300",
+        env = "PATHFINDER_RPC_MEMPOOL_TTL"
+    )]
+    pub ttl: std::num::NonZeroU64,
+}
+
+#[derive(clap::Args, Clone)]
+pub struct HistoricalStateConfig {
+    #[arg(
+        long = "rpc.historical-state.enabled",
+        long_help = "Answer state queries, `starknet_call`, `starknet_estimateFee` and \
+                     `starknet_simulateTransactions` for blocks removed by blockchain history \
+                     pruning by fetching the missing state diffs from the feeder gateway.",
+        default_value = "false",
+        env = "PATHFINDER_RPC_HISTORICAL_STATE_ENABLED"
+    )]
+    pub enabled: bool,
+    #[arg(
+        long = "rpc.historical-state.cache-size",
+        long_help = "The maximum number of state diffs fetched from the feeder gateway kept in \
+                     memory",
+        value_name = "BLOCKS",
+        default_value = "128",
+        env = "PATHFINDER_RPC_HISTORICAL_STATE_CACHE_SIZE"
+    )]
+    pub cache_size: NonZeroUsize,
+    #[arg(
+        long = "rpc.historical-state.max-blocks",
+        long_help = "The maximum number of state diffs fetched from the feeder gateway to answer \
+                     a single request. Requests needing more fail with `BLOCK_NOT_FOUND`.",
+        value_name = "BLOCKS",
+        default_value = "1000",
+        env = "PATHFINDER_RPC_HISTORICAL_STATE_MAX_BLOCKS"
+    )]
+    pub max_blocks: std::num::NonZeroU64,
+}
+
+#[derive(clap::Args, Clone)]
+pub struct AdminConfig {
+    #[arg(
+        id = "rpc_admin_address",
+        long = "rpc.admin.address",
+        long_help = "Serve the `admin_*` JSON-RPC methods for node operations on this address. \
+                     The methods are not served on the regular RPC address. \
+                     `admin_pauseSync`, `admin_resumeSync` and `admin_revertToBlock` are not \
+                     supported in p2p sync and fail with an error.",
+        value_name = "IP:PORT",
+        requires = "rpc_admin_token",
+        env = "PATHFINDER_RPC_ADMIN_ADDRESS"
+    )]
+    pub address: Option<SocketAddr>,
+    #[arg(
+        id = "rpc_admin_token",
+        long = "rpc.admin.token",
+        long_help = "The token admin requests must present as an `Authorization: Bearer <TOKEN>` \
+                     header",
+        value_name = "TOKEN",
+        env = "PATHFINDER_RPC_ADMIN_TOKEN"
+    )]
+    pub token: Option<String>,
+}
+
+#[derive(clap::Args, Clone)]
+pub struct RateLimitConfig {
+    #[arg(
+        long = "rpc.rate-limit.enabled",
+        long_help = "Rate limit JSON-RPC method calls per client. Every method call costs a \
+                     number of tokens relative to the work required to serve it, e.g. \
+                     `starknet_traceBlockTransactions` costs far more than `starknet_blockNumber`.",
+        default_value = "false",
+        env = "PATHFINDER_RPC_RATE_LIMIT_ENABLED"
+    )]
+    pub enabled: bool,
+    #[arg(
+        long = "rpc.rate-limit.capacity",
+        long_help = "The maximum number of tokens a client identified by its IP address can spend \
+                     in a burst. IPv6 clients are identified by the /64 prefix of their address. \
+                     New clients start with half of this.",
+        value_name = "TOKENS",
+        default_value = "1000",
+        env = "PATHFINDER_RPC_RATE_LIMIT_CAPACITY"
+    )]
+    pub capacity: NonZeroU32,
+    #[arg(
+        long = "rpc.rate-limit.refill-rate",
+        long_help = "The number of tokens per second a client identified by its IP address regains",
+        value_name = "TOKENS",
+        default_value = "100",
+        env = "PATHFINDER_RPC_RATE_LIMIT_REFILL_RATE"
+    )]
+    pub refill_rate: NonZeroU32,
+    #[arg(
+        long = "rpc.rate-limit.api-keys",
+        long_help = "Comma separated list of API keys. Clients presenting one of them in the \
+                     `X-API-Key` header are limited per key instead of per IP address.",
+        value_name = "KEYS",
+        value_delimiter = ',',
+        env = "PATHFINDER_RPC_RATE_LIMIT_API_KEYS"
+    )]
+    pub api_keys: Vec<String>,
+    #[arg(
+        long = "rpc.rate-limit.api-key-capacity",
+        long_help = "The maximum number of tokens a client identified by its API key can spend in \
+                     a burst",
+        value_name = "TOKENS",
+        default_value = "10000",
+        env = "PATHFINDER_RPC_RATE_LIMIT_API_KEY_CAPACITY"
+    )]
+    pub api_key_capacity: NonZeroU32,
+    #[arg(
+        long = "rpc.rate-limit.api-key-refill-rate",
+        long_help = "The number of tokens per second a client identified by its API key regains",
+        value_name = "TOKENS",
+        default_value = "1000",
+        env = "PATHFINDER_RPC_RATE_LIMIT_API_KEY_REFILL_RATE"
+    )]
+    pub api_key_refill_rate: NonZeroU32,
+}
+
+#[derive(clap::Args, Clone)]
+pub struct AuthCli {
+    #[arg(
+        long = "rpc.auth.routes",
+        long_help = "Comma separated list of RPC paths whose requests must be authenticated, e.g. \
+                     '/rpc/v0_8,/ws'. Other paths serving the same RPC version are authenticated \
+                     as well, e.g. '/ws/rpc/v0_8' along with '/rpc/v0_8', and '/' along with the \
+                     path of the default version. Requests to other paths are not authenticated.",
+        value_name = "PATHS",
+        value_delimiter = ',',
+        env = "PATHFINDER_RPC_AUTH_ROUTES"
+    )]
+    routes: Vec<String>,
+    #[arg(
+        long = "rpc.auth.api-keys",
+        long_help = "Comma separated list of API keys accepted on authenticated paths. Keys are \
+                     presented in the `X-API-Key` header or as an `Authorization: Bearer <KEY>` \
+                     header.",
+        value_name = "KEYS",
+        value_delimiter = ',',
+        env = "PATHFINDER_RPC_AUTH_API_KEYS"
+    )]
+    api_keys: Vec<String>,
+    #[arg(
+        long = "rpc.auth.jwt-secret",
+        long_help = "Path to a file holding a hex encoded 32 byte secret. HS256 JSON Web Tokens \
+                     signed with it are accepted on authenticated paths as an `Authorization: \
+                     Bearer <TOKEN>` header. Tokens must have an `iat` claim within 60 seconds of \
+                     the current time.",
+        value_name = "PATH",
+        env = "PATHFINDER_RPC_AUTH_JWT_SECRET"
+    )]
+    jwt_secret_path: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Clone)]
+pub struct ExecutionDeadlinesCli {
+    #[arg(
+        long = "rpc.execution-deadline.call",
+        value_name = "Seconds",
+        long_help = "How long executing `starknet_call` may take before the execution is aborted",
+        default_value = "120",
+        env = "PATHFINDER_RPC_EXECUTION_DEADLINE_CALL"
+    )]
+    call: std::num::NonZeroU64,
+    #[arg(
+        long = "rpc.execution-deadline.estimate-fee",
+        value_name = "Seconds",
+        long_help = "How long executing `starknet_estimateFee` and `starknet_estimateMessageFee` \
+                     may take before the execution is aborted",
+        default_value = "120",
+        env = "PATHFINDER_RPC_EXECUTION_DEADLINE_ESTIMATE_FEE"
+    )]
+    estimate_fee: std::num::NonZeroU64,
+    #[arg(
+        long = "rpc.execution-deadline.simulate",
+        value_name = "Seconds",
+        long_help = "How long executing `starknet_simulateTransactions` may take before the \
+                     execution is aborted",
+        default_value = "120",
+        env = "PATHFINDER_RPC_EXECUTION_DEADLINE_SIMULATE"
+    )]
+    simulate: std::num::NonZeroU64,
+    #[arg(
+        long = "rpc.execution-deadline.trace",
+        value_name = "Seconds",
+        long_help = "How long executing `starknet_traceTransaction` and \
+                     `starknet_traceBlockTransactions` may take before the execution is aborted",
+        default_value = "120",
+        env = "PATHFINDER_RPC_EXECUTION_DEADLINE_TRACE"
+    )]
+    trace: std::num::NonZeroU64,
+}
+
+impl From<ExecutionDeadlinesCli> for pathfinder_rpc::context::ExecutionDeadlines {
+    fn from(cli: ExecutionDeadlinesCli) -> Self {
+        Self {
+            call: Duration::from_secs(cli.call.get()),
+            estimate_fee: Duration::from_secs(cli.estimate_fee.get()),
+            simulate: Duration::from_secs(cli.simulate.get()),
+            trace: Duration::from_secs(cli.trace.get()),
+        }
+    }
+}
+
+#[derive(clap::Args, Clone)]
+pub struct MethodFiltersCli {
+    #[arg(
+        long = "rpc.methods.allow",
+        long_help = "Comma separated list of patterns selecting the only JSON-RPC methods served. \
+                     Patterns are method names which may contain `*` wildcards, e.g. \
+                     'starknet_get*'. A pattern prefixed with an endpoint, one of 'v06:', 'v07:', \
+                     'v08:' and 'pathfinder:', only applies to that endpoint. By default all \
+                     methods are served.",
+        value_name = "PATTERNS",
+        value_delimiter = ',',
+        env = "PATHFINDER_RPC_METHODS_ALLOW"
+    )]
+    allow: Vec<String>,
+    #[arg(
+        long = "rpc.methods.deny",
+        long_help = "Comma separated list of patterns selecting JSON-RPC methods which are not \
+                     served, even if allowed. Calling these fails with a `Method not found` \
+                     error. Patterns have the same format as for `--rpc.methods.allow`, e.g. \
+                     'starknet_add*Transaction,starknet_trace*,v08:starknet_subscribe*'.",
+        value_name = "PATTERNS",
+        value_delimiter = ',',
+        env = "PATHFINDER_RPC_METHODS_DENY"
+    )]
+    deny: Vec<String>,
+}
+
+#[derive(clap::Args, Clone)]
+pub struct ResponseCacheConfig {
+    #[arg(
+        long = "rpc.response-cache.enabled",
+        long_help = "Cache responses to JSON-RPC queries which can only change on reorgs, e.g. \
+                     `starknet_getBlockWithTxs` for a block selected by hash or number. The cache \
+                     is cleared on every reorg and L1 update.",
+        default_value = "false",
+        env = "PATHFINDER_RPC_RESPONSE_CACHE_ENABLED"
+    )]
+    pub enabled: bool,
+    #[arg(
+        long = "rpc.response-cache.size",
+        long_help = "The maximum total size of the responses kept in the cache",
+        value_name = "Bytes",
+        default_value = "104857600",
+        env = "PATHFINDER_RPC_RESPONSE_CACHE_SIZE"
+    )]
+    pub size: NonZeroUsize,
+}
+
+#[derive(clap::Args, Clone)]
+pub struct TraceCacheConfig {
+    #[arg(
+        long = "rpc.trace-cache.size",
+        long_help = "The number of blocks whose transaction traces are cached in memory",
+        value_name = "BLOCKS",
+        default_value = "128",
+        env = "PATHFINDER_RPC_TRACE_CACHE_SIZE"
+    )]
+    pub size: NonZeroUsize,
+    #[arg(
+        long = "rpc.trace-store.enabled",
+        long_help = "Trace every new block in the background as it is synced and persist the \
+                     traces in the database, so that they are served without executing the \
+                     block again, also after restarts. The store is best-effort: blocks synced \
+                     faster than they are traced are skipped, and their traces are computed on \
+                     request without being persisted.",
+        default_value = "false",
+        env = "PATHFINDER_RPC_TRACE_STORE_ENABLED"
+    )]
+    pub store: bool,
+    #[arg(
+        long = "rpc.trace-store.max-blocks",
+        long_help = "The maximum number of blocks whose transaction traces are stored in the \
+                     database. The least recently stored blocks are removed first. Traces of \
+                     blocks removed by blockchain history pruning are removed as well.",
+        value_name = "BLOCKS",
+        default_value = "10000",
+        env = "PATHFINDER_RPC_TRACE_STORE_MAX_BLOCKS"
+    )]
+    pub store_max_blocks: NonZeroUsize,
+}
+
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
@@ -1067,4 +1730,30 @@ mod tests {
     fn parse_versioned_constants_success() {
         super::parse_versioned_constants("fixtures/multi_versioned_constants.json".into()).unwrap();
     }
+
+    #[test]
+    fn parse_method_filters() {
+        let cli = super::MethodFiltersCli {
+            allow: vec![],
+            deny: vec![
+                "starknet_trace*".to_owned(),
+                "v08:starknet_subscribe*".to_owned(),
+            ],
+        };
+        let filters = super::parse_method_filters(cli).unwrap();
+
+        assert!(!filters.v06.is_enabled("starknet_traceTransaction"));
+        assert!(!filters.pathfinder.is_enabled("starknet_traceTransaction"));
+        assert!(!filters.v08.is_enabled("starknet_subscribeNewHeads"));
+        assert!(filters.v07.is_enabled("starknet_subscribeNewHeads"));
+
+        let cli = super::MethodFiltersCli {
+            allow: vec!["v09:starknet_call".to_owned()],
+            deny: vec![],
+        };
+        assert_eq!(
+            super::parse_method_filters(cli).unwrap_err(),
+            super::MethodFilterParseError::UnknownEndpoint("v09:starknet_call".to_owned())
+        );
+    }
 }
@@ -154,6 +154,27 @@ pub(crate) struct P2PSyncCli {
     )]
     pub l1_checkpoint_override: Option<String>,
 
+    #[arg(
+        long = "p2p.sync.experimental.gateway-free",
+        long_help = "Sync from the p2p network and L1 alone, without ever querying the feeder \
+                     gateway. The chain head is learned from peers and classes are only \
+                     compiled locally. Requires the sequencer public key to be configured with \
+                     `p2p.sync.experimental.sequencer-public-key`.",
+        default_value = "false",
+        action = clap::ArgAction::Set,
+        env = "PATHFINDER_P2P_EXPERIMENTAL_GATEWAY_FREE"
+    )]
+    pub gateway_free: bool,
+
+    #[arg(
+        long = "p2p.sync.experimental.sequencer-public-key",
+        long_help = "The public key of the sequencer, used to verify block signatures in \
+                     gateway-free sync instead of fetching it from the feeder gateway.",
+        value_name = "HEX",
+        env = "PATHFINDER_P2P_EXPERIMENTAL_SEQUENCER_PUBLIC_KEY"
+    )]
+    pub sequencer_public_key: Option<String>,
+
     #[arg(
         long = "p2p.sync.experimental.stream-timeout",
         long_help = "Timeout of the entire stream in the request/response-stream protocol.",
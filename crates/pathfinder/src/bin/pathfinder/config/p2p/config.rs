@@ -27,6 +27,9 @@ pub struct P2PSyncConfig {
     pub core: P2PCoreConfig,
     pub proxy: bool,
     pub l1_checkpoint_override: Option<pathfinder_ethereum::EthereumStateUpdate>,
+    /// The sequencer public key to use for gateway-free sync, `None` if sync
+    /// relies on the feeder gateway.
+    pub gateway_free: Option<pathfinder_common::PublicKey>,
     pub stream_timeout: Duration,
     pub response_timeout: Duration,
     pub max_concurrent_streams: usize,
@@ -136,6 +139,11 @@ impl P2PSyncConfig {
             core: args.core.into(),
             proxy: args.proxy,
             l1_checkpoint_override: parse_l1_checkpoint_or_exit(args.l1_checkpoint_override),
+            gateway_free: parse_gateway_free_or_exit(
+                args.gateway_free,
+                args.proxy,
+                args.sequencer_public_key,
+            ),
             stream_timeout: Duration::from_secs(args.stream_timeout.into()),
             response_timeout: Duration::from_secs(args.response_timeout.into()),
             max_concurrent_streams: args.max_concurrent_streams,
@@ -177,6 +185,38 @@ fn parse_l1_checkpoint_or_exit(
     })
 }
 
+fn parse_gateway_free_or_exit(
+    gateway_free: bool,
+    proxy: bool,
+    sequencer_public_key: Option<String>,
+) -> Option<pathfinder_common::PublicKey> {
+    use clap::error::ErrorKind;
+
+    fn exit_now(e: impl std::fmt::Display) -> ! {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!("p2p.sync.experimental.gateway-free: {e}"),
+            )
+            .exit()
+    }
+
+    if !gateway_free {
+        return None;
+    }
+    if proxy {
+        exit_now("cannot be used together with p2p.sync.proxy");
+    }
+
+    let Some(public_key) = sequencer_public_key else {
+        exit_now("requires p2p.sync.experimental.sequencer-public-key")
+    };
+    let public_key = pathfinder_crypto::Felt::from_hex_str(&public_key)
+        .unwrap_or_else(|e| exit_now(format!("invalid sequencer public key: {e}")));
+
+    Some(pathfinder_common::PublicKey(public_key))
+}
+
 impl P2PConsensusConfig {
     pub(crate) fn parse_or_exit(args: P2PConsensusCli) -> Self {
         Self {
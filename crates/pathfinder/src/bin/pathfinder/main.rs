@@ -1,7 +1,7 @@
 #![deny(rust_2018_idioms)]
 
 use std::net::SocketAddr;
-use std::num::NonZeroU32;
+use std::num::{NonZeroU32, NonZeroUsize};
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
@@ -13,6 +13,7 @@ use config::BlockchainHistory;
 use metrics_exporter_prometheus::PrometheusBuilder;
 use pathfinder_common::{BlockNumber, Chain, ChainId, EthereumChain};
 use pathfinder_ethereum::{EthereumApi, EthereumClient};
+use pathfinder_lib::feeder_gateway::FeederGatewayServer;
 use pathfinder_lib::monitoring::{self};
 use pathfinder_lib::state;
 use pathfinder_lib::state::SyncContext;
@@ -22,12 +23,13 @@ use pathfinder_storage::Storage;
 use starknet_gateway_client::GatewayApi;
 use tokio::signal::unix::{signal, SignalKind};
 use tokio::task::JoinError;
-use tracing::{info, warn};
+use tracing::info;
 
 use crate::config::{NetworkConfig, StateTries};
 
 mod config;
 mod p2p;
+mod snapshot;
 mod update;
 
 // The Cairo VM allocates felts on the stack, so during execution it's making
@@ -54,7 +56,15 @@ async fn async_main() -> anyhow::Result<Storage> {
         std::env::set_var("RUST_LOG", "pathfinder=info");
     }
 
-    let config = config::Config::parse();
+    let config = match config::Mode::parse() {
+        config::Mode::Node(config) => *config,
+        config::Mode::Command(command) => {
+            setup_tracing(config::Color::Auto, false, false);
+            return match command {
+                config::Command::Snapshot(config) => snapshot::run(config).await,
+            };
+        }
+    };
 
     setup_tracing(
         config.color,
@@ -89,9 +99,13 @@ async fn async_main() -> anyhow::Result<Storage> {
 
     let sync_state = Arc::new(SyncState::default());
 
-    let ethereum = EthereumContext::setup(config.ethereum.url.clone(), &config.ethereum.password)
-        .await
-        .context("Creating Ethereum context")?;
+    let ethereum = EthereumContext::setup(
+        config.ethereum.urls.clone(),
+        &config.ethereum.password,
+        config.ethereum.quorum,
+    )
+    .await
+    .context("Creating Ethereum context")?;
 
     // Use the default starknet network if none was configured.
     let network = match config.network {
@@ -118,14 +132,27 @@ async fn async_main() -> anyhow::Result<Storage> {
 
     verify_networks(pathfinder_context.network, ethereum.chain)?;
 
-    let gateway_public_key = pathfinder_context
-        .gateway
-        .public_key()
-        .await
-        .context("Fetching Starknet gateway public key")?;
+    let gateway_public_key = match gateway_free_public_key(&config) {
+        Some(public_key) => public_key,
+        None => pathfinder_context
+            .gateway
+            .public_key()
+            .await
+            .context("Fetching Starknet gateway public key")?,
+    };
 
     // Setup and verify database
 
+    if let Some(snapshot) = &config.restore_from_snapshot {
+        snapshot::restore(
+            snapshot.clone(),
+            pathfinder_context.database.clone(),
+            config.restore_allow_missing_manifest,
+        )
+        .await
+        .context("Restoring database from snapshot")?;
+    }
+
     let storage_manager =
         pathfinder_storage::StorageBuilder::file(pathfinder_context.database.clone())
             .journal_mode(config.sqlite_wal)
@@ -218,6 +245,8 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
     let mut int_signal = signal(SignalKind::interrupt())?;
 
     let (tx_pending, rx_pending) = tokio::sync::watch::channel(Default::default());
+    let (tx_pending_announcements, rx_pending_announcements) = tokio::sync::watch::channel(None);
+    let p2p_rx_pending = rx_pending.clone();
 
     let rpc_config = pathfinder_rpc::context::RpcConfig {
         batch_concurrency_limit: config.rpc_batch_concurrency_limit,
@@ -228,6 +257,7 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
         versioned_constants_map: config.versioned_constants_map.clone(),
         native_execution: config.native_execution.is_enabled(),
         native_class_cache_size: config.native_execution.class_cache_size(),
+        execution_deadlines: config.execution_deadlines,
     };
 
     let notifications = Notifications::default();
@@ -254,17 +284,146 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
         context
     };
 
+    let context = if config.mempool.enabled {
+        context.with_mempool(pathfinder_rpc::mempool::Mempool::new(
+            pathfinder_rpc::mempool::MempoolConfig {
+                capacity: config.mempool.capacity,
+                ttl: std::time::Duration::from_secs(config.mempool.ttl.get()),
+            },
+        ))
+    } else {
+        context
+    };
+
+    let trace_cache = pathfinder_executor::TraceCache::new(config.trace_cache.size);
+    let trace_cache = if config.trace_cache.store {
+        // Stored traces are read by tracing requests, like any other RPC data.
+        trace_cache.with_store(context.storage.clone())
+    } else {
+        trace_cache
+    };
+    let context = context.with_trace_cache(trace_cache.clone());
+
+    if config.trace_cache.store {
+        pathfinder_lib::state::TraceWriter::new(
+            storage_manager
+                .create_pool(NonZeroU32::new(1).unwrap())
+                .context("Creating database connection pool for the trace writer")?,
+            pathfinder_context.network_id,
+            context.contract_addresses,
+            config.versioned_constants_map.clone(),
+            context.native_class_cache.clone(),
+            trace_cache,
+            config.trace_cache.store_max_blocks,
+        )
+        .spawn(notifications.block_headers.subscribe());
+    }
+
+    if config.re_execution_verifier {
+        pathfinder_lib::state::ReExecutionVerifier::new(
+            storage_manager
+                .create_read_only_pool(NonZeroU32::new(1).unwrap())
+                .context("Creating database connection pool for the re-execution verifier")?,
+            pathfinder_context.network_id,
+            context.contract_addresses,
+            config.versioned_constants_map.clone(),
+            context.native_class_cache.clone(),
+        )
+        .spawn(notifications.block_headers.subscribe());
+    }
+
+    let context = if config.historical_state.enabled {
+        context.with_historical_state(pathfinder_rpc::historical_state::HistoricalState::new(
+            pathfinder_rpc::historical_state::HistoricalStateConfig {
+                cache_size: config.historical_state.cache_size,
+                max_blocks_to_fetch: config.historical_state.max_blocks,
+            },
+        ))
+    } else {
+        context
+    };
+
+    let context = if config.response_cache.enabled {
+        context.with_response_cache(pathfinder_rpc::response_cache::ResponseCache::new(
+            config.response_cache.size,
+            &notifications,
+        ))
+    } else {
+        context
+    };
+
+    let context = if config.rate_limit.enabled {
+        use pathfinder_rpc::rate_limit::{BucketConfig, RateLimitConfig, RateLimiter};
+
+        context.with_rate_limiter(RateLimiter::new(RateLimitConfig {
+            ip: BucketConfig {
+                capacity: config.rate_limit.capacity,
+                refill_rate: config.rate_limit.refill_rate,
+            },
+            api_key: BucketConfig {
+                capacity: config.rate_limit.api_key_capacity,
+                refill_rate: config.rate_limit.api_key_refill_rate,
+            },
+            api_keys: config.rate_limit.api_keys.iter().cloned().collect(),
+        }))
+    } else {
+        context
+    };
+
+    let (sync_control, sync_commands) = pathfinder_rpc::admin::sync_control();
+    let admin = match (config.admin.address, config.admin.token.clone()) {
+        (Some(address), Some(token)) => {
+            let admin = pathfinder_rpc::admin::AdminContext::new(
+                storage_manager
+                    .create_pool(NonZeroU32::new(1).unwrap())
+                    .context("Creating database connection pool for the admin RPC")?,
+                sync_control,
+            );
+            Some((address, token, context.clone().with_admin(admin)))
+        }
+        _ => None,
+    };
+
+    let feeder_gateway = match config.feeder_gateway_address {
+        Some(address) => {
+            let addresses = &pathfinder_context.contract_addresses;
+            let contract_addresses = starknet_gateway_types::reply::EthContractAddresses {
+                starknet: pathfinder_common::EthereumAddress(addresses.l1_contract_address),
+                strk_l2_token_address: Some(addresses.strk_l2_token_address),
+                eth_l2_token_address: Some(addresses.eth_l2_token_address),
+            };
+            let storage = storage_manager
+                .create_read_only_pool(NonZeroU32::new(10).unwrap())
+                .context("Creating database connection pool for the feeder gateway")?;
+
+            Some(FeederGatewayServer::new(
+                address,
+                storage,
+                contract_addresses,
+                gateway_public_key,
+            ))
+        }
+        None => None,
+    };
+
     let default_version = match config.rpc_root_version {
         config::RootRpcVersion::V06 => pathfinder_rpc::RpcVersion::V06,
         config::RootRpcVersion::V07 => pathfinder_rpc::RpcVersion::V07,
         config::RootRpcVersion::V08 => pathfinder_rpc::RpcVersion::V08,
     };
 
-    let rpc_server = pathfinder_rpc::RpcServer::new(config.rpc_address, context, default_version);
+    let rpc_server = pathfinder_rpc::RpcServer::new(config.rpc_address, context, default_version)
+        .with_request_max_size(config.rpc_request_max_size.get())
+        .with_request_timeout(config.rpc_request_timeout);
     let rpc_server = match config.rpc_cors_domains {
         Some(ref allowed_origins) => rpc_server.with_cors(allowed_origins.clone()),
         None => rpc_server,
     };
+    let rpc_server = match config.auth {
+        Some(auth) => rpc_server.with_auth(pathfinder_rpc::middleware::auth::Auth::new(auth)),
+        None => rpc_server,
+    };
+    let rpc_server = rpc_server.with_method_filters(config.method_filters);
 
     // Spawn monitoring if configured.
     if let Some(address) = config.monitor_address {
@@ -289,12 +448,17 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
         pathfinder_context.network_id,
         p2p_storage,
         config.sync_p2p.clone(),
+        p2p_rx_pending,
+        tx_pending_announcements,
     )
     .await;
 
     let (consensus_p2p_handle, _consensus_p2p_client) =
         p2p::consensus::start(pathfinder_context.network_id, config.consensus_p2p.clone()).await;
 
+    // Catches up on pruning after the blockchain history depth was reduced.
+    pathfinder_lib::state::spawn_blockchain_history_compaction(sync_storage.clone());
+
     let sync_handle = if config.is_sync_enabled {
         start_sync(
             sync_storage,
@@ -303,11 +467,13 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
             sync_state.clone(),
             &config,
             tx_pending,
+            rx_pending_announcements,
             rpc_server.get_topic_broadcasters().cloned(),
             notifications,
             gateway_public_key,
-            sync_p2p_client,
+            sync_p2p_client.clone(),
             config.verify_tree_hashes,
+            sync_commands,
         )
     } else {
         tokio::task::spawn(futures::future::pending())
@@ -331,6 +497,61 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
         tokio::spawn(std::future::pending())
     };
 
+    let admin_handle = match admin {
+        Some((address, token, context)) => {
+            let context = match sync_p2p_client {
+                Some(p2p_client) => {
+                    let admin = context.admin.clone().expect("Admin context was set");
+                    context.with_admin(admin.with_peers(move || {
+                        let p2p_client = p2p_client.clone();
+                        Box::pin(async move {
+                            p2p_client
+                                .connected_peers()
+                                .await
+                                .into_iter()
+                                .map(|(peer_id, peer)| pathfinder_rpc::admin::PeerInfo {
+                                    peer_id: peer_id.to_string(),
+                                    address: peer.addr.as_ref().map(ToString::to_string),
+                                    inbound: peer.is_inbound(),
+                                    useful: peer.useful,
+                                    min_ping: peer.min_ping,
+                                })
+                                .collect()
+                        })
+                    }))
+                }
+                None => context,
+            };
+
+            match pathfinder_rpc::admin::AdminServer::new(address, context, token)
+                .spawn()
+                .await
+            {
+                Ok((admin_handle, on)) => {
+                    info!(%on, "🔧 Admin RPC server started");
+                    admin_handle
+                }
+                Err(error) => tokio::task::spawn(std::future::ready(Err(
+                    error.context("Admin RPC server failed to start")
+                ))),
+            }
+        }
+        None => tokio::spawn(std::future::pending()),
+    };
+
+    let feeder_gateway_handle = match feeder_gateway {
+        Some(server) => match server.spawn().await {
+            Ok((feeder_gateway_handle, on)) => {
+                info!(%on, "📦 Feeder gateway server started");
+                feeder_gateway_handle
+            }
+            Err(error) => tokio::task::spawn(std::future::ready(Err(
+                error.context("Feeder gateway server failed to start")
+            ))),
+        },
+        None => tokio::spawn(std::future::pending()),
+    };
+
     if !config.disable_version_update_check {
         util::task::spawn(update::poll_github_for_releases());
     }
@@ -342,6 +563,8 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
     let main_result = tokio::select! {
         result = sync_handle => handle_critical_task_result("Sync", result),
         result = rpc_handle => handle_critical_task_result("RPC", result),
+        result = admin_handle => handle_critical_task_result("Admin RPC", result),
+        result = feeder_gateway_handle => handle_critical_task_result("Feeder gateway", result),
         result = sync_p2p_handle => handle_critical_task_result("Sync P2P", result),
         result = consensus_p2p_handle => handle_critical_task_result("Consensus P2P", result),
         _ = term_signal.recv() => {
@@ -466,11 +689,15 @@ fn start_sync(
     sync_state: Arc<SyncState>,
     config: &config::Config,
     tx_pending: tokio::sync::watch::Sender<pathfinder_rpc::PendingData>,
+    pending_announcements: tokio::sync::watch::Receiver<
+        Option<p2p_v2::PeerData<p2p_proto::pending::NewPendingBlock>>,
+    >,
     websocket_txs: Option<pathfinder_rpc::TopicBroadcasters>,
     notifications: Notifications,
     gateway_public_key: pathfinder_common::PublicKey,
     p2p_client: Option<P2PSyncClient>,
     verify_tree_hashes: bool,
+    sync_commands: pathfinder_rpc::admin::SyncCommands,
 ) -> tokio::task::JoinHandle<anyhow::Result<()>> {
     if config.sync_p2p.proxy {
         start_feeder_gateway_sync(
@@ -483,9 +710,14 @@ fn start_sync(
             websocket_txs,
             notifications,
             gateway_public_key,
+            sync_commands,
         )
     } else {
         let p2p_client = p2p_client.expect("P2P client is expected with the p2p feature enabled");
+        util::task::spawn(
+            sync_commands
+                .reject("Pausing, resuming and reverting sync is not supported in p2p sync"),
+        );
         start_p2p_sync(
             storage,
             pathfinder_context,
@@ -493,11 +725,26 @@ fn start_sync(
             p2p_client,
             gateway_public_key,
             config.sync_p2p.l1_checkpoint_override,
+            config.sync_p2p.gateway_free.is_some(),
             verify_tree_hashes,
+            notifications,
+            tx_pending,
+            pending_announcements,
         )
     }
 }
 
+/// The sequencer public key configured for gateway-free p2p sync, if enabled.
+#[cfg(feature = "p2p")]
+fn gateway_free_public_key(config: &config::Config) -> Option<pathfinder_common::PublicKey> {
+    config.sync_p2p.gateway_free
+}
+
+#[cfg(not(feature = "p2p"))]
+fn gateway_free_public_key(_: &config::Config) -> Option<pathfinder_common::PublicKey> {
+    None
+}
+
 #[cfg(not(feature = "p2p"))]
 #[allow(clippy::too_many_arguments)]
 fn start_sync(
@@ -507,11 +754,15 @@ fn start_sync(
     sync_state: Arc<SyncState>,
     config: &config::Config,
     tx_pending: tokio::sync::watch::Sender<pathfinder_rpc::PendingData>,
+    _pending_announcements: tokio::sync::watch::Receiver<
+        Option<p2p_v2::PeerData<p2p_proto::pending::NewPendingBlock>>,
+    >,
     websocket_txs: Option<pathfinder_rpc::TopicBroadcasters>,
     notifications: Notifications,
     gateway_public_key: pathfinder_common::PublicKey,
     _p2p_client: Option<P2PSyncClient>,
     _verify_tree_hashes: bool,
+    sync_commands: pathfinder_rpc::admin::SyncCommands,
 ) -> tokio::task::JoinHandle<anyhow::Result<()>> {
     start_feeder_gateway_sync(
         storage,
@@ -523,6 +774,7 @@ fn start_sync(
         websocket_txs,
         notifications,
         gateway_public_key,
+        sync_commands,
     )
 }
 
@@ -537,6 +789,7 @@ fn start_feeder_gateway_sync(
     websocket_txs: Option<pathfinder_rpc::TopicBroadcasters>,
     notifications: Notifications,
     gateway_public_key: pathfinder_common::PublicKey,
+    sync_commands: pathfinder_rpc::admin::SyncCommands,
 ) -> tokio::task::JoinHandle<anyhow::Result<()>> {
     let sync_context = SyncContext {
         storage,
@@ -558,12 +811,14 @@ fn start_feeder_gateway_sync(
         sequencer_public_key: gateway_public_key,
         fetch_concurrency: config.feeder_gateway_fetch_concurrency,
         fetch_casm_from_fgw: config.fetch_casm_from_fgw,
+        control: sync_commands,
     };
 
     util::task::spawn(state::sync(sync_context, state::l1::sync, state::l2::sync))
 }
 
 #[cfg(feature = "p2p")]
+#[allow(clippy::too_many_arguments)]
 fn start_p2p_sync(
     storage: Storage,
     pathfinder_context: PathfinderContext,
@@ -571,7 +826,13 @@ fn start_p2p_sync(
     p2p_client: P2PSyncClient,
     gateway_public_key: pathfinder_common::PublicKey,
     l1_checkpoint_override: Option<pathfinder_ethereum::EthereumStateUpdate>,
+    gateway_free: bool,
     verify_tree_hashes: bool,
+    notifications: Notifications,
+    pending_data: tokio::sync::watch::Sender<pathfinder_rpc::PendingData>,
+    pending_announcements: tokio::sync::watch::Receiver<
+        Option<p2p_v2::PeerData<p2p_proto::pending::NewPendingBlock>>,
+    >,
 ) -> tokio::task::JoinHandle<anyhow::Result<()>> {
     use pathfinder_block_hashes::BlockHashDb;
 
@@ -580,12 +841,15 @@ fn start_p2p_sync(
         p2p: p2p_client,
         eth_client: ethereum_client,
         eth_address: pathfinder_context.contract_addresses.l1_contract_address,
-        fgw_client: pathfinder_context.gateway,
+        fgw_client: (!gateway_free).then_some(pathfinder_context.gateway),
         chain_id: pathfinder_context.network_id,
         public_key: gateway_public_key,
         l1_checkpoint_override,
         verify_tree_hashes,
         block_hash_db: Some(BlockHashDb::new(pathfinder_context.network)),
+        notifications,
+        pending_data,
+        pending_announcements,
     };
     util::task::spawn(sync.run())
 }
@@ -623,23 +887,19 @@ struct EthereumContext {
 impl EthereumContext {
     /// Configure an [EthereumContext]'s transport and read the chain ID using
     /// it.
-    async fn setup(mut url: reqwest::Url, password: &Option<String>) -> anyhow::Result<Self> {
-        // Make sure the URL is a WS URL
-        if url.scheme().eq("http") {
-            warn!("The provided Ethereum URL is using HTTP, converting to WS");
-            url.set_scheme("ws")
-                .map_err(|_| anyhow::anyhow!("Failed to set Ethereum URL scheme to ws"))?;
-        } else if url.scheme().eq("https") {
-            warn!("The provided Ethereum URL is using HTTPS, converting to WSS");
-            url.set_scheme("wss")
-                .map_err(|_| anyhow::anyhow!("Failed to set Ethereum URL scheme to wss"))?;
+    async fn setup(
+        mut urls: Vec<reqwest::Url>,
+        password: &Option<String>,
+        quorum: NonZeroUsize,
+    ) -> anyhow::Result<Self> {
+        for url in &mut urls {
+            if let Some(password) = password.as_ref().filter(|_| url.password().is_none()) {
+                url.set_password(Some(password))
+                    .map_err(|_| anyhow::anyhow!("Setting Ethereum URL password failed"))?;
+            }
         }
 
-        let client = if let Some(password) = password.as_ref() {
-            EthereumClient::with_password(url, password).context("Creating Ethereum client")?
-        } else {
-            EthereumClient::new(url).context("Creating Ethereum client")?
-        };
+        let client = EthereumClient::with_urls(urls, quorum).context("Creating Ethereum client")?;
 
         let chain = client.get_chain().await.context(
             r"Determining Ethereum chain.
@@ -725,11 +985,13 @@ mod pathfinder_context {
                 },
                 NetworkConfig::Custom {
                     gateway,
-                    feeder_gateway,
+                    feeder_gateways,
+                    public_fallback,
                     chain_id,
                 } => Self::configure_custom(
                     gateway,
-                    feeder_gateway,
+                    feeder_gateways,
+                    public_fallback,
                     chain_id,
                     data_directory,
                     api_key,
@@ -748,7 +1010,8 @@ mod pathfinder_context {
         /// the known networks.
         async fn configure_custom(
             gateway: Url,
-            feeder: Url,
+            feeder_gateways: Vec<Url>,
+            public_fallback: bool,
             chain_id: String,
             data_directory: &Path,
             api_key: Option<String>,
@@ -757,9 +1020,10 @@ mod pathfinder_context {
             use pathfinder_crypto::Felt;
             use starknet_gateway_client::GatewayApi;
 
-            let gateway = GatewayClient::with_urls(gateway, feeder, gateway_timeout)
-                .context("Creating gateway client")?
-                .with_api_key(api_key);
+            let gateway =
+                GatewayClient::with_feeder_gateways(gateway, feeder_gateways, gateway_timeout)
+                    .context("Creating gateway client")?
+                    .with_api_key(api_key);
 
             let network_id =
                 ChainId(Felt::from_be_slice(chain_id.as_bytes()).context("Parsing chain ID")?);
@@ -788,6 +1052,13 @@ mod pathfinder_context {
                 tracing::info!(%network, "Proxy gateway detected");
             }
 
+            let gateway = if public_fallback && network != Chain::Custom {
+                tracing::info!(%network, "Falling back to the public feeder gateway if the proxies are down");
+                gateway.with_public_fallback(network)
+            } else {
+                gateway
+            };
+
             let context = Self {
                 network,
                 network_id,
@@ -1,6 +1,10 @@
+use p2p_proto::pending::NewPendingBlock;
 use p2p_v2::sync::client::peer_agnostic::Client;
+use p2p_v2::PeerData;
 use pathfinder_common::ChainId;
+use pathfinder_rpc::PendingData;
 use pathfinder_storage::Storage;
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
 
 use crate::config::p2p::P2PSyncConfig;
@@ -9,8 +13,10 @@ pub async fn start(
     chain_id: ChainId,
     storage: Storage,
     config: P2PSyncConfig,
+    pending: watch::Receiver<PendingData>,
+    pending_announcements: watch::Sender<Option<PeerData<NewPendingBlock>>>,
 ) -> (JoinHandle<anyhow::Result<()>>, Option<Client>) {
-    start_inner(chain_id, storage, config)
+    start_inner(chain_id, storage, config, pending, pending_announcements)
         .await
         .unwrap_or_else(|error| {
             (
@@ -27,6 +33,8 @@ async fn start_inner(
     chain_id: ChainId,
     storage: Storage,
     config: P2PSyncConfig,
+    pending: watch::Receiver<PendingData>,
+    pending_announcements: watch::Sender<Option<PeerData<NewPendingBlock>>>,
 ) -> anyhow::Result<(JoinHandle<anyhow::Result<()>>, Option<Client>)> {
     use std::time::Duration;
 
@@ -59,6 +67,8 @@ async fn start_inner(
         listen_on: config.core.listen_on,
         bootstrap_addresses: config.core.bootstrap_addresses,
         predefined_peers: config.core.predefined_peers,
+        pending,
+        pending_announcements,
     };
 
     let (p2p_client, p2p_handle) = sync::start(context).await?;
@@ -71,6 +81,8 @@ async fn start_inner(
     _: ChainId,
     _: Storage,
     _: P2PSyncConfig,
+    _: watch::Receiver<PendingData>,
+    _: watch::Sender<Option<PeerData<NewPendingBlock>>>,
 ) -> anyhow::Result<(JoinHandle<anyhow::Result<()>>, Option<Client>)> {
     Ok((tokio::task::spawn(futures::future::pending()), None))
 }
@@ -0,0 +1,157 @@
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, StateCommitment};
+use pathfinder_lib::state::revert;
+use pathfinder_merkle_tree::contract_state::calculate_contract_state_hash;
+use pathfinder_merkle_tree::{ClassCommitmentTree, ContractsStorageTree, StorageCommitmentTree};
+use pathfinder_rpc::Notifications;
+use pathfinder_storage::{JournalMode, Storage, StorageBuilder};
+
+use crate::config::SnapshotConfig;
+
+/// Creates a snapshot of the database without migrating or otherwise writing
+/// to it, so that the node using the database can keep running.
+pub async fn run(config: SnapshotConfig) -> anyhow::Result<Storage> {
+    tokio::task::spawn_blocking(move || {
+        let storage = StorageBuilder::file(config.database)
+            .journal_mode(JournalMode::WAL)
+            .open_existing()
+            .context("Opening database")?
+            .create_read_only_pool(NonZeroU32::new(1).unwrap())
+            .context("Creating database connection pool")?;
+
+        std::fs::create_dir_all(&config.output_directory)
+            .context("Creating snapshot output directory")?;
+
+        let block = config
+            .block
+            .map(|block| BlockNumber::new(block).context("Block number is out of range"))
+            .transpose()?;
+
+        tracing::info!(database=%storage.path().display(), "Creating database snapshot");
+        let manifest = storage
+            .snapshot(&config.output_directory, block, |connection, block| {
+                // Nobody is subscribed to the copy.
+                revert::revert_chain(connection, block + 1, &Notifications::default())
+            })
+            .context("Creating database snapshot")?;
+        tracing::info!(
+            block_number=%manifest.block_number,
+            block_hash=%manifest.block_hash,
+            file=%manifest.file_name,
+            sha256=%manifest.sha256,
+            "Database snapshot created"
+        );
+
+        Ok(storage)
+    })
+    .await
+    .context("Joining snapshot task")?
+}
+
+/// Creates the database from a snapshot, unless the database already exists.
+///
+/// The database is only created if the snapshot passes verification, including
+/// recalculating the state commitment of its latest block from the state
+/// tries.
+pub async fn restore(
+    snapshot: PathBuf,
+    database: PathBuf,
+    allow_missing_manifest: bool,
+) -> anyhow::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        if database.exists() {
+            tracing::warn!(database=%database.display(), "Database already exists, not restoring snapshot");
+            return Ok(());
+        }
+
+        // Restore next to the database and only move it into place once it has been
+        // verified, so that an interrupted restore is never mistaken for a database.
+        let mut partial = database.clone().into_os_string();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        if partial.exists() {
+            std::fs::remove_file(&partial).context("Removing partially restored database")?;
+        }
+
+        tracing::info!(snapshot=%snapshot.display(), "Restoring database from snapshot");
+        pathfinder_storage::restore_snapshot(&snapshot, &partial, allow_missing_manifest)?;
+
+        if let Err(error) = verify_state_commitment(&partial) {
+            let _ = std::fs::remove_file(&partial);
+            return Err(error.context("Verifying restored database"));
+        }
+
+        std::fs::rename(&partial, &database).context("Moving restored database into place")?;
+        tracing::info!(database=%database.display(), "Database restored from snapshot");
+
+        Ok(())
+    })
+    .await
+    .context("Joining snapshot restore task")?
+}
+
+/// Recalculates the storage and class commitment tries of the latest block
+/// and checks that they result in the block's state commitment.
+///
+/// The state hash of every contract in the storage commitment trie is
+/// recalculated as well, from the contract's storage trie, class hash and
+/// nonce.
+fn verify_state_commitment(database: &Path) -> anyhow::Result<()> {
+    let storage = StorageBuilder::file(database.to_owned())
+        // Avoids leaving WAL files behind when the database is moved into place.
+        .journal_mode(JournalMode::Rollback)
+        .migrate()
+        .context("Migrating restored database")?
+        .create_pool(NonZeroU32::new(1).unwrap())?;
+    let mut connection = storage.connection()?;
+    let tx = connection.transaction()?;
+
+    let header = tx
+        .block_header(pathfinder_storage::BlockId::Latest)?
+        .context("Restored database contains no blocks")?;
+
+    tracing::info!(block_number=%header.number, "Verifying state tries, this may take a while");
+
+    let block = header.number;
+    let storage_commitment = StorageCommitmentTree::load(&tx, block)
+        .context("Loading storage commitment trie")?
+        .verify_with_contracts(|contract, state_hash| {
+            let root = ContractsStorageTree::load(&tx, contract, block)
+                .context("Loading contract storage trie")?
+                .verify()
+                .with_context(|| format!("Verifying storage trie of contract {contract}"))?;
+            let class_hash = tx
+                .contract_class_hash(block.into(), contract)?
+                .unwrap_or_default();
+            let nonce = tx
+                .contract_nonce(contract, block.into())?
+                .unwrap_or_default();
+
+            let calculated = calculate_contract_state_hash(class_hash, root, nonce);
+            anyhow::ensure!(
+                calculated == state_hash,
+                "State hash {calculated} calculated for contract {contract} does not match the \
+                 stored state hash {state_hash}"
+            );
+            Ok(())
+        })
+        .context("Verifying storage commitment trie")?;
+    let class_commitment = ClassCommitmentTree::load(&tx, header.number)
+        .context("Loading class commitment trie")?
+        .verify()
+        .context("Verifying class commitment trie")?;
+
+    let state_commitment = StateCommitment::calculate(storage_commitment, class_commitment);
+    anyhow::ensure!(
+        state_commitment == header.state_commitment,
+        "State commitment {state_commitment} calculated from the state tries does not match the \
+         state commitment {} of block {}",
+        header.state_commitment,
+        header.number
+    );
+
+    Ok(())
+}
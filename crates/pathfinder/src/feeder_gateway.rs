@@ -0,0 +1,626 @@
+//! A read-only HTTP facade mirroring the Starknet feeder gateway API.
+//!
+//! Serves blocks, state updates, signatures and classes from the local database
+//! in the feeder gateway's format, so that other pathfinder instances and
+//! tooling written against the feeder gateway can sync from this node. Only
+//! the endpoints required for syncing are served, and pending data is not
+//! available: requesting the pending block fails as if it didn't exist.
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use anyhow::Context;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use pathfinder_common::prelude::*;
+use pathfinder_common::state_update::ContractClassUpdate;
+use pathfinder_storage::{BlockId, Storage};
+use serde::{Deserialize, Serialize};
+use starknet_gateway_types::error::{KnownStarknetErrorCode, StarknetError};
+use starknet_gateway_types::reply::state_update::{
+    DeclaredSierraClass,
+    DeployedContract,
+    ReplacedClass,
+    StorageDiff,
+};
+use starknet_gateway_types::reply::{self, EthContractAddresses, GasPrices, Status};
+use tokio::task::JoinHandle;
+
+use crate::state::block_hash::calculate_receipt_commitment;
+
+#[derive(Clone)]
+struct Context {
+    storage: Storage,
+    contract_addresses: EthContractAddresses,
+    public_key: PublicKey,
+}
+
+pub struct FeederGatewayServer {
+    addr: SocketAddr,
+    context: Context,
+}
+
+impl FeederGatewayServer {
+    /// The `contract_addresses` and `public_key` are those of the network
+    /// this node syncs from, which are served as they are.
+    pub fn new(
+        addr: SocketAddr,
+        storage: Storage,
+        contract_addresses: EthContractAddresses,
+        public_key: PublicKey,
+    ) -> Self {
+        Self {
+            addr,
+            context: Context {
+                storage,
+                contract_addresses,
+                public_key,
+            },
+        }
+    }
+
+    pub async fn spawn(self) -> anyhow::Result<(JoinHandle<anyhow::Result<()>>, SocketAddr)> {
+        let listener = tokio::net::TcpListener::bind(self.addr)
+            .await
+            .with_context(|| format!("Binding feeder gateway address {}", self.addr))?;
+        let addr = listener
+            .local_addr()
+            .context("Getting local address from listener")?;
+
+        let router = axum::Router::new()
+            .route("/feeder_gateway/get_block", axum::routing::get(get_block))
+            .route(
+                "/feeder_gateway/get_state_update",
+                axum::routing::get(get_state_update),
+            )
+            .route(
+                "/feeder_gateway/get_signature",
+                axum::routing::get(get_signature),
+            )
+            .route(
+                "/feeder_gateway/get_class_by_hash",
+                axum::routing::get(get_class_by_hash),
+            )
+            .route(
+                "/feeder_gateway/get_compiled_class_by_class_hash",
+                axum::routing::get(get_compiled_class_by_class_hash),
+            )
+            .route(
+                "/feeder_gateway/get_contract_addresses",
+                axum::routing::get(get_contract_addresses),
+            )
+            .route(
+                "/feeder_gateway/get_public_key",
+                axum::routing::get(get_public_key),
+            )
+            .with_state(self.context);
+
+        let server_handle = util::task::spawn(async move {
+            axum::serve(listener, router.into_make_service())
+                .with_graceful_shutdown(util::task::cancellation_token().cancelled_owned())
+                .await
+                .map_err(Into::into)
+        });
+
+        Ok((server_handle, addr))
+    }
+}
+
+/// Errors are reported the way the feeder gateway does, as a
+/// [StarknetError] with a `400 Bad Request` status.
+#[derive(Debug)]
+enum Error {
+    BlockNotFound,
+    UndeclaredClass,
+    MalformedRequest(String),
+    /// Reported as `503 Service Unavailable`, which clients retry.
+    Internal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(error: anyhow::Error) -> Self {
+        Self::Internal(error)
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let (code, message) = match self {
+            Error::BlockNotFound => (
+                KnownStarknetErrorCode::BlockNotFound,
+                "Block not found".to_owned(),
+            ),
+            Error::UndeclaredClass => (
+                KnownStarknetErrorCode::UndeclaredClass,
+                "Class not found".to_owned(),
+            ),
+            Error::MalformedRequest(message) => (KnownStarknetErrorCode::MalformedRequest, message),
+            Error::Internal(error) => {
+                tracing::error!(?error, "Feeder gateway request failed");
+                return http::StatusCode::SERVICE_UNAVAILABLE.into_response();
+            }
+        };
+
+        let error = StarknetError {
+            code: code.into(),
+            message,
+        };
+        (http::StatusCode::BAD_REQUEST, Json(error)).into_response()
+    }
+}
+
+fn params<T>(query: Result<Query<T>, QueryRejection>) -> Result<T, Error> {
+    query
+        .map(|Query(params)| params)
+        .map_err(|rejection| Error::MalformedRequest(rejection.body_text()))
+}
+
+/// Runs `f` on a database transaction in a blocking task.
+async fn read<T, F>(storage: Storage, f: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce(&pathfinder_storage::Transaction<'_>) -> Result<T, Error> + Send + 'static,
+{
+    util::task::spawn_blocking(move |_| {
+        let mut db = storage
+            .connection()
+            .context("Creating database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+        f(&tx)
+    })
+    .await
+    .context("Joining blocking task")?
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockParams {
+    block_number: Option<String>,
+    block_hash: Option<BlockHash>,
+    #[serde(default)]
+    header_only: bool,
+    #[serde(default)]
+    include_block: bool,
+}
+
+impl BlockParams {
+    /// Without parameters the latest block is selected.
+    fn block_id(&self) -> Result<BlockId, Error> {
+        match (self.block_number.as_deref(), self.block_hash) {
+            (None, None) | (Some("latest"), None) => Ok(BlockId::Latest),
+            (Some("pending"), None) => Err(Error::BlockNotFound),
+            (Some(number), None) => number
+                .parse()
+                .ok()
+                .and_then(BlockNumber::new)
+                .map(BlockId::Number)
+                .ok_or_else(|| Error::MalformedRequest(format!("Invalid block number: {number}"))),
+            (None, Some(hash)) => Ok(BlockId::Hash(hash)),
+            (Some(_), Some(_)) => Err(Error::MalformedRequest(
+                "Only one of blockNumber and blockHash may be given".to_owned(),
+            )),
+        }
+    }
+}
+
+async fn get_block(
+    State(context): State<Context>,
+    query: Result<Query<BlockParams>, QueryRejection>,
+) -> Result<Response, Error> {
+    #[derive(Serialize)]
+    struct HeaderOnly {
+        block_hash: BlockHash,
+        block_number: BlockNumber,
+    }
+
+    let params = params(query)?;
+    let block_id = params.block_id()?;
+
+    if params.header_only {
+        let header = read(context.storage, move |tx| {
+            tx.block_header(block_id)
+                .context("Fetching block header")?
+                .ok_or(Error::BlockNotFound)
+        })
+        .await?;
+
+        return Ok(Json(HeaderOnly {
+            block_hash: header.hash,
+            block_number: header.number,
+        })
+        .into_response());
+    }
+
+    let block = read(context.storage, move |tx| {
+        block(tx, block_id)?.ok_or(Error::BlockNotFound)
+    })
+    .await?;
+
+    Ok(Json(block).into_response())
+}
+
+async fn get_state_update(
+    State(context): State<Context>,
+    query: Result<Query<BlockParams>, QueryRejection>,
+) -> Result<Response, Error> {
+    #[derive(Serialize)]
+    struct StateUpdateWithBlock {
+        state_update: reply::StateUpdate,
+        block: reply::Block,
+    }
+
+    let params = params(query)?;
+    let block_id = params.block_id()?;
+    let include_block = params.include_block;
+
+    read(context.storage, move |tx| {
+        let state_update = state_update(tx, block_id)?.ok_or(Error::BlockNotFound)?;
+        if !include_block {
+            return Ok(Json(state_update).into_response());
+        }
+
+        // Reading both in the same transaction keeps them consistent.
+        let block = block(tx, block_id)?.ok_or(Error::BlockNotFound)?;
+        Ok(Json(StateUpdateWithBlock {
+            state_update,
+            block,
+        })
+        .into_response())
+    })
+    .await
+}
+
+async fn get_signature(
+    State(context): State<Context>,
+    query: Result<Query<BlockParams>, QueryRejection>,
+) -> Result<Json<reply::BlockSignature>, Error> {
+    let block_id = params(query)?.block_id()?;
+
+    read(context.storage, move |tx| {
+        signature(tx, block_id)?
+            .map(Json)
+            .ok_or(Error::BlockNotFound)
+    })
+    .await
+}
+
+/// The `blockNumber` parameter is ignored, classes are looked up regardless of
+/// the block they were declared in.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClassParams {
+    class_hash: ClassHash,
+}
+
+async fn get_class_by_hash(
+    State(context): State<Context>,
+    query: Result<Query<ClassParams>, QueryRejection>,
+) -> Result<Response, Error> {
+    let class_hash = params(query)?.class_hash;
+
+    let definition = read(context.storage, move |tx| {
+        tx.class_definition(class_hash)
+            .context("Fetching class definition")?
+            .ok_or(Error::UndeclaredClass)
+    })
+    .await?;
+
+    Ok(json_bytes(definition))
+}
+
+async fn get_compiled_class_by_class_hash(
+    State(context): State<Context>,
+    query: Result<Query<ClassParams>, QueryRejection>,
+) -> Result<Response, Error> {
+    let class_hash = params(query)?.class_hash;
+
+    let definition = read(context.storage, move |tx| {
+        tx.casm_definition(class_hash)
+            .context("Fetching compiled class definition")?
+            .ok_or(Error::UndeclaredClass)
+    })
+    .await?;
+
+    Ok(json_bytes(definition))
+}
+
+/// Class definitions are stored as JSON and served without re-encoding.
+fn json_bytes(json: Vec<u8>) -> Response {
+    ([(http::header::CONTENT_TYPE, "application/json")], json).into_response()
+}
+
+async fn get_contract_addresses(State(context): State<Context>) -> Json<EthContractAddresses> {
+    Json(context.contract_addresses)
+}
+
+async fn get_public_key(State(context): State<Context>) -> Json<PublicKey> {
+    Json(context.public_key)
+}
+
+/// Reads a block in the feeder gateway's format. Pruned blocks are not found,
+/// as their transactions are no longer stored.
+pub fn block(
+    tx: &pathfinder_storage::Transaction<'_>,
+    block_id: BlockId,
+) -> anyhow::Result<Option<reply::Block>> {
+    if tx
+        .block_pruned(block_id)
+        .context("Querying block pruned status")?
+    {
+        return Ok(None);
+    }
+
+    let Some(header) = tx.block_header(block_id).context("Fetching block header")? else {
+        return Ok(None);
+    };
+
+    let transaction_data = tx
+        .transaction_data_for_block(header.number.into())
+        .context("Fetching transaction data")?
+        .context("Transaction data missing")?;
+
+    let receipts = transaction_data
+        .iter()
+        .map(|(_, receipt, _)| receipt.clone())
+        .collect::<Vec<_>>();
+    let receipt_commitment = calculate_receipt_commitment(&receipts)?;
+
+    let (transactions, transaction_receipts): (Vec<_>, Vec<_>) = transaction_data
+        .into_iter()
+        .map(|(transaction, receipt, events)| (transaction, (receipt, events)))
+        .unzip();
+
+    let status = if tx
+        .block_is_l1_accepted(header.number.into())
+        .context("Querying block status")?
+    {
+        Status::AcceptedOnL1
+    } else {
+        Status::AcceptedOnL2
+    };
+
+    Ok(Some(reply::Block {
+        block_hash: header.hash,
+        block_number: header.number,
+        l1_gas_price: GasPrices {
+            price_in_wei: header.eth_l1_gas_price,
+            price_in_fri: header.strk_l1_gas_price,
+        },
+        l1_data_gas_price: GasPrices {
+            price_in_wei: header.eth_l1_data_gas_price,
+            price_in_fri: header.strk_l1_data_gas_price,
+        },
+        l2_gas_price: Some(GasPrices {
+            price_in_wei: header.eth_l2_gas_price,
+            price_in_fri: header.strk_l2_gas_price,
+        }),
+        parent_block_hash: header.parent_hash,
+        sequencer_address: Some(header.sequencer_address),
+        state_commitment: header.state_commitment,
+        status,
+        timestamp: header.timestamp,
+        transaction_receipts,
+        transactions,
+        starknet_version: header.starknet_version,
+        l1_da_mode: header.l1_da_mode.into(),
+        transaction_commitment: header.transaction_commitment,
+        event_commitment: header.event_commitment,
+        receipt_commitment: Some(receipt_commitment),
+        state_diff_commitment: Some(header.state_diff_commitment),
+        state_diff_length: Some(header.state_diff_length),
+    }))
+}
+
+/// Reads a block's signature in the feeder gateway's format.
+pub fn signature(
+    tx: &pathfinder_storage::Transaction<'_>,
+    block_id: BlockId,
+) -> anyhow::Result<Option<reply::BlockSignature>> {
+    let Some(block_hash) = tx.block_hash(block_id).context("Fetching block hash")? else {
+        return Ok(None);
+    };
+
+    let signature = tx
+        .signature(block_id)
+        .context("Fetching signature")?
+        // Fall back to zero since older databases may be missing signatures.
+        .unwrap_or(BlockCommitmentSignature {
+            r: BlockCommitmentSignatureElem::ZERO,
+            s: BlockCommitmentSignatureElem::ZERO,
+        });
+
+    Ok(Some(reply::BlockSignature {
+        block_hash,
+        signature: [signature.r, signature.s],
+    }))
+}
+
+/// Reads a state update in the feeder gateway's format.
+pub fn state_update(
+    tx: &pathfinder_storage::Transaction<'_>,
+    block_id: BlockId,
+) -> anyhow::Result<Option<reply::StateUpdate>> {
+    let state_update = tx.state_update(block_id).context("Fetching state update")?;
+
+    Ok(state_update.map(to_gateway_state_update))
+}
+
+fn to_gateway_state_update(state_update: StateUpdate) -> reply::StateUpdate {
+    let mut storage_diffs = HashMap::new();
+    let mut deployed_contracts = Vec::new();
+    let mut nonces = HashMap::new();
+    let mut replaced_classes = Vec::new();
+
+    for (address, update) in state_update.contract_updates {
+        if let Some(nonce) = update.nonce {
+            nonces.insert(address, nonce);
+        }
+
+        match update.class {
+            Some(ContractClassUpdate::Deploy(class_hash)) => {
+                deployed_contracts.push(DeployedContract {
+                    address,
+                    class_hash,
+                })
+            }
+            Some(ContractClassUpdate::Replace(class_hash)) => {
+                replaced_classes.push(ReplacedClass {
+                    address,
+                    class_hash,
+                })
+            }
+            None => {}
+        }
+
+        let storage = update
+            .storage
+            .into_iter()
+            .map(|(key, value)| StorageDiff { key, value })
+            .collect();
+        storage_diffs.insert(address, storage);
+    }
+
+    for (address, update) in state_update.system_contract_updates {
+        let storage = update
+            .storage
+            .into_iter()
+            .map(|(key, value)| StorageDiff { key, value })
+            .collect();
+        storage_diffs.insert(address, storage);
+    }
+
+    let declared_classes = state_update
+        .declared_sierra_classes
+        .into_iter()
+        .map(|(class_hash, compiled_class_hash)| DeclaredSierraClass {
+            class_hash,
+            compiled_class_hash,
+        })
+        .collect();
+
+    reply::StateUpdate {
+        block_hash: state_update.block_hash,
+        new_root: state_update.state_commitment,
+        old_root: state_update.parent_state_commitment,
+        state_diff: reply::state_update::StateDiff {
+            storage_diffs,
+            deployed_contracts,
+            old_declared_contracts: state_update.declared_cairo_classes,
+            declared_classes,
+            nonces,
+            replaced_classes,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_storage::fake::generate;
+    use pathfinder_storage::StorageBuilder;
+    use starknet_gateway_client::GatewayApi;
+    use starknet_gateway_types::error::SequencerError;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_stored_blocks_to_the_gateway_client() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let blocks = generate::n_blocks(3);
+        pathfinder_storage::fake::fill(&storage, &blocks, None);
+
+        let contract_addresses = EthContractAddresses {
+            starknet: EthereumAddress(primitive_types::H160::from_low_u64_be(1)),
+            strk_l2_token_address: Some(contract_address!("0x2")),
+            eth_l2_token_address: Some(contract_address!("0x3")),
+        };
+        let public_key = public_key!("0x4");
+
+        let (_handle, addr) = FeederGatewayServer::new(
+            "127.0.0.1:0".parse().unwrap(),
+            storage,
+            contract_addresses,
+            public_key,
+        )
+        .spawn()
+        .await
+        .unwrap();
+
+        let base = reqwest::Url::parse(&format!("http://{addr}/")).unwrap();
+        let client = starknet_gateway_client::Client::with_base_url(base, Duration::from_secs(5))
+            .unwrap()
+            .disable_retry_for_tests();
+
+        let latest = &blocks.last().unwrap().header.header;
+        assert_eq!(client.head().await.unwrap(), (latest.number, latest.hash));
+
+        let expected = &blocks[1];
+        let (block, state_update) = client
+            .state_update_with_block(expected.header.header.number)
+            .await
+            .unwrap();
+        assert_eq!(block.block_hash, expected.header.header.hash);
+        assert_eq!(block.transactions.len(), expected.transaction_data.len());
+        assert_eq!(state_update.block_hash, expected.header.header.hash);
+
+        let signature = client
+            .signature(expected.header.header.hash.into())
+            .await
+            .unwrap();
+        assert_eq!(signature.signature(), expected.header.signature);
+
+        assert_eq!(client.public_key().await.unwrap(), public_key);
+        assert_eq!(
+            client.eth_contract_addresses().await.unwrap().starknet,
+            EthereumAddress(primitive_types::H160::from_low_u64_be(1))
+        );
+
+        let error = client
+            .state_update_with_block(BlockNumber::new_or_panic(100))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            SequencerError::StarknetError(StarknetError { code, .. })
+                if code == KnownStarknetErrorCode::BlockNotFound.into()
+        ));
+
+        let error = client
+            .pending_class_by_hash(class_hash!("0x123"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            SequencerError::StarknetError(StarknetError { code, .. })
+                if code == KnownStarknetErrorCode::UndeclaredClass.into()
+        ));
+    }
+
+    #[test]
+    fn pruned_blocks_are_not_found() {
+        let storage = StorageBuilder::in_memory_with_blockchain_pruning_and_pool_size(
+            // Keep only the latest block.
+            pathfinder_storage::pruning::BlockchainHistoryMode::Prune { num_blocks_kept: 0 },
+            std::num::NonZeroU32::new(1).unwrap(),
+        )
+        .unwrap();
+        let blocks = generate::n_blocks(3);
+        pathfinder_storage::fake::fill(&storage, &blocks, None);
+
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        assert!(block(&tx, BlockNumber::GENESIS.into()).unwrap().is_none());
+        let latest = &blocks.last().unwrap().header.header;
+        assert_eq!(
+            block(&tx, latest.number.into())
+                .unwrap()
+                .unwrap()
+                .block_hash,
+            latest.hash
+        );
+    }
+}
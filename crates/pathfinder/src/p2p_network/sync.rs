@@ -1,16 +1,27 @@
 use anyhow::Context;
+use p2p_proto::common::Hash;
+use p2p_proto::pending::NewPendingBlock;
 use p2p_v2::libp2p::identity::Keypair;
 use p2p_v2::libp2p::multiaddr::{Multiaddr, Protocol};
 use p2p_v2::sync::client::peer_agnostic;
 use p2p_v2::sync::Event;
-use p2p_v2::{core, sync};
+use p2p_v2::{core, sync, PeerData};
 use pathfinder_common::ChainId;
+use pathfinder_rpc::PendingData;
 use pathfinder_storage::Storage;
+use tokio::sync::watch;
 use tracing::Instrument;
 
 mod sync_handlers;
 
-use sync_handlers::{get_classes, get_events, get_headers, get_state_diffs, get_transactions};
+use sync_handlers::{
+    get_classes,
+    get_events,
+    get_headers,
+    get_pending_block,
+    get_state_diffs,
+    get_transactions,
+};
 
 // Silence clippy
 pub type P2PNetworkHandle = (
@@ -27,6 +38,10 @@ pub struct P2PContext {
     pub listen_on: Vec<Multiaddr>,
     pub bootstrap_addresses: Vec<Multiaddr>,
     pub predefined_peers: Vec<Multiaddr>,
+    /// The pending block served to peers and announced whenever it changes.
+    pub pending: watch::Receiver<PendingData>,
+    /// Receives peers' announcements that their pending block changed.
+    pub pending_announcements: watch::Sender<Option<PeerData<NewPendingBlock>>>,
 }
 
 #[tracing::instrument(name = "p2p", skip_all)]
@@ -40,6 +55,8 @@ pub async fn start(context: P2PContext) -> anyhow::Result<P2PNetworkHandle> {
         listen_on,
         bootstrap_addresses,
         predefined_peers,
+        pending,
+        pending_announcements,
     } = context;
 
     let peer_id = keypair.public().to_peer_id();
@@ -86,6 +103,9 @@ pub async fn start(context: P2PContext) -> anyhow::Result<P2PNetworkHandle> {
         core_client.dial(peer_id, peer).await?;
     }
 
+    let sync_client: sync::Client = core_client.as_pair().into();
+    util::task::spawn(announce_pending_blocks(sync_client, pending.clone()).in_current_span());
+
     let join_handle = {
         util::task::spawn(
             async move {
@@ -96,7 +116,7 @@ pub async fn start(context: P2PContext) -> anyhow::Result<P2PNetworkHandle> {
                             anyhow::bail!("p2p task ended unexpectedly");
                         }
                         Some(event) = p2p_events.recv() => {
-                            match handle_p2p_event(event, storage.clone()).await {
+                            match handle_p2p_event(event, storage.clone(), pending.clone(), &pending_announcements).await {
                                 Ok(()) => {},
                                 Err(e) => { tracing::error!("Failed to handle P2P event: {:#}", e) },
                             }
@@ -114,7 +134,33 @@ pub async fn start(context: P2PContext) -> anyhow::Result<P2PNetworkHandle> {
     ))
 }
 
-async fn handle_p2p_event(event: Event, storage: Storage) -> anyhow::Result<()> {
+/// Gossips that the local pending block changed, so that peers know when to
+/// request it.
+async fn announce_pending_blocks(client: sync::Client, mut pending: watch::Receiver<PendingData>) {
+    while pending.changed().await.is_ok() {
+        let announcement = {
+            let pending = pending.borrow_and_update();
+            if *pending == PendingData::default() {
+                continue;
+            }
+            NewPendingBlock {
+                parent_hash: Hash(pending.block.parent_hash.0),
+                transaction_count: pending.block.transactions.len() as u64,
+            }
+        };
+
+        if let Err(error) = client.broadcast_new_pending_block(announcement).await {
+            tracing::debug!(%error, "Failed to announce pending block");
+        }
+    }
+}
+
+async fn handle_p2p_event(
+    event: Event,
+    storage: Storage,
+    pending: watch::Receiver<PendingData>,
+    pending_announcements: &watch::Sender<Option<PeerData<NewPendingBlock>>>,
+) -> anyhow::Result<()> {
     match event {
         Event::InboundHeadersRequest {
             request, channel, ..
@@ -141,6 +187,14 @@ async fn handle_p2p_event(event: Event, storage: Storage) -> anyhow::Result<()>
         } => {
             get_events(storage, request, channel).await;
         }
+        Event::InboundPendingBlockRequest {
+            request, channel, ..
+        } => {
+            get_pending_block(pending, request, channel).await;
+        }
+        Event::NewPendingBlock { from, announcement } => {
+            pending_announcements.send_replace(Some(PeerData::new(from, announcement)));
+        }
     }
 
     Ok(())
@@ -12,6 +12,7 @@ use p2p_proto::common::{
 };
 use p2p_proto::event::{EventsRequest, EventsResponse};
 use p2p_proto::header::{BlockHeadersRequest, BlockHeadersResponse};
+use p2p_proto::pending::{PendingBlockHeader, PendingBlockRequest, PendingBlockResponse};
 use p2p_proto::state::{
     ContractDiff,
     ContractStoredValue,
@@ -21,9 +22,10 @@ use p2p_proto::state::{
 };
 use p2p_proto::transaction::{TransactionWithReceipt, TransactionsRequest, TransactionsResponse};
 use p2p_v2::sync::client::conv::ToDto;
-use pathfinder_common::{class_definition, BlockHash, BlockNumber, SignedBlockHeader};
+use pathfinder_common::{class_definition, BlockHash, BlockNumber, SignedBlockHeader, StateUpdate};
+use pathfinder_rpc::PendingData;
 use pathfinder_storage::{Storage, Transaction};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 
 #[cfg(test)]
 mod tests;
@@ -76,6 +78,27 @@ pub async fn get_events(
     spawn_blocking_get(request, storage, blocking::get_events, tx).await
 }
 
+/// Serves the pending block if it is built on top of the requested parent,
+/// otherwise only `Fin` is sent.
+pub async fn get_pending_block(
+    pending: watch::Receiver<PendingData>,
+    request: PendingBlockRequest,
+    mut tx: futures::channel::mpsc::Sender<PendingBlockResponse>,
+) {
+    let pending = pending.borrow().clone();
+    let responses = pending_block_responses(pending, BlockHash(request.parent_hash.0));
+
+    // Detach so we can exit the function asap
+    util::task::spawn(async move {
+        for response in responses {
+            tx.send(response)
+                .await
+                .context("Sending pending block response")?;
+        }
+        Ok::<_, anyhow::Error>(())
+    });
+}
+
 pub(crate) mod blocking {
     use super::*;
 
@@ -227,8 +250,28 @@ fn get_state_diff(
         return Ok(false);
     };
 
-    for (address, update) in state_diff.contract_updates {
-        tx.blocking_send(StateDiffsResponse::ContractDiff(ContractDiff {
+    let (contract_diffs, declared_classes) = state_diff_dtos(state_diff);
+
+    for contract_diff in contract_diffs {
+        tx.blocking_send(StateDiffsResponse::ContractDiff(contract_diff))
+            .map_err(|_| anyhow::anyhow!("Sending contract diff"))?;
+    }
+
+    for declared_class in declared_classes {
+        tx.blocking_send(StateDiffsResponse::DeclaredClass(declared_class))
+            .map_err(|_| anyhow::anyhow!("Sending declared class"))?;
+    }
+
+    Ok(true)
+}
+
+/// Splits the state diff into the contract diffs, including those of system
+/// contracts, and the declared classes, in the order they are sent to peers.
+fn state_diff_dtos(state_diff: StateUpdate) -> (Vec<ContractDiff>, Vec<DeclaredClass>) {
+    let contract_diffs = state_diff
+        .contract_updates
+        .into_iter()
+        .map(|(address, update)| ContractDiff {
             address: Address(address.0),
             nonce: update.nonce.map(|n| n.0),
             class_hash: update.class.as_ref().map(|c| Hash(c.class_hash().0)),
@@ -241,45 +284,45 @@ fn get_state_diff(
                 })
                 .collect(),
             domain: VolitionDomain::L1, // TODO
-        }))
-        .map_err(|_| anyhow::anyhow!("Sending contract diff"))?;
-    }
-
-    for (address, update) in state_diff.system_contract_updates {
-        tx.blocking_send(StateDiffsResponse::ContractDiff(ContractDiff {
-            address: Address(address.0),
-            nonce: None,
-            class_hash: None,
-            values: update
-                .storage
+        })
+        .chain(
+            state_diff
+                .system_contract_updates
                 .into_iter()
-                .map(|(k, v)| ContractStoredValue {
-                    key: k.0,
-                    value: v.0,
-                })
-                .collect(),
-            domain: VolitionDomain::L1, // TODO
-        }))
-        .map_err(|_| anyhow::anyhow!("Sending system contract diff"))?;
-    }
-
-    for class_hash in state_diff.declared_cairo_classes {
-        tx.blocking_send(StateDiffsResponse::DeclaredClass(DeclaredClass {
-            class_hash: Hash(class_hash.0),
-            compiled_class_hash: None,
-        }))
-        .map_err(|_| anyhow::anyhow!("Sending declared cairo class"))?;
-    }
-
-    for (sierra_hash, casm_hash) in state_diff.declared_sierra_classes {
-        tx.blocking_send(StateDiffsResponse::DeclaredClass(DeclaredClass {
-            class_hash: Hash(sierra_hash.0),
-            compiled_class_hash: Some(Hash(casm_hash.0)),
-        }))
-        .map_err(|_| anyhow::anyhow!("Sending declared sierra class"))?;
-    }
+                .map(|(address, update)| ContractDiff {
+                    address: Address(address.0),
+                    nonce: None,
+                    class_hash: None,
+                    values: update
+                        .storage
+                        .into_iter()
+                        .map(|(k, v)| ContractStoredValue {
+                            key: k.0,
+                            value: v.0,
+                        })
+                        .collect(),
+                    domain: VolitionDomain::L1, // TODO
+                }),
+        )
+        .collect();
+
+    let declared_classes =
+        state_diff
+            .declared_cairo_classes
+            .into_iter()
+            .map(|class_hash| DeclaredClass {
+                class_hash: Hash(class_hash.0),
+                compiled_class_hash: None,
+            })
+            .chain(state_diff.declared_sierra_classes.into_iter().map(
+                |(sierra_hash, casm_hash)| DeclaredClass {
+                    class_hash: Hash(sierra_hash.0),
+                    compiled_class_hash: Some(Hash(casm_hash.0)),
+                },
+            ))
+            .collect();
 
-    Ok(true)
+    (contract_diffs, declared_classes)
 }
 
 fn get_transactions_for_block(
@@ -330,6 +373,70 @@ fn get_events_for_block(
     Ok(true)
 }
 
+fn pending_block_responses(
+    pending: PendingData,
+    parent_hash: BlockHash,
+) -> Vec<PendingBlockResponse> {
+    // The default pending data is a placeholder for "no pending block".
+    if pending.block.parent_hash != parent_hash || pending == PendingData::default() {
+        return vec![PendingBlockResponse::Fin];
+    }
+
+    let header = pending.header();
+    let mut responses = vec![PendingBlockResponse::Header(PendingBlockHeader {
+        parent_hash: Hash(header.parent_hash.0),
+        number: header.number.get(),
+        time: header.timestamp.get(),
+        sequencer_address: Address(header.sequencer_address.0),
+        protocol_version: header.starknet_version.to_string(),
+        gas_price_fri: header.strk_l1_gas_price.0,
+        gas_price_wei: header.eth_l1_gas_price.0,
+        data_gas_price_fri: header.strk_l1_data_gas_price.0,
+        data_gas_price_wei: header.eth_l1_data_gas_price.0,
+        l2_gas_price_fri: header.strk_l2_gas_price.0,
+        l2_gas_price_wei: header.eth_l2_gas_price.0,
+        l1_data_availability_mode: header.l1_da_mode.to_dto(),
+    })];
+
+    for (txn, (receipt, events)) in pending
+        .block
+        .transactions
+        .iter()
+        .zip(pending.block.transaction_receipts.iter())
+    {
+        let transaction_hash = receipt.transaction_hash;
+        responses.push(PendingBlockResponse::TransactionWithReceipt(
+            TransactionWithReceipt {
+                receipt: (&txn.variant, receipt.clone()).to_dto(),
+                transaction: p2p_proto::transaction::Transaction {
+                    txn: txn.variant.clone().to_dto(),
+                    transaction_hash: Hash(txn.hash.0),
+                },
+            },
+        ));
+        responses.extend(
+            events.iter().map(|event| {
+                PendingBlockResponse::Event((transaction_hash, event.clone()).to_dto())
+            }),
+        );
+    }
+
+    let (contract_diffs, declared_classes) = state_diff_dtos(pending.state_update.as_ref().clone());
+    responses.extend(
+        contract_diffs
+            .into_iter()
+            .map(PendingBlockResponse::ContractDiff),
+    );
+    responses.extend(
+        declared_classes
+            .into_iter()
+            .map(PendingBlockResponse::DeclaredClass),
+    );
+    responses.push(PendingBlockResponse::Fin);
+
+    responses
+}
+
 /// Assupmtions:
 /// - `block_handler` returns `Ok(true)` if the iteration should continue,
 /// - `T::default()` always returns the `Fin` variant of the implementing type.
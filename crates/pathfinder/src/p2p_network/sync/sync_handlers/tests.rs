@@ -641,3 +641,110 @@ mod prop {
         }
     }
 }
+
+mod pending_block {
+    use std::sync::Arc;
+
+    use fake::{Fake, Faker};
+    use p2p_proto::pending::PendingBlockResponse;
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::prelude::*;
+    use pathfinder_common::receipt::Receipt;
+    use pathfinder_common::transaction::Transaction;
+    use pathfinder_rpc::PendingData;
+    use starknet_gateway_types::reply::PendingBlock;
+
+    use crate::p2p_network::sync::sync_handlers::pending_block_responses;
+
+    fn pending(parent_hash: BlockHash) -> PendingData {
+        let transactions = vec![Faker.fake::<Transaction>(), Faker.fake::<Transaction>()];
+        let transaction_receipts = transactions
+            .iter()
+            .enumerate()
+            .map(|(i, txn)| {
+                let receipt = Receipt {
+                    transaction_hash: txn.hash,
+                    transaction_index: TransactionIndex::new_or_panic(i as u64),
+                    ..Default::default()
+                };
+                // The first transaction emits one event, the second two.
+                let events = (0..=i).map(|_| Faker.fake()).collect();
+                (receipt, events)
+            })
+            .collect();
+
+        PendingData {
+            block: Arc::new(PendingBlock {
+                parent_hash,
+                transactions,
+                transaction_receipts,
+                ..Default::default()
+            }),
+            state_update: Arc::new(
+                StateUpdate::default()
+                    .with_storage_update(
+                        contract_address!("0x1"),
+                        storage_address!("0x2"),
+                        storage_value!("0x3"),
+                    )
+                    .with_declared_cairo_class(class_hash!("0x4")),
+            ),
+            number: BlockNumber::new_or_panic(10),
+        }
+    }
+
+    /// Abbreviates the responses to their kind.
+    fn kinds(responses: Vec<PendingBlockResponse>) -> Vec<&'static str> {
+        responses
+            .into_iter()
+            .map(|response| match response {
+                PendingBlockResponse::Header(_) => "header",
+                PendingBlockResponse::TransactionWithReceipt(_) => "transaction",
+                PendingBlockResponse::Event(_) => "event",
+                PendingBlockResponse::ContractDiff(_) => "contract diff",
+                PendingBlockResponse::DeclaredClass(_) => "declared class",
+                PendingBlockResponse::Fin => "fin",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pending_block_is_served_in_order() {
+        let parent_hash = block_hash!("0xabc");
+        let responses = pending_block_responses(pending(parent_hash), parent_hash);
+
+        let PendingBlockResponse::Header(header) = &responses[0] else {
+            panic!("Expected header, got {:?}", responses[0]);
+        };
+        assert_eq!(header.parent_hash.0, parent_hash.0);
+        assert_eq!(header.number, 10);
+
+        assert_eq!(
+            kinds(responses),
+            vec![
+                "header",
+                "transaction",
+                "event",
+                "transaction",
+                "event",
+                "event",
+                "contract diff",
+                "declared class",
+                "fin"
+            ]
+        );
+    }
+
+    #[test]
+    fn only_fin_for_another_parent() {
+        let responses =
+            pending_block_responses(pending(block_hash!("0xabc")), block_hash!("0xdef"));
+        assert_eq!(responses, vec![PendingBlockResponse::Fin]);
+    }
+
+    #[test]
+    fn only_fin_without_pending_data() {
+        let responses = pending_block_responses(PendingData::default(), BlockHash::ZERO);
+        assert_eq!(responses, vec![PendingBlockResponse::Fin]);
+    }
+}
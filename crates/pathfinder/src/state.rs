@@ -1,4 +1,10 @@
 pub mod block_hash;
+mod pruning;
+mod re_execution;
 mod sync;
+mod trace_writer;
 
+pub use pruning::spawn_blockchain_history_compaction;
+pub use re_execution::ReExecutionVerifier;
 pub use sync::{l1, l2, revert, sync, SyncContext, RESET_DELAY_ON_FAILURE};
+pub use trace_writer::TraceWriter;
@@ -0,0 +1,55 @@
+use anyhow::Context;
+use pathfinder_storage::{Storage, TransactionBehavior};
+
+/// The number of blocks pruned per database transaction. Kept small so that
+/// sync is not blocked for long.
+const COMPACTION_BATCH_SIZE: u64 = 100;
+
+/// Prunes the blocks left outside of the blockchain history window after the
+/// number of blocks kept was reduced, or pruning was enabled on an archive
+/// database. Does nothing if no such compaction is pending.
+///
+/// Progress is stored in the database, so the compaction resumes after a
+/// restart.
+pub fn spawn_blockchain_history_compaction(storage: Storage) {
+    util::task::spawn_blocking(move |cancellation_token| {
+        let result = (|| -> anyhow::Result<()> {
+            let mut connection = storage
+                .connection()
+                .context("Creating database connection")?
+                .with_retry()
+                .context("Enabling database retries")?;
+
+            let mut batches = 0u64;
+            loop {
+                if cancellation_token.is_cancelled() {
+                    return Ok(());
+                }
+
+                let tx = connection
+                    .transaction_with_behavior(TransactionBehavior::Immediate)
+                    .context("Creating database transaction")?;
+                let next = tx
+                    .compact_blockchain_history(COMPACTION_BATCH_SIZE)
+                    .context("Compacting blockchain history")?;
+                tx.commit().context("Committing database transaction")?;
+
+                let Some(next) = next else {
+                    if batches > 0 {
+                        tracing::info!("Blockchain history compaction complete");
+                    }
+                    return Ok(());
+                };
+
+                if batches % 100 == 0 {
+                    tracing::info!(next_block=%next, "Compacting blockchain history");
+                }
+                batches += 1;
+            }
+        })();
+
+        if let Err(error) = result {
+            tracing::error!(?error, "Blockchain history compaction failed");
+        }
+    });
+}
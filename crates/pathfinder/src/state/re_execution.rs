@@ -0,0 +1,998 @@
+//! Background re-execution of newly synced blocks.
+//!
+//! Every block added by sync is executed again on top of its parent state and
+//! the results are compared against the receipts and the state update received
+//! from the network: revert status, fees and gas, events, L2 to L1 messages and
+//! the block's state diff. Any difference points to a divergence between our
+//! executor (or the versioned constants we use) and the sequencer.
+//!
+//! Mismatches are logged with the differing values and counted in the
+//! `re_execution_mismatches_total` metric, labelled by kind.
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::Context;
+use pathfinder_common::event::Event;
+use pathfinder_common::receipt::{L2ToL1Message, Receipt};
+use pathfinder_common::state_update::ContractClassUpdate;
+use pathfinder_common::{
+    BlockHeader,
+    BlockNumber,
+    CasmHash,
+    ChainId,
+    ClassHash,
+    ContractAddress,
+    ContractNonce,
+    EventData,
+    EventKey,
+    L2ToL1MessagePayloadElem,
+    SierraHash,
+    StateUpdate,
+    StorageAddress,
+    StorageValue,
+    TransactionHash,
+};
+use pathfinder_executor::types::{
+    ExecuteInvocation,
+    FunctionInvocation,
+    StateDiff,
+    TransactionSimulation,
+    TransactionTrace,
+};
+use pathfinder_executor::{
+    ExecutionState,
+    NativeClassCache,
+    TransactionExecutionError,
+    VersionedConstantsMap,
+};
+use pathfinder_rpc::context::EthContractAddresses;
+use pathfinder_storage::{BlockId, Storage, Transaction};
+use primitive_types::U256;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use util::percentage::Percentage;
+
+#[derive(Clone)]
+pub struct ReExecutionVerifier {
+    storage: Storage,
+    chain_id: ChainId,
+    contract_addresses: EthContractAddresses,
+    versioned_constants_map: VersionedConstantsMap,
+    native_class_cache: Option<NativeClassCache>,
+}
+
+impl ReExecutionVerifier {
+    pub fn new(
+        storage: Storage,
+        chain_id: ChainId,
+        contract_addresses: EthContractAddresses,
+        versioned_constants_map: VersionedConstantsMap,
+        native_class_cache: Option<NativeClassCache>,
+    ) -> Self {
+        Self {
+            storage,
+            chain_id,
+            contract_addresses,
+            versioned_constants_map,
+            native_class_cache,
+        }
+    }
+
+    /// Re-executes every block announced on `block_headers`, one at a time.
+    ///
+    /// Blocks announced while the verifier is busy are skipped once the
+    /// channel lags behind, so the verifier never holds back sync.
+    pub fn spawn(self, mut block_headers: broadcast::Receiver<Arc<BlockHeader>>) {
+        util::task::spawn(async move {
+            loop {
+                let header = match block_headers.recv().await {
+                    Ok(header) => header,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::debug!(%skipped, "Re-execution verifier lagging behind sync");
+                        metrics::counter!("re_execution_skipped_blocks_total", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+
+                let verifier = self.clone();
+                let block_number = header.number;
+                let result =
+                    util::task::spawn_blocking(move |_| verifier.verify_block(&header)).await;
+
+                match result {
+                    Ok(Ok(Some(mismatches))) => {
+                        metrics::increment_counter!("re_execution_blocks_total");
+                        for mismatch in &mismatches {
+                            mismatch.log(block_number);
+                            metrics::increment_counter!("re_execution_mismatches_total", "kind" => mismatch.kind());
+                        }
+                        if mismatches.is_empty() {
+                            tracing::trace!(%block_number, "Re-executed block matches");
+                        }
+                    }
+                    // The block was reorged away in the meantime.
+                    Ok(Ok(None)) => {}
+                    Ok(Err(error)) => {
+                        tracing::warn!(%block_number, ?error, "Re-executing block failed");
+                        metrics::increment_counter!("re_execution_failures_total");
+                    }
+                    Err(error) => {
+                        tracing::error!(%block_number, %error, "Re-execution task panicked");
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Re-executes the block and returns the differences found, or [None] if
+    /// the block is no longer in the database.
+    fn verify_block(&self, header: &BlockHeader) -> anyhow::Result<Option<Vec<Mismatch>>> {
+        let mut db = self
+            .storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = db.transaction().context("Creating database transaction")?;
+
+        let block_id = BlockId::Hash(header.hash);
+        let Some(header) = db.block_header(block_id).context("Fetching block header")? else {
+            return Ok(None);
+        };
+        let Some(transactions) = db
+            .transaction_data_for_block(block_id)
+            .context("Fetching transaction data")?
+        else {
+            return Ok(None);
+        };
+        let Some(state_update) = db.state_update(block_id).context("Fetching state update")? else {
+            return Ok(None);
+        };
+
+        let executor_transactions = transactions
+            .iter()
+            .map(|(transaction, _, _)| {
+                pathfinder_rpc::compose_executor_transaction(transaction, &db)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("Converting transactions")?;
+
+        let parent = header.number.parent();
+        let execution_state = ExecutionState::trace(
+            &db,
+            self.chain_id,
+            header,
+            None,
+            self.versioned_constants_map.clone(),
+            self.contract_addresses.eth_l2_token_address,
+            self.contract_addresses.strk_l2_token_address,
+            self.native_class_cache.clone(),
+        );
+
+        let simulations = match pathfinder_executor::simulate(
+            execution_state,
+            executor_transactions,
+            Percentage::new(0),
+        ) {
+            Ok(simulations) => simulations,
+            Err(TransactionExecutionError::ExecutionError {
+                transaction_index,
+                error,
+                ..
+            }) => {
+                let transaction_hash = transactions
+                    .get(transaction_index)
+                    .map(|(transaction, _, _)| transaction.hash)
+                    .unwrap_or_default();
+                return Ok(Some(vec![Mismatch::ExecutionFailed {
+                    transaction_hash,
+                    error,
+                }]));
+            }
+            Err(TransactionExecutionError::DeadlineExceeded) => {
+                anyhow::bail!("Re-executing transactions: execution deadline exceeded")
+            }
+            Err(TransactionExecutionError::Internal(error))
+            | Err(TransactionExecutionError::Custom(error)) => {
+                return Err(error.context("Re-executing transactions"))
+            }
+        };
+
+        let mut mismatches = Vec::new();
+        let mut executed_diff = BlockDiff::default();
+        for (simulation, (_, receipt, events)) in simulations.iter().zip(transactions.iter()) {
+            compare_transaction(simulation, receipt, events, &mut mismatches);
+            executed_diff.apply(state_diff(&simulation.trace));
+        }
+
+        let stored_diff = BlockDiff::from(&state_update);
+        compare_state_diffs(&db, parent, &stored_diff, &executed_diff, &mut mismatches)
+            .context("Comparing state diffs")?;
+
+        Ok(Some(mismatches))
+    }
+}
+
+/// A difference between the stored block and its re-execution.
+///
+/// `stored` values come from the network, `executed` values from our own
+/// re-execution.
+#[derive(Debug, PartialEq)]
+enum Mismatch {
+    ExecutionFailed {
+        transaction_hash: TransactionHash,
+        error: String,
+    },
+    RevertStatus {
+        transaction_hash: TransactionHash,
+        stored: Option<String>,
+        executed: Option<String>,
+    },
+    Fee {
+        transaction_hash: TransactionHash,
+        stored: Fees,
+        executed: Fees,
+    },
+    Events {
+        transaction_hash: TransactionHash,
+        missing: Vec<Event>,
+        unexpected: Vec<Event>,
+    },
+    Messages {
+        transaction_hash: TransactionHash,
+        missing: Vec<L2ToL1Message>,
+        unexpected: Vec<L2ToL1Message>,
+    },
+    Storage {
+        contract_address: ContractAddress,
+        key: StorageAddress,
+        stored: StorageValue,
+        executed: StorageValue,
+    },
+    Nonce {
+        contract_address: ContractAddress,
+        stored: ContractNonce,
+        executed: ContractNonce,
+    },
+    Class {
+        contract_address: ContractAddress,
+        stored: Option<ClassHash>,
+        executed: Option<ClassHash>,
+    },
+    DeclaredClasses {
+        missing: Vec<ClassHash>,
+        unexpected: Vec<ClassHash>,
+    },
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Fees {
+    overall_fee: U256,
+    l1_gas: u128,
+    l1_data_gas: u128,
+    l2_gas: u128,
+}
+
+impl Mismatch {
+    fn kind(&self) -> &'static str {
+        match self {
+            Mismatch::ExecutionFailed { .. } => "execution_failed",
+            Mismatch::RevertStatus { .. } => "revert_status",
+            Mismatch::Fee { .. } => "fee",
+            Mismatch::Events { .. } => "events",
+            Mismatch::Messages { .. } => "messages",
+            Mismatch::Storage { .. } => "storage",
+            Mismatch::Nonce { .. } => "nonce",
+            Mismatch::Class { .. } => "class",
+            Mismatch::DeclaredClasses { .. } => "declared_classes",
+        }
+    }
+
+    fn log(&self, block_number: BlockNumber) {
+        let kind = self.kind();
+        match self {
+            Mismatch::ExecutionFailed {
+                transaction_hash,
+                error,
+            } => {
+                tracing::warn!(%block_number, %kind, %transaction_hash, %error, "Re-execution mismatch")
+            }
+            Mismatch::RevertStatus {
+                transaction_hash,
+                stored,
+                executed,
+            } => {
+                tracing::warn!(%block_number, %kind, %transaction_hash, ?stored, ?executed, "Re-execution mismatch")
+            }
+            Mismatch::Fee {
+                transaction_hash,
+                stored,
+                executed,
+            } => {
+                tracing::warn!(%block_number, %kind, %transaction_hash, ?stored, ?executed, "Re-execution mismatch")
+            }
+            Mismatch::Events {
+                transaction_hash,
+                missing,
+                unexpected,
+            } => {
+                tracing::warn!(%block_number, %kind, %transaction_hash, ?missing, ?unexpected, "Re-execution mismatch")
+            }
+            Mismatch::Messages {
+                transaction_hash,
+                missing,
+                unexpected,
+            } => {
+                tracing::warn!(%block_number, %kind, %transaction_hash, ?missing, ?unexpected, "Re-execution mismatch")
+            }
+            Mismatch::Storage {
+                contract_address,
+                key,
+                stored,
+                executed,
+            } => {
+                tracing::warn!(%block_number, %kind, %contract_address, %key, %stored, %executed, "Re-execution mismatch")
+            }
+            Mismatch::Nonce {
+                contract_address,
+                stored,
+                executed,
+            } => {
+                tracing::warn!(%block_number, %kind, %contract_address, %stored, %executed, "Re-execution mismatch")
+            }
+            Mismatch::Class {
+                contract_address,
+                stored,
+                executed,
+            } => {
+                tracing::warn!(%block_number, %kind, %contract_address, ?stored, ?executed, "Re-execution mismatch")
+            }
+            Mismatch::DeclaredClasses {
+                missing,
+                unexpected,
+            } => {
+                tracing::warn!(%block_number, %kind, ?missing, ?unexpected, "Re-execution mismatch")
+            }
+        }
+    }
+}
+
+fn compare_transaction(
+    simulation: &TransactionSimulation,
+    receipt: &Receipt,
+    events: &[Event],
+    mismatches: &mut Vec<Mismatch>,
+) {
+    let transaction_hash = receipt.transaction_hash;
+
+    let stored = receipt.revert_reason();
+    let executed = simulation.revert_reason();
+    if stored.is_some() != executed.is_some() {
+        mismatches.push(Mismatch::RevertStatus {
+            transaction_hash,
+            stored: stored.map(ToOwned::to_owned),
+            executed: executed.map(ToOwned::to_owned),
+        });
+    }
+
+    // L1 handler transactions have a fee of zero in the receipt.
+    if receipt.actual_fee.0 != Default::default() {
+        let stored = Fees {
+            overall_fee: U256::from_big_endian(receipt.actual_fee.0.as_be_bytes()),
+            l1_gas: receipt.execution_resources.total_gas_consumed.l1_gas,
+            l1_data_gas: receipt.execution_resources.total_gas_consumed.l1_data_gas,
+            l2_gas: receipt.execution_resources.l2_gas.0,
+        };
+        let estimate = &simulation.fee_estimation;
+        let executed = Fees {
+            overall_fee: estimate.overall_fee,
+            l1_gas: estimate.l1_gas_consumed.as_u128(),
+            l1_data_gas: estimate.l1_data_gas_consumed.as_u128(),
+            l2_gas: estimate.l2_gas_consumed.as_u128(),
+        };
+        if stored != executed {
+            mismatches.push(Mismatch::Fee {
+                transaction_hash,
+                stored,
+                executed,
+            });
+        }
+    }
+
+    let mut executed_events = Vec::new();
+    let mut executed_messages = Vec::new();
+    for invocation in invocations(&simulation.trace) {
+        collect_outputs(invocation, &mut executed_events, &mut executed_messages);
+    }
+    // Only the emitted events and messages are compared, not their ordering.
+    let (missing, unexpected) = difference(events, executed_events);
+    if !missing.is_empty() || !unexpected.is_empty() {
+        mismatches.push(Mismatch::Events {
+            transaction_hash,
+            missing,
+            unexpected,
+        });
+    }
+
+    let (missing, unexpected) = difference(&receipt.l2_to_l1_messages, executed_messages);
+    if !missing.is_empty() || !unexpected.is_empty() {
+        mismatches.push(Mismatch::Messages {
+            transaction_hash,
+            missing,
+            unexpected,
+        });
+    }
+}
+
+/// The top-level invocations of a transaction, in execution order.
+fn invocations(trace: &TransactionTrace) -> Vec<&FunctionInvocation> {
+    match trace {
+        TransactionTrace::Declare(trace) => [
+            trace.validate_invocation.as_ref(),
+            trace.fee_transfer_invocation.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+        TransactionTrace::DeployAccount(trace) => [
+            trace.validate_invocation.as_ref(),
+            trace.constructor_invocation.as_ref(),
+            trace.fee_transfer_invocation.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+        TransactionTrace::Invoke(trace) => {
+            let execute_invocation = match &trace.execute_invocation {
+                ExecuteInvocation::FunctionInvocation(invocation) => invocation.as_ref(),
+                ExecuteInvocation::RevertedReason(_) => None,
+            };
+            [
+                trace.validate_invocation.as_ref(),
+                execute_invocation,
+                trace.fee_transfer_invocation.as_ref(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect()
+        }
+        TransactionTrace::L1Handler(trace) => trace.function_invocation.iter().collect(),
+    }
+}
+
+/// Collects the events and messages emitted by an invocation and its inner
+/// calls. Reverted calls emit nothing.
+fn collect_outputs(
+    invocation: &FunctionInvocation,
+    events: &mut Vec<Event>,
+    messages: &mut Vec<L2ToL1Message>,
+) {
+    if invocation.is_reverted {
+        return;
+    }
+
+    events.extend(invocation.events.iter().map(|event| Event {
+        data: event.data.iter().copied().map(EventData).collect(),
+        from_address: invocation.contract_address,
+        keys: event.keys.iter().copied().map(EventKey).collect(),
+    }));
+    messages.extend(invocation.messages.iter().map(|message| {
+        L2ToL1Message {
+            from_address: ContractAddress(message.from_address),
+            payload: message
+                .payload
+                .iter()
+                .copied()
+                .map(L2ToL1MessagePayloadElem)
+                .collect(),
+            to_address: ContractAddress(message.to_address),
+        }
+    }));
+
+    for call in &invocation.internal_calls {
+        collect_outputs(call, events, messages);
+    }
+}
+
+/// Returns the items of `stored` missing from `executed` and the items of
+/// `executed` not in `stored`, counting duplicates.
+fn difference<T: Clone + PartialEq>(stored: &[T], mut executed: Vec<T>) -> (Vec<T>, Vec<T>) {
+    let mut missing = Vec::new();
+    for item in stored {
+        match executed.iter().position(|x| x == item) {
+            Some(index) => {
+                executed.remove(index);
+            }
+            None => missing.push(item.clone()),
+        }
+    }
+
+    (missing, executed)
+}
+
+fn state_diff(trace: &TransactionTrace) -> &StateDiff {
+    match trace {
+        TransactionTrace::Declare(trace) => &trace.state_diff,
+        TransactionTrace::DeployAccount(trace) => &trace.state_diff,
+        TransactionTrace::Invoke(trace) => &trace.state_diff,
+        TransactionTrace::L1Handler(trace) => &trace.state_diff,
+    }
+}
+
+/// The state changes of a block, excluding system contracts.
+///
+/// System contract storage is written by the sequencer outside of transaction
+/// execution, so it can't be compared.
+#[derive(Debug, Default)]
+struct BlockDiff {
+    storage: HashMap<(ContractAddress, StorageAddress), StorageValue>,
+    nonces: HashMap<ContractAddress, ContractNonce>,
+    classes: HashMap<ContractAddress, ClassHash>,
+    declared_cairo_classes: HashSet<ClassHash>,
+    declared_sierra_classes: HashMap<SierraHash, CasmHash>,
+}
+
+impl BlockDiff {
+    /// Applies the state diff of the next transaction in the block.
+    fn apply(&mut self, diff: &StateDiff) {
+        for (contract_address, diffs) in &diff.storage_diffs {
+            if contract_address.is_system_contract() {
+                continue;
+            }
+            for diff in diffs {
+                self.storage
+                    .insert((*contract_address, diff.key), diff.value);
+            }
+        }
+        self.nonces.extend(&diff.nonces);
+        for deployed in &diff.deployed_contracts {
+            self.classes.insert(deployed.address, deployed.class_hash);
+        }
+        for replaced in &diff.replaced_classes {
+            self.classes
+                .insert(replaced.contract_address, replaced.class_hash);
+        }
+        self.declared_cairo_classes
+            .extend(&diff.deprecated_declared_classes);
+        for declared in &diff.declared_classes {
+            self.declared_sierra_classes
+                .insert(declared.class_hash, declared.compiled_class_hash);
+        }
+    }
+}
+
+impl From<&StateUpdate> for BlockDiff {
+    fn from(state_update: &StateUpdate) -> Self {
+        let mut diff = BlockDiff::default();
+        for (contract_address, update) in &state_update.contract_updates {
+            for (key, value) in &update.storage {
+                diff.storage.insert((*contract_address, *key), *value);
+            }
+            if let Some(nonce) = update.nonce {
+                diff.nonces.insert(*contract_address, nonce);
+            }
+            match update.class {
+                Some(ContractClassUpdate::Deploy(class_hash))
+                | Some(ContractClassUpdate::Replace(class_hash)) => {
+                    diff.classes.insert(*contract_address, class_hash);
+                }
+                None => {}
+            }
+        }
+        diff.declared_cairo_classes = state_update.declared_cairo_classes.clone();
+        diff.declared_sierra_classes = state_update.declared_sierra_classes.clone();
+        diff
+    }
+}
+
+/// Compares the state each diff results in. A value missing from one side is
+/// taken from the parent block, so writes that don't change a value are not
+/// reported.
+fn compare_state_diffs(
+    db: &Transaction<'_>,
+    parent: Option<BlockNumber>,
+    stored: &BlockDiff,
+    executed: &BlockDiff,
+    mismatches: &mut Vec<Mismatch>,
+) -> anyhow::Result<()> {
+    let keys = stored
+        .storage
+        .keys()
+        .chain(executed.storage.keys())
+        .collect::<HashSet<_>>();
+    for &(contract_address, key) in keys {
+        let parent_value = || -> anyhow::Result<StorageValue> {
+            let Some(parent) = parent else {
+                return Ok(StorageValue::ZERO);
+            };
+            Ok(db
+                .storage_value(parent.into(), contract_address, key)?
+                .unwrap_or_default())
+        };
+        let stored_value = stored.storage.get(&(contract_address, key)).copied();
+        let executed_value = executed.storage.get(&(contract_address, key)).copied();
+        if stored_value == executed_value {
+            continue;
+        }
+
+        let stored_value = stored_value.map_or_else(parent_value, Ok)?;
+        let executed_value = executed_value.map_or_else(parent_value, Ok)?;
+        if stored_value != executed_value {
+            mismatches.push(Mismatch::Storage {
+                contract_address,
+                key,
+                stored: stored_value,
+                executed: executed_value,
+            });
+        }
+    }
+
+    let contracts = stored
+        .nonces
+        .keys()
+        .chain(executed.nonces.keys())
+        .collect::<HashSet<_>>();
+    for &contract_address in contracts {
+        let parent_nonce = || -> anyhow::Result<ContractNonce> {
+            let Some(parent) = parent else {
+                return Ok(ContractNonce::ZERO);
+            };
+            Ok(db
+                .contract_nonce(contract_address, parent.into())?
+                .unwrap_or_default())
+        };
+        let stored_nonce = stored.nonces.get(&contract_address).copied();
+        let executed_nonce = executed.nonces.get(&contract_address).copied();
+        if stored_nonce == executed_nonce {
+            continue;
+        }
+
+        let stored_nonce = stored_nonce.map_or_else(parent_nonce, Ok)?;
+        let executed_nonce = executed_nonce.map_or_else(parent_nonce, Ok)?;
+        if stored_nonce != executed_nonce {
+            mismatches.push(Mismatch::Nonce {
+                contract_address,
+                stored: stored_nonce,
+                executed: executed_nonce,
+            });
+        }
+    }
+
+    let contracts = stored
+        .classes
+        .keys()
+        .chain(executed.classes.keys())
+        .collect::<HashSet<_>>();
+    for &contract_address in contracts {
+        let parent_class = || -> anyhow::Result<Option<ClassHash>> {
+            let Some(parent) = parent else {
+                return Ok(None);
+            };
+            db.contract_class_hash(parent.into(), contract_address)
+        };
+        let stored_class = stored.classes.get(&contract_address).copied();
+        let executed_class = executed.classes.get(&contract_address).copied();
+        if stored_class == executed_class {
+            continue;
+        }
+
+        let stored_class = stored_class.map_or_else(parent_class, |x| Ok(Some(x)))?;
+        let executed_class = executed_class.map_or_else(parent_class, |x| Ok(Some(x)))?;
+        if stored_class != executed_class {
+            mismatches.push(Mismatch::Class {
+                contract_address,
+                stored: stored_class,
+                executed: executed_class,
+            });
+        }
+    }
+
+    let stored_classes = stored
+        .declared_cairo_classes
+        .iter()
+        .copied()
+        .chain(
+            stored
+                .declared_sierra_classes
+                .keys()
+                .map(|x| ClassHash(x.0)),
+        )
+        .collect::<HashSet<_>>();
+    let executed_classes = executed
+        .declared_cairo_classes
+        .iter()
+        .copied()
+        .chain(
+            executed
+                .declared_sierra_classes
+                .keys()
+                .map(|x| ClassHash(x.0)),
+        )
+        .collect::<HashSet<_>>();
+    if stored_classes != executed_classes {
+        mismatches.push(Mismatch::DeclaredClasses {
+            missing: stored_classes
+                .difference(&executed_classes)
+                .copied()
+                .collect(),
+            unexpected: executed_classes
+                .difference(&stored_classes)
+                .copied()
+                .collect(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::prelude::*;
+    use pathfinder_common::transaction::{InvokeTransactionV1, TransactionVariant};
+    use pathfinder_executor::types::{DeployedContract, StorageDiff};
+    use pathfinder_storage::StorageBuilder;
+    use primitive_types::H160;
+    use starknet_gateway_test_fixtures::class_definitions::{
+        ERC20_CONTRACT_DEFINITION,
+        ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+    };
+
+    use super::*;
+
+    /// An OpenZeppelin account without signature checks.
+    const ACCOUNT_CLASS_HASH: ClassHash =
+        class_hash!("0x019cabebe31b9fb6bf5e7ce9a971bd7d06e9999e0b97eee943869141a46fd978");
+    const ACCOUNT_SIERRA_DEFINITION: &[u8] = include_bytes!(
+        "../../../rpc/fixtures/contracts/openzeppelin/openzeppelin_presets_AccountUpgradeable.\
+         starknet_contract_class.json"
+    );
+    const ACCOUNT_CASM_HASH: CasmHash =
+        casm_hash!("0x0224b815fab6827eb21993e02e45e532e5476af6536dcf1f7085989ba9dc5bf0");
+    const ACCOUNT_CASM_DEFINITION: &[u8] = include_bytes!(
+        "../../../rpc/fixtures/contracts/openzeppelin/openzeppelin_presets_AccountUpgradeable.\
+         compiled_contract_class.json"
+    );
+
+    /// Stores a block with an account, funded by fee tokens deployed at
+    /// `contract_addresses`, followed by a block in which the account sends
+    /// a transaction. The stored state update of that block bumps the nonce
+    /// of the account by two instead of one.
+    fn storage_with_block(
+        contract_addresses: &EthContractAddresses,
+    ) -> (Storage, BlockHeader, ContractAddress) {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let db_tx = db.transaction().unwrap();
+
+        let version = StarknetVersion::new(0, 13, 1, 1);
+        let parent = BlockHeader::builder()
+            .timestamp(BlockTimestamp::new_or_panic(1))
+            .eth_l1_gas_price(GasPrice(1))
+            .strk_l1_gas_price(GasPrice(2))
+            .eth_l1_data_gas_price(GasPrice(2))
+            .strk_l1_data_gas_price(GasPrice(2))
+            .eth_l2_gas_price(GasPrice(1))
+            .strk_l2_gas_price(GasPrice(1))
+            .l1_da_mode(L1DataAvailabilityMode::Blob)
+            .sequencer_address(sequencer_address!("0x5e9"))
+            .starknet_version(version)
+            .finalize_with_hash(block_hash!("0xb00"));
+        db_tx.insert_block_header(&parent).unwrap();
+
+        db_tx
+            .insert_sierra_class(
+                &SierraHash(ACCOUNT_CLASS_HASH.0),
+                ACCOUNT_SIERRA_DEFINITION,
+                &ACCOUNT_CASM_HASH,
+                ACCOUNT_CASM_DEFINITION,
+            )
+            .unwrap();
+        db_tx
+            .insert_cairo_class(
+                ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+                ERC20_CONTRACT_DEFINITION,
+            )
+            .unwrap();
+
+        let account = contract_address!("0xc01");
+        let balance_key = StorageAddress::from_map_name_and_key(b"ERC20_balances", account.0);
+        let state_update = StateUpdate::default()
+            .with_block_hash(parent.hash)
+            .with_declared_cairo_class(ERC20_CONTRACT_DEFINITION_CLASS_HASH)
+            .with_declared_sierra_class(SierraHash(ACCOUNT_CLASS_HASH.0), ACCOUNT_CASM_HASH)
+            .with_deployed_contract(account, ACCOUNT_CLASS_HASH)
+            .with_deployed_contract(
+                contract_addresses.eth_l2_token_address,
+                ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+            )
+            .with_deployed_contract(
+                contract_addresses.strk_l2_token_address,
+                ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+            )
+            .with_storage_update(
+                contract_addresses.eth_l2_token_address,
+                balance_key,
+                storage_value!("0x10000000000000000000000000000"),
+            )
+            .with_storage_update(
+                contract_addresses.strk_l2_token_address,
+                balance_key,
+                storage_value!("0x10000000000000000000000000000"),
+            );
+        db_tx
+            .insert_state_update(parent.number, &state_update)
+            .unwrap();
+
+        let header = BlockHeader::child_builder(&parent)
+            .timestamp(BlockTimestamp::new_or_panic(2))
+            .eth_l1_gas_price(parent.eth_l1_gas_price)
+            .strk_l1_gas_price(parent.strk_l1_gas_price)
+            .eth_l1_data_gas_price(parent.eth_l1_data_gas_price)
+            .strk_l1_data_gas_price(parent.strk_l1_data_gas_price)
+            .eth_l2_gas_price(parent.eth_l2_gas_price)
+            .strk_l2_gas_price(parent.strk_l2_gas_price)
+            .l1_da_mode(parent.l1_da_mode)
+            .sequencer_address(parent.sequencer_address)
+            .starknet_version(version)
+            .finalize_with_hash(block_hash!("0xb01"));
+        db_tx.insert_block_header(&header).unwrap();
+
+        // Executes no calls.
+        let variant = TransactionVariant::InvokeV1(InvokeTransactionV1 {
+            calldata: vec![call_param!("0x0")],
+            sender_address: account,
+            max_fee: fee!("0x10000000"),
+            signature: vec![],
+            nonce: transaction_nonce!("0x0"),
+        });
+        let transaction = pathfinder_common::transaction::Transaction {
+            hash: variant.calculate_hash(ChainId::SEPOLIA_TESTNET, false),
+            variant,
+        };
+        let receipt = Receipt {
+            transaction_hash: transaction.hash,
+            ..Default::default()
+        };
+        db_tx
+            .insert_transaction_data(header.number, &[(transaction, receipt)], Some(&[vec![]]))
+            .unwrap();
+        db_tx
+            .insert_state_update(
+                header.number,
+                &StateUpdate::default()
+                    .with_block_hash(header.hash)
+                    .with_parent_state_commitment(parent.state_commitment)
+                    .with_contract_nonce(account, contract_nonce!("0x2")),
+            )
+            .unwrap();
+
+        db_tx.commit().unwrap();
+
+        (storage, header, account)
+    }
+
+    #[test]
+    fn difference_counts_duplicates() {
+        let (missing, unexpected) = difference(&[1, 1, 2, 3], vec![3, 1, 4]);
+        assert_eq!(missing, vec![1, 2]);
+        assert_eq!(unexpected, vec![4]);
+
+        let (missing, unexpected) = difference(&[1, 2], vec![2, 1]);
+        assert!(missing.is_empty());
+        assert!(unexpected.is_empty());
+    }
+
+    #[test]
+    fn state_diffs() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let db = db.transaction().unwrap();
+
+        let stored = StateUpdate::default()
+            .with_storage_update(
+                contract_address!("0x10"),
+                storage_address!("0x1"),
+                storage_value!("0x1"),
+            )
+            .with_storage_update(
+                contract_address!("0x10"),
+                storage_address!("0x2"),
+                storage_value!("0x2"),
+            )
+            .with_contract_nonce(contract_address!("0x10"), contract_nonce!("0x1"))
+            .with_deployed_contract(contract_address!("0x20"), class_hash!("0x123"))
+            .with_system_storage_update(
+                ContractAddress::ONE,
+                storage_address!("0x1"),
+                storage_value!("0x99"),
+            );
+        let stored = BlockDiff::from(&stored);
+
+        let mut executed = BlockDiff::default();
+        executed.apply(&StateDiff {
+            storage_diffs: [(
+                contract_address!("0x10"),
+                vec![
+                    StorageDiff {
+                        key: storage_address!("0x1"),
+                        value: storage_value!("0x1"),
+                    },
+                    StorageDiff {
+                        key: storage_address!("0x2"),
+                        value: storage_value!("0x3"),
+                    },
+                    // Writing the value the slot already held is not a change.
+                    StorageDiff {
+                        key: storage_address!("0x3"),
+                        value: StorageValue::ZERO,
+                    },
+                ],
+            )]
+            .into(),
+            deployed_contracts: vec![DeployedContract {
+                address: contract_address!("0x20"),
+                class_hash: class_hash!("0x123"),
+            }],
+            deprecated_declared_classes: [class_hash!("0x456")].into(),
+            declared_classes: vec![],
+            nonces: [(contract_address!("0x10"), contract_nonce!("0x1"))].into(),
+            replaced_classes: vec![],
+        });
+
+        let mut mismatches = Vec::new();
+        compare_state_diffs(&db, None, &stored, &executed, &mut mismatches).unwrap();
+
+        assert_eq!(
+            mismatches,
+            vec![
+                Mismatch::Storage {
+                    contract_address: contract_address!("0x10"),
+                    key: storage_address!("0x2"),
+                    stored: storage_value!("0x2"),
+                    executed: storage_value!("0x3"),
+                },
+                Mismatch::DeclaredClasses {
+                    missing: vec![],
+                    unexpected: vec![class_hash!("0x456")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn re_executed_block_with_configured_fee_tokens() {
+        // Not the fee token addresses of the public networks, so the block can
+        // only be executed with the configured addresses.
+        let contract_addresses = EthContractAddresses {
+            l1_contract_address: H160::zero(),
+            eth_l2_token_address: contract_address!("0xe7e"),
+            strk_l2_token_address: contract_address!("0x57e"),
+        };
+        let (storage, header, account) = storage_with_block(&contract_addresses);
+
+        let verifier = ReExecutionVerifier::new(
+            storage,
+            ChainId::SEPOLIA_TESTNET,
+            contract_addresses,
+            VersionedConstantsMap::default(),
+            None,
+        );
+        let mismatches = verifier.verify_block(&header).unwrap().unwrap();
+
+        assert!(
+            !mismatches
+                .iter()
+                .any(|mismatch| matches!(mismatch, Mismatch::ExecutionFailed { .. })),
+            "{mismatches:?}"
+        );
+        assert!(
+            mismatches.contains(&Mismatch::Nonce {
+                contract_address: account,
+                stored: contract_nonce!("0x2"),
+                executed: contract_nonce!("0x1"),
+            }),
+            "{mismatches:?}"
+        );
+    }
+}
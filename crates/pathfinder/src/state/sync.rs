@@ -14,15 +14,18 @@ use pathfinder_common::Chain;
 use pathfinder_crypto::Felt;
 use pathfinder_ethereum::{EthereumApi, EthereumStateUpdate};
 use pathfinder_merkle_tree::starknet_state::update_starknet_state;
+use pathfinder_rpc::admin::{SyncCommand, SyncCommands};
 use pathfinder_rpc::types::syncing::{self, NumberedBlock, Syncing};
-use pathfinder_rpc::{Notifications, PendingData, Reorg, SyncState, TopicBroadcasters};
+use pathfinder_rpc::{Notifications, PendingData, SyncState, TopicBroadcasters};
 use pathfinder_storage::pruning::BlockchainHistoryMode;
 use pathfinder_storage::{Connection, Storage, TransactionBehavior};
 use primitive_types::H160;
 use starknet_gateway_client::GatewayApi;
 use starknet_gateway_types::reply::{Block, PendingBlock};
 use tokio::sync::mpsc::{self, Receiver};
+use tokio::sync::oneshot;
 use tokio::sync::watch::Sender as WatchSender;
+use tokio::task::JoinHandle;
 
 use crate::state::l1::L1SyncContext;
 use crate::state::l2::{BlockChain, L2SyncContext};
@@ -66,6 +69,11 @@ pub enum SyncEvent {
     },
     /// A new L2 pending update was polled.
     Pending((Arc<PendingBlock>, Arc<StateUpdate>)),
+    /// An operator requested that `target` becomes the latest block.
+    Revert {
+        target: BlockNumber,
+        reply: oneshot::Sender<anyhow::Result<()>>,
+    },
 }
 
 pub struct SyncContext<G, E> {
@@ -88,6 +96,7 @@ pub struct SyncContext<G, E> {
     pub sequencer_public_key: PublicKey,
     pub fetch_concurrency: std::num::NonZeroUsize,
     pub fetch_casm_from_fgw: bool,
+    pub control: SyncCommands,
 }
 
 impl<G, E> From<&SyncContext<G, E>> for L1SyncContext<E>
@@ -98,8 +107,10 @@ where
         Self {
             ethereum: value.ethereum.clone(),
             chain: value.chain,
+            chain_id: value.chain_id,
             core_address: value.core_address,
             poll_interval: value.l1_poll_interval,
+            storage: value.storage.clone(),
         }
     }
 }
@@ -166,6 +177,7 @@ where
         sequencer_public_key: _,
         fetch_concurrency: _,
         fetch_casm_from_fgw,
+        mut control,
     } = context;
 
     let mut db_conn = storage
@@ -257,8 +269,74 @@ where
         fetch_casm_from_fgw,
     ));
 
+    // Whether the producers are running, they are stopped while sync is paused
+    // or being reverted.
+    let mut running = true;
+
     loop {
         tokio::select! {
+            Some(command) = control.recv() => {
+                let mut resume = false;
+                let mut resume_reply = None;
+                match command {
+                    SyncCommand::Pause { reply } => {
+                        if running {
+                            stop_producers(&mut l1_handle, &mut l2_handle, &mut pending_handle).await;
+                            running = false;
+                            control.set_paused(true);
+                            tracing::info!("Sync paused");
+                        }
+                        let _ = reply.send(Ok(()));
+                    }
+                    SyncCommand::Resume { reply } => {
+                        resume = true;
+                        resume_reply = Some(reply);
+                    }
+                    SyncCommand::Revert { target, reply } => {
+                        // Stop fetching blocks so none get stored on top of the reverted chain.
+                        // Blocks that were already fetched are stored before the revert, since
+                        // the consumer handles events in order.
+                        let was_running = running;
+                        if running {
+                            stop_producers(&mut l1_handle, &mut l2_handle, &mut pending_handle).await;
+                            running = false;
+                        }
+
+                        let (result_tx, result_rx) = oneshot::channel();
+                        let revert = SyncEvent::Revert { target, reply: result_tx };
+                        let result = match event_sender.send(revert).await {
+                            Ok(()) => result_rx
+                                .await
+                                .unwrap_or_else(|_| Err(anyhow::anyhow!("Sync consumer task exited"))),
+                            Err(_) => Err(anyhow::anyhow!("Sync consumer task exited")),
+                        };
+                        let _ = reply.send(result);
+
+                        resume = was_running;
+                    }
+                }
+
+                if resume && !running {
+                    let (l2_head, block_chain) = l2_start(&mut db_conn, block_cache_size).await?;
+                    l1_handle = util::task::spawn(l1_sync(event_sender.clone(), l1_context.clone()));
+                    l2_handle = util::task::spawn(l2_sync(event_sender.clone(), l2_context.clone(), l2_head, block_chain, rx_latest.clone()));
+                    pending_handle = util::task::spawn(pending::poll_pending(
+                        event_sender.clone(),
+                        sequencer.clone(),
+                        Duration::from_secs(2),
+                        storage.clone(),
+                        rx_latest.clone(),
+                        rx_current.clone(),
+                        fetch_casm_from_fgw,
+                    ));
+                    running = true;
+                    control.set_paused(false);
+                    tracing::info!("Sync resumed");
+                }
+                if let Some(reply) = resume_reply {
+                    let _ = reply.send(Ok(()));
+                }
+            },
             _ = &mut pending_handle => {
                 tracing::error!("Pending tracking task ended unexpectedly");
 
@@ -315,15 +393,7 @@ where
                     }
                 }
 
-                let l2_head = tokio::task::block_in_place(|| {
-                    let tx = db_conn.transaction()?;
-                    tx.block_header(pathfinder_storage::BlockId::Latest)
-                })
-                .context("Query L2 head from database")?
-                .map(|block| (block.number, block.hash, block.state_commitment));
-
-                let latest_blocks = latest_n_blocks(&mut db_conn, block_cache_size).await.context("Fetching latest blocks from storage")?;
-                let block_chain = BlockChain::with_capacity(1_000, latest_blocks);
+                let (l2_head, block_chain) = l2_start(&mut db_conn, block_cache_size).await?;
                 let fut = l2_sync(event_sender.clone(), l2_context.clone(), l2_head, block_chain, rx_latest.clone());
 
                 l2_handle = util::task::spawn(async move {
@@ -459,6 +529,12 @@ async fn consumer(
                 tracing::trace!("Updating L1 sync to block {}", update.block_number);
                 l1_update(&mut db_conn, &update).await?;
                 tracing::info!("L1 sync updated to block {}", update.block_number);
+                notifications
+                    .l1_updates
+                    .send(update.block_number)
+                    // Ignore errors in case nobody is listening. New listeners may subscribe in
+                    // the future.
+                    .ok();
             }
             Block(
                 (block, (tx_comm, ev_comm, rc_comm)),
@@ -587,6 +663,25 @@ async fn consumer(
                     None => tracing::info!("L2 reorg occurred, new L2 head is genesis"),
                 }
             }
+            Revert { target, reply } => {
+                if target + 1 >= next_number {
+                    let _ = reply.send(Err(anyhow::anyhow!(
+                        "Block {target} is not older than the latest block"
+                    )));
+                    continue;
+                }
+
+                tracing::info!(%target, "Reverting L2 state");
+                let reorg_tail = target + 1;
+                let result = l2_reorg(&mut db_conn, reorg_tail, &mut notifications)
+                    .await
+                    .with_context(|| format!("Revert L2 state to {target}"));
+                if result.is_ok() {
+                    next_number = reorg_tail;
+                    tracing::info!("L2 state reverted, new L2 head is block {target}");
+                }
+                let _ = reply.send(result);
+            }
             CairoClass { definition, hash } => {
                 tracing::trace!("Inserting new Cairo class with hash: {hash}");
                 tokio::task::block_in_place(|| {
@@ -658,6 +753,45 @@ async fn consumer(
     Ok(())
 }
 
+/// The latest L2 block in storage and the recent chain to start L2 sync from.
+async fn l2_start(
+    connection: &mut Connection,
+    block_cache_size: usize,
+) -> anyhow::Result<(
+    Option<(BlockNumber, BlockHash, StateCommitment)>,
+    BlockChain,
+)> {
+    let l2_head = tokio::task::block_in_place(|| {
+        let tx = connection.transaction()?;
+        tx.block_header(pathfinder_storage::BlockId::Latest)
+    })
+    .context("Query L2 head from database")?
+    .map(|block| (block.number, block.hash, block.state_commitment));
+
+    let latest_blocks = latest_n_blocks(connection, block_cache_size)
+        .await
+        .context("Fetching latest blocks from storage")?;
+    let block_chain = BlockChain::with_capacity(1_000, latest_blocks);
+
+    Ok((l2_head, block_chain))
+}
+
+/// Stops the L1, L2 and pending producer tasks, replacing their handles with
+/// ones that never complete.
+async fn stop_producers(
+    l1_handle: &mut JoinHandle<anyhow::Result<()>>,
+    l2_handle: &mut JoinHandle<anyhow::Result<()>>,
+    pending_handle: &mut JoinHandle<()>,
+) {
+    l1_handle.abort();
+    l2_handle.abort();
+    pending_handle.abort();
+
+    _ = std::mem::replace(l1_handle, util::task::spawn(std::future::pending())).await;
+    _ = std::mem::replace(l2_handle, util::task::spawn(std::future::pending())).await;
+    _ = std::mem::replace(pending_handle, util::task::spawn(std::future::pending())).await;
+}
+
 async fn latest_n_blocks(
     connection: &mut Connection,
     n: usize,
@@ -979,111 +1113,7 @@ async fn l2_reorg(
     reorg_tail: BlockNumber,
     notifications: &mut Notifications,
 ) -> anyhow::Result<()> {
-    tokio::task::block_in_place(move || {
-        let transaction = connection
-            .transaction_with_behavior(TransactionBehavior::Immediate)
-            .context("Create database transaction")?;
-
-        let mut head = transaction
-            .block_id(pathfinder_storage::BlockId::Latest)
-            .context("Querying latest block number")?
-            .context("Latest block number is none during reorg")?
-            .0;
-
-        let Some(reorg_tail_hash) = transaction
-            .block_hash(reorg_tail.into())
-            .context("Fetching first block hash")?
-        else {
-            anyhow::bail!(
-                r"Reorg tail (block number: {reorg_tail}) does not exist (likely due to blockchain history pruning).
-Blockchain history must include the reorg tail and its parent block to perform a reorg."
-            );
-        };
-
-        // Roll back Merkle trie updates.
-        //
-        // If we're rolling back genesis then there will be no blocks left so state will
-        // be empty.
-        if let Some(target_block) = reorg_tail.parent() {
-            let Some(target_header) = transaction
-                .block_header(target_block.into())
-                .context("Fetching target block header")?
-            else {
-                anyhow::bail!(
-                    r"Reorg tail parent (block number: {target_block}) does not exist (likely due to blockchain history pruning).
-Blockchain history must include the reorg tail and its parent block to perform a reorg."
-                );
-            };
-            revert::revert_starknet_state(&transaction, head, target_block, target_header)?;
-        }
-
-        let head_hash = transaction
-            .block_hash(head.into())
-            .context("Fetching last block hash")?
-            .context("Expected last block hash to exist because reorg tail exists")?;
-
-        // Purge each block one at a time.
-        //
-        // This is done 1-by-1 to allow sending the reorg'd block data
-        // to websocket subscriptions while keeping a constant memory footprint.
-        //
-        // This is acceptable performance because reorgs are rare and need not be
-        // 100% optimal. However a large reorg could cause a massive memory spike
-        // which is not acceptable.
-        while head >= reorg_tail {
-            transaction
-                .purge_block(head)
-                .with_context(|| format!("Purging block {head} from database"))?;
-
-            // No further blocks to purge if we just purged genesis.
-            if head == BlockNumber::GENESIS {
-                break;
-            }
-
-            head -= 1;
-        }
-
-        transaction
-            .reset_in_memory_state(head)
-            .context("Resetting in-memory DB state after reorg")?;
-
-        // Track combined L1 and L2 state.
-        let l1_l2_head = transaction.l1_l2_pointer().context("Query L1-L2 head")?;
-        if let Some(l1_l2_head) = l1_l2_head {
-            if reorg_tail == BlockNumber::GENESIS {
-                // If we purged genesis then unset the L1 L2 pointer as well since there
-                // are now no blocks remaining.
-                transaction
-                    .update_l1_l2_pointer(None)
-                    .context("Unsetting L1-L2 head")?;
-            } else if l1_l2_head >= reorg_tail {
-                transaction
-                    .update_l1_l2_pointer(Some(reorg_tail - 1))
-                    .context("Updating L1-L2 head")?;
-            }
-        }
-
-        transaction
-            .commit()
-            .context("Commit database transaction")?;
-
-        notifications
-            .reorgs
-            .send(
-                Reorg {
-                    first_block_number: reorg_tail,
-                    first_block_hash: reorg_tail_hash,
-                    last_block_number: head,
-                    last_block_hash: head_hash,
-                }
-                .into(),
-            )
-            // Ignore errors in case nobody is listening. New listeners may subscribe in the
-            // future.
-            .ok();
-
-        Ok(())
-    })
+    tokio::task::block_in_place(move || revert::revert_chain(connection, reorg_tail, notifications))
 }
 
 #[cfg(test)]
@@ -1747,6 +1777,64 @@ mod tests {
         assert!(!genesis_exists);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn revert_on_request() {
+        let storage = StorageBuilder::in_memory_with_trie_pruning_and_pool_size(
+            pathfinder_storage::TriePruneMode::Archive,
+            std::num::NonZeroU32::new(5).unwrap(),
+        )
+        .unwrap();
+        let mut connection = storage.connection().unwrap();
+
+        let (event_tx, event_rx) = tokio::sync::mpsc::channel(100);
+
+        // Five blocks are stored, so reverting to the latest block is rejected.
+        for (a, b, c, d, e) in generate_block_data() {
+            event_tx
+                .send(SyncEvent::Block(a, b, c, d, e))
+                .await
+                .unwrap();
+        }
+        let (latest_tx, latest_rx) = tokio::sync::oneshot::channel();
+        event_tx
+            .send(SyncEvent::Revert {
+                target: BlockNumber::new_or_panic(4),
+                reply: latest_tx,
+            })
+            .await
+            .unwrap();
+        let (revert_tx, revert_rx) = tokio::sync::oneshot::channel();
+        event_tx
+            .send(SyncEvent::Revert {
+                target: BlockNumber::new_or_panic(1),
+                reply: revert_tx,
+            })
+            .await
+            .unwrap();
+        // Close the event channel which allows the consumer task to exit.
+        drop(event_tx);
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        let context = ConsumerContext {
+            storage,
+            state: Arc::new(SyncState::default()),
+            pending_data: tx,
+            verify_tree_hashes: false,
+            websocket_txs: None,
+            notifications: Default::default(),
+        };
+
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        consumer(event_rx, context, tx).await.unwrap();
+
+        latest_rx.await.unwrap().unwrap_err();
+        revert_rx.await.unwrap().unwrap();
+
+        let tx = connection.transaction().unwrap();
+        let latest = tx.block_id(BlockId::Latest).unwrap();
+        assert_eq!(latest.unwrap().0, BlockNumber::new_or_panic(1));
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn new_cairo_contract() {
         let storage = StorageBuilder::in_memory_with_trie_pruning_and_pool_size(
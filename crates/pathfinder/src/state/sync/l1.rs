@@ -1,9 +1,12 @@
 use std::time::Duration;
 
-use pathfinder_common::Chain;
+use anyhow::Context;
+use pathfinder_common::{Chain, ChainId};
 use pathfinder_ethereum::EthereumApi;
+use pathfinder_storage::{L1ToL2Message, Storage};
 use primitive_types::H160;
 use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
 
 use crate::state::sync::SyncEvent;
 
@@ -12,15 +15,20 @@ pub struct L1SyncContext<EthereumClient> {
     pub ethereum: EthereumClient,
     /// The Ethereum chain to sync from
     pub chain: Chain,
+    /// The Starknet chain ID, which L1 handler transaction hashes depend on
+    pub chain_id: ChainId,
     /// The Starknet core contract address on Ethereum
     pub core_address: H160,
     /// The interval at which to poll for updates on finalized blocks
     pub poll_interval: Duration,
+    pub storage: Storage,
 }
 
 /// Syncs L1 state update logs. Emits [Ethereum state
 /// update](pathfinder_ethereum::EthereumStateUpdate) which should be handled to
 /// update storage and respond to queries.
+///
+/// Messages sent from L1 to L2 are indexed into storage alongside.
 pub async fn sync<T>(
     tx_event: mpsc::Sender<SyncEvent>,
     context: L1SyncContext<T>,
@@ -31,21 +39,228 @@ where
     let L1SyncContext {
         mut ethereum,
         chain: _,
+        chain_id,
         core_address,
         poll_interval,
+        storage,
     } = context;
 
+    let messages = index_messages(
+        ethereum.clone(),
+        storage,
+        chain_id,
+        core_address,
+        poll_interval,
+    );
+
     let tx_event = std::sync::Arc::new(tx_event);
 
     // Subscribe to subsequent state updates and message logs
-    ethereum
-        .sync_and_listen(&core_address, poll_interval, move |state_update| {
+    let state_updates =
+        ethereum.sync_and_listen(&core_address, poll_interval, move |state_update| {
             let tx_event = tx_event.clone();
             async move {
                 let _ = tx_event.send(SyncEvent::L1Update(state_update)).await;
             }
-        })
-        .await?;
+        });
+
+    tokio::try_join!(state_updates, messages)?;
 
     Ok(())
 }
+
+/// Indexes the messages sent from L1 to L2 in finalized L1 blocks, so that
+/// their status can be served without querying L1.
+///
+/// Indexing starts at the L1 block which is finalized when the index is first
+/// created, older messages are not indexed.
+async fn index_messages<T: EthereumApi>(
+    ethereum: T,
+    storage: Storage,
+    chain_id: ChainId,
+    core_address: H160,
+    poll_interval: Duration,
+) -> anyhow::Result<()> {
+    let head = {
+        let storage = storage.clone();
+        util::task::spawn_blocking(move |_| {
+            let mut db = storage
+                .connection()
+                .context("Creating database connection")?;
+            let db = db.transaction().context("Creating database transaction")?;
+            db.l1_to_l2_messages_head()
+        })
+        .await
+        .context("Joining blocking task")??
+    };
+    let mut next_block = head.map(|head| head + 1);
+
+    let mut interval = tokio::time::interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        // L1 errors are retried on the next tick instead of restarting the L1 sync.
+        let finalized = match ethereum.get_finalized_block_number().await {
+            Ok(finalized) => finalized,
+            Err(e) => {
+                tracing::warn!(error=%e, "Error fetching L1 finalized block");
+                continue;
+            }
+        };
+        let from = *next_block.get_or_insert(finalized);
+        if from > finalized {
+            continue;
+        }
+
+        let messages = match ethereum
+            .get_messages_to_l2(&core_address, from, finalized)
+            .await
+        {
+            Ok(messages) => messages,
+            Err(e) => {
+                tracing::warn!(error=%e, "Error fetching messages to L2");
+                continue;
+            }
+        };
+        let messages = messages
+            .into_iter()
+            .map(|message| L1ToL2Message {
+                message_hash: message.transaction.calculate_message_hash(),
+                l1_block_number: message.l1_block_number,
+                l1_transaction_hash: message.l1_transaction_hash,
+                l2_transaction_hash: message.transaction.calculate_hash(chain_id),
+            })
+            .collect::<Vec<_>>();
+        tracing::trace!(%from, to=%finalized, count=%messages.len(), "Indexing messages to L2");
+
+        let storage = storage.clone();
+        util::task::spawn_blocking(move |_| {
+            let mut db = storage
+                .connection()
+                .context("Creating database connection")?;
+            let db = db.transaction().context("Creating database transaction")?;
+            db.insert_l1_to_l2_messages(finalized, &messages)?;
+            db.commit().context("Committing database transaction")
+        })
+        .await
+        .context("Joining blocking task")??;
+
+        next_block = Some(finalized + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::transaction::L1HandlerTransaction;
+    use pathfinder_common::{EthereumChain, L1BlockNumber, L1TransactionHash};
+    use pathfinder_ethereum::{EthereumStateUpdate, L1ToL2MessageLog};
+    use pathfinder_storage::StorageBuilder;
+    use primitive_types::H256;
+
+    use super::*;
+
+    const FINALIZED: L1BlockNumber = L1BlockNumber::new_or_panic(10);
+
+    /// Serves a single message sent in the finalized L1 block.
+    struct FakeEthereum(L1ToL2MessageLog);
+
+    #[async_trait::async_trait]
+    impl EthereumApi for FakeEthereum {
+        async fn get_starknet_state(&self, _: &H160) -> anyhow::Result<EthereumStateUpdate> {
+            unimplemented!()
+        }
+
+        async fn get_chain(&self) -> anyhow::Result<EthereumChain> {
+            unimplemented!()
+        }
+
+        async fn get_finalized_block_number(&self) -> anyhow::Result<L1BlockNumber> {
+            Ok(FINALIZED)
+        }
+
+        async fn get_l1_handler_txs(
+            &self,
+            _: &H160,
+            _: &L1TransactionHash,
+        ) -> anyhow::Result<Vec<L1HandlerTransaction>> {
+            unimplemented!()
+        }
+
+        async fn get_messages_to_l2(
+            &self,
+            _: &H160,
+            from: L1BlockNumber,
+            to: L1BlockNumber,
+        ) -> anyhow::Result<Vec<L1ToL2MessageLog>> {
+            Ok(std::iter::once(self.0.clone())
+                .filter(|message| (from..=to).contains(&message.l1_block_number))
+                .collect())
+        }
+
+        async fn sync_and_listen<F, Fut>(
+            &mut self,
+            _: &H160,
+            _: Duration,
+            _: F,
+        ) -> anyhow::Result<()>
+        where
+            F: Fn(EthereumStateUpdate) -> Fut + Send + 'static,
+            Fut: Future<Output = ()> + Send + 'static,
+        {
+            unimplemented!()
+        }
+    }
+
+    #[tokio::test]
+    async fn index_messages_persists_messages() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let l1_transaction_hash = L1TransactionHash::new(H256::from_low_u64_be(1));
+        let transaction = L1HandlerTransaction {
+            contract_address: contract_address!("0x1"),
+            entry_point_selector: entry_point!("0x2"),
+            nonce: transaction_nonce!("0x3"),
+            calldata: vec![call_param!("0x4")],
+        };
+        let expected = transaction.calculate_hash(ChainId::SEPOLIA_TESTNET);
+        let ethereum = FakeEthereum(L1ToL2MessageLog {
+            l1_block_number: FINALIZED,
+            l1_transaction_hash,
+            transaction,
+        });
+
+        let indexing = tokio::spawn(index_messages(
+            ethereum,
+            storage.clone(),
+            ChainId::SEPOLIA_TESTNET,
+            H160::zero(),
+            Duration::from_millis(10),
+        ));
+
+        let indexed = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let (head, messages) = {
+                    let mut db = storage.connection().unwrap();
+                    let db = db.transaction().unwrap();
+                    (
+                        db.l1_to_l2_messages_head().unwrap(),
+                        db.l1_to_l2_messages(l1_transaction_hash).unwrap(),
+                    )
+                };
+                if head == Some(FINALIZED) {
+                    return messages;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        indexing.abort();
+
+        assert_eq!(indexed, vec![expected]);
+    }
+}
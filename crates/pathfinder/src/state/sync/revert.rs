@@ -1,7 +1,122 @@
 use anyhow::Context;
 use pathfinder_common::prelude::*;
 use pathfinder_merkle_tree::{ClassCommitmentTree, StorageCommitmentTree};
-use pathfinder_storage::Transaction;
+use pathfinder_rpc::{Notifications, Reorg};
+use pathfinder_storage::{Connection, Transaction, TransactionBehavior};
+
+/// Revert the chain to the parent of `reorg_tail`.
+///
+/// Starknet state is reverted to the parent block, then `reorg_tail` and all
+/// blocks after it are purged and the reorg is broadcast to `notifications`.
+pub fn revert_chain(
+    connection: &mut Connection,
+    reorg_tail: BlockNumber,
+    notifications: &Notifications,
+) -> anyhow::Result<()> {
+    let transaction = connection
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .context("Create database transaction")?;
+
+    let mut head = transaction
+        .block_id(pathfinder_storage::BlockId::Latest)
+        .context("Querying latest block number")?
+        .context("Latest block number is none during reorg")?
+        .0;
+
+    let Some(reorg_tail_hash) = transaction
+        .block_hash(reorg_tail.into())
+        .context("Fetching first block hash")?
+    else {
+        anyhow::bail!(
+            r"Reorg tail (block number: {reorg_tail}) does not exist (likely due to blockchain history pruning).
+Blockchain history must include the reorg tail and its parent block to perform a reorg."
+        );
+    };
+
+    // Roll back Merkle trie updates.
+    //
+    // If we're rolling back genesis then there will be no blocks left so state will
+    // be empty.
+    if let Some(target_block) = reorg_tail.parent() {
+        let Some(target_header) = transaction
+            .block_header(target_block.into())
+            .context("Fetching target block header")?
+        else {
+            anyhow::bail!(
+                r"Reorg tail parent (block number: {target_block}) does not exist (likely due to blockchain history pruning).
+Blockchain history must include the reorg tail and its parent block to perform a reorg."
+            );
+        };
+        revert_starknet_state(&transaction, head, target_block, target_header)?;
+    }
+
+    let head_hash = transaction
+        .block_hash(head.into())
+        .context("Fetching last block hash")?
+        .context("Expected last block hash to exist because reorg tail exists")?;
+
+    // Purge each block one at a time.
+    //
+    // This is done 1-by-1 to allow sending the reorg'd block data
+    // to websocket subscriptions while keeping a constant memory footprint.
+    //
+    // This is acceptable performance because reorgs are rare and need not be
+    // 100% optimal. However a large reorg could cause a massive memory spike
+    // which is not acceptable.
+    while head >= reorg_tail {
+        transaction
+            .purge_block(head)
+            .with_context(|| format!("Purging block {head} from database"))?;
+
+        // No further blocks to purge if we just purged genesis.
+        if head == BlockNumber::GENESIS {
+            break;
+        }
+
+        head -= 1;
+    }
+
+    transaction
+        .reset_in_memory_state(head)
+        .context("Resetting in-memory DB state after reorg")?;
+
+    // Track combined L1 and L2 state.
+    let l1_l2_head = transaction.l1_l2_pointer().context("Query L1-L2 head")?;
+    if let Some(l1_l2_head) = l1_l2_head {
+        if reorg_tail == BlockNumber::GENESIS {
+            // If we purged genesis then unset the L1 L2 pointer as well since there
+            // are now no blocks remaining.
+            transaction
+                .update_l1_l2_pointer(None)
+                .context("Unsetting L1-L2 head")?;
+        } else if l1_l2_head >= reorg_tail {
+            transaction
+                .update_l1_l2_pointer(Some(reorg_tail - 1))
+                .context("Updating L1-L2 head")?;
+        }
+    }
+
+    transaction
+        .commit()
+        .context("Commit database transaction")?;
+
+    notifications
+        .reorgs
+        .send(
+            Reorg {
+                first_block_number: reorg_tail,
+                first_block_hash: reorg_tail_hash,
+                last_block_number: head,
+                last_block_hash: head_hash,
+            }
+            .into(),
+        )
+        // Ignore errors in case nobody is listening. New listeners may subscribe in the
+        // future.
+        .ok();
+
+    Ok(())
+}
 
 /// Revert Starknet state by applying reverse-updates.
 ///
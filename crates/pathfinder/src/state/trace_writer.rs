@@ -0,0 +1,191 @@
+//! Background tracing of newly synced blocks.
+//!
+//! Every block added by sync is traced and its traces are persisted in the
+//! database, from where `starknet_traceBlockTransactions` and
+//! `starknet_traceTransaction` serve them without executing the block again,
+//! also after a restart. Tracing requests only ever read the stored traces.
+//!
+//! The store is best-effort. Blocks synced while the writer lags behind are
+//! never persisted, and tracing them executes the block on every cache miss.
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use anyhow::Context;
+use pathfinder_common::{BlockHeader, ChainId};
+use pathfinder_executor::{
+    ExecutionState,
+    NativeClassCache,
+    TraceCache,
+    TransactionExecutionError,
+    VersionedConstantsMap,
+};
+use pathfinder_rpc::context::EthContractAddresses;
+use pathfinder_rpc::VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY;
+use pathfinder_storage::{BlockId, Storage, TransactionBehavior};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+#[derive(Clone)]
+pub struct TraceWriter {
+    storage: Storage,
+    chain_id: ChainId,
+    contract_addresses: EthContractAddresses,
+    versioned_constants_map: VersionedConstantsMap,
+    native_class_cache: Option<NativeClassCache>,
+    cache: TraceCache,
+    max_blocks: NonZeroUsize,
+}
+
+impl TraceWriter {
+    /// The traces are also put into `cache`, which should be the cache used
+    /// for tracing requests.
+    pub fn new(
+        storage: Storage,
+        chain_id: ChainId,
+        contract_addresses: EthContractAddresses,
+        versioned_constants_map: VersionedConstantsMap,
+        native_class_cache: Option<NativeClassCache>,
+        cache: TraceCache,
+        max_blocks: NonZeroUsize,
+    ) -> Self {
+        Self {
+            storage,
+            chain_id,
+            contract_addresses,
+            versioned_constants_map,
+            native_class_cache,
+            cache,
+            max_blocks,
+        }
+    }
+
+    /// Traces every block announced on `block_headers`, one at a time.
+    ///
+    /// Blocks announced while the writer is busy are skipped once the channel
+    /// lags behind, so the writer never holds back sync. Skipped blocks are
+    /// traced on demand instead, and only cached in memory.
+    pub fn spawn(self, mut block_headers: broadcast::Receiver<Arc<BlockHeader>>) {
+        util::task::spawn(async move {
+            loop {
+                let header = match block_headers.recv().await {
+                    Ok(header) => header,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::debug!(%skipped, "Trace writer lagging behind sync");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+
+                let writer = self.clone();
+                let block_number = header.number;
+                let result =
+                    util::task::spawn_blocking(move |_| writer.store_block_traces(&header)).await;
+
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(error)) => {
+                        tracing::debug!(%block_number, ?error, "Storing traces of new block failed");
+                    }
+                    Err(error) => {
+                        tracing::error!(%block_number, %error, "Trace writer task panicked");
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    fn store_block_traces(&self, header: &BlockHeader) -> anyhow::Result<()> {
+        // Traces of older blocks are fetched from the feeder gateway.
+        if header.starknet_version
+            < VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY
+        {
+            return Ok(());
+        }
+
+        let mut db = self
+            .storage
+            .connection()
+            .context("Creating database connection")?
+            .with_retry()
+            .context("Enabling database retries")?;
+
+        let block_id = BlockId::Hash(header.hash);
+        let traces = {
+            let db = db.transaction().context("Creating database transaction")?;
+
+            // The block was reorged away in the meantime.
+            let Some(header) = db.block_header(block_id).context("Fetching block header")? else {
+                return Ok(());
+            };
+            let Some(transactions) = db
+                .transactions_for_block(block_id)
+                .context("Fetching transactions")?
+            else {
+                return Ok(());
+            };
+
+            let transactions = transactions
+                .iter()
+                .map(|transaction| pathfinder_rpc::compose_executor_transaction(transaction, &db))
+                .collect::<anyhow::Result<Vec<_>>>()
+                .context("Converting transactions")?;
+
+            let hash = header.hash;
+            let execution_state = ExecutionState::trace(
+                &db,
+                self.chain_id,
+                header,
+                None,
+                self.versioned_constants_map.clone(),
+                self.contract_addresses.eth_l2_token_address,
+                self.contract_addresses.strk_l2_token_address,
+                self.native_class_cache.clone(),
+            );
+            match pathfinder_executor::trace(
+                execution_state,
+                self.cache.clone(),
+                hash,
+                transactions,
+            ) {
+                Ok(traces) => traces,
+                Err(TransactionExecutionError::ExecutionError {
+                    transaction_index,
+                    error,
+                    ..
+                }) => {
+                    anyhow::bail!("Tracing transaction {transaction_index} failed: {error}")
+                }
+                Err(TransactionExecutionError::DeadlineExceeded) => {
+                    anyhow::bail!("Tracing block: execution deadline exceeded")
+                }
+                Err(TransactionExecutionError::Internal(error))
+                | Err(TransactionExecutionError::Custom(error)) => {
+                    return Err(error.context("Tracing block"))
+                }
+            }
+        };
+
+        // Tracing takes a while, so the write transaction is only started
+        // afterwards to not block sync.
+        let db = db
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("Creating database transaction")?;
+        if db.block_number(block_id)?.is_none() {
+            return Ok(());
+        }
+        pathfinder_executor::store_traces(
+            &db,
+            header.number,
+            header.hash,
+            &traces,
+            self.max_blocks,
+        )
+        .context("Storing block traces")?;
+        db.commit().context("Committing database transaction")?;
+
+        tracing::trace!(block_number=%header.number, "Stored block traces");
+
+        Ok(())
+    }
+}
@@ -5,11 +5,14 @@ use std::time::Duration;
 use anyhow::Context;
 use error::SyncError;
 use futures::{pin_mut, Stream, StreamExt};
+use p2p_proto::pending::NewPendingBlock;
 use p2p_v2::sync::client::peer_agnostic::traits::{
     BlockClient,
     ClassStream,
     EventStream,
     HeaderStream,
+    PeerReputation,
+    PendingBlockClient,
     StateDiffStream,
     StreamItem,
     TransactionStream,
@@ -19,6 +22,7 @@ use pathfinder_block_hashes::BlockHashDb;
 use pathfinder_common::block_hash;
 use pathfinder_common::prelude::*;
 use pathfinder_ethereum::EthereumStateUpdate;
+use pathfinder_rpc::{Notifications, PendingData};
 use pathfinder_storage::Transaction;
 use primitive_types::H160;
 use starknet_gateway_client::{Client as GatewayClient, GatewayApi};
@@ -27,13 +31,16 @@ use tokio::sync::watch::{self, Receiver};
 use tokio_stream::wrappers::WatchStream;
 use util::error::AnyhowExt;
 
-use crate::state::RESET_DELAY_ON_FAILURE;
+use crate::state::{revert, RESET_DELAY_ON_FAILURE};
+use crate::sync::headers::VerifyHashAndSignature;
 
 mod checkpoint;
 mod class_definitions;
 mod error;
 mod events;
 mod headers;
+mod pending;
+mod reorg;
 mod state_updates;
 mod storage_adapters;
 mod stream;
@@ -42,17 +49,32 @@ mod transactions;
 
 const CHECKPOINT_MARGIN: u64 = 10;
 
+const HEAD_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Minimum time between pending block requests triggered by announcements.
+const PENDING_ANNOUNCEMENT_DEBOUNCE: Duration = Duration::from_millis(500);
+
 pub struct Sync<P, G> {
     pub storage: pathfinder_storage::Storage,
     pub p2p: P,
     pub eth_client: pathfinder_ethereum::EthereumClient,
     pub eth_address: H160,
-    pub fgw_client: G,
+    /// Used to follow the chain head and to fetch CASM for classes which fail
+    /// to compile locally. Without it the chain head is learned from peers and
+    /// classes are only compiled locally, so that sync depends on p2p and L1
+    /// alone.
+    pub fgw_client: Option<G>,
     pub chain_id: ChainId,
     pub public_key: PublicKey,
     pub l1_checkpoint_override: Option<EthereumStateUpdate>,
     pub verify_tree_hashes: bool,
     pub block_hash_db: Option<BlockHashDb>,
+    pub notifications: Notifications,
+    /// Updated with the pending block served by peers once checkpoint sync
+    /// is done.
+    pub pending_data: watch::Sender<PendingData>,
+    /// Peers' announcements that their pending block changed.
+    pub pending_announcements: watch::Receiver<Option<PeerData<NewPendingBlock>>>,
 }
 
 impl<P, G> Sync<P, G>
@@ -61,6 +83,8 @@ where
         + ClassStream
         + EventStream
         + HeaderStream
+        + PeerReputation
+        + PendingBlockClient
         + StateDiffStream
         + TransactionStream
         + Clone
@@ -71,12 +95,29 @@ where
     pub async fn run(self) -> anyhow::Result<()> {
         let (next, parent_hash) = self.checkpoint_sync().await?;
 
-        self.track_sync(next, parent_hash).await
+        let pending = pending::track(
+            self.p2p.clone(),
+            self.storage.clone(),
+            self.chain_id,
+            self.pending_data.clone(),
+            self.pending_announcements.clone(),
+            HEAD_POLL_INTERVAL,
+            PENDING_ANNOUNCEMENT_DEBOUNCE,
+        );
+
+        tokio::try_join!(self.track_sync(next, parent_hash), pending)?;
+
+        Ok(())
     }
 
     async fn handle_recoverable_error(&self, err: &error::SyncError) {
-        // TODO
-        tracing::debug!(%err, "Log and punish as appropriate");
+        match err.peer_penalty() {
+            Some((peer, penalty)) => {
+                tracing::debug!(%err, %peer, ?penalty, "Punishing peer");
+                self.p2p.punish(peer, penalty).await;
+            }
+            None => tracing::debug!(%err, "Recoverable sync error"),
+        }
     }
 
     /// Retry forever until a valid L1 checkpoint is retrieved
@@ -129,6 +170,7 @@ where
                 public_key: self.public_key,
                 verify_tree_hashes: self.verify_tree_hashes,
                 block_hash_db: self.block_hash_db.clone(),
+                notifications: self.notifications.clone(),
             }
             .run(checkpoint)
             .await;
@@ -182,8 +224,21 @@ where
         tracing::info!(next_block=%next, "Track sync started");
 
         loop {
+            let latest = match &self.fgw_client {
+                Some(fgw) => LatestStream::spawn(fgw.clone(), HEAD_POLL_INTERVAL),
+                None => LatestStream::spawn_from_peers(
+                    self.p2p.clone(),
+                    next,
+                    parent_hash,
+                    self.chain_id,
+                    self.public_key,
+                    self.block_hash_db.clone(),
+                    HEAD_POLL_INTERVAL,
+                ),
+            };
+
             let mut result = track::Sync {
-                latest: LatestStream::spawn(self.fgw_client.clone(), Duration::from_secs(2)),
+                latest,
                 p2p: self.p2p.clone(),
                 storage: self.storage.clone(),
                 chain_id: self.chain_id,
@@ -194,6 +249,24 @@ where
             .run(&mut next, &mut parent_hash, self.fgw_client.clone())
             .await;
 
+            // Latest headers from peers end once peers fork off the local chain.
+            let maybe_fork = match &result {
+                Ok(_) => self.fgw_client.is_none(),
+                Err(SyncError::Discontinuity(_)) => true,
+                Err(_) => false,
+            };
+            if maybe_fork {
+                match self.handle_fork(&mut next, &mut parent_hash).await {
+                    Ok(true) => {
+                        tracing::debug!(next_block=%next, "Restarting track sync: fork reverted");
+                        continue;
+                    }
+                    // Not a fork, the peer is at fault.
+                    Ok(false) => {}
+                    Err(error) => result = Err(error),
+                }
+            }
+
             match result {
                 Ok(_) => tracing::debug!("Restarting track sync: unexpected end of Block stream"),
                 Err(SyncError::Fatal(mut error)) => {
@@ -207,6 +280,51 @@ where
             }
         }
     }
+
+    /// Checks whether a discontinuity in track sync is caused by a fork, and if
+    /// so reverts the local chain to the last block in common with peers, so
+    /// that track sync continues on the peers' chain. Returns `true` if the
+    /// local chain was reverted.
+    async fn handle_fork(
+        &self,
+        next: &mut BlockNumber,
+        parent_hash: &mut BlockHash,
+    ) -> Result<bool, SyncError> {
+        let Some(head) = next.parent() else {
+            return Ok(false);
+        };
+
+        let Some(fork) = reorg::find_fork(
+            self.p2p.clone(),
+            self.storage.clone(),
+            head,
+            self.chain_id,
+            self.public_key,
+            self.block_hash_db.clone(),
+        )
+        .await?
+        else {
+            return Ok(false);
+        };
+
+        tracing::info!(first_reverted_block=%fork.tail, %head, "Fork detected, reverting local chain");
+
+        let storage = self.storage.clone();
+        let notifications = self.notifications.clone();
+        util::task::spawn_blocking(move |_| {
+            let mut db = storage
+                .connection()
+                .context("Creating database connection")?;
+            revert::revert_chain(&mut db, fork.tail, &notifications)
+        })
+        .await
+        .context("Joining blocking task")??;
+
+        *next = fork.tail;
+        *parent_hash = fork.parent_hash;
+
+        Ok(true)
+    }
 }
 
 struct LatestStream {
@@ -285,6 +403,125 @@ impl LatestStream {
             stream: WatchStream::from_changes(rx),
         }
     }
+
+    /// Learns the chain head from peers instead of the feeder gateway, by
+    /// periodically asking them for the headers following the latest known
+    /// one, `next - 1` with hash `parent_hash`. Only headers with a valid hash
+    /// and sequencer signature which extend the latest known one are taken into
+    /// account.
+    ///
+    /// The stream ends once peers serve a different block at the latest known
+    /// height, as they forked off the local chain. Track sync is then expected
+    /// to look for the fork.
+    fn spawn_from_peers<P>(
+        p2p: P,
+        mut next: BlockNumber,
+        mut parent_hash: BlockHash,
+        chain_id: ChainId,
+        public_key: PublicKey,
+        block_hash_db: Option<BlockHashDb>,
+        head_poll_interval: Duration,
+    ) -> Self
+    where
+        P: BlockClient + PeerReputation + Clone + Send + 'static,
+    {
+        /// Maximum number of headers requested in a single poll.
+        const HEADERS_PER_POLL: u64 = 100;
+
+        // No buffer, for backpressure
+        let (tx, rx) = watch::channel((BlockNumber::GENESIS, BlockHash::ZERO));
+
+        util::task::spawn(async move {
+            let mut interval = tokio::time::interval(head_poll_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut verify = VerifyHashAndSignature::new(chain_id, public_key, block_hash_db);
+            let mut caught_up = true;
+
+            loop {
+                // Keep polling without delay while peers are far ahead.
+                if caught_up {
+                    interval.tick().await;
+                }
+                caught_up = true;
+
+                if tx.is_closed() {
+                    tracing::debug!("Channel closed, exiting");
+                    break;
+                }
+
+                // The latest known block is requested as well, to tell whether peers are still
+                // on the same chain.
+                let start = next.parent().unwrap_or(next);
+                let Some((peer, headers)) = p2p.clone().headers_from(start, HEADERS_PER_POLL).await
+                else {
+                    tracing::trace!(%next, "No peer is ahead of the latest known block");
+                    continue;
+                };
+
+                let is_full = headers.len() as u64 == HEADERS_PER_POLL;
+                let mut expected_number = start;
+                let mut latest: Option<(BlockNumber, BlockHash)> = None;
+
+                for header in headers {
+                    let is_continuous = header.header.number == expected_number
+                        && (header.header.number < next
+                            || header.header.parent_hash == parent_hash);
+                    let result = if is_continuous {
+                        verify.map(&peer, header)
+                    } else {
+                        tracing::debug!(%peer, %expected_number, actual_block_number=%header.header.number, "Block chain discontinuity in latest headers");
+                        Err(SyncError::Discontinuity(peer))
+                    };
+
+                    let header = match result {
+                        Ok(header) => header.header,
+                        Err(error) => {
+                            if let Some((peer, penalty)) = error.peer_penalty() {
+                                tracing::debug!(%error, %peer, ?penalty, "Punishing peer");
+                                p2p.punish(peer, penalty).await;
+                            }
+                            break;
+                        }
+                    };
+                    expected_number = header.number + 1;
+
+                    if header.number < next {
+                        if header.hash != parent_hash {
+                            tracing::info!(%peer, block_number=%header.number, local_block_hash=%parent_hash, peer_block_hash=%header.hash, "Peers forked off the local chain");
+                            return;
+                        }
+                        continue;
+                    }
+
+                    latest = Some((header.number, header.hash));
+                    next = header.number + 1;
+                    parent_hash = header.hash;
+                }
+
+                let Some(latest) = latest else {
+                    continue;
+                };
+                caught_up = !is_full;
+
+                tracing::trace!(?latest, "LatestStream");
+
+                tx.send_if_modified(|current| {
+                    if *current != latest {
+                        tracing::info!(?latest, "LatestStream");
+                        *current = latest;
+                        true
+                    } else {
+                        false
+                    }
+                });
+            }
+        });
+
+        Self {
+            rx: rx.clone(),
+            stream: WatchStream::from_changes(rx),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -303,10 +540,12 @@ mod tests {
         ClassDefinitionsError,
         EventsForBlockByTransaction,
         EventsResponseStreamFailure,
+        PendingBlock,
         Receipt as P2PReceipt,
         StateDiffsError,
         TransactionData,
     };
+    use p2p_v2::Penalty;
     use pathfinder_common::event::Event;
     use pathfinder_common::prelude::*;
     use pathfinder_common::receipt::Receipt;
@@ -347,7 +586,14 @@ mod tests {
     pub fn generate_fake_blocks(num_blocks: usize) -> (PublicKey, Vec<Block>) {
         let private_key = Faker.fake();
         let public_key = PublicKey(pathfinder_crypto::signature::get_pk(private_key).unwrap());
-        let blocks = generate::with_config(
+        (
+            public_key,
+            generate_fake_blocks_signed_by(private_key, num_blocks),
+        )
+    }
+
+    fn generate_fake_blocks_signed_by(private_key: Felt, num_blocks: usize) -> Vec<Block> {
+        generate::with_config(
             num_blocks,
             Config {
                 calculate_block_hash: Box::new(|header: &BlockHeader| {
@@ -360,8 +606,34 @@ mod tests {
                 update_tries: Box::new(update_starknet_state),
                 ..Default::default()
             },
-        );
-        (public_key, blocks)
+        )
+    }
+
+    /// Forks a chain of fake blocks at block `at`: the blocks from `at` onwards
+    /// get a different timestamp, so their hashes and signatures change while
+    /// their contents and state stay the same.
+    fn fork_fake_blocks(blocks: &[Block], at: BlockNumber, private_key: Felt) -> Vec<Block> {
+        let mut parent_hash = BlockHash::ZERO;
+        blocks
+            .iter()
+            .cloned()
+            .map(|mut block| {
+                let header = &mut block.header.header;
+                if header.number >= at {
+                    header.timestamp = BlockTimestamp::new_or_panic(header.timestamp.get() + 1);
+                    header.parent_hash = parent_hash;
+                    header.hash = compute_final_hash(&BlockHeaderData::from_header(header));
+                    let (r, s) = ecdsa_sign(private_key, header.hash.0).unwrap();
+                    block.header.signature = BlockCommitmentSignature {
+                        r: BlockCommitmentSignatureElem(r),
+                        s: BlockCommitmentSignatureElem(s),
+                    };
+                    block.state_update.as_mut().unwrap().block_hash = header.hash;
+                }
+                parent_hash = block.header.header.hash;
+                block
+            })
+            .collect()
     }
 
     async fn sync_done_watch(
@@ -483,9 +755,9 @@ mod tests {
             // We use `l1_checkpoint_override` instead
             eth_client: EthereumClient::new("https://unused.com").unwrap(),
             eth_address: H160::zero(), // Unused
-            fgw_client: FakeFgw {
+            fgw_client: Some(FakeFgw {
                 head: (last_header.number, last_header.hash),
-            },
+            }),
             chain_id: ChainId::SEPOLIA_TESTNET,
             public_key,
             l1_checkpoint_override: Some(EthereumStateUpdate {
@@ -495,6 +767,9 @@ mod tests {
             }),
             verify_tree_hashes: true,
             block_hash_db: None,
+            notifications: Default::default(),
+            pending_data: watch::channel(Default::default()).0,
+            pending_announcements: watch::channel(None).1,
         };
 
         let sync_done = if error_setup.fatal_at.is_some() {
@@ -628,6 +903,236 @@ mod tests {
         }
     }
 
+    mod fork {
+        use super::*;
+
+        const LOCAL_BLOCKS: usize = 6;
+        const PEERS_AHEAD: usize = 2;
+        const FORK_AT: BlockNumber = BlockNumber::new_or_panic(3);
+
+        /// Stores a chain of blocks locally and returns it, along with the same
+        /// chain forked at [`FORK_AT`] and extended by [`PEERS_AHEAD`] blocks.
+        fn setup(storage: &Storage) -> (PublicKey, Vec<Block>, Vec<Block>) {
+            let private_key = Faker.fake();
+            let public_key = PublicKey(pathfinder_crypto::signature::get_pk(private_key).unwrap());
+            let mut local = generate_fake_blocks_signed_by(private_key, LOCAL_BLOCKS + PEERS_AHEAD);
+            let forked = fork_fake_blocks(&local, FORK_AT, private_key);
+            local.truncate(LOCAL_BLOCKS);
+            pathfinder_storage::fake::fill(storage, &local, Some(Box::new(update_starknet_state)));
+            (public_key, local, forked)
+        }
+
+        fn sync_with_peers(
+            storage: Storage,
+            public_key: PublicKey,
+            blocks: Vec<Block>,
+        ) -> Sync<FakeP2PClient, FakeFgw> {
+            let head = &blocks.last().unwrap().header.header;
+            let (last_event_tx, _) = tokio::sync::mpsc::channel(1);
+            Sync {
+                storage: storage.clone(),
+                p2p: FakeP2PClient {
+                    blocks,
+                    error_trigger: ErrorTrigger::Fatal(Arc::new(AtomicU64::new(ERROR_CONSUMED))),
+                    storage,
+                    last_event_tx,
+                },
+                eth_client: EthereumClient::new("https://unused.com").unwrap(),
+                eth_address: H160::zero(),
+                fgw_client: Some(FakeFgw {
+                    head: (head.number, head.hash),
+                }),
+                chain_id: ChainId::SEPOLIA_TESTNET,
+                public_key,
+                l1_checkpoint_override: None,
+                verify_tree_hashes: true,
+                block_hash_db: None,
+                notifications: Default::default(),
+                pending_data: watch::channel(Default::default()).0,
+                pending_announcements: watch::channel(None).1,
+            }
+        }
+
+        fn local_head(storage: &Storage) -> Option<(BlockNumber, BlockHash)> {
+            let mut db = storage.connection().unwrap();
+            let db = db.transaction().unwrap();
+            db.block_id(pathfinder_storage::BlockId::Latest).unwrap()
+        }
+
+        #[test_log::test(tokio::test)]
+        async fn is_reverted_to_common_ancestor() {
+            let storage = StorageBuilder::in_tempdir().unwrap();
+            let (public_key, local, forked) = setup(&storage);
+            let sync = sync_with_peers(storage.clone(), public_key, forked);
+            let mut reorgs = sync.notifications.reorgs.subscribe();
+
+            let head = &local.last().unwrap().header.header;
+            let mut next = head.number + 1;
+            let mut parent_hash = head.hash;
+            assert!(sync.handle_fork(&mut next, &mut parent_hash).await.unwrap());
+
+            let ancestor = &local[FORK_AT.get() as usize - 1].header.header;
+            assert_eq!(next, FORK_AT);
+            assert_eq!(parent_hash, ancestor.hash);
+            assert_eq!(local_head(&storage), Some((ancestor.number, ancestor.hash)));
+
+            let reorg = reorgs.try_recv().unwrap();
+            assert_eq!(reorg.first_block_number, FORK_AT);
+            assert_eq!(
+                reorg.first_block_hash,
+                local[FORK_AT.get() as usize].header.header.hash
+            );
+            assert_eq!(reorg.last_block_hash, head.hash);
+        }
+
+        #[test_log::test(tokio::test)]
+        async fn is_followed_by_track_sync() {
+            let storage = StorageBuilder::in_tempdir().unwrap();
+            let (public_key, local, forked) = setup(&storage);
+            let sync = sync_with_peers(storage.clone(), public_key, forked.clone());
+            let mut reorgs = sync.notifications.reorgs.subscribe();
+
+            let head = &local.last().unwrap().header.header;
+            let peers_head = &forked.last().unwrap().header.header;
+            let expected = Some((peers_head.number, peers_head.hash));
+
+            let synced = async {
+                while local_head(&storage) != expected {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            };
+
+            tokio::select! {
+                result = sync.track_sync(head.number + 1, head.hash) => {
+                    panic!("Track sync exited unexpectedly: {result:?}")
+                }
+                _ = tokio::time::timeout(TIMEOUT, synced) => {}
+            }
+
+            assert_eq!(local_head(&storage), expected);
+            for expected in &forked {
+                let expected = &expected.header.header;
+                let mut db = storage.connection().unwrap();
+                let db = db.transaction().unwrap();
+                let header = db.block_header(expected.number.into()).unwrap().unwrap();
+                assert_eq!(header.hash, expected.hash, "block {}", expected.number);
+            }
+
+            let reorg = reorgs.try_recv().unwrap();
+            assert_eq!(reorg.first_block_number, FORK_AT);
+            assert_eq!(reorg.last_block_hash, head.hash);
+        }
+
+        #[test_log::test(tokio::test)]
+        async fn is_not_detected_if_peers_agree_with_local_chain() {
+            let storage = StorageBuilder::in_tempdir().unwrap();
+            let (public_key, local, _) = setup(&storage);
+            let sync = sync_with_peers(storage.clone(), public_key, local.clone());
+
+            let head = &local.last().unwrap().header.header;
+            let mut next = head.number + 1;
+            let mut parent_hash = head.hash;
+            assert!(!sync.handle_fork(&mut next, &mut parent_hash).await.unwrap());
+
+            assert_eq!(next, head.number + 1);
+            assert_eq!(parent_hash, head.hash);
+            assert_eq!(local_head(&storage), Some((head.number, head.hash)));
+        }
+
+        #[test_log::test(tokio::test)]
+        async fn is_rejected_if_it_reverts_l1_accepted_blocks() {
+            let storage = StorageBuilder::in_tempdir().unwrap();
+            let (public_key, local, forked) = setup(&storage);
+            let anchor = &local[FORK_AT.get() as usize].header.header;
+            {
+                let mut db = storage.connection().unwrap();
+                let db = db.transaction().unwrap();
+                db.upsert_l1_state(&EthereumStateUpdate {
+                    state_root: anchor.state_commitment,
+                    block_number: anchor.number,
+                    block_hash: anchor.hash,
+                })
+                .unwrap();
+                db.commit().unwrap();
+            }
+            let sync = sync_with_peers(storage.clone(), public_key, forked);
+
+            let head = &local.last().unwrap().header.header;
+            let mut next = head.number + 1;
+            let mut parent_hash = head.hash;
+            let result = sync.handle_fork(&mut next, &mut parent_hash).await;
+            assert!(matches!(result, Err(SyncError::Discontinuity(_))));
+
+            assert_eq!(next, head.number + 1);
+            assert_eq!(local_head(&storage), Some((head.number, head.hash)));
+        }
+    }
+
+    mod latest_from_peers {
+        use super::*;
+
+        /// Follows the head served by peers serving `blocks`, on top of the
+        /// local `head`.
+        fn spawn(blocks: Vec<Block>, public_key: PublicKey, head: &BlockHeader) -> LatestStream {
+            let (last_event_tx, _) = tokio::sync::mpsc::channel(1);
+            LatestStream::spawn_from_peers(
+                FakeP2PClient {
+                    blocks,
+                    error_trigger: ErrorTrigger::Fatal(Arc::new(AtomicU64::new(ERROR_CONSUMED))),
+                    storage: StorageBuilder::in_memory().unwrap(),
+                    last_event_tx,
+                },
+                head.number + 1,
+                head.hash,
+                ChainId::SEPOLIA_TESTNET,
+                public_key,
+                None,
+                Duration::from_millis(10),
+            )
+        }
+
+        #[test_log::test(tokio::test)]
+        async fn follows_the_head_served_by_peers() {
+            let (public_key, blocks) = generate_fake_blocks(5);
+            let head = &blocks.last().unwrap().header.header;
+            let expected = (head.number, head.hash);
+
+            let genesis = blocks[0].header.header.clone();
+            let mut latest = spawn(blocks, public_key, &genesis);
+
+            let actual = tokio::time::timeout(TIMEOUT, latest.next()).await.unwrap();
+            assert_eq!(actual, Some(expected));
+        }
+
+        #[test_log::test(tokio::test)]
+        async fn stops_before_a_header_with_bad_signature() {
+            let (public_key, mut blocks) = generate_fake_blocks(5);
+            blocks[3].header.signature = Faker.fake();
+            let valid_head = &blocks[2].header.header;
+            let expected = (valid_head.number, valid_head.hash);
+
+            let genesis = blocks[0].header.header.clone();
+            let mut latest = spawn(blocks, public_key, &genesis);
+
+            let actual = tokio::time::timeout(TIMEOUT, latest.next()).await.unwrap();
+            assert_eq!(actual, Some(expected));
+        }
+
+        #[test_log::test(tokio::test)]
+        async fn ends_if_peers_forked_at_the_local_head() {
+            let private_key = Faker.fake();
+            let public_key = PublicKey(pathfinder_crypto::signature::get_pk(private_key).unwrap());
+            let local = generate_fake_blocks_signed_by(private_key, 5);
+            let forked = fork_fake_blocks(&local, BlockNumber::new_or_panic(3), private_key);
+            let head = &local.last().unwrap().header.header;
+
+            let mut latest = spawn(forked, public_key, head);
+
+            let actual = tokio::time::timeout(TIMEOUT, latest.next()).await.unwrap();
+            assert_eq!(actual, None);
+        }
+    }
+
     #[derive(Clone)]
     struct FakeP2PClient {
         pub blocks: Vec<Block>,
@@ -909,6 +1414,20 @@ mod tests {
         }
     }
 
+    impl PeerReputation for FakeP2PClient {
+        async fn punish(&self, _: PeerId, _: Penalty) {}
+    }
+
+    impl PendingBlockClient for FakeP2PClient {
+        async fn pending_block(
+            self,
+            _: BlockHash,
+            _: Option<PeerId>,
+        ) -> Option<(PeerId, PendingBlock)> {
+            None
+        }
+    }
+
     impl BlockClient for FakeP2PClient {
         async fn transactions_for_block(
             self,
@@ -1056,6 +1575,15 @@ mod tests {
 
             Some((PeerId::random(), stream::iter(e)))
         }
+
+        async fn headers_from(
+            self,
+            start: BlockNumber,
+            limit: u64,
+        ) -> Option<(PeerId, Vec<SignedBlockHeader>)> {
+            let headers = self.blocks(start, start + (limit - 1), false, |b| b.header);
+            (!headers.is_empty()).then(|| (PeerId::random(), headers))
+        }
     }
 
     #[derive(Clone)]
@@ -24,6 +24,7 @@ use pathfinder_common::receipt::Receipt;
 use pathfinder_common::state_update::StateUpdateData;
 use pathfinder_common::transaction::{Transaction, TransactionVariant};
 use pathfinder_ethereum::EthereumStateUpdate;
+use pathfinder_rpc::Notifications;
 use pathfinder_storage::Storage;
 use primitive_types::H160;
 use serde_json::de;
@@ -32,10 +33,19 @@ use tokio::sync::Mutex;
 use tracing::Instrument;
 
 use crate::state::block_hash::calculate_transaction_commitment;
+use crate::state::revert;
 use crate::sync::error::SyncError;
 use crate::sync::stream::{InfallibleSource, Source, SyncReceiver, SyncResult};
 use crate::sync::{class_definitions, events, headers, state_updates, transactions};
 
+/// Number of blocks in a chunk of a block range, which is requested from a
+/// single peer.
+const CHUNK_SIZE: u64 = 100;
+
+/// Maximum number of chunks which are downloaded at the same time, each from a
+/// different peer if enough peers are available.
+const MAX_CONCURRENT_CHUNKS: usize = 8;
+
 /// Provides P2P sync capability for blocks secured by L1.
 #[derive(Clone)]
 pub struct Sync<P, G> {
@@ -44,11 +54,13 @@ pub struct Sync<P, G> {
     // TODO: merge these two inside the client.
     pub eth_client: pathfinder_ethereum::EthereumClient,
     pub eth_address: H160,
-    pub fgw_client: G,
+    /// Used to fetch CASM for classes which fail to compile locally, if set.
+    pub fgw_client: Option<G>,
     pub chain_id: ChainId,
     pub public_key: PublicKey,
     pub verify_tree_hashes: bool,
     pub block_hash_db: Option<pathfinder_block_hashes::BlockHashDb>,
+    pub notifications: Notifications,
 }
 
 impl<P, G> Sync<P, G>
@@ -68,12 +80,13 @@ where
         storage: Storage,
         p2p: P,
         ethereum: (pathfinder_ethereum::EthereumClient, H160),
-        fgw_client: G,
+        fgw_client: Option<G>,
         chain_id: ChainId,
         public_key: PublicKey,
         l1_anchor_override: Option<EthereumStateUpdate>,
         verify_tree_hashes: bool,
         block_hash_db: Option<BlockHashDb>,
+        notifications: Notifications,
     ) -> Self {
         Self {
             storage,
@@ -85,6 +98,7 @@ where
             public_key,
             verify_tree_hashes,
             block_hash_db,
+            notifications,
         }
     }
 
@@ -104,7 +118,7 @@ where
 
         // Ensure our local state is consistent with the L1 checkpoint.
         CheckpointAnalysis::analyse(&local_state, &checkpoint)
-            .handle(self.storage.clone())
+            .handle(self.storage.clone(), self.notifications.clone())
             .await
             .context("Analysing local storage against L1 checkpoint")?;
 
@@ -118,6 +132,12 @@ where
         persist_anchor(self.storage.clone(), anchor)
             .await
             .context("Persisting new Ethereum anchor")?;
+        self.notifications
+            .l1_updates
+            .send(anchor.block_number)
+            // Ignore errors in case nobody is listening. New listeners may subscribe in
+            // the future.
+            .ok();
 
         let head = anchor.block_number;
 
@@ -159,8 +179,11 @@ where
         {
             tracing::info!(?gap, "Syncing headers");
 
+            let p2p = self.p2p.clone();
             handle_header_stream(
-                self.p2p.clone().header_stream(gap.tail, gap.head, true),
+                download_in_chunks(gap.tail, gap.head, true, move |start, stop| {
+                    p2p.clone().header_stream(start, stop, true)
+                }),
                 gap.head(),
                 self.chain_id,
                 self.public_key,
@@ -186,16 +209,20 @@ where
             return Ok(());
         };
 
-        let transaction_stream = self.p2p.clone().transaction_stream(
-            start,
-            stop,
-            transactions::counts_stream(
-                self.storage.clone(),
+        let p2p = self.p2p.clone();
+        let storage = self.storage.clone();
+        let transaction_stream = download_in_chunks(start, stop, false, move |start, stop| {
+            p2p.clone().transaction_stream(
                 start,
                 stop,
-                NonZeroUsize::new(100).expect("100>0"),
-            ),
-        );
+                transactions::counts_stream(
+                    storage.clone(),
+                    start,
+                    stop,
+                    NonZeroUsize::new(100).expect("100>0"),
+                ),
+            )
+        });
 
         handle_transaction_stream(transaction_stream, self.storage.clone(), chain_id, start)
             .await?;
@@ -216,16 +243,20 @@ where
             return Ok(());
         };
 
-        let stream = self.p2p.clone().state_diff_stream(
-            start,
-            stop,
-            state_updates::state_diff_length_stream(
-                self.storage.clone(),
+        let p2p = self.p2p.clone();
+        let storage = self.storage.clone();
+        let stream = download_in_chunks(start, stop, false, move |start, stop| {
+            p2p.clone().state_diff_stream(
                 start,
                 stop,
-                NonZeroUsize::new(100).expect("100>0"),
-            ),
-        );
+                state_updates::state_diff_length_stream(
+                    storage.clone(),
+                    start,
+                    stop,
+                    NonZeroUsize::new(100).expect("100>0"),
+                ),
+            )
+        });
 
         handle_state_diff_stream(stream, self.storage.clone(), start, verify_tree_hashes).await?;
 
@@ -241,16 +272,20 @@ where
             return Ok(());
         };
 
-        let class_stream = self.p2p.clone().class_stream(
-            start,
-            stop,
-            class_definitions::declared_class_counts_stream(
-                self.storage.clone(),
+        let p2p = self.p2p.clone();
+        let storage = self.storage.clone();
+        let class_stream = download_in_chunks(start, stop, false, move |start, stop| {
+            p2p.clone().class_stream(
                 start,
                 stop,
-                NonZeroUsize::new(100).expect("100>0"),
-            ),
-        );
+                class_definitions::declared_class_counts_stream(
+                    storage.clone(),
+                    start,
+                    stop,
+                    NonZeroUsize::new(100).expect("100>0"),
+                ),
+            )
+        });
 
         let expected_declarations =
             class_definitions::expected_declarations_stream(self.storage.clone(), start, stop);
@@ -274,16 +309,20 @@ where
             return Ok(());
         };
 
-        let event_stream = self.p2p.clone().event_stream(
-            start,
-            stop,
-            events::counts_stream(
-                self.storage.clone(),
+        let p2p = self.p2p.clone();
+        let storage = self.storage.clone();
+        let event_stream = download_in_chunks(start, stop, false, move |start, stop| {
+            p2p.clone().event_stream(
                 start,
                 stop,
-                NonZeroUsize::new(100).expect("100>0"),
-            ),
-        );
+                events::counts_stream(
+                    storage.clone(),
+                    start,
+                    stop,
+                    NonZeroUsize::new(100).expect("100>0"),
+                ),
+            )
+        });
 
         handle_event_stream(event_stream, self.storage.clone()).await?;
 
@@ -291,6 +330,46 @@ where
     }
 }
 
+/// Splits the block range `start..=stop` into chunks of [`CHUNK_SIZE`] blocks
+/// and downloads up to [`MAX_CONCURRENT_CHUNKS`] of them at the same time using
+/// `fetch`. The items are yielded in the order of the range, from `stop` down
+/// to `start` if `reverse` is set, so that they can be verified and persisted
+/// as if they were downloaded sequentially.
+fn download_in_chunks<T, S>(
+    start: BlockNumber,
+    stop: BlockNumber,
+    reverse: bool,
+    mut fetch: impl FnMut(BlockNumber, BlockNumber) -> S + Send + 'static,
+) -> impl Stream<Item = T> + Send + 'static
+where
+    T: Send + 'static,
+    S: Stream<Item = T> + Send + 'static,
+{
+    futures::stream::iter(chunks(start, stop, reverse))
+        .map(move |(start, stop)| fetch(start, stop).collect::<Vec<_>>())
+        .buffered(MAX_CONCURRENT_CHUNKS)
+        .flat_map(futures::stream::iter)
+}
+
+/// The `(start, stop)` ranges of the chunks of `start..=stop`, in reverse order
+/// if `reverse` is set.
+fn chunks(start: BlockNumber, stop: BlockNumber, reverse: bool) -> Vec<(BlockNumber, BlockNumber)> {
+    let mut chunks = (start.get()..=stop.get())
+        .step_by(CHUNK_SIZE as usize)
+        .map(|chunk_start| {
+            let chunk_stop = chunk_start.saturating_add(CHUNK_SIZE - 1).min(stop.get());
+            (
+                BlockNumber::new_or_panic(chunk_start),
+                BlockNumber::new_or_panic(chunk_stop),
+            )
+        })
+        .collect::<Vec<_>>();
+    if reverse {
+        chunks.reverse();
+    }
+    chunks
+}
+
 async fn handle_header_stream(
     stream: impl Stream<Item = PeerData<SignedBlockHeader>> + Send + 'static,
     head: (BlockNumber, BlockHash),
@@ -367,7 +446,7 @@ async fn handle_state_diff_stream(
 async fn handle_class_stream<SequencerClient: GatewayApi + Clone + Send + 'static>(
     class_definitions: impl Stream<Item = StreamItem<ClassDefinition>> + Send + 'static,
     storage: Storage,
-    fgw: SequencerClient,
+    fgw: Option<SequencerClient>,
     expected_declarations: impl Stream<Item = anyhow::Result<(BlockNumber, HashSet<ClassHash>)>>
         + Send
         + 'static,
@@ -522,7 +601,7 @@ impl CheckpointAnalysis {
     /// insecure local data intact. Always rolling back to the L1 anchor
     /// would result in a poor user experience if restarting frequently as each
     /// restart would purge new data.
-    async fn handle(self, storage: Storage) -> anyhow::Result<()> {
+    async fn handle(self, storage: Storage, notifications: Notifications) -> anyhow::Result<()> {
         match self {
             CheckpointAnalysis::HashMismatchWithAnchor {
                 block,
@@ -554,7 +633,7 @@ impl CheckpointAnalysis {
                     "Rolling back local chain to latest anchor point. Local data is potentially invalid as the Ethereum checkpoint is newer the local chain."
                 );
 
-                rollback_to_anchor(storage, local, anchor)
+                rollback_to_anchor(storage, anchor, notifications)
                     .await
                     .context("Rolling back chain state to L1 anchor")?;
             }
@@ -569,7 +648,7 @@ impl CheckpointAnalysis {
                     "Rolling back local chain to latest anchor point. Local data is invalid as it did not match the Ethereum checkpoint's hash."
                 );
 
-                rollback_to_anchor(storage, block, anchor)
+                rollback_to_anchor(storage, anchor, notifications)
                     .await
                     .context("Rolling back chain state to L1 anchor")?;
             }
@@ -628,42 +707,28 @@ impl LocalState {
 /// of the local chain. If this is ['None'] then all data will be rolled back.
 async fn rollback_to_anchor(
     storage: Storage,
-    local: BlockNumber,
     anchor: Option<BlockNumber>,
+    notifications: Notifications,
 ) -> anyhow::Result<()> {
     util::task::spawn_blocking(move |_| {
-        tracing::info!(%local, ?anchor, "Rolling back storage to anchor point");
+        tracing::info!(?anchor, "Rolling back storage to anchor point");
 
-        let last_block_to_remove = anchor.map(|n| n + 1).unwrap_or_default();
-        let mut head = local;
+        let reorg_tail = anchor.map(|n| n + 1).unwrap_or_default();
 
         let mut db = storage
             .connection()
             .context("Creating database connection")?;
-        let transaction = db.transaction().context("Create database transaction")?;
-
-        // TODO: roll back Merkle tree state once we're updating that
-
-        while head >= last_block_to_remove {
-            transaction
-                .purge_block(head)
-                .with_context(|| format!("Purging block {head} from database"))?;
-
-            // No further blocks to purge if we just purged genesis.
-            if head == BlockNumber::GENESIS {
-                break;
-            }
-
-            head -= 1;
+        let reorg_tail_exists = db
+            .transaction()
+            .context("Creating database transaction")?
+            .block_exists(reorg_tail.into())
+            .context("Querying reorg tail")?;
+        // The local chain ends at the anchor already.
+        if !reorg_tail_exists {
+            return Ok(());
         }
 
-        transaction
-            .reset_in_memory_state(head)
-            .context("Resetting in-memory DB state after reorg")?;
-
-        transaction.commit().context("Committing transaction")?;
-
-        Ok(())
+        revert::revert_chain(&mut db, reorg_tail, &notifications)
     })
     .await
     .context("Joining blocking task")?
@@ -1539,7 +1604,7 @@ mod tests {
             handle_class_stream(
                 stream::iter(streamed_classes),
                 storage.clone(),
-                FakeFgw,
+                Some(FakeFgw),
                 declared_classes.to_stream(),
             )
             .await
@@ -1576,6 +1641,27 @@ mod tests {
             assert_eq!(actual_defs, expected_defs);
         }
 
+        #[tokio::test]
+        async fn compilation_failure_without_fgw() {
+            let Setup {
+                streamed_classes,
+                declared_classes,
+                storage,
+                ..
+            } = setup(true).await;
+
+            assert_matches!(
+                handle_class_stream(
+                    stream::iter(streamed_classes),
+                    storage,
+                    None::<FakeFgw>,
+                    declared_classes.to_stream(),
+                )
+                .await,
+                Err(SyncError::Fatal(_))
+            );
+        }
+
         #[rstest::rstest]
         #[case::cairo(ClassDefinition::Cairo {
             block_number: BlockNumber::GENESIS + 1,
@@ -1597,7 +1683,7 @@ mod tests {
                     handle_class_stream(
                         stream::once(std::future::ready(Ok(data))),
                         storage,
-                        FakeFgw,
+                        Some(FakeFgw),
                         Faker.fake::<DeclaredClasses>().to_stream(),
                     )
                     .await,
@@ -1628,7 +1714,7 @@ mod tests {
                     handle_class_stream(
                         stream::iter(streamed_classes),
                         storage,
-                        FakeFgw,
+                        Some(FakeFgw),
                         declared_classes.to_stream(),
                     )
                     .await,
@@ -1641,7 +1727,7 @@ mod tests {
                 handle_class_stream(
                     stream::once(std::future::ready(Err(anyhow::anyhow!("")))),
                     StorageBuilder::in_memory().unwrap(),
-                    FakeFgw,
+                    Some(FakeFgw),
                     Faker.fake::<DeclaredClasses>().to_stream(),
                 )
                 .await,
@@ -1807,4 +1893,64 @@ mod tests {
             );
         }
     }
+
+    mod download_in_chunks {
+        use std::time::Duration;
+
+        use futures::stream;
+
+        use super::*;
+
+        fn block(n: u64) -> BlockNumber {
+            BlockNumber::new_or_panic(n)
+        }
+
+        #[test]
+        fn chunks_cover_the_range() {
+            let stop = 2 * CHUNK_SIZE + 4;
+            let expected = vec![
+                (block(5), block(CHUNK_SIZE + 4)),
+                (block(CHUNK_SIZE + 5), block(stop)),
+            ];
+
+            assert_eq!(chunks(block(5), block(stop), false), expected);
+            assert_eq!(
+                chunks(block(5), block(stop), true),
+                expected.into_iter().rev().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                chunks(block(7), block(7), false),
+                vec![(block(7), block(7))]
+            );
+        }
+
+        #[rstest::rstest]
+        #[tokio::test]
+        async fn items_are_reassembled_in_order(#[values(false, true)] reverse: bool) {
+            let stop = block(5 * CHUNK_SIZE);
+
+            // Chunks which are requested earlier take longer to download, so they
+            // complete out of order.
+            let actual =
+                download_in_chunks(BlockNumber::GENESIS, stop, reverse, move |start, stop| {
+                    let chunk = start.get() / CHUNK_SIZE;
+                    let later_chunks = if reverse { chunk } else { 5 - chunk };
+                    let delay = Duration::from_millis(10 * later_chunks);
+                    let mut blocks = (start.get()..=stop.get()).collect::<Vec<_>>();
+                    if reverse {
+                        blocks.reverse();
+                    }
+                    stream::once(tokio::time::sleep(delay))
+                        .flat_map(move |_| stream::iter(blocks.clone()))
+                })
+                .collect::<Vec<_>>()
+                .await;
+
+            let mut expected = (0..=stop.get()).collect::<Vec<_>>();
+            if reverse {
+                expected.reverse();
+            }
+            assert_eq!(actual, expected);
+        }
+    }
 }
@@ -413,12 +413,13 @@ pub(super) fn expected_declarations_stream(
 }
 
 pub struct CompileSierraToCasm<T> {
-    fgw: T,
+    fgw: Option<T>,
     tokio_handle: tokio::runtime::Handle,
 }
 
 impl<T> CompileSierraToCasm<T> {
-    pub fn new(fgw: T, tokio_handle: tokio::runtime::Handle) -> Self {
+    /// CASM is fetched from `fgw` for classes which fail to compile locally.
+    pub fn new(fgw: Option<T>, tokio_handle: tokio::runtime::Handle) -> Self {
         Self { fgw, tokio_handle }
     }
 }
@@ -433,7 +434,7 @@ impl<T: GatewayApi + Clone + Send + 'static> ProcessStage for CompileSierraToCas
         input
             .into_par_iter()
             .map(|class| {
-                let compiled = compile_or_fetch_impl(class, &self.fgw, &self.tokio_handle)?;
+                let compiled = compile_or_fetch_impl(class, self.fgw.as_ref(), &self.tokio_handle)?;
                 Ok(compiled)
             })
             .collect::<Result<Vec<CompiledClass>, SyncError>>()
@@ -444,7 +445,7 @@ pub(super) async fn compile_sierra_to_casm_or_fetch<
     SequencerClient: GatewayApi + Clone + Send + 'static,
 >(
     peer_data: Vec<PeerData<Class>>,
-    fgw: SequencerClient,
+    fgw: Option<SequencerClient>,
     tokio_handle: tokio::runtime::Handle,
 ) -> Result<Vec<PeerData<CompiledClass>>, SyncError> {
     use rayon::prelude::*;
@@ -454,7 +455,7 @@ pub(super) async fn compile_sierra_to_casm_or_fetch<
             .into_par_iter()
             .map(|x| {
                 let PeerData { peer, data } = x;
-                let compiled = compile_or_fetch_impl(data, &fgw, &tokio_handle)?;
+                let compiled = compile_or_fetch_impl(data, fgw.as_ref(), &tokio_handle)?;
                 Ok(PeerData::new(peer, compiled))
             })
             .collect::<Result<Vec<PeerData<CompiledClass>>, SyncError>>();
@@ -465,7 +466,7 @@ pub(super) async fn compile_sierra_to_casm_or_fetch<
 
 fn compile_or_fetch_impl<SequencerClient: GatewayApi + Clone + Send + 'static>(
     class: Class,
-    fgw: &SequencerClient,
+    fgw: Option<&SequencerClient>,
     tokio_handle: &tokio::runtime::Handle,
 ) -> Result<CompiledClass, SyncError> {
     let Class {
@@ -480,12 +481,22 @@ fn compile_or_fetch_impl<SequencerClient: GatewayApi + Clone + Send + 'static>(
             let casm_definition = pathfinder_compiler::compile_to_casm(&sierra_definition)
                 .context("Compiling Sierra class");
 
-            let casm_definition = match casm_definition {
-                Ok(x) => x,
+            let casm_definition = match (casm_definition, fgw) {
+                (Ok(x), _) => x,
+                // Without a feeder gateway there is nowhere else to get the CASM from, and
+                // retrying won't make the class compile.
+                (Err(error), None) => {
+                    return Err(error
+                        .context(format!(
+                            "Class {hash} declared in block {block_number} cannot be compiled \
+                             and there is no feeder gateway to fetch its CASM from"
+                        ))
+                        .into())
+                }
                 // Feeder gateway request errors are recoverable at this point because we know
                 // that the class is declared and exists so if the gateway responds with an
                 // error we should restart the sync and retry later.
-                Err(_) => tokio_handle
+                (Err(_), Some(fgw)) => tokio_handle
                     .block_on(fgw.pending_casm_by_hash(hash))
                     .map_err(|error| {
                         tracing::debug!(%block_number, class_hash=%hash, %error, "Fetching casm from feeder gateway failed");
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use p2p_v2::libp2p::PeerId;
-use p2p_v2::PeerData;
+use p2p_v2::{PeerData, Penalty};
 use pathfinder_common::{BlockNumber, ClassHash, SignedBlockHeader};
 
 #[derive(Debug, thiserror::Error, Clone)]
@@ -62,6 +62,43 @@ pub(super) enum SyncError {
     UnexpectedClass(PeerId),
 }
 
+impl SyncError {
+    /// The peer at fault for this error and how it should be punished.
+    /// Provably invalid data, i.e. failed hash, signature or commitment
+    /// verification, is punished the hardest.
+    pub fn peer_penalty(&self) -> Option<(PeerId, Penalty)> {
+        use SyncError::*;
+
+        match self {
+            Fatal(_) | FetchingCasmFailed => None,
+            BadBlockHash(peer)
+            | BadClassHash(peer)
+            | BadHeaderSignature(peer)
+            | BadTransactionHash(peer)
+            | EventCommitmentMismatch(peer)
+            | StateDiffCommitmentMismatch(peer)
+            | TransactionCommitmentMismatch(peer) => Some((*peer, Penalty::Invalid)),
+            BadClassLayout(peer)
+            | CairoDefinitionError(peer)
+            | ClassDefinitionsDeclarationsMismatch(peer)
+            | ClassHashComputationError(peer)
+            | ContractClassMissing(peer)
+            | Discontinuity(peer)
+            | EventsTransactionsMismatch(peer)
+            | IncorrectClassDefinitionCount(peer)
+            | IncorrectStateDiffCount(peer)
+            | InvalidDto(peer)
+            | SierraDefinitionError(peer)
+            | StateRootMismatch(peer)
+            | TooFewEvents(peer)
+            | TooFewTransactions(peer)
+            | TooManyEvents(peer)
+            | TooManyTransactions(peer)
+            | UnexpectedClass(peer) => Some((*peer, Penalty::Minor)),
+        }
+    }
+}
+
 impl PartialEq for SyncError {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -0,0 +1,538 @@
+//! Pending block tracking for p2p sync.
+//!
+//! The pending block is requested from peers whenever a peer announces that it
+//! changed, and periodically in case announcements are missed. Pending data is
+//! not signed, so only the transaction hashes can be verified.
+//!
+//! Announcements are debounced, and the pending block is requested from the
+//! announcing peer first.
+//!
+//! As any peer could serve made up transactions, the first pending block on
+//! top of the local chain head is trusted, and only replaced by pending blocks
+//! which extend its transactions until the next block is synced.
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use p2p_proto::pending::NewPendingBlock;
+use p2p_v2::libp2p::PeerId;
+use p2p_v2::sync::client::peer_agnostic::traits::{PeerReputation, PendingBlockClient};
+use p2p_v2::sync::client::types::PendingBlock;
+use p2p_v2::PeerData;
+use pathfinder_common::prelude::*;
+use pathfinder_common::receipt::Receipt;
+use pathfinder_common::state_update::{ContractClassUpdate, StateUpdate};
+use pathfinder_common::transaction::Transaction;
+use pathfinder_rpc::PendingData;
+use pathfinder_storage::Storage;
+use starknet_gateway_types::reply::{GasPrices, PendingBlock as GatewayPendingBlock, Status};
+use tokio::sync::watch;
+use tokio::time::{Instant, MissedTickBehavior};
+use util::error::AnyhowExt;
+
+use crate::sync::error::SyncError;
+
+/// Keeps `pending` up to date with the pending block served by peers on top of
+/// the local chain head.
+///
+/// Only fatal errors cause this function to exit, peers serving bad pending
+/// data are punished and the pending block is requested again later.
+///
+/// An announcement is handled at least `debounce` after the previous request,
+/// announcements received in the meantime are superseded by the latest one.
+pub(super) async fn track<P>(
+    p2p: P,
+    storage: Storage,
+    chain_id: ChainId,
+    pending: watch::Sender<PendingData>,
+    mut announcements: watch::Receiver<Option<PeerData<NewPendingBlock>>>,
+    poll_interval: Duration,
+    debounce: Duration,
+) -> anyhow::Result<()>
+where
+    P: PendingBlockClient + PeerReputation + Clone + Send + 'static,
+{
+    let mut interval = tokio::time::interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut announcements_closed = false;
+    let mut last_request: Option<Instant> = None;
+
+    loop {
+        let announcement = tokio::select! {
+            _ = interval.tick() => None,
+            changed = announcements.changed(), if !announcements_closed => {
+                if changed.is_err() {
+                    tracing::debug!("Pending block announcements closed, polling only");
+                    announcements_closed = true;
+                    continue;
+                }
+                if let Some(last_request) = last_request {
+                    tokio::time::sleep_until(last_request + debounce).await;
+                }
+                // Don't poll right after the announcement was handled.
+                interval.reset();
+                announcements.borrow_and_update().clone()
+            }
+        };
+        last_request = Some(Instant::now());
+
+        match update(
+            p2p.clone(),
+            storage.clone(),
+            chain_id,
+            &pending,
+            announcement,
+        )
+        .await
+        {
+            Ok(()) => {}
+            Err(SyncError::Fatal(mut error)) => {
+                tracing::error!(?error, "Stopping pending block tracking");
+                return Err(error.take_or_deep_clone());
+            }
+            Err(error) => match error.peer_penalty() {
+                Some((peer, penalty)) => {
+                    tracing::debug!(%error, %peer, ?penalty, "Punishing peer");
+                    p2p.punish(peer, penalty).await;
+                }
+                None => tracing::debug!(%error, "Recoverable pending block error"),
+            },
+        }
+    }
+}
+
+/// Requests the pending block built on top of the local chain head and
+/// replaces the current pending data with it, unless it is stale.
+///
+/// If the request follows an `announcement`, the announcing peer is asked
+/// first. Announcements of pending blocks which don't build on the local chain
+/// head, or which aren't newer than the current pending block, are ignored.
+///
+/// A pending block on top of the local chain head only replaces the current
+/// one if it [extends] its transactions.
+async fn update<P>(
+    p2p: P,
+    storage: Storage,
+    chain_id: ChainId,
+    pending: &watch::Sender<PendingData>,
+    announcement: Option<PeerData<NewPendingBlock>>,
+) -> Result<(), SyncError>
+where
+    P: PendingBlockClient,
+{
+    let Some(head) = latest_header(storage.clone()).await? else {
+        return Ok(());
+    };
+
+    let announced_by = match announcement {
+        Some(PeerData { peer, data }) => {
+            let parent_hash = BlockHash(data.parent_hash.0);
+            if parent_hash != head.hash {
+                tracing::trace!(%peer, %parent_hash, local_head=%head.hash, "Ignoring pending block announcement on top of another block");
+                return Ok(());
+            }
+            let current = pending.borrow();
+            if current.block.parent_hash == head.hash
+                && current.block.transactions.len() as u64 >= data.transaction_count
+            {
+                tracing::trace!(%peer, transaction_count=%data.transaction_count, "Ignoring announcement of an older pending block");
+                return Ok(());
+            }
+            Some(peer)
+        }
+        None => None,
+    };
+
+    let Some((peer, block)) = p2p.pending_block(head.hash, announced_by).await else {
+        return Ok(());
+    };
+
+    if block.header.number != head.number + 1 {
+        tracing::debug!(%peer, expected_block_number=%(head.number + 1), actual_block_number=%block.header.number, "Pending block number mismatch");
+        return Err(SyncError::Discontinuity(peer));
+    }
+
+    // Peers might serve an older version of the pending block than the one we
+    // already have, or a different one.
+    {
+        let current = pending.borrow();
+        if current.block.parent_hash == head.hash && !extends(&current.block.transactions, &block) {
+            tracing::trace!(%peer, transactions=%block.transactions.len(), "Ignoring pending block which doesn't extend the current one");
+            return Ok(());
+        }
+    }
+
+    let data = util::task::spawn_blocking(move |_| {
+        let mut db = storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = db.transaction().context("Creating database transaction")?;
+        pending_data(&db, chain_id, peer, head, block)
+    })
+    .await
+    .context("Joining blocking task")??;
+
+    tracing::trace!(number=%data.number, transactions=%data.block.transactions.len(), "Updated pending block from peers");
+    pending.send_replace(data);
+
+    Ok(())
+}
+
+/// Whether `block` has more transactions than `current`, starting with the
+/// same ones.
+fn extends(current: &[Transaction], block: &PendingBlock) -> bool {
+    block.transactions.len() > current.len()
+        && current
+            .iter()
+            .zip(&block.transactions)
+            .all(|(current, (new, _, _))| current.hash == new.hash)
+}
+
+async fn latest_header(storage: Storage) -> anyhow::Result<Option<BlockHeader>> {
+    util::task::spawn_blocking(move |_| {
+        let mut db = storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = db.transaction().context("Creating database transaction")?;
+        db.block_header(pathfinder_storage::BlockId::Latest)
+            .context("Querying latest block header")
+    })
+    .await
+    .context("Joining blocking task")?
+}
+
+/// Verifies the transaction hashes of the pending block served by `peer` and
+/// converts it into pending data on top of `head`.
+fn pending_data(
+    db: &pathfinder_storage::Transaction<'_>,
+    chain_id: ChainId,
+    peer: PeerId,
+    head: BlockHeader,
+    block: PendingBlock,
+) -> Result<PendingData, SyncError> {
+    let PendingBlock {
+        header,
+        transactions,
+        state_diff,
+    } = block;
+
+    let mut block_transactions = Vec::with_capacity(transactions.len());
+    let mut transaction_receipts = Vec::with_capacity(transactions.len());
+    for (mut tx, r, events) in transactions {
+        // Contract address for deploy and deploy account transactions is not propagated
+        // via p2p
+        tx.variant.calculate_contract_address();
+
+        let computed_hash = tx.variant.calculate_hash(chain_id, false);
+        if tx.hash != computed_hash {
+            tracing::debug!(%peer, expected_hash=%tx.hash, %computed_hash, "Pending transaction hash mismatch");
+            return Err(SyncError::BadTransactionHash(peer));
+        }
+
+        let receipt = Receipt {
+            actual_fee: r.actual_fee,
+            execution_resources: r.execution_resources,
+            l2_to_l1_messages: r.l2_to_l1_messages,
+            execution_status: r.execution_status,
+            transaction_hash: computed_hash,
+            transaction_index: r.transaction_index,
+        };
+        block_transactions.push(tx);
+        transaction_receipts.push((receipt, events));
+    }
+
+    let mut state_update = StateUpdate {
+        parent_state_commitment: head.state_commitment,
+        contract_updates: state_diff.contract_updates,
+        system_contract_updates: state_diff.system_contract_updates,
+        declared_cairo_classes: state_diff.declared_cairo_classes,
+        declared_sierra_classes: state_diff.declared_sierra_classes,
+        ..Default::default()
+    };
+    // Peers serve every class update as a deployment, contracts which already exist
+    // had their class replaced.
+    for (address, update) in state_update.contract_updates.iter_mut() {
+        if let Some(ContractClassUpdate::Deploy(class_hash)) = update.class {
+            if db.contract_exists(*address, pathfinder_storage::BlockId::Latest)? {
+                update.class = Some(ContractClassUpdate::Replace(class_hash));
+            }
+        }
+    }
+
+    let block = GatewayPendingBlock {
+        l1_gas_price: GasPrices {
+            price_in_wei: header.eth_l1_gas_price,
+            price_in_fri: header.strk_l1_gas_price,
+        },
+        l1_data_gas_price: GasPrices {
+            price_in_wei: header.eth_l1_data_gas_price,
+            price_in_fri: header.strk_l1_data_gas_price,
+        },
+        l2_gas_price: GasPrices {
+            price_in_wei: header.eth_l2_gas_price,
+            price_in_fri: header.strk_l2_gas_price,
+        },
+        parent_hash: head.hash,
+        sequencer_address: header.sequencer_address,
+        status: Status::Pending,
+        timestamp: header.timestamp,
+        transaction_receipts,
+        transactions: block_transactions,
+        starknet_version: header.starknet_version,
+        l1_da_mode: header.l1_da_mode.into(),
+    };
+
+    Ok(PendingData {
+        block: Arc::new(block),
+        state_update: Arc::new(state_update),
+        number: header.number,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use p2p_proto::common::Hash;
+    use p2p_v2::Penalty;
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::transaction::{InvokeTransactionV0, Transaction, TransactionVariant};
+    use pathfinder_storage::StorageBuilder;
+
+    use super::*;
+
+    const DEBOUNCE: Duration = Duration::from_millis(200);
+
+    /// Records the peers asked first, without serving any pending block.
+    #[derive(Clone, Default)]
+    struct FakeP2P {
+        requests: Arc<Mutex<Vec<Option<PeerId>>>>,
+    }
+
+    impl PendingBlockClient for FakeP2P {
+        async fn pending_block(
+            self,
+            _: BlockHash,
+            peer: Option<PeerId>,
+        ) -> Option<(PeerId, PendingBlock)> {
+            self.requests.lock().unwrap().push(peer);
+            None
+        }
+    }
+
+    impl PeerReputation for FakeP2P {
+        async fn punish(&self, _: PeerId, _: Penalty) {}
+    }
+
+    impl FakeP2P {
+        fn requests(&self) -> Vec<Option<PeerId>> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        async fn wait_for_requests(&self, count: usize) {
+            tokio::time::timeout(Duration::from_secs(10), async {
+                while self.requests.lock().unwrap().len() < count {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .unwrap();
+        }
+    }
+
+    /// Spawns pending block tracking which only polls once, at the start.
+    fn spawn_track(
+        p2p: FakeP2P,
+        storage: Storage,
+    ) -> watch::Sender<Option<PeerData<NewPendingBlock>>> {
+        let (tx, rx) = watch::channel(None);
+        tokio::spawn(track(
+            p2p,
+            storage,
+            ChainId::SEPOLIA_TESTNET,
+            watch::channel(Default::default()).0,
+            rx,
+            Duration::from_secs(3600),
+            DEBOUNCE,
+        ));
+        tx
+    }
+
+    fn announcement(peer: PeerId, parent_hash: BlockHash) -> Option<PeerData<NewPendingBlock>> {
+        Some(PeerData::new(
+            peer,
+            NewPendingBlock {
+                parent_hash: Hash(parent_hash.0),
+                transaction_count: 1,
+            },
+        ))
+    }
+
+    fn storage_with_head() -> (Storage, BlockHeader) {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let head = BlockHeader::builder()
+            .state_commitment(state_commitment_bytes!(b"state commitment"))
+            .finalize_with_hash(block_hash_bytes!(b"head"));
+        let mut db = storage.connection().unwrap();
+        let db_tx = db.transaction().unwrap();
+        db_tx.insert_block_header(&head).unwrap();
+        db_tx
+            .insert_state_update_data(
+                head.number,
+                &StateUpdate::default()
+                    .with_deployed_contract(
+                        contract_address_bytes!(b"existing"),
+                        class_hash_bytes!(b"old class"),
+                    )
+                    .into(),
+            )
+            .unwrap();
+        db_tx.commit().unwrap();
+        (storage, head)
+    }
+
+    fn invoke(chain_id: ChainId) -> Transaction {
+        let variant = TransactionVariant::InvokeV0(InvokeTransactionV0 {
+            sender_address: contract_address_bytes!(b"sender"),
+            ..Default::default()
+        });
+        Transaction {
+            hash: variant.calculate_hash(chain_id, false),
+            variant,
+        }
+    }
+
+    fn pending_block(head: &BlockHeader, transaction: Transaction) -> PendingBlock {
+        PendingBlock {
+            header: BlockHeader {
+                number: head.number + 1,
+                parent_hash: head.hash,
+                ..Default::default()
+            },
+            transactions: vec![(transaction, Default::default(), vec![])],
+            state_diff: StateUpdate::default()
+                .with_deployed_contract(
+                    contract_address_bytes!(b"existing"),
+                    class_hash_bytes!(b"new class"),
+                )
+                .with_deployed_contract(
+                    contract_address_bytes!(b"new"),
+                    class_hash_bytes!(b"new class"),
+                )
+                .into(),
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn announcements_are_debounced_and_requested_from_the_announcer() {
+        let (storage, head) = storage_with_head();
+        let p2p = FakeP2P::default();
+        let announcements = spawn_track(p2p.clone(), storage);
+        p2p.wait_for_requests(1).await;
+
+        let peers = (0..5).map(|_| PeerId::random()).collect::<Vec<_>>();
+        for peer in &peers {
+            announcements.send_replace(announcement(*peer, head.hash));
+        }
+        p2p.wait_for_requests(2).await;
+        tokio::time::sleep(2 * DEBOUNCE).await;
+
+        assert_eq!(p2p.requests(), vec![None, peers.last().copied()]);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn announcements_on_top_of_other_blocks_are_ignored() {
+        let (storage, _) = storage_with_head();
+        let p2p = FakeP2P::default();
+        let announcements = spawn_track(p2p.clone(), storage);
+        p2p.wait_for_requests(1).await;
+
+        announcements.send_replace(announcement(
+            PeerId::random(),
+            block_hash_bytes!(b"other block"),
+        ));
+        tokio::time::sleep(2 * DEBOUNCE).await;
+
+        assert_eq!(p2p.requests(), vec![None]);
+    }
+
+    #[test]
+    fn converts_pending_block() {
+        let (storage, head) = storage_with_head();
+        let transaction = invoke(ChainId::SEPOLIA_TESTNET);
+        let block = pending_block(&head, transaction.clone());
+
+        let mut db = storage.connection().unwrap();
+        let db = db.transaction().unwrap();
+        let data = pending_data(
+            &db,
+            ChainId::SEPOLIA_TESTNET,
+            PeerId::random(),
+            head.clone(),
+            block,
+        )
+        .unwrap();
+
+        assert_eq!(data.number, head.number + 1);
+        assert_eq!(data.block.parent_hash, head.hash);
+        assert_eq!(data.block.transactions, vec![transaction.clone()]);
+        assert_eq!(
+            data.block.transaction_receipts[0].0.transaction_hash,
+            transaction.hash
+        );
+        assert_eq!(
+            data.state_update.parent_state_commitment,
+            head.state_commitment
+        );
+        assert_eq!(
+            data.state_update.contract_updates[&contract_address_bytes!(b"existing")].class,
+            Some(ContractClassUpdate::Replace(class_hash_bytes!(
+                b"new class"
+            )))
+        );
+        assert_eq!(
+            data.state_update.contract_updates[&contract_address_bytes!(b"new")].class,
+            Some(ContractClassUpdate::Deploy(class_hash_bytes!(b"new class")))
+        );
+    }
+
+    #[test]
+    fn pending_blocks_must_extend_the_current_transactions() {
+        let (_, head) = storage_with_head();
+        let transaction = |hash: &[u8]| Transaction {
+            hash: transaction_hash_bytes!(hash),
+            ..invoke(ChainId::SEPOLIA_TESTNET)
+        };
+        let first = transaction(b"first");
+        let second = transaction(b"second");
+        let other = transaction(b"other");
+        let block = |transactions: &[&Transaction]| PendingBlock {
+            transactions: transactions
+                .iter()
+                .map(|tx| ((*tx).clone(), Default::default(), vec![]))
+                .collect(),
+            ..pending_block(&head, first.clone())
+        };
+        let current = vec![first.clone()];
+
+        assert!(extends(&[], &block(&[&other])));
+        assert!(extends(&current, &block(&[&first, &second])));
+        assert!(!extends(&current, &block(&[&first])));
+        assert!(!extends(&current, &block(&[&other, &second])));
+    }
+
+    #[test]
+    fn bad_transaction_hash() {
+        let (storage, head) = storage_with_head();
+        let mut transaction = invoke(ChainId::SEPOLIA_TESTNET);
+        transaction.hash = transaction_hash_bytes!(b"bad hash");
+        let block = pending_block(&head, transaction);
+        let peer = PeerId::random();
+
+        let mut db = storage.connection().unwrap();
+        let db = db.transaction().unwrap();
+        let error = pending_data(&db, ChainId::SEPOLIA_TESTNET, peer, head, block).unwrap_err();
+
+        assert_matches::assert_matches!(error, SyncError::BadTransactionHash(p) => assert_eq!(p, peer));
+    }
+}
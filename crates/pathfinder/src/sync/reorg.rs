@@ -0,0 +1,125 @@
+//! Fork detection for track sync.
+//!
+//! A block from peers which doesn't extend the local chain is either bad data
+//! or a sign that the local chain was reorganized away. To tell the two apart,
+//! peers' headers are followed backwards from the local head until they meet
+//! the local chain.
+use anyhow::Context;
+use futures::StreamExt;
+use p2p_v2::sync::client::peer_agnostic::traits::HeaderStream;
+use p2p_v2::PeerData;
+use pathfinder_common::prelude::*;
+use pathfinder_storage::Storage;
+
+use crate::sync::error::SyncError;
+use crate::sync::headers::VerifyHashAndSignature;
+use crate::sync::stream::ProcessStage;
+
+/// The point at which the chain served by peers forks off the local chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Fork {
+    /// The first local block which is not part of the peers' chain.
+    pub tail: BlockNumber,
+    /// The hash of the last block in common, which is the parent of `tail`.
+    pub parent_hash: BlockHash,
+}
+
+/// Follows the verified headers served by peers backwards from the local
+/// `head` until they match the local chain.
+///
+/// Returns `None` if peers agree with the local head, i.e. there is no fork,
+/// or if peers don't serve the headers needed to tell. Blocks accepted on L1
+/// are never reverted, so a peer disagreeing with the local chain at the L1
+/// anchor is at fault.
+pub(super) async fn find_fork<P>(
+    p2p: P,
+    storage: Storage,
+    head: BlockNumber,
+    chain_id: ChainId,
+    public_key: PublicKey,
+    block_hash_db: Option<pathfinder_block_hashes::BlockHashDb>,
+) -> Result<Option<Fork>, SyncError>
+where
+    P: HeaderStream + Send + 'static,
+{
+    let anchor = {
+        let storage = storage.clone();
+        util::task::spawn_blocking(move |_| {
+            let mut db = storage
+                .connection()
+                .context("Creating database connection")?;
+            let db = db.transaction().context("Creating database transaction")?;
+            db.latest_l1_state().context("Querying latest L1 anchor")
+        })
+        .await
+        .context("Joining blocking task")??
+        .map(|anchor| anchor.block_number)
+    };
+
+    if anchor.is_some_and(|anchor| anchor >= head) {
+        return Ok(None);
+    }
+    let stop = anchor.unwrap_or(BlockNumber::GENESIS);
+
+    let mut verify = VerifyHashAndSignature::new(chain_id, public_key, block_hash_db);
+    let mut expected_number = head;
+    let mut expected_hash = None;
+
+    let headers = p2p.header_stream(stop, head, true);
+    futures::pin_mut!(headers);
+
+    while let Some(PeerData { peer, data }) = headers.next().await {
+        let header = verify.map(&peer, data)?.header;
+
+        if header.number != expected_number || expected_hash.is_some_and(|h| h != header.hash) {
+            tracing::debug!(%peer, %expected_number, actual_block_number=%header.number, ?expected_hash, actual_block_hash=%header.hash, "Block chain discontinuity while looking for a fork");
+            return Err(SyncError::Discontinuity(peer));
+        }
+
+        if local_block_hash(storage.clone(), header.number).await? == header.hash {
+            return Ok((header.number != head).then_some(Fork {
+                tail: header.number + 1,
+                parent_hash: header.hash,
+            }));
+        }
+
+        match header.number.parent() {
+            Some(parent) if header.number > stop => {
+                expected_number = parent;
+                expected_hash = Some(header.parent_hash);
+            }
+            // The peers' chain doesn't even share genesis with the local chain.
+            None if anchor.is_none() => {
+                return Ok(Some(Fork {
+                    tail: BlockNumber::GENESIS,
+                    parent_hash: BlockHash::ZERO,
+                }))
+            }
+            _ => {
+                tracing::debug!(%peer, anchor=%stop, "Peer's chain conflicts with the L1 anchor");
+                return Err(SyncError::Discontinuity(peer));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+async fn local_block_hash(storage: Storage, number: BlockNumber) -> anyhow::Result<BlockHash> {
+    util::task::spawn_blocking(move |_| {
+        let mut db = storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = db.transaction().context("Creating database transaction")?;
+        db.block_hash(number.into())
+            .context("Querying block hash")?
+            .with_context(|| {
+                format!(
+                    "Block {number} is missing from the local chain (likely due to blockchain \
+                     history pruning)"
+                )
+            })
+    })
+    .await
+    .context("Joining blocking task")?
+}
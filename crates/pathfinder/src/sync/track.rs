@@ -48,7 +48,7 @@ impl<L, P> Sync<L, P> {
         self,
         next: &mut BlockNumber,
         parent_hash: &mut BlockHash,
-        fgw: SequencerClient,
+        fgw: Option<SequencerClient>,
     ) -> Result<(), SyncError>
     where
         L: Stream<Item = (BlockNumber, BlockHash)> + Clone + Send + 'static,
@@ -0,0 +1,615 @@
+//! Operational `admin_*` JSON-RPC methods.
+//!
+//! These methods control the node itself, so they are never served on the
+//! public RPC address. The [AdminServer] listens on its own address and
+//! requires every request to carry the configured token as an
+//! `Authorization: Bearer <token>` header.
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures::future::BoxFuture;
+use pathfinder_common::{BlockHash, BlockNumber};
+use pathfinder_storage::{BlockId, Storage, TransactionBehavior};
+use serde::de::Error as _;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+
+use crate::context::RpcContext;
+use crate::jsonrpc::{rpc_handler, RpcRouter, RpcRouterBuilder};
+use crate::middleware::auth::constant_time_eq;
+use crate::types::syncing::Syncing;
+
+#[rustfmt::skip]
+fn register_routes() -> RpcRouterBuilder {
+    RpcRouter::builder(crate::RpcVersion::PathfinderV01)
+        .register("admin_syncStatus",                 sync_status)
+        .register("admin_pauseSync",                  pause_sync)
+        .register("admin_resumeSync",                 resume_sync)
+        .register("admin_revertToBlock",              revert_to_block)
+        .register("admin_pruneTries",                 prune_tries)
+        .register("admin_rebuildRunningEventFilter",  rebuild_running_event_filter)
+        .register("admin_peers",                      peers)
+}
+
+/// A request from the admin API to the sync process.
+#[derive(Debug)]
+pub enum SyncCommand {
+    /// Stops fetching new blocks. Blocks already fetched are still stored.
+    Pause {
+        reply: oneshot::Sender<anyhow::Result<()>>,
+    },
+    Resume {
+        reply: oneshot::Sender<anyhow::Result<()>>,
+    },
+    /// Reverts the chain so that `target` becomes the latest block, after
+    /// which sync continues from there unless it is paused.
+    Revert {
+        target: BlockNumber,
+        reply: oneshot::Sender<anyhow::Result<()>>,
+    },
+}
+
+/// Creates the connected halves through which the admin API controls sync.
+pub fn sync_control() -> (SyncControl, SyncCommands) {
+    let (command_sender, command_receiver) = mpsc::channel(1);
+    let (paused_sender, paused_receiver) = watch::channel(false);
+
+    (
+        SyncControl {
+            commands: command_sender,
+            paused: paused_receiver,
+        },
+        SyncCommands {
+            commands: command_receiver,
+            paused: paused_sender,
+        },
+    )
+}
+
+/// The admin API's half of [sync_control].
+#[derive(Clone)]
+pub struct SyncControl {
+    commands: mpsc::Sender<SyncCommand>,
+    paused: watch::Receiver<bool>,
+}
+
+/// The sync process' half of [sync_control].
+pub struct SyncCommands {
+    commands: mpsc::Receiver<SyncCommand>,
+    paused: watch::Sender<bool>,
+}
+
+impl SyncControl {
+    async fn send(&self, command: SyncCommand) -> anyhow::Result<()> {
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| anyhow::anyhow!("Sync is not running or can't be controlled"))
+    }
+
+    fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+}
+
+impl SyncCommands {
+    /// Returns [None] once the admin API is gone.
+    pub async fn recv(&mut self) -> Option<SyncCommand> {
+        self.commands.recv().await
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.send_replace(paused);
+    }
+
+    /// Fails every command with `reason`, for sync processes which can't be
+    /// controlled.
+    pub async fn reject(mut self, reason: &'static str) {
+        while let Some(command) = self.recv().await {
+            let (SyncCommand::Pause { reply }
+            | SyncCommand::Resume { reply }
+            | SyncCommand::Revert { reply, .. }) = command;
+            let _ = reply.send(Err(anyhow::anyhow!(reason)));
+        }
+    }
+}
+
+/// A peer connected to the p2p network.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerInfo {
+    pub peer_id: String,
+    pub address: Option<String>,
+    pub inbound: bool,
+    pub useful: bool,
+    pub min_ping: Option<Duration>,
+}
+
+type PeerSource = Arc<dyn Fn() -> BoxFuture<'static, Vec<PeerInfo>> + Send + Sync>;
+
+#[derive(Clone)]
+pub struct AdminContext {
+    /// Writable storage, the RPC pools are read-only.
+    storage: Storage,
+    sync: SyncControl,
+    peers: Option<PeerSource>,
+}
+
+impl AdminContext {
+    pub fn new(storage: Storage, sync: SyncControl) -> Self {
+        Self {
+            storage,
+            sync,
+            peers: None,
+        }
+    }
+
+    /// Sets the source of `admin_peers`. Without one the node is assumed not
+    /// to be connected to the p2p network.
+    pub fn with_peers<F>(self, peers: F) -> Self
+    where
+        F: Fn() -> BoxFuture<'static, Vec<PeerInfo>> + Send + Sync + 'static,
+    {
+        Self {
+            peers: Some(Arc::new(peers)),
+            ..self
+        }
+    }
+}
+
+pub struct AdminServer {
+    addr: SocketAddr,
+    context: RpcContext,
+    token: Arc<str>,
+}
+
+impl AdminServer {
+    /// The `context` must have an [AdminContext] set.
+    pub fn new(addr: SocketAddr, context: RpcContext, token: String) -> Self {
+        Self {
+            addr,
+            context,
+            token: token.into(),
+        }
+    }
+
+    /// Starts the admin HTTP-RPC server.
+    pub async fn spawn(self) -> anyhow::Result<(JoinHandle<anyhow::Result<()>>, SocketAddr)> {
+        let listener = tokio::net::TcpListener::bind(self.addr)
+            .await
+            .with_context(|| format!("Binding admin RPC address {}", self.addr))?;
+        let addr = listener
+            .local_addr()
+            .context("Getting local address from listener")?;
+
+        let routes = register_routes().build(self.context);
+        let router = axum::Router::new()
+            .route("/", axum::routing::post(rpc_handler))
+            .with_state(routes)
+            .layer(axum::middleware::from_fn_with_state(
+                self.token,
+                authenticate,
+            ));
+
+        let server_handle = util::task::spawn(async move {
+            axum::serve(listener, router.into_make_service())
+                .with_graceful_shutdown(util::task::cancellation_token().cancelled_owned())
+                .await
+                .map_err(Into::into)
+        });
+
+        Ok((server_handle, addr))
+    }
+}
+
+async fn authenticate(State(token): State<Arc<str>>, request: Request, next: Next) -> Response {
+    let provided = request
+        .headers()
+        .get(http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(provided) if constant_time_eq(provided.as_bytes(), token.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => http::StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+crate::error::generate_rpc_error_subset!(AdminError:);
+
+fn admin(context: &RpcContext) -> Result<&AdminContext, AdminError> {
+    context
+        .admin
+        .as_ref()
+        .ok_or_else(|| AdminError::Custom(anyhow::anyhow!("Admin API is not enabled")))
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SyncStatus {
+    paused: bool,
+    syncing: Syncing,
+    latest: Option<(BlockNumber, BlockHash)>,
+}
+
+async fn sync_status(context: RpcContext) -> Result<SyncStatus, AdminError> {
+    let paused = admin(&context)?.sync.is_paused();
+    let syncing = context.sync_status.status.read().await.clone();
+
+    let storage = context.storage.clone();
+    let latest = util::task::spawn_blocking(move |_| {
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?;
+        let db = db.transaction().context("Creating database transaction")?;
+        db.block_id(BlockId::Latest)
+            .context("Fetching latest block")
+    })
+    .await
+    .context("Joining database task")??;
+
+    Ok(SyncStatus {
+        paused,
+        syncing,
+        latest,
+    })
+}
+
+async fn pause_sync(context: RpcContext) -> Result<SyncStatus, AdminError> {
+    let (reply, done) = oneshot::channel();
+    admin(&context)?
+        .sync
+        .send(SyncCommand::Pause { reply })
+        .await
+        .map_err(AdminError::Custom)?;
+    done.await
+        .context("Waiting for sync to pause")?
+        .map_err(AdminError::Custom)?;
+
+    sync_status(context).await
+}
+
+async fn resume_sync(context: RpcContext) -> Result<SyncStatus, AdminError> {
+    let (reply, done) = oneshot::channel();
+    admin(&context)?
+        .sync
+        .send(SyncCommand::Resume { reply })
+        .await
+        .map_err(AdminError::Custom)?;
+    done.await
+        .context("Waiting for sync to resume")?
+        .map_err(AdminError::Custom)?;
+
+    sync_status(context).await
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RevertToBlockInput {
+    block_number: BlockNumber,
+}
+
+impl crate::dto::DeserializeForVersion for RevertToBlockInput {
+    fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+        value.deserialize_map(|value| {
+            let block_number = value.deserialize::<u64>("block_number")?;
+            let block_number = BlockNumber::new(block_number).ok_or_else(|| {
+                serde_json::Error::custom(format!("Block number out of range: {block_number}"))
+            })?;
+            Ok(Self { block_number })
+        })
+    }
+}
+
+async fn revert_to_block(
+    context: RpcContext,
+    input: RevertToBlockInput,
+) -> Result<SyncStatus, AdminError> {
+    let (reply, done) = oneshot::channel();
+    admin(&context)?
+        .sync
+        .send(SyncCommand::Revert {
+            target: input.block_number,
+            reply,
+        })
+        .await
+        .map_err(AdminError::Custom)?;
+    done.await
+        .context("Waiting for revert to finish")?
+        .map_err(AdminError::Custom)?;
+
+    sync_status(context).await
+}
+
+/// The latest block at the time a maintenance operation was performed.
+#[derive(Debug, PartialEq)]
+pub struct MaintenanceOutput {
+    block_number: Option<BlockNumber>,
+}
+
+async fn prune_tries(context: RpcContext) -> Result<MaintenanceOutput, AdminError> {
+    let storage = admin(&context)?.storage.clone();
+    let output = util::task::spawn_blocking(move |_| {
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?
+            .with_retry()
+            .context("Enabling database retries")?;
+        let db = db
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("Creating database transaction")?;
+        let latest = db
+            .block_id(BlockId::Latest)
+            .context("Fetching latest block")?;
+        db.prune_tries().context("Pruning tries")?;
+        db.commit().context("Committing database transaction")?;
+
+        Ok(MaintenanceOutput {
+            block_number: latest.map(|x| x.0),
+        })
+    })
+    .await
+    .context("Joining database task")??;
+
+    Ok(output)
+}
+
+async fn rebuild_running_event_filter(
+    context: RpcContext,
+) -> Result<MaintenanceOutput, AdminError> {
+    let storage = admin(&context)?.storage.clone();
+    let output = util::task::spawn_blocking(move |_| {
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?
+            .with_retry()
+            .context("Enabling database retries")?;
+        // Holding the write lock keeps sync from adding blocks to the filter
+        // while it is being rebuilt.
+        let db = db
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("Creating database transaction")?;
+        let Some((head, _)) = db
+            .block_id(BlockId::Latest)
+            .context("Fetching latest block")?
+        else {
+            return Ok(MaintenanceOutput { block_number: None });
+        };
+        db.rebuild_running_event_filter(head)
+            .context("Rebuilding running event filter")?;
+
+        Ok(MaintenanceOutput {
+            block_number: Some(head),
+        })
+    })
+    .await
+    .context("Joining database task")??;
+
+    Ok(output)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PeersOutput(Vec<PeerInfo>);
+
+async fn peers(context: RpcContext) -> Result<PeersOutput, AdminError> {
+    let Some(peers) = admin(&context)?.peers.clone() else {
+        return Err(AdminError::Custom(anyhow::anyhow!(
+            "Not connected to the p2p network"
+        )));
+    };
+
+    Ok(PeersOutput(peers().await))
+}
+
+impl crate::dto::SerializeForVersion for SyncStatus {
+    fn serialize(
+        &self,
+        serializer: crate::dto::Serializer,
+    ) -> Result<crate::dto::Ok, crate::dto::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("paused", &self.paused)?;
+        serializer.serialize_field("syncing", &self.syncing)?;
+        serializer.serialize_optional_with_null("latest_block_number", self.latest.map(|x| x.0))?;
+        serializer.serialize_optional_with_null("latest_block_hash", self.latest.map(|x| x.1))?;
+        serializer.end()
+    }
+}
+
+impl crate::dto::SerializeForVersion for MaintenanceOutput {
+    fn serialize(
+        &self,
+        serializer: crate::dto::Serializer,
+    ) -> Result<crate::dto::Ok, crate::dto::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_optional_with_null("block_number", self.block_number)?;
+        serializer.end()
+    }
+}
+
+impl crate::dto::SerializeForVersion for PeersOutput {
+    fn serialize(
+        &self,
+        serializer: crate::dto::Serializer,
+    ) -> Result<crate::dto::Ok, crate::dto::Error> {
+        serializer.serialize_iter(self.0.len(), &mut self.0.iter())
+    }
+}
+
+impl crate::dto::SerializeForVersion for &PeerInfo {
+    fn serialize(
+        &self,
+        serializer: crate::dto::Serializer,
+    ) -> Result<crate::dto::Ok, crate::dto::Error> {
+        let mut serializer = serializer.serialize_struct()?;
+        serializer.serialize_field("peer_id", &self.peer_id)?;
+        serializer.serialize_optional_with_null("address", self.address.as_ref())?;
+        serializer.serialize_field("inbound", &self.inbound)?;
+        serializer.serialize_field("useful", &self.useful)?;
+        serializer.serialize_optional_with_null(
+            "min_ping_ms",
+            self.min_ping.map(|x| x.as_millis() as u64),
+        )?;
+        serializer.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use serde_json::json;
+
+    use super::*;
+
+    const TOKEN: &str = "secret";
+
+    fn context() -> (RpcContext, SyncCommands) {
+        let context = RpcContext::for_tests();
+        let (control, commands) = sync_control();
+        let admin = AdminContext::new(context.storage.clone(), control);
+        (context.with_admin(admin), commands)
+    }
+
+    async fn spawn_server(context: RpcContext) -> String {
+        let server = AdminServer::new("127.0.0.1:0".parse().unwrap(), context, TOKEN.to_owned());
+        let (_, addr) = server.spawn().await.unwrap();
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn requests_must_be_authenticated() {
+        let (context, _commands) = context();
+        let url = spawn_server(context).await;
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "admin_syncStatus"});
+        let client = reqwest::Client::new();
+
+        let response = client.post(&url).json(&request).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client
+            .post(&url)
+            .bearer_auth("wrong")
+            .json(&request)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client
+            .post(&url)
+            .bearer_auth(TOKEN)
+            .json(&request)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let response = response.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(response["result"]["paused"], json!(false));
+    }
+
+    #[tokio::test]
+    async fn pause_and_resume() {
+        let (context, mut commands) = context();
+        tokio::spawn(async move {
+            while let Some(command) = commands.recv().await {
+                match command {
+                    SyncCommand::Pause { reply } => {
+                        commands.set_paused(true);
+                        reply.send(Ok(())).unwrap();
+                    }
+                    SyncCommand::Resume { reply } => {
+                        commands.set_paused(false);
+                        reply.send(Ok(())).unwrap();
+                    }
+                    SyncCommand::Revert { .. } => unreachable!(),
+                }
+            }
+        });
+
+        let status = pause_sync(context.clone()).await.unwrap();
+        assert!(status.paused);
+        let status = resume_sync(context).await.unwrap();
+        assert!(!status.paused);
+    }
+
+    #[tokio::test]
+    async fn revert_to_block() {
+        let (context, mut commands) = context();
+        tokio::spawn(async move {
+            let Some(SyncCommand::Revert { target, reply }) = commands.recv().await else {
+                panic!("Expected a revert");
+            };
+            if target == BlockNumber::GENESIS {
+                reply.send(Ok(())).unwrap();
+            } else {
+                reply.send(Err(anyhow::anyhow!("Failed"))).unwrap();
+            }
+        });
+
+        let input = RevertToBlockInput {
+            block_number: BlockNumber::GENESIS,
+        };
+        let status = super::revert_to_block(context.clone(), input)
+            .await
+            .unwrap();
+        assert_eq!(
+            status.latest,
+            Some((BlockNumber::new_or_panic(2), block_hash_bytes!(b"latest")))
+        );
+
+        // The command loop above has exited.
+        let input = RevertToBlockInput {
+            block_number: BlockNumber::GENESIS,
+        };
+        let error = super::revert_to_block(context, input).await.unwrap_err();
+        assert_matches::assert_matches!(error, AdminError::Custom(_));
+    }
+
+    #[tokio::test]
+    async fn rejected_commands() {
+        let (context, commands) = context();
+        tokio::spawn(commands.reject("Not supported"));
+
+        let error = pause_sync(context.clone()).await.unwrap_err();
+        assert_matches::assert_matches!(error, AdminError::Custom(e) if e.to_string() == "Not supported");
+        let error = resume_sync(context.clone()).await.unwrap_err();
+        assert_matches::assert_matches!(error, AdminError::Custom(e) if e.to_string() == "Not supported");
+        let input = RevertToBlockInput {
+            block_number: BlockNumber::GENESIS,
+        };
+        let error = super::revert_to_block(context, input).await.unwrap_err();
+        assert_matches::assert_matches!(error, AdminError::Custom(e) if e.to_string() == "Not supported");
+    }
+
+    #[tokio::test]
+    async fn peers_without_p2p() {
+        let (context, _commands) = context();
+        let error = peers(context).await.unwrap_err();
+        assert_matches::assert_matches!(error, AdminError::Custom(_));
+    }
+
+    #[tokio::test]
+    async fn peers_with_p2p() {
+        let (context, _commands) = context();
+        let peer = PeerInfo {
+            peer_id: "peer".to_owned(),
+            address: None,
+            inbound: true,
+            useful: true,
+            min_ping: Some(Duration::from_millis(10)),
+        };
+        let admin = context.admin.clone().unwrap().with_peers({
+            let peer = peer.clone();
+            move || {
+                let peer = peer.clone();
+                Box::pin(async move { vec![peer] })
+            }
+        });
+        let context = context.with_admin(admin);
+
+        assert_eq!(peers(context).await.unwrap(), PeersOutput(vec![peer]));
+    }
+}
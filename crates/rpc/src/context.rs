@@ -1,5 +1,6 @@
 use std::num::NonZeroUsize;
 use std::sync::Arc;
+use std::time::Duration;
 
 use pathfinder_common::{contract_address, ChainId, ContractAddress};
 use pathfinder_ethereum::EthereumClient;
@@ -8,9 +9,14 @@ use pathfinder_storage::Storage;
 use primitive_types::H160;
 use util::percentage::Percentage;
 
+use crate::admin::AdminContext;
+use crate::historical_state::HistoricalState;
 pub use crate::jsonrpc::websocket::WebsocketContext;
 use crate::jsonrpc::Notifications;
+use crate::mempool::Mempool;
 use crate::pending::{PendingData, PendingWatcher};
+use crate::rate_limit::RateLimiter;
+use crate::response_cache::ResponseCache;
 use crate::SyncState;
 
 type SequencerClient = starknet_gateway_client::Client;
@@ -71,6 +77,31 @@ pub struct RpcConfig {
     pub versioned_constants_map: VersionedConstantsMap,
     pub native_execution: bool,
     pub native_class_cache_size: NonZeroUsize,
+    pub execution_deadlines: ExecutionDeadlines,
+}
+
+/// How long executing a method may take before it is aborted. Aborting the
+/// execution frees its blocking thread and database connection.
+#[derive(Copy, Clone, Debug)]
+pub struct ExecutionDeadlines {
+    pub call: Duration,
+    /// Applies to `starknet_estimateFee` and `starknet_estimateMessageFee`.
+    pub estimate_fee: Duration,
+    pub simulate: Duration,
+    /// Applies to `starknet_traceTransaction` and
+    /// `starknet_traceBlockTransactions`.
+    pub trace: Duration,
+}
+
+impl Default for ExecutionDeadlines {
+    fn default() -> Self {
+        Self {
+            call: Duration::from_secs(120),
+            estimate_fee: Duration::from_secs(120),
+            simulate: Duration::from_secs(120),
+            trace: Duration::from_secs(120),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -88,6 +119,11 @@ pub struct RpcContext {
     pub ethereum: EthereumClient,
     pub config: RpcConfig,
     pub native_class_cache: Option<NativeClassCache>,
+    pub mempool: Option<Mempool>,
+    pub historical_state: Option<HistoricalState>,
+    pub admin: Option<AdminContext>,
+    pub rate_limiter: Option<RateLimiter>,
+    pub response_cache: Option<ResponseCache>,
 }
 
 impl RpcContext {
@@ -124,6 +160,11 @@ impl RpcContext {
             ethereum,
             config,
             native_class_cache,
+            mempool: None,
+            historical_state: None,
+            admin: None,
+            rate_limiter: None,
+            response_cache: None,
         }
     }
 
@@ -150,6 +191,45 @@ impl RpcContext {
         }
     }
 
+    pub fn with_mempool(self, mempool: Mempool) -> Self {
+        Self {
+            mempool: Some(mempool),
+            ..self
+        }
+    }
+
+    pub fn with_trace_cache(self, cache: TraceCache) -> Self {
+        Self { cache, ..self }
+    }
+
+    pub fn with_historical_state(self, historical_state: HistoricalState) -> Self {
+        Self {
+            historical_state: Some(historical_state),
+            ..self
+        }
+    }
+
+    pub fn with_admin(self, admin: AdminContext) -> Self {
+        Self {
+            admin: Some(admin),
+            ..self
+        }
+    }
+
+    pub fn with_rate_limiter(self, rate_limiter: RateLimiter) -> Self {
+        Self {
+            rate_limiter: Some(rate_limiter),
+            ..self
+        }
+    }
+
+    pub fn with_response_cache(self, response_cache: ResponseCache) -> Self {
+        Self {
+            response_cache: Some(response_cache),
+            ..self
+        }
+    }
+
     #[cfg(test)]
     pub fn with_notifications(self, notifications: Notifications) -> Self {
         Self {
@@ -213,6 +293,7 @@ impl RpcContext {
             versioned_constants_map: Default::default(),
             native_execution: true,
             native_class_cache_size: NonZeroUsize::new(10).unwrap(),
+            execution_deadlines: Default::default(),
         };
 
         let ethereum =
@@ -104,6 +104,8 @@ pub enum ApplicationError {
     StorageProofNotSupported,
     #[error("Proof is missing")]
     ProofMissing,
+    #[error("Execution deadline exceeded")]
+    ExecutionDeadlineExceeded,
     #[error("Invalid subscription id")]
     InvalidSubscriptionID,
     #[error("Too many addresses in filter sender_address filter")]
@@ -165,6 +167,7 @@ impl ApplicationError {
             // specs/rpc/pathfinder_rpc_api.json
             ApplicationError::ProofLimitExceeded { .. } => 10000,
             ApplicationError::ProofMissing => 10001,
+            ApplicationError::ExecutionDeadlineExceeded => 10002,
             ApplicationError::SubscriptionTransactionHashNotFound { .. } => 10029,
             ApplicationError::SubscriptionGatewayDown { .. } => 10030,
             // specs/rpc/starknet_ws_api.json
@@ -291,6 +294,7 @@ impl ApplicationError {
             })),
             ApplicationError::StorageProofNotSupported => None,
             ApplicationError::ProofMissing => None,
+            ApplicationError::ExecutionDeadlineExceeded => None,
             ApplicationError::SubscriptionTransactionHashNotFound {
                 subscription_id,
                 transaction_hash,
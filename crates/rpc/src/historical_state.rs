@@ -0,0 +1,914 @@
+//! Reconstruction of historical state on nodes with blockchain history
+//! pruning.
+//!
+//! A pruned database only keeps the latest update of each storage slot, nonce
+//! and class hash made before the oldest complete block, so state queries for
+//! older blocks cannot be answered from the database alone. The value at a
+//! pruned block is however equal to the value at the oldest complete block,
+//! unless it was written in between. The state diffs of these blocks are
+//! fetched from the feeder gateway to check that, and if the value was written
+//! the diffs of the preceding blocks are searched for its last write.
+//!
+//! Executing on top of a pruned block works the same way, except that every
+//! value written between the block and the oldest complete block has to be
+//! reverted, see [StateOverlay].
+//!
+//! Fetched state diffs are kept in a bounded cache, since queries for the same
+//! historical range tend to come in bursts.
+use std::collections::HashSet;
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use cached::{Cached, SizedCache};
+use futures::{StreamExt, TryStreamExt};
+use pathfinder_common::{
+    BlockHeader,
+    BlockNumber,
+    ClassHash,
+    ContractAddress,
+    ContractNonce,
+    SequencerAddress,
+    StateUpdate,
+    StorageAddress,
+    StorageValue,
+};
+use pathfinder_executor::StateOverlay;
+use pathfinder_storage::pruning::BlockchainHistoryMode;
+use pathfinder_storage::{BlockId, Storage, Transaction};
+use starknet_gateway_client::GatewayApi;
+use starknet_gateway_types::error::{KnownStarknetErrorCode, SequencerError, StarknetError};
+use starknet_gateway_types::reply;
+
+/// The number of state diffs requested from the feeder gateway concurrently.
+const FETCH_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct HistoricalStateConfig {
+    /// Maximum number of state diffs kept in the cache.
+    pub cache_size: NonZeroUsize,
+    /// Maximum number of state diffs fetched to answer a single query.
+    pub max_blocks_to_fetch: NonZeroU64,
+}
+
+#[derive(Clone)]
+pub struct HistoricalState {
+    config: HistoricalStateConfig,
+    cache: Arc<Mutex<SizedCache<BlockNumber, Arc<StateUpdate>>>>,
+}
+
+/// The outcome of a historical state query.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Reconstructed<T> {
+    Value(T),
+    ContractNotFound,
+    /// The block has not been pruned, or is too old to be reconstructed within
+    /// the configured limits.
+    Unavailable,
+}
+
+/// Where the value at a pruned block comes from.
+#[derive(Debug, PartialEq, Eq)]
+enum Lookup<T> {
+    /// Last written at or before the block.
+    Written(T),
+    /// Not written between the block and the oldest complete block, so the
+    /// value stored for the latter applies.
+    Unchanged,
+    /// Never written up to and including the block.
+    Unset,
+}
+
+impl HistoricalState {
+    pub fn new(config: HistoricalStateConfig) -> Self {
+        Self {
+            config,
+            cache: Arc::new(Mutex::new(SizedCache::with_size(config.cache_size.get()))),
+        }
+    }
+
+    pub(crate) async fn storage_value(
+        &self,
+        storage: Storage,
+        sequencer: &impl GatewayApi,
+        block: pathfinder_common::BlockId,
+        contract: ContractAddress,
+        key: StorageAddress,
+    ) -> anyhow::Result<Reconstructed<StorageValue>> {
+        self.reconstruct(
+            storage,
+            sequencer,
+            block,
+            contract,
+            move |state_update| state_update.storage_value(contract, key),
+            move |tx, anchor| tx.storage_value(anchor.into(), contract, key),
+            Some(StorageValue::ZERO),
+        )
+        .await
+    }
+
+    pub(crate) async fn contract_nonce(
+        &self,
+        storage: Storage,
+        sequencer: &impl GatewayApi,
+        block: pathfinder_common::BlockId,
+        contract: ContractAddress,
+    ) -> anyhow::Result<Reconstructed<ContractNonce>> {
+        self.reconstruct(
+            storage,
+            sequencer,
+            block,
+            contract,
+            move |state_update| state_update.contract_nonce(contract),
+            move |tx, anchor| tx.contract_nonce(contract, anchor.into()),
+            // Early starknet contracts had no nonces, so its possible for a contract to
+            // exist without having the nonce explicitly set to zero on deployment.
+            Some(ContractNonce::ZERO),
+        )
+        .await
+    }
+
+    pub(crate) async fn contract_class_hash(
+        &self,
+        storage: Storage,
+        sequencer: &impl GatewayApi,
+        block: pathfinder_common::BlockId,
+        contract: ContractAddress,
+    ) -> anyhow::Result<Reconstructed<ClassHash>> {
+        self.reconstruct(
+            storage,
+            sequencer,
+            block,
+            contract,
+            move |state_update| state_update.contract_class(contract),
+            move |tx, anchor| tx.contract_class_hash(anchor.into(), contract),
+            None,
+        )
+        .await
+    }
+
+    /// Reconstructs a value of `contract` at the pruned `block`.
+    ///
+    /// `written` extracts the value from a state diff if it was written in it,
+    /// `stored` reads the value at the oldest complete block from the database
+    /// and `default` is the value of an existing contract that never had it
+    /// written.
+    #[allow(clippy::too_many_arguments)]
+    async fn reconstruct<T: Send + 'static>(
+        &self,
+        storage: Storage,
+        sequencer: &impl GatewayApi,
+        block: pathfinder_common::BlockId,
+        contract: ContractAddress,
+        written: impl Fn(&StateUpdate) -> Option<T> + Send + Sync,
+        stored: impl FnOnce(&Transaction<'_>, BlockNumber) -> anyhow::Result<Option<T>> + Send + 'static,
+        default: Option<T>,
+    ) -> anyhow::Result<Reconstructed<T>> {
+        let Some(block) = self.block_number(storage.clone(), sequencer, block).await? else {
+            return Ok(Reconstructed::Unavailable);
+        };
+
+        let max_blocks_to_fetch = self.config.max_blocks_to_fetch.get();
+        let span = tracing::Span::current();
+        let anchor = util::task::spawn_blocking({
+            let storage = storage.clone();
+            move |_| -> anyhow::Result<_> {
+                let _g = span.enter();
+                let mut db = storage
+                    .connection()
+                    .context("Opening database connection")?;
+                let tx = db.transaction().context("Creating database transaction")?;
+
+                let Some(anchor) = pruned_range_anchor(&tx, block, max_blocks_to_fetch)? else {
+                    return Ok(None);
+                };
+
+                // The deployment of a contract is never pruned, which saves searching the
+                // entire history for contracts that did not exist yet. System contracts
+                // are never deployed.
+                if !contract.is_system_contract()
+                    && !tx
+                        .contract_exists(contract, block.into())
+                        .context("Querying contract existence")?
+                {
+                    return Ok(Some(Err(Reconstructed::ContractNotFound)));
+                }
+
+                Ok(Some(Ok(anchor)))
+            }
+        })
+        .await
+        .context("Joining blocking task")??;
+
+        let anchor = match anchor {
+            None => return Ok(Reconstructed::Unavailable),
+            Some(Err(reconstructed)) => return Ok(reconstructed),
+            Some(Ok(anchor)) => anchor,
+        };
+
+        let Some(lookup) = self.lookup(sequencer, block, anchor, written).await? else {
+            return Ok(Reconstructed::Unavailable);
+        };
+
+        let span = tracing::Span::current();
+        util::task::spawn_blocking(move |_| {
+            let _g = span.enter();
+            let mut db = storage
+                .connection()
+                .context("Opening database connection")?;
+            let tx = db.transaction().context("Creating database transaction")?;
+
+            let value = match lookup {
+                Lookup::Written(value) => Some(value),
+                Lookup::Unchanged => stored(&tx, anchor).context("Querying stored value")?,
+                Lookup::Unset => None,
+            };
+            if let Some(value) = value {
+                return Ok(Reconstructed::Value(value));
+            }
+
+            let contract_exists = tx
+                .contract_exists(contract, block.into())
+                .context("Querying contract existence")?;
+            Ok(match default {
+                Some(default) if contract_exists => Reconstructed::Value(default),
+                _ => Reconstructed::ContractNotFound,
+            })
+        })
+        .await
+        .context("Joining blocking task")?
+    }
+
+    /// Searches the state diffs for the last write of a value at or before
+    /// `block`. Returns `None` if more state diffs would have to be fetched
+    /// than allowed.
+    async fn lookup<T>(
+        &self,
+        sequencer: &impl GatewayApi,
+        block: BlockNumber,
+        anchor: BlockNumber,
+        written: impl Fn(&StateUpdate) -> Option<T>,
+    ) -> anyhow::Result<Option<Lookup<T>>> {
+        let mut budget = self.config.max_blocks_to_fetch.get();
+
+        budget -= anchor.get() - block.get();
+        let after = block.get() + 1..=anchor.get();
+        let mut diffs = futures::stream::iter(after.map(BlockNumber::new_or_panic))
+            .map(|number| self.state_update(sequencer, number))
+            .buffered(FETCH_CONCURRENCY);
+        let mut written_after = false;
+        while let Some(state_update) = diffs.try_next().await? {
+            if written(&state_update).is_some() {
+                written_after = true;
+                break;
+            }
+        }
+        if !written_after {
+            return Ok(Some(Lookup::Unchanged));
+        }
+
+        let exhausted = block.get() + 1 > budget;
+        let before = (0..=block.get()).rev().take(budget as usize);
+        let mut diffs = futures::stream::iter(before)
+            .map(|number| self.state_update(sequencer, BlockNumber::new_or_panic(number)))
+            .buffered(FETCH_CONCURRENCY);
+        while let Some(state_update) = diffs.try_next().await? {
+            if let Some(value) = written(&state_update) {
+                return Ok(Some(Lookup::Written(value)));
+            }
+        }
+
+        if exhausted {
+            tracing::debug!(%block, "Historical state lookup exceeded the fetch limit");
+            Ok(None)
+        } else {
+            Ok(Some(Lookup::Unset))
+        }
+    }
+
+    /// Prepares executing on top of `block`, if it has been pruned.
+    ///
+    /// Returns `None` if the block has not been pruned, in which case it is
+    /// executed on the stored state, or if reverting the writes made after it
+    /// would take more state diffs than allowed.
+    pub(crate) async fn execution_state(
+        &self,
+        storage: Storage,
+        sequencer: &impl GatewayApi,
+        block: pathfinder_common::BlockId,
+    ) -> anyhow::Result<Option<(BlockHeader, StateOverlay)>> {
+        let Ok(block_id) = BlockId::try_from(block) else {
+            return Ok(None);
+        };
+        let span = tracing::Span::current();
+        let pruned = util::task::spawn_blocking({
+            let storage = storage.clone();
+            move |_| -> anyhow::Result<_> {
+                let _g = span.enter();
+                let mut db = storage
+                    .connection()
+                    .context("Opening database connection")?;
+                let tx = db.transaction().context("Creating database transaction")?;
+                tx.block_pruned(block_id)
+                    .context("Querying block pruned status")
+            }
+        })
+        .await
+        .context("Joining blocking task")??;
+        if !pruned {
+            return Ok(None);
+        }
+
+        let Some(block) = self.block_number(storage.clone(), sequencer, block).await? else {
+            return Ok(None);
+        };
+
+        let max_blocks_to_fetch = self.config.max_blocks_to_fetch.get();
+        let span = tracing::Span::current();
+        let stored = util::task::spawn_blocking(move |_| -> anyhow::Result<_> {
+            let _g = span.enter();
+            let mut db = storage
+                .connection()
+                .context("Opening database connection")?;
+            let tx = db.transaction().context("Creating database transaction")?;
+
+            let Some(anchor) = pruned_range_anchor(&tx, block, max_blocks_to_fetch)? else {
+                return Ok(None);
+            };
+            // Headers are only pruned once all state updates of the block have been
+            // superseded, so some of them are still around.
+            let header = tx
+                .block_header(block.into())
+                .context("Querying block header")?;
+            let old_block_hash = match block.checked_sub(10) {
+                Some(old_block) => tx
+                    .block_hash(old_block.into())
+                    .context("Querying block hash")?,
+                None => None,
+            };
+
+            Ok(Some((anchor, header, old_block_hash)))
+        })
+        .await
+        .context("Joining blocking task")??;
+        let Some((anchor, header, old_block_hash)) = stored else {
+            return Ok(None);
+        };
+
+        let header = match header {
+            Some(header) => header,
+            None => self.header(sequencer, block).await?,
+        };
+        let old_block_hash = match (old_block_hash, block.checked_sub(10)) {
+            (Some(hash), _) => Some(hash),
+            (None, Some(old_block)) => {
+                let (_, hash) = sequencer
+                    .block_header(old_block.into())
+                    .await
+                    .with_context(|| format!("Fetching block header for block {old_block}"))?;
+                Some(hash)
+            }
+            (None, None) => None,
+        };
+
+        let Some(reverted) = self.revert_after(sequencer, block, anchor).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some((
+            header,
+            StateOverlay {
+                base: anchor,
+                reverted: Arc::new(reverted),
+                old_block_hash,
+            },
+        )))
+    }
+
+    /// Collects the values at `block` of everything written after it, up to
+    /// and including `anchor`. Returns `None` if more state diffs would have
+    /// to be fetched than allowed.
+    async fn revert_after(
+        &self,
+        sequencer: &impl GatewayApi,
+        block: BlockNumber,
+        anchor: BlockNumber,
+    ) -> anyhow::Result<Option<StateUpdate>> {
+        let mut budget = self.config.max_blocks_to_fetch.get();
+
+        let mut storage = HashSet::new();
+        let mut nonces = HashSet::new();
+        let mut classes = HashSet::new();
+        budget -= anchor.get() - block.get();
+        let after = block.get() + 1..=anchor.get();
+        let mut diffs = futures::stream::iter(after.map(BlockNumber::new_or_panic))
+            .map(|number| self.state_update(sequencer, number))
+            .buffered(FETCH_CONCURRENCY);
+        while let Some(state_update) = diffs.try_next().await? {
+            for (contract, update) in &state_update.contract_updates {
+                storage.extend(update.storage.keys().map(|key| (*contract, *key)));
+                if update.nonce.is_some() {
+                    nonces.insert(*contract);
+                }
+                if update.class.is_some() {
+                    classes.insert(*contract);
+                }
+            }
+            for (contract, update) in &state_update.system_contract_updates {
+                storage.extend(update.storage.keys().map(|key| (*contract, *key)));
+            }
+        }
+
+        let mut reverted_storage = Vec::new();
+        let mut reverted_nonces = Vec::new();
+        let mut reverted_classes = Vec::new();
+        let exhausted = block.get() + 1 > budget;
+        let before = (0..=block.get()).rev().take(budget as usize);
+        let mut diffs = futures::stream::iter(before)
+            .map(|number| self.state_update(sequencer, BlockNumber::new_or_panic(number)))
+            .buffered(FETCH_CONCURRENCY);
+        while storage.len() + nonces.len() + classes.len() > 0 {
+            let Some(state_update) = diffs.try_next().await? else {
+                break;
+            };
+            storage.retain(|&(contract, key)| {
+                let value = state_update.storage_value(contract, key);
+                reverted_storage.extend(value.map(|value| (contract, key, value)));
+                value.is_none()
+            });
+            nonces.retain(|&contract| {
+                let nonce = state_update.contract_nonce(contract);
+                reverted_nonces.extend(nonce.map(|nonce| (contract, nonce)));
+                nonce.is_none()
+            });
+            classes.retain(|&contract| {
+                let class_hash = state_update.contract_class(contract);
+                reverted_classes.extend(class_hash.map(|class_hash| (contract, class_hash)));
+                class_hash.is_none()
+            });
+        }
+
+        let unresolved = storage.len() + nonces.len() + classes.len() > 0;
+        if unresolved && exhausted {
+            tracing::debug!(%block, "Historical state revert exceeded the fetch limit");
+            return Ok(None);
+        }
+
+        // Values which were never written before are reverted to their defaults,
+        // including the class hash of contracts which weren't deployed yet. Classes
+        // are reverted as replacements, since a deployment also resets the nonce
+        // and storage of the contract.
+        reverted_storage.extend(
+            storage
+                .into_iter()
+                .map(|(contract, key)| (contract, key, StorageValue::ZERO)),
+        );
+        reverted_nonces.extend(
+            nonces
+                .into_iter()
+                .map(|contract| (contract, ContractNonce::ZERO)),
+        );
+        reverted_classes.extend(
+            classes
+                .into_iter()
+                .map(|contract| (contract, ClassHash::ZERO)),
+        );
+
+        let mut reverted = StateUpdate::default();
+        for (contract, key, value) in reverted_storage {
+            reverted = if contract.is_system_contract() {
+                reverted.with_system_storage_update(contract, key, value)
+            } else {
+                reverted.with_storage_update(contract, key, value)
+            };
+        }
+        for (contract, nonce) in reverted_nonces {
+            reverted = reverted.with_contract_nonce(contract, nonce);
+        }
+        for (contract, class_hash) in reverted_classes {
+            reverted = reverted.with_replaced_class(contract, class_hash);
+        }
+
+        Ok(Some(reverted))
+    }
+
+    /// Resolves `block` to the number of a block which may have been pruned,
+    /// looking up hashes of pruned blocks on the feeder gateway.
+    async fn block_number(
+        &self,
+        storage: Storage,
+        sequencer: &impl GatewayApi,
+        block: pathfinder_common::BlockId,
+    ) -> anyhow::Result<Option<BlockNumber>> {
+        let hash = match block {
+            pathfinder_common::BlockId::Number(number) => return Ok(Some(number)),
+            pathfinder_common::BlockId::Hash(hash) => hash,
+            pathfinder_common::BlockId::Latest | pathfinder_common::BlockId::Pending => {
+                return Ok(None)
+            }
+        };
+
+        let span = tracing::Span::current();
+        let stored = util::task::spawn_blocking(move |_| -> anyhow::Result<_> {
+            let _g = span.enter();
+            let mut db = storage
+                .connection()
+                .context("Opening database connection")?;
+            let tx = db.transaction().context("Creating database transaction")?;
+            tx.block_number(hash.into())
+                .context("Querying block number")
+        })
+        .await
+        .context("Joining blocking task")??;
+        if stored.is_some() {
+            return Ok(stored);
+        }
+
+        match sequencer.block_header(hash.into()).await {
+            Ok((number, _)) => Ok(Some(number)),
+            Err(SequencerError::StarknetError(StarknetError { code, .. }))
+                if code == KnownStarknetErrorCode::BlockNotFound.into() =>
+            {
+                Ok(None)
+            }
+            Err(error) => Err(error).with_context(|| format!("Fetching block header for {hash}")),
+        }
+    }
+
+    /// Fetches the header of a pruned block, caching its state diff on the way.
+    async fn header(
+        &self,
+        sequencer: &impl GatewayApi,
+        block: BlockNumber,
+    ) -> anyhow::Result<BlockHeader> {
+        let (block, state_update) = sequencer
+            .state_update_with_block(block)
+            .await
+            .with_context(|| format!("Fetching block {block}"))?;
+        let header = header_from_block(&block, &state_update);
+        self.cache
+            .lock()
+            .unwrap()
+            .cache_set(block.block_number, Arc::new(state_update));
+
+        Ok(header)
+    }
+
+    async fn state_update(
+        &self,
+        sequencer: &impl GatewayApi,
+        block: BlockNumber,
+    ) -> anyhow::Result<Arc<StateUpdate>> {
+        let cached = self.cache.lock().unwrap().cache_get(&block).cloned();
+        if let Some(state_update) = cached {
+            return Ok(state_update);
+        }
+
+        let (_, state_update) = sequencer
+            .state_update_with_block(block)
+            .await
+            .with_context(|| format!("Fetching state update for block {block}"))?;
+        let state_update = Arc::new(state_update);
+        self.cache
+            .lock()
+            .unwrap()
+            .cache_set(block, state_update.clone());
+
+        Ok(state_update)
+    }
+}
+
+/// The oldest block whose state is completely stored in the database, or
+/// `None` if no block has been pruned.
+fn oldest_complete_block(tx: &Transaction<'_>) -> anyhow::Result<Option<BlockNumber>> {
+    let BlockchainHistoryMode::Prune { num_blocks_kept } = tx.blockchain_history_mode else {
+        return Ok(None);
+    };
+    let latest = tx
+        .block_number(BlockId::Latest)
+        .context("Querying latest block number")?;
+
+    Ok(latest
+        .and_then(|latest| latest.checked_sub(num_blocks_kept))
+        .filter(|anchor| anchor.get() > 0))
+}
+
+/// The oldest complete block, if `block` lies before it by no more than
+/// `max_blocks_to_fetch` blocks.
+fn pruned_range_anchor(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+    max_blocks_to_fetch: u64,
+) -> anyhow::Result<Option<BlockNumber>> {
+    Ok(oldest_complete_block(tx)?
+        .filter(|anchor| block < *anchor && anchor.get() - block.get() <= max_blocks_to_fetch))
+}
+
+/// The header of a block fetched from the feeder gateway. Only the fields used
+/// for execution matter, the commitments are taken as served.
+fn header_from_block(block: &reply::Block, state_update: &StateUpdate) -> BlockHeader {
+    BlockHeader {
+        hash: block.block_hash,
+        parent_hash: block.parent_block_hash,
+        number: block.block_number,
+        timestamp: block.timestamp,
+        eth_l1_gas_price: block.l1_gas_price.price_in_wei,
+        strk_l1_gas_price: block.l1_gas_price.price_in_fri,
+        eth_l1_data_gas_price: block.l1_data_gas_price.price_in_wei,
+        strk_l1_data_gas_price: block.l1_data_gas_price.price_in_fri,
+        eth_l2_gas_price: block.l2_gas_price.unwrap_or_default().price_in_wei,
+        strk_l2_gas_price: block.l2_gas_price.unwrap_or_default().price_in_fri,
+        sequencer_address: block.sequencer_address.unwrap_or(SequencerAddress::ZERO),
+        starknet_version: block.starknet_version,
+        event_commitment: block.event_commitment,
+        state_commitment: block.state_commitment,
+        transaction_commitment: block.transaction_commitment,
+        transaction_count: block.transactions.len(),
+        event_count: block
+            .transaction_receipts
+            .iter()
+            .map(|(_, events)| events.len())
+            .sum(),
+        l1_da_mode: block.l1_da_mode.into(),
+        receipt_commitment: block.receipt_commitment.unwrap_or_default(),
+        state_diff_commitment: block.state_diff_commitment.unwrap_or_default(),
+        state_diff_length: state_update.state_diff_length(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::BlockHash;
+    use pathfinder_storage::StorageBuilder;
+    use starknet_gateway_client::MockGatewayApi;
+
+    use super::*;
+
+    const CONTRACT: ContractAddress = contract_address!("0xc1");
+    const LATE_CONTRACT: ContractAddress = contract_address!("0xc2");
+    const REWRITTEN: StorageAddress = storage_address!("0x1");
+    const UNCHANGED: StorageAddress = storage_address!("0x2");
+    const UNSET: StorageAddress = storage_address!("0x3");
+
+    /// Blocks 0 to 5, where only the last two are kept.
+    fn setup() -> (Storage, MockGatewayApi) {
+        let state_updates = vec![
+            StateUpdate::default()
+                .with_deployed_contract(CONTRACT, class_hash!("0xa"))
+                .with_storage_update(CONTRACT, REWRITTEN, storage_value!("0x10"))
+                .with_storage_update(CONTRACT, UNCHANGED, storage_value!("0x20")),
+            StateUpdate::default(),
+            StateUpdate::default().with_contract_nonce(CONTRACT, contract_nonce!("0x1")),
+            StateUpdate::default()
+                .with_storage_update(CONTRACT, REWRITTEN, storage_value!("0x11"))
+                .with_deployed_contract(LATE_CONTRACT, class_hash!("0xb")),
+            StateUpdate::default().with_replaced_class(CONTRACT, class_hash!("0xaa")),
+            StateUpdate::default(),
+        ];
+
+        let storage = StorageBuilder::in_memory_with_blockchain_pruning_and_pool_size(
+            BlockchainHistoryMode::Prune { num_blocks_kept: 1 },
+            std::num::NonZeroU32::new(2).unwrap(),
+        )
+        .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        for (number, state_update) in state_updates.iter().enumerate() {
+            let header = BlockHeader::builder()
+                .number(BlockNumber::new_or_panic(number as u64))
+                .finalize_with_hash(BlockHash(pathfinder_crypto::Felt::from_u64(number as u64)));
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_state_update(header.number, state_update).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let mut sequencer = MockGatewayApi::new();
+        sequencer
+            .expect_state_update_with_block()
+            .returning(move |block| {
+                Ok((
+                    Default::default(),
+                    state_updates[block.get() as usize].clone(),
+                ))
+            });
+
+        (storage, sequencer)
+    }
+
+    fn historical_state(max_blocks_to_fetch: u64) -> HistoricalState {
+        HistoricalState::new(HistoricalStateConfig {
+            cache_size: NonZeroUsize::new(10).unwrap(),
+            max_blocks_to_fetch: NonZeroU64::new(max_blocks_to_fetch).unwrap(),
+        })
+    }
+
+    #[tokio::test]
+    async fn storage_value() {
+        let (storage, sequencer) = setup();
+        let state = historical_state(10);
+        let block = BlockNumber::new_or_panic(1);
+
+        for (key, expected) in [
+            (REWRITTEN, storage_value!("0x10")),
+            (UNCHANGED, storage_value!("0x20")),
+            (UNSET, StorageValue::ZERO),
+        ] {
+            let value = state
+                .storage_value(storage.clone(), &sequencer, block.into(), CONTRACT, key)
+                .await
+                .unwrap();
+            assert_eq!(value, Reconstructed::Value(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn contract_nonce() {
+        let (storage, sequencer) = setup();
+        let state = historical_state(10);
+
+        let nonce = state
+            .contract_nonce(
+                storage.clone(),
+                &sequencer,
+                BlockNumber::new_or_panic(1).into(),
+                CONTRACT,
+            )
+            .await
+            .unwrap();
+        assert_eq!(nonce, Reconstructed::Value(ContractNonce::ZERO));
+
+        let nonce = state
+            .contract_nonce(
+                storage,
+                &sequencer,
+                BlockNumber::new_or_panic(2).into(),
+                CONTRACT,
+            )
+            .await
+            .unwrap();
+        assert_eq!(nonce, Reconstructed::Value(contract_nonce!("0x1")));
+    }
+
+    #[tokio::test]
+    async fn contract_class_hash() {
+        let (storage, sequencer) = setup();
+        let state = historical_state(10);
+        let block = BlockNumber::new_or_panic(2);
+
+        let class_hash = state
+            .contract_class_hash(storage.clone(), &sequencer, block.into(), CONTRACT)
+            .await
+            .unwrap();
+        assert_eq!(class_hash, Reconstructed::Value(class_hash!("0xa")));
+
+        let class_hash = state
+            .contract_class_hash(storage, &sequencer, block.into(), LATE_CONTRACT)
+            .await
+            .unwrap();
+        assert_eq!(class_hash, Reconstructed::ContractNotFound);
+    }
+
+    #[tokio::test]
+    async fn complete_blocks_are_not_reconstructed() {
+        let (storage, sequencer) = setup();
+        let state = historical_state(10);
+
+        let value = state
+            .storage_value(
+                storage,
+                &sequencer,
+                BlockNumber::new_or_panic(4).into(),
+                CONTRACT,
+                REWRITTEN,
+            )
+            .await
+            .unwrap();
+        assert_eq!(value, Reconstructed::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn fetch_limit() {
+        let (storage, sequencer) = setup();
+        let block = BlockNumber::new_or_panic(1);
+
+        // Blocks 2 to 4 are enough to tell the value is unchanged.
+        let value = historical_state(3)
+            .storage_value(
+                storage.clone(),
+                &sequencer,
+                block.into(),
+                CONTRACT,
+                UNCHANGED,
+            )
+            .await
+            .unwrap();
+        assert_eq!(value, Reconstructed::Value(storage_value!("0x20")));
+
+        // Finding the last write also needs blocks 1 and 0.
+        let value = historical_state(4)
+            .storage_value(storage, &sequencer, block.into(), CONTRACT, REWRITTEN)
+            .await
+            .unwrap();
+        assert_eq!(value, Reconstructed::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn block_hash() {
+        let (storage, mut sequencer) = setup();
+        sequencer
+            .expect_block_header()
+            .returning(|block| match block {
+                pathfinder_common::BlockId::Hash(hash) if hash == block_hash!("0x100") => {
+                    Ok((BlockNumber::new_or_panic(1), hash))
+                }
+                _ => Err(SequencerError::StarknetError(StarknetError {
+                    code: KnownStarknetErrorCode::BlockNotFound.into(),
+                    message: String::new(),
+                })),
+            });
+        let state = historical_state(10);
+
+        // Stored locally.
+        let value = state
+            .storage_value(
+                storage.clone(),
+                &sequencer,
+                BlockHash(pathfinder_crypto::Felt::from_u64(1)).into(),
+                CONTRACT,
+                REWRITTEN,
+            )
+            .await
+            .unwrap();
+        assert_eq!(value, Reconstructed::Value(storage_value!("0x10")));
+
+        // Header pruned, resolved by the feeder gateway.
+        let value = state
+            .storage_value(
+                storage.clone(),
+                &sequencer,
+                block_hash!("0x100").into(),
+                CONTRACT,
+                REWRITTEN,
+            )
+            .await
+            .unwrap();
+        assert_eq!(value, Reconstructed::Value(storage_value!("0x10")));
+
+        let value = state
+            .storage_value(
+                storage,
+                &sequencer,
+                block_hash!("0x200").into(),
+                CONTRACT,
+                REWRITTEN,
+            )
+            .await
+            .unwrap();
+        assert_eq!(value, Reconstructed::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn execution_state() {
+        let (storage, sequencer) = setup();
+        let state = historical_state(10);
+
+        let (header, overlay) = state
+            .execution_state(
+                storage.clone(),
+                &sequencer,
+                BlockNumber::new_or_panic(1).into(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(header.number, BlockNumber::new_or_panic(1));
+        assert_eq!(overlay.base, BlockNumber::new_or_panic(4));
+        assert_eq!(overlay.old_block_hash, None);
+        let expected = StateUpdate::default()
+            .with_storage_update(CONTRACT, REWRITTEN, storage_value!("0x10"))
+            .with_contract_nonce(CONTRACT, ContractNonce::ZERO)
+            .with_replaced_class(CONTRACT, class_hash!("0xa"))
+            .with_replaced_class(LATE_CONTRACT, ClassHash::ZERO);
+        assert_eq!(*overlay.reverted, expected);
+
+        // Blocks which are not pruned are executed on the stored state.
+        let execution_state = state
+            .execution_state(
+                storage.clone(),
+                &sequencer,
+                BlockNumber::new_or_panic(4).into(),
+            )
+            .await
+            .unwrap();
+        assert!(execution_state.is_none());
+
+        // Blocks 2 to 4 are enough to tell what was written, but not what to
+        // revert it to.
+        let execution_state = historical_state(3)
+            .execution_state(storage, &sequencer, BlockNumber::new_or_panic(1).into())
+            .await
+            .unwrap();
+        assert!(execution_state.is_none());
+    }
+}
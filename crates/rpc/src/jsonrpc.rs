@@ -44,6 +44,8 @@ pub struct Notifications {
     pub block_headers: broadcast::Sender<Arc<pathfinder_common::BlockHeader>>,
     pub l2_blocks: broadcast::Sender<Arc<Block>>,
     pub reorgs: broadcast::Sender<Arc<Reorg>>,
+    /// The number of the latest block accepted on L1.
+    pub l1_updates: broadcast::Sender<BlockNumber>,
 }
 
 #[derive(Debug, Clone)]
@@ -59,10 +61,12 @@ impl Default for Notifications {
         let (block_headers, _) = broadcast::channel(1024);
         let (l2_blocks, _) = broadcast::channel(1024);
         let (reorgs, _) = broadcast::channel(1024);
+        let (l1_updates, _) = broadcast::channel(1024);
         Self {
             block_headers,
             l2_blocks,
             reorgs,
+            l1_updates,
         }
     }
 }
@@ -11,6 +11,8 @@ pub enum RpcError {
     MethodNotFound,
     InvalidParams(String),
     InternalError(anyhow::Error),
+    /// The client's rate limit was exceeded.
+    LimitExceeded,
     ApplicationError(crate::error::ApplicationError),
     WebsocketSubscriptionClosed {
         subscription_id: u32,
@@ -36,6 +38,8 @@ impl RpcError {
             RpcError::MethodNotFound => -32601,
             RpcError::InvalidParams(..) => -32602,
             RpcError::InternalError(_) => -32603,
+            // Not part of the JSON-RPC specification, but commonly used by Ethereum clients.
+            RpcError::LimitExceeded => -32005,
             RpcError::ApplicationError(err) => err.code(version),
             RpcError::WebsocketSubscriptionClosed { .. } => -32099,
         }
@@ -48,6 +52,7 @@ impl RpcError {
             RpcError::MethodNotFound => "Method not found".into(),
             RpcError::InvalidParams(..) => "Invalid params".into(),
             RpcError::InternalError(_) => "Internal error".into(),
+            RpcError::LimitExceeded => "Limit exceeded".into(),
             RpcError::ApplicationError(e) => e.message(version).into(),
             RpcError::WebsocketSubscriptionClosed { .. } => "Websocket subscription closed".into(),
         }
@@ -65,6 +70,7 @@ impl RpcError {
             RpcError::ApplicationError(e) => e.data(version),
             RpcError::InternalError(_) => None,
             RpcError::MethodNotFound => None,
+            RpcError::LimitExceeded => None,
             RpcError::ParseError(e) | RpcError::InvalidRequest(e) | RpcError::InvalidParams(e) => {
                 Some(json!({
                     "reason": e
@@ -39,6 +39,14 @@ impl RpcResponse {
         }
     }
 
+    pub const fn limit_exceeded(id: RequestId, version: RpcVersion) -> RpcResponse {
+        Self {
+            output: Err(RpcError::LimitExceeded),
+            id,
+            version,
+        }
+    }
+
     pub const fn invalid_params(id: RequestId, error: String, version: RpcVersion) -> RpcResponse {
         Self {
             output: Err(RpcError::InvalidParams(error)),
@@ -1,7 +1,8 @@
 use std::collections::HashMap;
-use std::num::NonZeroUsize;
+use std::net::SocketAddr;
+use std::num::{NonZeroU32, NonZeroUsize};
 
-use axum::extract::{State, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use futures::{Future, FutureExt, StreamExt};
@@ -15,6 +16,9 @@ use crate::context::RpcContext;
 use crate::jsonrpc::error::RpcError;
 use crate::jsonrpc::request::RpcRequest;
 use crate::jsonrpc::response::RpcResponse;
+use crate::method_filter::MethodFilter;
+use crate::rate_limit::Client;
+use crate::response_cache::ResponseCache;
 use crate::RpcVersion;
 
 mod method;
@@ -27,12 +31,16 @@ pub struct RpcRouter {
     pub context: RpcContext,
     method_endpoints: &'static HashMap<&'static str, Box<dyn RpcMethodEndpoint>>,
     subscription_endpoints: &'static HashMap<&'static str, Box<dyn RpcSubscriptionEndpoint>>,
+    method_costs: &'static HashMap<&'static str, NonZeroU32>,
     pub version: RpcVersion,
+    /// The rate limited client the request is being served for.
+    client: Option<Client>,
 }
 
 pub struct RpcRouterBuilder {
     method_endpoints: HashMap<&'static str, Box<dyn RpcMethodEndpoint>>,
     subscription_endpoints: HashMap<&'static str, Box<dyn RpcSubscriptionEndpoint>>,
+    method_costs: HashMap<&'static str, NonZeroU32>,
     version: RpcVersion,
 }
 
@@ -67,6 +75,34 @@ impl RpcRouterBuilder {
                 }
             }
         }
+        self.method_costs.insert(
+            method_name,
+            crate::rate_limit::default_method_cost(method_name),
+        );
+        self
+    }
+
+    /// Sets the number of rate limiting tokens a call of the method costs. For
+    /// subscriptions, this is the cost of starting the subscription.
+    ///
+    /// Panics if the method is not registered.
+    pub fn with_cost(mut self, method_name: &'static str, cost: NonZeroU32) -> Self {
+        match self.method_costs.get_mut(method_name) {
+            Some(method_cost) => *method_cost = cost,
+            None => panic!("'{method_name}' is not registered"),
+        }
+        self
+    }
+
+    /// Removes the methods and subscriptions disabled by the filter, so that
+    /// calling them fails with a `Method not found` error.
+    pub fn with_method_filter(mut self, filter: &MethodFilter) -> Self {
+        self.method_endpoints
+            .retain(|method_name, _| filter.is_enabled(method_name));
+        self.subscription_endpoints
+            .retain(|method_name, _| filter.is_enabled(method_name));
+        self.method_costs
+            .retain(|method_name, _| filter.is_enabled(method_name));
         self
     }
 
@@ -77,11 +113,15 @@ impl RpcRouterBuilder {
         let methods = Box::leak(methods);
         let subscriptions = Box::new(self.subscription_endpoints);
         let subscriptions = Box::leak(subscriptions);
+        let method_costs = Box::new(self.method_costs);
+        let method_costs = Box::leak(method_costs);
         RpcRouter {
             context,
             method_endpoints: methods,
             subscription_endpoints: subscriptions,
+            method_costs,
             version: self.version,
+            client: None,
         }
     }
 
@@ -89,6 +129,7 @@ impl RpcRouterBuilder {
         RpcRouterBuilder {
             method_endpoints: Default::default(),
             subscription_endpoints: Default::default(),
+            method_costs: Default::default(),
             version,
         }
     }
@@ -99,6 +140,30 @@ impl RpcRouter {
         RpcRouterBuilder::new(version)
     }
 
+    /// Identifies the client the request is served for, if rate limiting is
+    /// enabled.
+    pub(crate) fn for_client(
+        self,
+        connect_info: Option<ConnectInfo<SocketAddr>>,
+        headers: &http::HeaderMap,
+    ) -> Self {
+        let client = self.context.rate_limiter.as_ref().and_then(|rate_limiter| {
+            rate_limiter.client(connect_info.map(|ConnectInfo(addr)| addr.ip()), headers)
+        });
+        Self { client, ..self }
+    }
+
+    /// Takes the cost of calling the method from the client's rate limiting
+    /// bucket. Returns `false` if the client exceeded its limit.
+    pub(super) fn try_acquire(&self, method_name: &'static str) -> bool {
+        match (&self.context.rate_limiter, &self.client) {
+            (Some(rate_limiter), Some(client)) => {
+                rate_limiter.try_acquire(client, self.method_costs[method_name], method_name)
+            }
+            _ => true,
+        }
+    }
+
     /// Parses and executes a request. Returns [None] if its a notification.
     async fn run_request(&self, request: &str) -> Option<RpcResponse> {
         tracing::trace!(%request, "Running request");
@@ -123,8 +188,34 @@ impl RpcRouter {
             return Some(RpcResponse::method_not_found(request.id, self.version));
         };
 
+        if !self.try_acquire(method_name) {
+            return Some(RpcResponse::limit_exceeded(request.id, self.version));
+        }
+
         metrics::increment_counter!("rpc_method_calls_total", "method" => method_name, "version" => self.version.to_str());
 
+        let cache_key = self
+            .context
+            .response_cache
+            .as_ref()
+            .and_then(|_| ResponseCache::key(self.version, method_name, request.params.0));
+        if let (Some(cache), Some(key)) = (&self.context.response_cache, &cache_key) {
+            if let Some(output) = cache.get(key) {
+                return Some(RpcResponse {
+                    output: Ok(output),
+                    id: request.id,
+                    version: self.version,
+                });
+            }
+        }
+
+        // The response is outdated if the cache is cleared while computing it.
+        let cache_generation = self
+            .context
+            .response_cache
+            .as_ref()
+            .map(ResponseCache::generation);
+
         let method = method
             .invoke(self.context.clone(), request.params, self.version)
             .instrument(tracing::debug_span!("rpc_call", method=%method_name));
@@ -144,6 +235,15 @@ impl RpcRouter {
             metrics::increment_counter!("rpc_method_calls_failed_total", "method" => method_name, "version" => self.version.to_str());
         }
 
+        if let (Some(cache), Some(key), Some(generation), Ok(output)) = (
+            &self.context.response_cache,
+            cache_key,
+            cache_generation,
+            &output,
+        ) {
+            cache.insert(key, output, generation);
+        }
+
         Some(RpcResponse {
             output,
             id: request.id,
@@ -184,11 +284,13 @@ fn is_utf8_encoded_json(headers: http::HeaderMap) -> bool {
 #[axum::debug_handler]
 pub async fn rpc_handler(
     State(state): State<RpcRouter>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     headers: http::HeaderMap,
     method: http::Method,
     ws: Option<WebSocketUpgrade>,
     body: axum::body::Bytes,
 ) -> impl axum::response::IntoResponse {
+    let state = state.for_client(connect_info, &headers);
     match ws {
         Some(ws) => ws.on_upgrade(|ws| async move {
             let (ws_tx, ws_rx) = split_ws(ws, state.version);
@@ -393,7 +495,11 @@ mod tests {
             let router = axum::Router::new()
                 .route("/", axum::routing::post(rpc_handler).get(rpc_handler))
                 .with_state(router);
-            axum::serve(listener, router.into_make_service()).await
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            )
+            .await
         });
 
         url
@@ -667,6 +773,168 @@ mod tests {
         }
     }
 
+    mod rate_limiting {
+        use std::collections::HashSet;
+
+        use super::*;
+        use crate::rate_limit::{BucketConfig, RateLimitConfig, RateLimiter};
+
+        fn limited_router() -> RpcRouter {
+            fn cheap() -> &'static str {
+                "Cheap"
+            }
+
+            fn expensive() -> &'static str {
+                "Expensive"
+            }
+
+            let bucket = BucketConfig {
+                capacity: NonZeroU32::new(6).unwrap(),
+                refill_rate: NonZeroU32::new(1).unwrap(),
+            };
+            let rate_limiter = RateLimiter::new(RateLimitConfig {
+                ip: bucket,
+                api_key: bucket,
+                api_keys: HashSet::new(),
+            });
+
+            RpcRouter::builder(Default::default())
+                .register("cheap", cheap)
+                .register("expensive", expensive)
+                .with_cost("expensive", NonZeroU32::new(5).unwrap())
+                .build(RpcContext::for_tests().with_rate_limiter(rate_limiter))
+        }
+
+        #[tokio::test]
+        async fn calls_are_limited_by_cost() {
+            let router = limited_router();
+            let expensive = json!({"jsonrpc": "2.0", "method": "expensive", "id": 1});
+            let cheap = json!({"jsonrpc": "2.0", "method": "cheap", "id": 1});
+
+            let response = serve_and_query(router.clone(), expensive.clone()).await;
+            let expected = json!({"jsonrpc": "2.0", "result": "Expensive", "id": 1});
+            assert_eq!(response, expected);
+
+            let response = serve_and_query(router.clone(), expensive).await;
+            let expected = json!({"jsonrpc": "2.0", "error": {"code": -32005, "message": "Limit exceeded"}, "id": 1});
+            assert_eq!(response, expected);
+
+            // The remaining token still covers a cheap call.
+            let response = serve_and_query(router, cheap).await;
+            let expected = json!({"jsonrpc": "2.0", "result": "Cheap", "id": 1});
+            assert_eq!(response, expected);
+        }
+
+        #[test]
+        #[should_panic(expected = "'missing' is not registered")]
+        fn cost_of_unregistered_method() {
+            let _ = RpcRouter::builder(Default::default())
+                .with_cost("missing", NonZeroU32::new(5).unwrap());
+        }
+    }
+
+    mod response_cache {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use super::*;
+        use crate::dto::DeserializeForVersion;
+        use crate::jsonrpc::Notifications;
+
+        #[tokio::test]
+        async fn immutable_responses_are_cached() {
+            crate::error::generate_rpc_error_subset!(ExampleError:);
+
+            static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+            struct Input;
+
+            impl DeserializeForVersion for Input {
+                fn deserialize(value: crate::dto::Value) -> Result<Self, serde_json::Error> {
+                    value.deserialize_map(|value| {
+                        let _: Value = value.deserialize_serde("block_id")?;
+                        Ok(Self)
+                    })
+                }
+            }
+
+            async fn get_state_update(_input: Input) -> Result<Value, ExampleError> {
+                let calls = CALLS.fetch_add(1, Ordering::Relaxed) + 1;
+                Ok(json!({ "calls": calls }))
+            }
+
+            let cache =
+                ResponseCache::new(NonZeroUsize::new(10).unwrap(), &Notifications::default());
+            let router = RpcRouter::builder(Default::default())
+                .register("starknet_getStateUpdate", get_state_update)
+                .build(RpcContext::for_tests().with_response_cache(cache));
+
+            let pinned = json!({
+                "jsonrpc": "2.0",
+                "method": "starknet_getStateUpdate",
+                "params": {"block_id": {"block_number": 1}},
+                "id": 1
+            });
+            for _ in 0..2 {
+                let response = serve_and_query(router.clone(), pinned.clone()).await;
+                let expected = json!({"jsonrpc": "2.0", "result": {"calls": 1}, "id": 1});
+                assert_eq!(response, expected);
+            }
+
+            let latest = json!({
+                "jsonrpc": "2.0",
+                "method": "starknet_getStateUpdate",
+                "params": {"block_id": "latest"},
+                "id": 1
+            });
+            for calls in [2, 3] {
+                let response = serve_and_query(router.clone(), latest.clone()).await;
+                let expected = json!({"jsonrpc": "2.0", "result": {"calls": calls}, "id": 1});
+                assert_eq!(response, expected);
+            }
+        }
+    }
+
+    mod method_filter {
+        use super::*;
+
+        #[tokio::test]
+        async fn disabled_methods_are_not_found() {
+            fn read() -> &'static str {
+                "Read"
+            }
+
+            fn write() -> &'static str {
+                "Write"
+            }
+
+            let filter = MethodFilter {
+                allow: vec![],
+                deny: vec!["write*".to_owned()],
+            };
+            let router = RpcRouter::builder(Default::default())
+                .register("read", read)
+                .register("write", write)
+                .with_method_filter(&filter)
+                .build(RpcContext::for_tests());
+
+            let response = serve_and_query(
+                router.clone(),
+                json!({"jsonrpc": "2.0", "method": "read", "id": 1}),
+            )
+            .await;
+            let expected = json!({"jsonrpc": "2.0", "result": "Read", "id": 1});
+            assert_eq!(response, expected);
+
+            let response = serve_and_query(
+                router,
+                json!({"jsonrpc": "2.0", "method": "write", "id": 1}),
+            )
+            .await;
+            let expected = json!({"jsonrpc": "2.0", "error": {"code": -32601, "message": "Method not found"}, "id": 1});
+            assert_eq!(response, expected);
+        }
+    }
+
     mod panic_handling {
         use super::*;
 
@@ -656,6 +656,11 @@ async fn handle_request(
         .subscription_endpoints
         .get_key_value(rpc_request.method.as_ref())
         .ok_or_else(|| RpcResponse::method_not_found(req_id.clone(), state.version))?;
+
+    if !state.try_acquire(method_name) {
+        return Err(RpcResponse::limit_exceeded(req_id, state.version));
+    }
+
     metrics::increment_counter!("rpc_method_calls_total", "method" => method_name, "version" => state.version.to_str());
 
     let params = serde_json::to_value(rpc_request.params)
@@ -819,8 +824,13 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+    use std::net::SocketAddr;
+    use std::num::NonZeroU32;
+
     use axum::async_trait;
     use axum::extract::ws::Message;
+    use axum::extract::ConnectInfo;
     use pathfinder_common::{BlockHash, BlockHeader, BlockNumber};
     use pathfinder_crypto::Felt;
     use pathfinder_storage::StorageBuilder;
@@ -836,6 +846,7 @@ mod tests {
         RpcSubscriptionFlow,
         SubscriptionMessage,
     };
+    use crate::rate_limit::{BucketConfig, RateLimitConfig, RateLimiter};
     use crate::types::request::SubscriptionBlockId;
     use crate::Notifications;
 
@@ -993,6 +1004,90 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn starting_subscriptions_is_rate_limited() {
+        struct Idle;
+
+        #[async_trait]
+        impl RpcSubscriptionFlow for Idle {
+            type Params = Params;
+            type Notification = serde_json::Value;
+
+            fn starting_block(_params: &Self::Params) -> SubscriptionBlockId {
+                SubscriptionBlockId::Latest
+            }
+
+            async fn catch_up(
+                _state: &RpcContext,
+                _params: &Self::Params,
+                _from: BlockNumber,
+                _to: BlockNumber,
+            ) -> Result<CatchUp<Self::Notification>, crate::jsonrpc::RpcError> {
+                Ok(Default::default())
+            }
+
+            async fn subscribe(
+                _state: RpcContext,
+                _params: Self::Params,
+                _tx: tokio::sync::mpsc::Sender<SubscriptionMessage<Self::Notification>>,
+            ) -> Result<(), crate::jsonrpc::RpcError> {
+                std::future::pending().await
+            }
+        }
+
+        let bucket = BucketConfig {
+            capacity: NonZeroU32::new(1).unwrap(),
+            refill_rate: NonZeroU32::new(1).unwrap(),
+        };
+        let rate_limiter = RateLimiter::new(RateLimitConfig {
+            ip: bucket,
+            api_key: bucket,
+            api_keys: HashSet::new(),
+        });
+        let mut router = setup(5, Idle).await;
+        router.context = router.context.with_rate_limiter(rate_limiter);
+        let router = router.for_client(
+            Some(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0)))),
+            &http::HeaderMap::new(),
+        );
+
+        let (sender_tx, mut sender_rx) = mpsc::channel(1024);
+        let (receiver_tx, receiver_rx) = mpsc::channel(1024);
+        handle_json_rpc_socket(router, sender_tx, receiver_rx);
+
+        let mut responses = Vec::new();
+        for id in 1..=2 {
+            receiver_tx
+                .send(Ok(Message::Text(
+                    serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "method": "test",
+                        "params": {}
+                    })
+                    .to_string(),
+                )))
+                .await
+                .unwrap();
+            match sender_rx.recv().await.unwrap().unwrap() {
+                Message::Text(json) => {
+                    responses.push(serde_json::from_str::<serde_json::Value>(&json).unwrap())
+                }
+                _ => panic!("Expected text message"),
+            }
+        }
+
+        assert!(responses[0]["result"].is_string());
+        assert_eq!(
+            responses[1],
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "error": { "code": -32005, "message": "Limit exceeded" },
+                "id": 2
+            })
+        );
+    }
+
     #[derive(Debug, Clone)]
     struct Params;
 
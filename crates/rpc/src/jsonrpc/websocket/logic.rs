@@ -2,13 +2,14 @@
 
 use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::net::SocketAddr;
 use std::num::NonZeroUsize;
 use std::ops::ControlFlow;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use axum::extract::ws::{Message, WebSocket};
-use axum::extract::{State, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
 use axum::response::IntoResponse;
 use futures::sink::Buffer;
 use futures::stream::{SplitSink, SplitStream};
@@ -43,6 +44,8 @@ const UNSUBSCRIBE_METHOD: &str = "pathfinder_unsubscribe";
 #[derive(Clone)]
 pub struct WebsocketContext {
     socket_buffer_capacity: NonZeroUsize,
+    /// Set by the server to its maximum request size.
+    pub(crate) max_message_size: usize,
     pub broadcasters: TopicBroadcasters,
 }
 
@@ -54,6 +57,7 @@ impl WebsocketContext {
     ) -> Self {
         Self {
             socket_buffer_capacity,
+            max_message_size: crate::DEFAULT_REQUEST_MAX_SIZE,
             broadcasters: TopicBroadcasters::new(topic_sender_capacity, pending_data),
         }
     }
@@ -62,9 +66,19 @@ impl WebsocketContext {
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(router): State<RpcRouter>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: http::HeaderMap,
 ) -> impl IntoResponse {
+    let router = router.for_client(connect_info, &headers);
+    let max_message_size = router
+        .context
+        .websocket
+        .as_ref()
+        .map_or(crate::DEFAULT_REQUEST_MAX_SIZE, |websocket| {
+            websocket.max_message_size
+        });
     let mut upgrade_response = ws
-        .max_message_size(crate::REQUEST_MAX_SIZE)
+        .max_message_size(max_message_size)
         .on_failed_upgrade(|error| tracing::debug!(%error, "Websocket upgrade failed"))
         .on_upgrade(|socket| handle_socket(socket, router));
 
@@ -1,14 +1,20 @@
 //! Starknet node JSON-RPC related modules.
+pub mod admin;
 pub mod context;
 mod dto;
 mod error;
 mod executor;
 mod felt;
+pub mod historical_state;
 mod jsonrpc;
+pub mod mempool;
 pub(crate) mod method;
+pub mod method_filter;
 pub mod middleware;
 mod pathfinder;
 mod pending;
+pub mod rate_limit;
+pub mod response_cache;
 #[cfg(test)]
 mod test_setup;
 pub mod types;
@@ -24,7 +30,10 @@ use axum::error_handling::HandleErrorLayer;
 use axum::extract::DefaultBodyLimit;
 use axum::response::IntoResponse;
 use context::RpcContext;
-pub use executor::compose_executor_transaction;
+pub use executor::{
+    compose_executor_transaction,
+    VERSIONS_LOWER_THAN_THIS_SHOULD_FALL_BACK_TO_FETCHING_TRACE_FROM_GATEWAY,
+};
 use http_body::Body;
 pub use jsonrpc::{Notifications, Reorg};
 use pathfinder_common::AllowedOrigins;
@@ -41,7 +50,7 @@ use crate::types::syncing::Syncing;
 
 const DEFAULT_MAX_CONNECTIONS: usize = 1024;
 
-#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd)]
 pub enum RpcVersion {
     V06,
     #[default]
@@ -59,18 +68,35 @@ impl RpcVersion {
             RpcVersion::PathfinderV01 => "v0.1",
         }
     }
+
+    /// The version served on `path`, where `/` and `/ws` serve the `default`
+    /// version. Returns [None] if `path` doesn't serve any version.
+    pub(crate) fn from_path(path: &str, default: RpcVersion) -> Option<RpcVersion> {
+        // Websocket paths mirror the HTTP paths.
+        let path = path.strip_prefix("/ws").unwrap_or(path);
+        match path {
+            "" | "/" => Some(default),
+            "/rpc/v0_6" => Some(RpcVersion::V06),
+            "/rpc/v0_7" => Some(RpcVersion::V07),
+            "/rpc/v0_8" => Some(RpcVersion::V08),
+            "/rpc/pathfinder/v0.1" | "/rpc/pathfinder/v0_1" => Some(RpcVersion::PathfinderV01),
+            _ => None,
+        }
+    }
 }
 
-// TODO: make this configurable
-const REQUEST_MAX_SIZE: usize = 10 * 1024 * 1024;
-// TODO: make this configurable
-const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+pub const DEFAULT_REQUEST_MAX_SIZE: usize = 10 * 1024 * 1024;
+pub const DEFAULT_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
 
 pub struct RpcServer {
     addr: SocketAddr,
     context: RpcContext,
     max_connections: usize,
+    request_max_size: usize,
+    request_timeout: std::time::Duration,
     cors: Option<CorsLayer>,
+    auth: Option<middleware::auth::Auth>,
+    method_filters: method_filter::MethodFilters,
     default_version: RpcVersion,
 }
 
@@ -80,7 +106,11 @@ impl RpcServer {
             addr,
             context,
             max_connections: DEFAULT_MAX_CONNECTIONS,
+            request_max_size: DEFAULT_REQUEST_MAX_SIZE,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
             cors: None,
+            auth: None,
+            method_filters: Default::default(),
             default_version,
         }
     }
@@ -90,6 +120,23 @@ impl RpcServer {
         self
     }
 
+    /// Sets the maximum size of request bodies and websocket messages in bytes.
+    pub fn with_request_max_size(self, request_max_size: usize) -> Self {
+        Self {
+            request_max_size,
+            ..self
+        }
+    }
+
+    /// Sets how long serving a request may take before it is answered with
+    /// `408 Request Timeout`.
+    pub fn with_request_timeout(self, request_timeout: std::time::Duration) -> Self {
+        Self {
+            request_timeout,
+            ..self
+        }
+    }
+
     pub fn with_cors(self, allowed_origins: AllowedOrigins) -> Self {
         Self {
             cors: Some(middleware::cors::with_allowed_origins(allowed_origins)),
@@ -97,9 +144,25 @@ impl RpcServer {
         }
     }
 
+    /// Requires requests to the configured routes to be authenticated.
+    pub fn with_auth(self, auth: middleware::auth::Auth) -> Self {
+        Self {
+            auth: Some(auth),
+            ..self
+        }
+    }
+
+    /// Disables the methods not passing the filter of their RPC version.
+    pub fn with_method_filters(self, method_filters: method_filter::MethodFilters) -> Self {
+        Self {
+            method_filters,
+            ..self
+        }
+    }
+
     /// Starts the HTTP-RPC server.
     pub async fn spawn(
-        self,
+        mut self,
     ) -> Result<(JoinHandle<anyhow::Result<()>>, SocketAddr), anyhow::Error> {
         use axum::routing::{get, post};
 
@@ -147,8 +210,8 @@ impl RpcServer {
             // make sure to set request ids before the request reaches `TraceLayer`
             .set_x_request_id(middleware::request_id::RequestIdSource::default())
             .concurrency_limit(self.max_connections)
-            .layer(DefaultBodyLimit::max(REQUEST_MAX_SIZE))
-            .timeout(REQUEST_TIMEOUT)
+            .layer(DefaultBodyLimit::max(self.request_max_size))
+            .timeout(self.request_timeout)
             .layer(middleware::tracing::trace_layer())
             .option_layer(self.cors)
             .propagate_x_request_id();
@@ -163,10 +226,22 @@ impl RpcServer {
             }
         }
 
-        let v06_routes = v06::register_routes().build(self.context.clone());
-        let v07_routes = v07::register_routes().build(self.context.clone());
-        let v08_routes = v08::register_routes().build(self.context.clone());
-        let pathfinder_routes = pathfinder::register_routes().build(self.context.clone());
+        if let Some(websocket) = &mut self.context.websocket {
+            websocket.max_message_size = self.request_max_size;
+        }
+
+        let v06_routes = v06::register_routes()
+            .with_method_filter(&self.method_filters.v06)
+            .build(self.context.clone());
+        let v07_routes = v07::register_routes()
+            .with_method_filter(&self.method_filters.v07)
+            .build(self.context.clone());
+        let v08_routes = v08::register_routes()
+            .with_method_filter(&self.method_filters.v08)
+            .build(self.context.clone());
+        let pathfinder_routes = pathfinder::register_routes()
+            .with_method_filter(&self.method_filters.pathfinder)
+            .build(self.context.clone());
 
         let default_router = match self.default_version {
             RpcVersion::V06 => v06_routes.clone(),
@@ -208,13 +283,24 @@ impl RpcServer {
             router.with_state(default_router)
         };
 
+        let router = match self.auth {
+            Some(auth) => router.layer(axum::middleware::from_fn_with_state(
+                auth.with_default_version(self.default_version)?,
+                middleware::auth::authenticate,
+            )),
+            None => router,
+        };
+
         let router = router.layer(middleware);
 
         let server_handle = util::task::spawn(async move {
-            axum::serve(listener, router.into_make_service())
-                .with_graceful_shutdown(util::task::cancellation_token().cancelled_owned())
-                .await
-                .map_err(Into::into)
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(util::task::cancellation_token().cancelled_owned())
+            .await
+            .map_err(Into::into)
         });
 
         Ok((server_handle, addr))
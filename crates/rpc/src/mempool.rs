@@ -0,0 +1,417 @@
+//! A local pool of submitted transactions.
+//!
+//! Transactions submitted via the `starknet_add*Transaction` methods are
+//! validated against the latest state before being relayed to the gateway.
+//! This lets us reject invalid transactions without a gateway round-trip and
+//! with deterministic error reasons. The pool also remembers the hashes of
+//! recently relayed transactions so that duplicates are rejected locally.
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use pathfinder_common::TransactionHash;
+use pathfinder_executor::{ExecutionState, L1BlobDataAvailability, TransactionValidationError};
+
+use crate::context::RpcContext;
+use crate::types::request::BroadcastedTransaction;
+
+#[derive(Debug, Clone, Copy)]
+pub struct MempoolConfig {
+    /// Maximum number of transaction hashes remembered for deduplication.
+    pub capacity: NonZeroUsize,
+    /// How long a relayed transaction is remembered for deduplication.
+    pub ttl: Duration,
+}
+
+#[derive(Clone)]
+pub struct Mempool(Arc<Mutex<Entries>>);
+
+/// Transaction hashes in insertion order, used to deduplicate submissions.
+struct Entries {
+    config: MempoolConfig,
+    /// Maps the hash to the sequence number of its latest insertion.
+    by_hash: HashMap<TransactionHash, u64>,
+    by_age: VecDeque<Entry>,
+    next_sequence: u64,
+}
+
+struct Entry {
+    transaction_hash: TransactionHash,
+    inserted_at: Instant,
+    sequence: u64,
+}
+
+/// Reasons for a transaction to be rejected by the local mempool.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum MempoolError {
+    DuplicateTransaction,
+    InvalidTransactionNonce,
+    InsufficientResourcesForValidate,
+    InsufficientAccountBalance,
+    ValidationFailure(String),
+    NonAccount,
+}
+
+impl Mempool {
+    pub fn new(config: MempoolConfig) -> Self {
+        Self(Arc::new(Mutex::new(Entries {
+            config,
+            by_hash: Default::default(),
+            by_age: Default::default(),
+            next_sequence: 0,
+        })))
+    }
+
+    /// Validates the transaction against the latest (pending) state and
+    /// records its hash.
+    ///
+    /// `None` is returned if the transaction could not be checked locally, in
+    /// which case it should be relayed as-is and the gateway gets to decide.
+    pub(crate) async fn admit(
+        &self,
+        context: &RpcContext,
+        transaction: BroadcastedTransaction,
+    ) -> Result<Option<Admitted>, MempoolError> {
+        let mempool = self.clone();
+        let context = context.clone();
+        let span = tracing::Span::current();
+
+        let result = util::task::spawn_blocking(move |_| {
+            let _g = span.enter();
+            mempool.admit_blocking(&context, transaction)
+        })
+        .await;
+
+        match result {
+            Ok(result) => result.map(|transaction_hash| {
+                transaction_hash.map(|transaction_hash| Admitted {
+                    mempool: self.clone(),
+                    transaction_hash,
+                })
+            }),
+            Err(error) => {
+                tracing::warn!(%error, "Local transaction validation task failed");
+                Ok(None)
+            }
+        }
+    }
+
+    fn admit_blocking(
+        &self,
+        context: &RpcContext,
+        transaction: BroadcastedTransaction,
+    ) -> Result<Option<TransactionHash>, MempoolError> {
+        let transaction = match crate::executor::map_broadcasted_transaction(
+            &transaction,
+            context.chain_id,
+            false,
+            false,
+        ) {
+            Ok(transaction) => transaction,
+            Err(error) => {
+                tracing::debug!(%error, "Skipping local validation of transaction");
+                return Ok(None);
+            }
+        };
+
+        let transaction_hash = pathfinder_executor::transaction_hash(&transaction);
+        if !self.insert(transaction_hash, Instant::now()) {
+            metrics::increment_counter!("rpc_mempool_rejected_transactions", "reason" => "duplicate");
+            return Err(MempoolError::DuplicateTransaction);
+        }
+
+        let error = match validate(context, transaction) {
+            Ok(()) => {
+                metrics::increment_counter!("rpc_mempool_accepted_transactions");
+                return Ok(Some(transaction_hash));
+            }
+            Err(error) => error,
+        };
+
+        let (reason, error) = match MempoolError::from_validation_error(error) {
+            Ok(rejection) => rejection,
+            Err(error) => {
+                tracing::warn!(%transaction_hash, ?error, "Local transaction validation failed");
+                return Ok(Some(transaction_hash));
+            }
+        };
+
+        self.remove(&transaction_hash);
+        metrics::increment_counter!("rpc_mempool_rejected_transactions", "reason" => reason);
+        tracing::debug!(%transaction_hash, ?error, "Transaction rejected by local mempool");
+
+        Err(error)
+    }
+
+    /// Records the transaction hash. Returns `false` if the hash is already
+    /// present.
+    fn insert(&self, transaction_hash: TransactionHash, now: Instant) -> bool {
+        let mut entries = self.0.lock().unwrap();
+        entries.evict_expired(now);
+
+        if entries.by_hash.contains_key(&transaction_hash) {
+            return false;
+        }
+
+        while entries.by_hash.len() >= entries.config.capacity.get() {
+            entries.evict_oldest();
+        }
+
+        let sequence = entries.next_sequence;
+        entries.next_sequence += 1;
+        entries.by_hash.insert(transaction_hash, sequence);
+        entries.by_age.push_back(Entry {
+            transaction_hash,
+            inserted_at: now,
+            sequence,
+        });
+
+        true
+    }
+
+    fn remove(&self, transaction_hash: &TransactionHash) {
+        let mut entries = self.0.lock().unwrap();
+        if let Some(sequence) = entries.by_hash.remove(transaction_hash) {
+            // Sequence numbers increase with age.
+            if let Ok(index) = entries
+                .by_age
+                .binary_search_by_key(&sequence, |entry| entry.sequence)
+            {
+                entries.by_age.remove(index);
+            }
+        }
+    }
+}
+
+impl MempoolError {
+    /// Maps a local validation failure to a rejection and the reason reported
+    /// in metrics. Errors which say nothing about the transaction itself are
+    /// returned as-is.
+    fn from_validation_error(
+        error: TransactionValidationError,
+    ) -> Result<(&'static str, Self), anyhow::Error> {
+        let rejection = match error {
+            TransactionValidationError::InvalidNonce => ("nonce", Self::InvalidTransactionNonce),
+            TransactionValidationError::InsufficientResourcesForValidate => {
+                ("resource_bounds", Self::InsufficientResourcesForValidate)
+            }
+            TransactionValidationError::InsufficientAccountBalance => {
+                ("balance", Self::InsufficientAccountBalance)
+            }
+            TransactionValidationError::ValidationFailure(error) => {
+                ("validate", Self::ValidationFailure(error))
+            }
+            TransactionValidationError::NonAccount => ("non_account", Self::NonAccount),
+            TransactionValidationError::Internal(error)
+            | TransactionValidationError::Custom(error) => return Err(error),
+        };
+
+        Ok(rejection)
+    }
+}
+
+/// Implements the conversion from [MempoolError] for the error types of the
+/// `starknet_add*Transaction` methods, which share these variants.
+macro_rules! impl_from_mempool_error {
+    ($($error:ty),* $(,)?) => {$(
+        impl From<MempoolError> for $error {
+            fn from(e: MempoolError) -> Self {
+                match e {
+                    MempoolError::DuplicateTransaction => Self::DuplicateTransaction,
+                    MempoolError::InvalidTransactionNonce => Self::InvalidTransactionNonce,
+                    MempoolError::InsufficientResourcesForValidate => {
+                        Self::InsufficientResourcesForValidate
+                    }
+                    MempoolError::InsufficientAccountBalance => Self::InsufficientAccountBalance,
+                    MempoolError::ValidationFailure(error) => Self::ValidationFailure(error),
+                    MempoolError::NonAccount => Self::NonAccount,
+                }
+            }
+        }
+    )*};
+}
+
+impl_from_mempool_error!(
+    crate::method::add_declare_transaction::AddDeclareTransactionError,
+    crate::method::add_deploy_account_transaction::AddDeployAccountTransactionError,
+    crate::method::add_invoke_transaction::AddInvokeTransactionError,
+);
+
+/// A transaction that passed local validation and is about to be relayed.
+pub(crate) struct Admitted {
+    mempool: Mempool,
+    transaction_hash: TransactionHash,
+}
+
+impl Admitted {
+    /// Forgets the transaction so that it can be resubmitted.
+    pub(crate) fn relay_failed(self) {
+        self.mempool.remove(&self.transaction_hash);
+    }
+}
+
+impl Entries {
+    fn evict_expired(&mut self, now: Instant) {
+        while let Some(entry) = self.by_age.front() {
+            if now.saturating_duration_since(entry.inserted_at) < self.config.ttl {
+                break;
+            }
+            self.evict_oldest();
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        if let Some(entry) = self.by_age.pop_front() {
+            self.by_hash.remove(&entry.transaction_hash);
+        }
+    }
+}
+
+fn validate(
+    context: &RpcContext,
+    transaction: pathfinder_executor::Transaction,
+) -> Result<(), TransactionValidationError> {
+    let mut db = context
+        .execution_storage
+        .connection()
+        .context("Creating database connection")?;
+    let db = db.transaction().context("Creating database transaction")?;
+
+    let pending = context
+        .pending_data
+        .get(&db)
+        .context("Querying pending data")?;
+
+    let state = ExecutionState::simulation(
+        &db,
+        context.chain_id,
+        pending.header(),
+        Some(pending.state_update.clone()),
+        L1BlobDataAvailability::Enabled,
+        context.config.versioned_constants_map.clone(),
+        context.contract_addresses.eth_l2_token_address,
+        context.contract_addresses.strk_l2_token_address,
+        context.native_class_cache.clone(),
+    );
+
+    pathfinder_executor::validate(state, transaction)
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+
+    use super::*;
+
+    fn mempool(capacity: usize, ttl: Duration) -> Mempool {
+        Mempool::new(MempoolConfig {
+            capacity: NonZeroUsize::new(capacity).unwrap(),
+            ttl,
+        })
+    }
+
+    #[test]
+    fn duplicates_are_rejected() {
+        let mempool = mempool(10, Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert!(mempool.insert(transaction_hash!("0x1"), now));
+        assert!(mempool.insert(transaction_hash!("0x2"), now));
+        assert!(!mempool.insert(transaction_hash!("0x1"), now));
+    }
+
+    #[test]
+    fn removed_transactions_can_be_resubmitted() {
+        let mempool = mempool(10, Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert!(mempool.insert(transaction_hash!("0x1"), now));
+        mempool.remove(&transaction_hash!("0x1"));
+        assert!(mempool.insert(transaction_hash!("0x1"), now));
+        assert!(!mempool.insert(transaction_hash!("0x1"), now));
+
+        let entries = mempool.0.lock().unwrap();
+        assert_eq!(entries.by_hash.len(), 1);
+        assert_eq!(entries.by_age.len(), 1);
+    }
+
+    #[test]
+    fn failed_relay_allows_resubmission() {
+        let mempool = mempool(10, Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert!(mempool.insert(transaction_hash!("0x1"), now));
+        assert!(mempool.insert(transaction_hash!("0x2"), now));
+
+        Admitted {
+            mempool: mempool.clone(),
+            transaction_hash: transaction_hash!("0x1"),
+        }
+        .relay_failed();
+
+        assert!(mempool.insert(transaction_hash!("0x1"), now));
+        // Relayed transactions are still remembered.
+        assert!(!mempool.insert(transaction_hash!("0x2"), now));
+    }
+
+    #[test]
+    fn validation_errors_are_mapped() {
+        use TransactionValidationError::*;
+
+        let cases = [
+            (InvalidNonce, "nonce", MempoolError::InvalidTransactionNonce),
+            (
+                InsufficientResourcesForValidate,
+                "resource_bounds",
+                MempoolError::InsufficientResourcesForValidate,
+            ),
+            (
+                InsufficientAccountBalance,
+                "balance",
+                MempoolError::InsufficientAccountBalance,
+            ),
+            (
+                ValidationFailure("reason".to_owned()),
+                "validate",
+                MempoolError::ValidationFailure("reason".to_owned()),
+            ),
+            (NonAccount, "non_account", MempoolError::NonAccount),
+        ];
+        for (error, reason, expected) in cases {
+            assert_eq!(
+                MempoolError::from_validation_error(error).unwrap(),
+                (reason, expected)
+            );
+        }
+
+        // Failures unrelated to the transaction let it through to the gateway.
+        MempoolError::from_validation_error(Internal(anyhow::anyhow!("db"))).unwrap_err();
+        MempoolError::from_validation_error(Custom(anyhow::anyhow!("other"))).unwrap_err();
+    }
+
+    #[test]
+    fn expired_transactions_are_evicted() {
+        let ttl = Duration::from_secs(60);
+        let mempool = mempool(10, ttl);
+        let now = Instant::now();
+
+        assert!(mempool.insert(transaction_hash!("0x1"), now));
+        assert!(!mempool.insert(transaction_hash!("0x1"), now + ttl / 2));
+        assert!(mempool.insert(transaction_hash!("0x1"), now + ttl));
+    }
+
+    #[test]
+    fn oldest_transactions_are_evicted_when_full() {
+        let mempool = mempool(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert!(mempool.insert(transaction_hash!("0x1"), now));
+        assert!(mempool.insert(transaction_hash!("0x2"), now));
+        assert!(mempool.insert(transaction_hash!("0x3"), now));
+
+        assert!(mempool.insert(transaction_hash!("0x1"), now));
+        assert!(!mempool.insert(transaction_hash!("0x3"), now));
+    }
+}
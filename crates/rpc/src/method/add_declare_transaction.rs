@@ -8,7 +8,7 @@ use starknet_gateway_types::request::add_transaction::{
 };
 
 use crate::context::RpcContext;
-use crate::types::request::BroadcastedDeclareTransaction;
+use crate::types::request::{BroadcastedDeclareTransaction, BroadcastedTransaction};
 
 #[derive(Debug)]
 pub enum AddDeclareTransactionError {
@@ -292,6 +292,31 @@ pub struct Output {
 pub async fn add_declare_transaction(
     context: RpcContext,
     input: Input,
+) -> Result<Output, AddDeclareTransactionError> {
+    let admitted = match (&context.mempool, &input.declare_transaction) {
+        // Rejected as unsupported without relaying.
+        (_, Transaction::Declare(BroadcastedDeclareTransaction::V0(_))) => None,
+        (Some(mempool), Transaction::Declare(tx)) => {
+            mempool
+                .admit(&context, BroadcastedTransaction::Declare(tx.clone()))
+                .await?
+        }
+        (None, _) => None,
+    };
+
+    let result = add_declare_transaction_impl(&context, input).await;
+    if result.is_err() {
+        if let Some(admitted) = admitted {
+            admitted.relay_failed();
+        }
+    }
+
+    result
+}
+
+async fn add_declare_transaction_impl(
+    context: &RpcContext,
+    input: Input,
 ) -> Result<Output, AddDeclareTransactionError> {
     use starknet_gateway_types::request::add_transaction;
 
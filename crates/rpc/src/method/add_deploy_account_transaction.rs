@@ -7,6 +7,7 @@ use crate::context::RpcContext;
 use crate::types::request::{
     BroadcastedDeployAccountTransaction,
     BroadcastedDeployAccountTransactionV1,
+    BroadcastedTransaction,
 };
 
 #[derive(Debug, PartialEq, Eq)]
@@ -176,7 +177,25 @@ pub async fn add_deploy_account_transaction(
         Transaction::DeployAccount(tx) => tx.deployed_contract_address(),
     };
     let Transaction::DeployAccount(tx) = input.deploy_account_transaction;
-    let response = add_deploy_account_transaction_impl(&context, tx).await?;
+
+    let admitted = match &context.mempool {
+        Some(mempool) => {
+            mempool
+                .admit(&context, BroadcastedTransaction::DeployAccount(tx.clone()))
+                .await?
+        }
+        None => None,
+    };
+
+    let response = match add_deploy_account_transaction_impl(&context, tx).await {
+        Ok(response) => response,
+        Err(error) => {
+            if let Some(admitted) = admitted {
+                admitted.relay_failed();
+            }
+            return Err(error.into());
+        }
+    };
 
     Ok(Output {
         transaction_hash: response.transaction_hash,
@@ -4,7 +4,7 @@ use starknet_gateway_client::GatewayApi;
 use starknet_gateway_types::error::SequencerError;
 
 use crate::context::RpcContext;
-use crate::types::request::BroadcastedInvokeTransaction;
+use crate::types::request::{BroadcastedInvokeTransaction, BroadcastedTransaction};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Transaction {
@@ -181,7 +181,25 @@ pub async fn add_invoke_transaction(
     input: Input,
 ) -> Result<Output, AddInvokeTransactionError> {
     let Transaction::Invoke(tx) = input.invoke_transaction;
-    let response = add_invoke_transaction_impl(&context, tx).await?;
+
+    let admitted = match &context.mempool {
+        Some(mempool) => {
+            mempool
+                .admit(&context, BroadcastedTransaction::Invoke(tx.clone()))
+                .await?
+        }
+        None => None,
+    };
+
+    let response = match add_invoke_transaction_impl(&context, tx).await {
+        Ok(response) => response,
+        Err(error) => {
+            if let Some(admitted) = admitted {
+                admitted.relay_failed();
+            }
+            return Err(error.into());
+        }
+    };
 
     Ok(Output {
         transaction_hash: response.transaction_hash,
@@ -12,6 +12,7 @@ pub enum CallError {
     BlockNotFound,
     ContractNotFound,
     EntrypointNotFound,
+    DeadlineExceeded,
     ContractError {
         revert_error: Option<String>,
         revert_error_stack: pathfinder_executor::ErrorStack,
@@ -34,6 +35,7 @@ impl From<pathfinder_executor::CallError> for CallError {
                 revert_error: Some(format!("Execution error: {}", error)),
                 revert_error_stack: error_stack,
             },
+            DeadlineExceeded => Self::DeadlineExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -56,6 +58,7 @@ impl From<CallError> for ApplicationError {
             CallError::BlockNotFound => ApplicationError::BlockNotFound,
             CallError::ContractNotFound => ApplicationError::ContractNotFound,
             CallError::EntrypointNotFound => ApplicationError::EntrypointNotFound,
+            CallError::DeadlineExceeded => ApplicationError::ExecutionDeadlineExceeded,
             CallError::ContractError {
                 revert_error,
                 revert_error_stack,
@@ -110,6 +113,14 @@ impl crate::dto::DeserializeForVersion for Input {
 pub struct Output(pub Vec<CallResultValue>);
 
 pub async fn call(context: RpcContext, input: Input) -> Result<Output, CallError> {
+    let deadline = std::time::Instant::now() + context.config.execution_deadlines.call;
+    let historical = match &context.historical_state {
+        Some(historical_state) => historical_state
+            .execution_state(context.storage.clone(), &context.sequencer, input.block_id)
+            .await
+            .context("Reconstructing historical state")?,
+        None => None,
+    };
     let span = tracing::Span::current();
     let result = util::task::spawn_blocking(move |_| {
         let _g = span.enter();
@@ -120,16 +131,17 @@ pub async fn call(context: RpcContext, input: Input) -> Result<Output, CallError
             .context("Creating database connection")?;
         let db = db.transaction().context("Creating database transaction")?;
 
-        let (header, pending) = match input.block_id {
-            BlockId::Pending => {
+        let (header, pending, overlay) = match (input.block_id, historical) {
+            (BlockId::Pending, _) => {
                 let pending = context
                     .pending_data
                     .get(&db)
                     .context("Querying pending data")?;
 
-                (pending.header(), Some(pending.state_update.clone()))
+                (pending.header(), Some(pending.state_update.clone()), None)
             }
-            other => {
+            (_, Some((header, overlay))) => (header, None, Some(overlay)),
+            (other, None) => {
                 let block_id = other.try_into().expect("Only pending cast should fail");
 
                 let pruned = db
@@ -144,7 +156,7 @@ pub async fn call(context: RpcContext, input: Input) -> Result<Output, CallError
                     .context("Querying block header")?
                     .ok_or(CallError::BlockNotFound)?;
 
-                (header, None)
+                (header, None, None)
             }
         };
 
@@ -158,7 +170,12 @@ pub async fn call(context: RpcContext, input: Input) -> Result<Output, CallError
             context.contract_addresses.eth_l2_token_address,
             context.contract_addresses.strk_l2_token_address,
             context.native_class_cache,
-        );
+        )
+        .with_deadline(deadline);
+        let state = match overlay {
+            Some(overlay) => state.with_state_overlay(overlay),
+            None => state,
+        };
 
         let result = pathfinder_executor::call(
             state,
@@ -332,6 +349,24 @@ mod tests {
             assert_eq!(result, Output(vec![CallResultValue(test_value.0)]));
         }
 
+        #[tokio::test]
+        async fn deadline_exceeded() {
+            let (mut context, _last_block_header, contract_address, test_key, _test_value) =
+                test_context().await;
+            context.config.execution_deadlines.call = std::time::Duration::ZERO;
+
+            let input = Input {
+                request: FunctionCall {
+                    contract_address,
+                    entry_point_selector: EntryPoint::hashed(b"get_value"),
+                    calldata: vec![CallParam(*test_key.get())],
+                },
+                block_id: BlockId::Latest,
+            };
+            let error = call(context, input).await.unwrap_err();
+            assert_matches::assert_matches!(error, CallError::DeadlineExceeded);
+        }
+
         #[tokio::test]
         async fn storage_updated_in_pending() {
             let (context, last_block_header, contract_address, test_key, test_value) =
@@ -46,6 +46,14 @@ impl crate::dto::DeserializeForVersion for SimulationFlag {
 pub struct Output(Vec<pathfinder_executor::types::FeeEstimate>);
 
 pub async fn estimate_fee(context: RpcContext, input: Input) -> Result<Output, EstimateFeeError> {
+    let deadline = std::time::Instant::now() + context.config.execution_deadlines.estimate_fee;
+    let historical = match &context.historical_state {
+        Some(historical_state) => historical_state
+            .execution_state(context.storage.clone(), &context.sequencer, input.block_id)
+            .await
+            .context("Reconstructing historical state")?,
+        None => None,
+    };
     let span = tracing::Span::current();
     let result = util::task::spawn_blocking(move |_| {
         let _g = span.enter();
@@ -55,16 +63,17 @@ pub async fn estimate_fee(context: RpcContext, input: Input) -> Result<Output, E
             .context("Creating database connection")?;
         let db = db.transaction().context("Creating database transaction")?;
 
-        let (header, pending) = match input.block_id {
-            BlockId::Pending => {
+        let (header, pending, overlay) = match (input.block_id, historical) {
+            (BlockId::Pending, _) => {
                 let pending = context
                     .pending_data
                     .get(&db)
                     .context("Querying pending data")?;
 
-                (pending.header(), Some(pending.state_update.clone()))
+                (pending.header(), Some(pending.state_update.clone()), None)
             }
-            other => {
+            (_, Some((header, overlay))) => (header, None, Some(overlay)),
+            (other, None) => {
                 let block_id = other.try_into().expect("Only pending cast should fail");
 
                 let pruned = db
@@ -79,7 +88,7 @@ pub async fn estimate_fee(context: RpcContext, input: Input) -> Result<Output, E
                     .context("Querying block header")?
                     .ok_or(EstimateFeeError::BlockNotFound)?;
 
-                (header, None)
+                (header, None, None)
             }
         };
 
@@ -93,7 +102,12 @@ pub async fn estimate_fee(context: RpcContext, input: Input) -> Result<Output, E
             context.contract_addresses.eth_l2_token_address,
             context.contract_addresses.strk_l2_token_address,
             context.native_class_cache,
-        );
+        )
+        .with_deadline(deadline);
+        let state = match overlay {
+            Some(overlay) => state.with_state_overlay(overlay),
+            None => state,
+        };
 
         let skip_validate = input
             .simulation_flags
@@ -132,6 +146,7 @@ pub enum EstimateFeeError {
     Internal(anyhow::Error),
     Custom(anyhow::Error),
     BlockNotFound,
+    DeadlineExceeded,
     TransactionExecutionError {
         transaction_index: usize,
         error: String,
@@ -158,6 +173,7 @@ impl From<pathfinder_executor::TransactionExecutionError> for EstimateFeeError {
                 error,
                 error_stack,
             },
+            DeadlineExceeded => Self::DeadlineExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -178,6 +194,7 @@ impl From<EstimateFeeError> for ApplicationError {
     fn from(value: EstimateFeeError) -> Self {
         match value {
             EstimateFeeError::BlockNotFound => ApplicationError::BlockNotFound,
+            EstimateFeeError::DeadlineExceeded => ApplicationError::ExecutionDeadlineExceeded,
             EstimateFeeError::TransactionExecutionError {
                 transaction_index,
                 error,
@@ -57,6 +57,7 @@ pub async fn estimate_message_fee(
     context: RpcContext,
     input: EstimateMessageFeeInput,
 ) -> Result<Output, EstimateMessageFeeError> {
+    let deadline = std::time::Instant::now() + context.config.execution_deadlines.estimate_fee;
     let span = tracing::Span::current();
     let mut result = util::task::spawn_blocking(move |_| {
         let _g = span.enter();
@@ -108,7 +109,8 @@ pub async fn estimate_message_fee(
             context.contract_addresses.eth_l2_token_address,
             context.contract_addresses.strk_l2_token_address,
             context.native_class_cache,
-        );
+        )
+        .with_deadline(deadline);
 
         let transaction = create_executor_transaction(input, context.chain_id)?;
 
@@ -195,6 +197,7 @@ pub enum EstimateMessageFeeError {
     Internal(anyhow::Error),
     BlockNotFound,
     ContractNotFound,
+    DeadlineExceeded,
     ContractError {
         revert_error: String,
         revert_error_stack: pathfinder_executor::ErrorStack,
@@ -218,6 +221,7 @@ impl From<pathfinder_executor::TransactionExecutionError> for EstimateMessageFee
                 revert_error: format!("Execution error: {}", error),
                 revert_error_stack: error_stack,
             },
+            DeadlineExceeded => Self::DeadlineExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -239,6 +243,9 @@ impl From<EstimateMessageFeeError> for ApplicationError {
         match value {
             EstimateMessageFeeError::BlockNotFound => ApplicationError::BlockNotFound,
             EstimateMessageFeeError::ContractNotFound => ApplicationError::ContractNotFound,
+            EstimateMessageFeeError::DeadlineExceeded => {
+                ApplicationError::ExecutionDeadlineExceeded
+            }
             EstimateMessageFeeError::ContractError {
                 revert_error,
                 revert_error_stack,
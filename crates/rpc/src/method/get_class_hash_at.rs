@@ -2,10 +2,11 @@ use anyhow::Context;
 use pathfinder_common::{BlockId, ClassHash, ContractAddress};
 
 use crate::context::RpcContext;
+use crate::historical_state::Reconstructed;
 
 crate::error::generate_rpc_error_subset!(Error: BlockNotFound, ContractNotFound);
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Input {
     block_id: BlockId,
     contract_address: ContractAddress,
@@ -26,8 +27,9 @@ impl crate::dto::DeserializeForVersion for Input {
 pub struct Output(ClassHash);
 
 pub async fn get_class_hash_at(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let historical_context = context.clone();
     let span = tracing::Span::current();
-    util::task::spawn_blocking(move |_| {
+    let result = util::task::spawn_blocking(move |_| {
         let _g = span.enter();
         let mut db = context
             .storage
@@ -45,7 +47,7 @@ pub async fn get_class_hash_at(context: RpcContext, input: Input) -> Result<Outp
                 .contract_class(input.contract_address);
 
             if let Some(pending) = pending {
-                return Ok(Output(pending));
+                return Ok(Some(Output(pending)));
             }
         }
 
@@ -59,7 +61,8 @@ pub async fn get_class_hash_at(context: RpcContext, input: Input) -> Result<Outp
             .block_pruned(block_id)
             .context("Querying block pruned status")?;
         if pruned {
-            return Err(Error::BlockNotFound);
+            // Reconstructed below, if enabled.
+            return Ok(None);
         }
 
         // Check for block existence.
@@ -70,10 +73,38 @@ pub async fn get_class_hash_at(context: RpcContext, input: Input) -> Result<Outp
         tx.contract_class_hash(block_id, input.contract_address)
             .context("Fetching class hash from database")?
             .ok_or(Error::ContractNotFound)
-            .map(Output)
+            .map(|class_hash| Some(Output(class_hash)))
     })
     .await
-    .context("Joining blocking task")?
+    .context("Joining blocking task")??;
+
+    match result {
+        Some(output) => Ok(output),
+        None => historical_class_hash_at(historical_context, input).await,
+    }
+}
+
+/// Reconstructs the class hash at a pruned block, if enabled.
+async fn historical_class_hash_at(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let Some(historical_state) = &context.historical_state else {
+        return Err(Error::BlockNotFound);
+    };
+
+    let reconstructed = historical_state
+        .contract_class_hash(
+            context.storage.clone(),
+            &context.sequencer,
+            input.block_id,
+            input.contract_address,
+        )
+        .await
+        .context("Reconstructing historical class hash")?;
+
+    match reconstructed {
+        Reconstructed::Value(class_hash) => Ok(Output(class_hash)),
+        Reconstructed::ContractNotFound => Err(Error::ContractNotFound),
+        Reconstructed::Unavailable => Err(Error::BlockNotFound),
+    }
 }
 
 impl crate::dto::SerializeForVersion for Output {
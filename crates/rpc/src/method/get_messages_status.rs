@@ -73,28 +73,55 @@ crate::error::generate_rpc_error_subset!(Error: TxnHashNotFound);
 pub async fn get_messages_status(context: RpcContext, input: Input) -> Result<Output, Error> {
     let span = tracing::Span::current();
 
-    let _g = span.enter();
-
-    // Fetch the L1 handler transactions for the given transaction hash
-    let ethereum = context.ethereum.clone();
-
-    let l1_handler_txs = ethereum
-        .get_l1_handler_txs(
-            &context.contract_addresses.l1_contract_address,
-            &input.transaction_hash,
-        )
+    // Messages in finalized L1 blocks are served from the index
+    let indexed = {
+        let storage = context.storage.clone();
+        let transaction_hash = input.transaction_hash;
+        util::task::spawn_blocking(move |_| {
+            let _g = span.enter();
+            let mut db = storage
+                .connection()
+                .context("Opening database connection")?;
+            let db = db.transaction().context("Creating database transaction")?;
+            db.l1_to_l2_messages(transaction_hash)
+        })
         .await
-        .context("Fetching L1 handler tx hashes")
-        .map_err(|_| Error::TxnHashNotFound)?;
+        .context("Joining database task")??
+    };
+
+    // Other messages are fetched from L1, which only knows them once they are
+    // included in an L1 block
+    let (tx_hashes, is_indexed) = if indexed.is_empty() {
+        let l1_handler_txs = context
+            .ethereum
+            .get_l1_handler_txs(
+                &context.contract_addresses.l1_contract_address,
+                &input.transaction_hash,
+            )
+            .await
+            .context("Fetching L1 handler tx hashes")
+            .map_err(|_| Error::TxnHashNotFound)?;
+        let tx_hashes = l1_handler_txs
+            .into_iter()
+            .map(|tx| tx.calculate_hash(context.chain_id))
+            .collect::<Vec<_>>();
+        (tx_hashes, false)
+    } else {
+        (indexed, true)
+    };
 
     let mut res = vec![];
-    for tx in l1_handler_txs {
-        let tx_hash = tx.calculate_hash(context.chain_id);
-
+    for tx_hash in tx_hashes {
         let input = get_transaction_status::Input::new(tx_hash);
-        let status = get_transaction_status(context.clone(), input)
-            .await
-            .map_err(|_| Error::TxnHashNotFound)?;
+        let status = match get_transaction_status(context.clone(), input).await {
+            Ok(status) => status,
+            // An indexed message was sent in a finalized L1 block, but has not
+            // been consumed on L2 yet.
+            Err(get_transaction_status::Error::TxnHashNotFound) if is_indexed => {
+                get_transaction_status::Output::Received
+            }
+            Err(_) => return Err(Error::TxnHashNotFound),
+        };
 
         use get_transaction_status::Output as TxStatus;
         let finality_status = match status {
@@ -110,7 +137,7 @@ pub async fn get_messages_status(context: RpcContext, input: Input) -> Result<Ou
         };
 
         res.push(L1HandlerTransactionStatus {
-            transaction_hash: tx.calculate_hash(context.chain_id),
+            transaction_hash: tx_hash,
             finality_status,
             failure_reason,
         });
@@ -140,3 +167,61 @@ impl crate::dto::SerializeForVersion for L1HandlerTransactionStatus {
         serializer.end()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::L1BlockNumber;
+    use pathfinder_storage::L1ToL2Message;
+    use primitive_types::H256;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn indexed_messages() {
+        let context = RpcContext::for_tests();
+        let l1_transaction_hash = L1TransactionHash::new(H256::from_low_u64_be(1));
+        // This transaction is in block 0 which is L1 accepted.
+        let consumed = transaction_hash_bytes!(b"txn 0");
+        let not_consumed = transaction_hash_bytes!(b"non-existent");
+
+        let mut db = context.storage.connection().unwrap();
+        let db_tx = db.transaction().unwrap();
+        let messages = [consumed, not_consumed]
+            .into_iter()
+            .enumerate()
+            .map(|(i, l2_transaction_hash)| L1ToL2Message {
+                message_hash: H256::from_low_u64_be(i as u64),
+                l1_block_number: L1BlockNumber::new_or_panic(1),
+                l1_transaction_hash,
+                l2_transaction_hash,
+            })
+            .collect::<Vec<_>>();
+        db_tx
+            .insert_l1_to_l2_messages(L1BlockNumber::new_or_panic(1), &messages)
+            .unwrap();
+        db_tx.commit().unwrap();
+
+        // L1 isn't queried for indexed messages.
+        let Output(statuses) = get_messages_status(
+            context,
+            Input {
+                transaction_hash: l1_transaction_hash,
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(statuses[0].transaction_hash, consumed);
+        assert!(matches!(
+            statuses[0].finality_status,
+            FinalityStatus::AcceptedOnL1
+        ));
+        assert_eq!(statuses[1].transaction_hash, not_consumed);
+        assert!(matches!(
+            statuses[1].finality_status,
+            FinalityStatus::Received
+        ));
+    }
+}
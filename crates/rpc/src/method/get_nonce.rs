@@ -2,8 +2,9 @@ use anyhow::Context;
 use pathfinder_common::{BlockId, ContractAddress, ContractNonce};
 
 use crate::context::RpcContext;
+use crate::historical_state::Reconstructed;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Input {
     block_id: BlockId,
     contract_address: ContractAddress,
@@ -26,8 +27,9 @@ pub struct Output(ContractNonce);
 crate::error::generate_rpc_error_subset!(Error: BlockNotFound, ContractNotFound);
 
 pub async fn get_nonce(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let historical_context = context.clone();
     let span = tracing::Span::current();
-    util::task::spawn_blocking(move |_| -> Result<_, Error> {
+    let result = util::task::spawn_blocking(move |_| -> Result<_, Error> {
         let _g = span.enter();
         let mut db = context
             .storage
@@ -43,7 +45,7 @@ pub async fn get_nonce(context: RpcContext, input: Input) -> Result<Output, Erro
                 .state_update
                 .contract_nonce(input.contract_address)
             {
-                return Ok(Output(nonce));
+                return Ok(Some(Output(nonce)));
             }
         }
 
@@ -56,7 +58,8 @@ pub async fn get_nonce(context: RpcContext, input: Input) -> Result<Output, Erro
             .block_pruned(block_id)
             .context("Querying block pruned status")?;
         if pruned {
-            return Err(Error::BlockNotFound);
+            // Reconstructed below, if enabled.
+            return Ok(None);
         }
 
         // Check that block exists. This should occur first as the block number
@@ -71,7 +74,7 @@ pub async fn get_nonce(context: RpcContext, input: Input) -> Result<Output, Erro
             .context("Querying contract nonce from database")?;
 
         if let Some(nonce) = nonce {
-            return Ok(Output(nonce));
+            return Ok(Some(Output(nonce)));
         };
 
         // Early starknet contracts had no nonces, so its possible for a contract to
@@ -81,13 +84,41 @@ pub async fn get_nonce(context: RpcContext, input: Input) -> Result<Output, Erro
             .context("Checking contract exists")?;
 
         if contract_exists {
-            Ok(Output(ContractNonce::ZERO))
+            Ok(Some(Output(ContractNonce::ZERO)))
         } else {
             Err(Error::ContractNotFound)
         }
     })
     .await
-    .context("Joining blocking task")?
+    .context("Joining blocking task")??;
+
+    match result {
+        Some(output) => Ok(output),
+        None => historical_nonce(historical_context, input).await,
+    }
+}
+
+/// Reconstructs the nonce at a pruned block, if enabled.
+async fn historical_nonce(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let Some(historical_state) = &context.historical_state else {
+        return Err(Error::BlockNotFound);
+    };
+
+    let reconstructed = historical_state
+        .contract_nonce(
+            context.storage.clone(),
+            &context.sequencer,
+            input.block_id,
+            input.contract_address,
+        )
+        .await
+        .context("Reconstructing historical contract nonce")?;
+
+    match reconstructed {
+        Reconstructed::Value(nonce) => Ok(Output(nonce)),
+        Reconstructed::ContractNotFound => Err(Error::ContractNotFound),
+        Reconstructed::Unavailable => Err(Error::BlockNotFound),
+    }
 }
 
 impl crate::dto::SerializeForVersion for Output {
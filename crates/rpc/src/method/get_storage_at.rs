@@ -2,8 +2,9 @@ use anyhow::Context;
 use pathfinder_common::{BlockId, ContractAddress, StorageAddress, StorageValue};
 
 use crate::context::RpcContext;
+use crate::historical_state::Reconstructed;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Input {
     pub contract_address: ContractAddress,
     pub key: StorageAddress,
@@ -29,6 +30,7 @@ crate::error::generate_rpc_error_subset!(Error: ContractNotFound, BlockNotFound)
 
 /// Get the value of the storage at the given address and key.
 pub async fn get_storage_at(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let historical_context = context.clone();
     let span = tracing::Span::current();
     let jh = util::task::spawn_blocking(move |_| {
         let _g = span.enter();
@@ -47,7 +49,7 @@ pub async fn get_storage_at(context: RpcContext, input: Input) -> Result<Output,
                 .state_update
                 .storage_value(input.contract_address, input.key)
             {
-                return Ok(Output(value));
+                return Ok(Some(Output(value)));
             }
         }
 
@@ -60,7 +62,8 @@ pub async fn get_storage_at(context: RpcContext, input: Input) -> Result<Output,
             .block_pruned(block_id)
             .context("Querying block pruned status")?;
         if pruned {
-            return Err(Error::BlockNotFound);
+            // Reconstructed below, if enabled.
+            return Ok(None);
         }
 
         // Check for block existence.
@@ -73,10 +76,10 @@ pub async fn get_storage_at(context: RpcContext, input: Input) -> Result<Output,
             .context("Querying storage value")?;
 
         match value {
-            Some(value) => Ok(Output(value)),
+            Some(value) => Ok(Some(Output(value))),
             None => {
                 if tx.contract_exists(input.contract_address, block_id)? {
-                    Ok(Output(StorageValue::ZERO))
+                    Ok(Some(Output(StorageValue::ZERO)))
                 } else {
                     Err(Error::ContractNotFound)
                 }
@@ -84,7 +87,34 @@ pub async fn get_storage_at(context: RpcContext, input: Input) -> Result<Output,
         }
     });
 
-    jh.await.context("Database read panic or shutting down")?
+    match jh.await.context("Database read panic or shutting down")?? {
+        Some(output) => Ok(output),
+        None => historical_storage_at(historical_context, input).await,
+    }
+}
+
+/// Reconstructs the storage value at a pruned block, if enabled.
+async fn historical_storage_at(context: RpcContext, input: Input) -> Result<Output, Error> {
+    let Some(historical_state) = &context.historical_state else {
+        return Err(Error::BlockNotFound);
+    };
+
+    let reconstructed = historical_state
+        .storage_value(
+            context.storage.clone(),
+            &context.sequencer,
+            input.block_id,
+            input.contract_address,
+            input.key,
+        )
+        .await
+        .context("Reconstructing historical storage value")?;
+
+    match reconstructed {
+        Reconstructed::Value(value) => Ok(Output(value)),
+        Reconstructed::ContractNotFound => Err(Error::ContractNotFound),
+        Reconstructed::Unavailable => Err(Error::BlockNotFound),
+    }
 }
 
 impl crate::dto::SerializeForVersion for Output {
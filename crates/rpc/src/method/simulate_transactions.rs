@@ -33,6 +33,14 @@ pub async fn simulate_transactions(
     context: RpcContext,
     input: SimulateTransactionInput,
 ) -> Result<Output, SimulateTransactionError> {
+    let deadline = std::time::Instant::now() + context.config.execution_deadlines.simulate;
+    let historical = match &context.historical_state {
+        Some(historical_state) => historical_state
+            .execution_state(context.storage.clone(), &context.sequencer, input.block_id)
+            .await
+            .context("Reconstructing historical state")?,
+        None => None,
+    };
     let span = tracing::Span::current();
     util::task::spawn_blocking(move |_| {
         let _g = span.enter();
@@ -55,16 +63,17 @@ pub async fn simulate_transactions(
             .context("Creating database connection")?;
         let db = db.transaction().context("Creating database transaction")?;
 
-        let (header, pending) = match input.block_id {
-            BlockId::Pending => {
+        let (header, pending, overlay) = match (input.block_id, historical) {
+            (BlockId::Pending, _) => {
                 let pending = context
                     .pending_data
                     .get(&db)
                     .context("Querying pending data")?;
 
-                (pending.header(), Some(pending.state_update.clone()))
+                (pending.header(), Some(pending.state_update.clone()), None)
             }
-            other => {
+            (_, Some((header, overlay))) => (header, None, Some(overlay)),
+            (other, None) => {
                 let block_id = other.try_into().expect("Only pending should fail");
 
                 let pruned = db
@@ -79,7 +88,7 @@ pub async fn simulate_transactions(
                     .context("Fetching block header")?
                     .ok_or(SimulateTransactionError::BlockNotFound)?;
 
-                (header, None)
+                (header, None, None)
             }
         };
 
@@ -93,7 +102,12 @@ pub async fn simulate_transactions(
             context.contract_addresses.eth_l2_token_address,
             context.contract_addresses.strk_l2_token_address,
             context.native_class_cache,
-        );
+        )
+        .with_deadline(deadline);
+        let state = match overlay {
+            Some(overlay) => state.with_state_overlay(overlay),
+            None => state,
+        };
 
         let transactions = input
             .transactions
@@ -153,6 +167,7 @@ pub enum SimulateTransactionError {
     Internal(anyhow::Error),
     Custom(anyhow::Error),
     BlockNotFound,
+    DeadlineExceeded,
     TransactionExecutionError {
         transaction_index: usize,
         error: String,
@@ -172,6 +187,7 @@ impl From<SimulateTransactionError> for crate::error::ApplicationError {
             SimulateTransactionError::Internal(internal) => Self::Internal(internal),
             SimulateTransactionError::Custom(internal) => Self::Custom(internal),
             SimulateTransactionError::BlockNotFound => Self::BlockNotFound,
+            SimulateTransactionError::DeadlineExceeded => Self::ExecutionDeadlineExceeded,
             SimulateTransactionError::TransactionExecutionError {
                 transaction_index,
                 error,
@@ -198,6 +214,7 @@ impl From<TransactionExecutionError> for SimulateTransactionError {
                 error,
                 error_stack,
             },
+            DeadlineExceeded => Self::DeadlineExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -43,6 +43,7 @@ pub async fn trace_block_transactions(
         Unsupported(Vec<pathfinder_common::transaction::Transaction>),
     }
 
+    let deadline = std::time::Instant::now() + context.config.execution_deadlines.trace;
     let span = tracing::Span::current();
 
     let storage = context.execution_storage.clone();
@@ -125,7 +126,8 @@ pub async fn trace_block_transactions(
             context.contract_addresses.eth_l2_token_address,
             context.contract_addresses.strk_l2_token_address,
             context.native_class_cache,
-        );
+        )
+        .with_deadline(deadline);
         let traces = match pathfinder_executor::trace(state, cache, hash, executor_transactions) {
             Ok(traces) => traces,
             Err(TransactionExecutionError::ExecutionError { .. }) => {
@@ -599,6 +601,7 @@ pub enum TraceBlockTransactionsError {
     Internal(anyhow::Error),
     Custom(anyhow::Error),
     BlockNotFound,
+    DeadlineExceeded,
 }
 
 impl From<anyhow::Error> for TraceBlockTransactionsError {
@@ -613,6 +616,7 @@ impl From<TraceBlockTransactionsError> for crate::error::ApplicationError {
             TraceBlockTransactionsError::Internal(e) => Self::Internal(e),
             TraceBlockTransactionsError::BlockNotFound => Self::BlockNotFound,
             TraceBlockTransactionsError::Custom(e) => Self::Custom(e),
+            TraceBlockTransactionsError::DeadlineExceeded => Self::ExecutionDeadlineExceeded,
         }
     }
 }
@@ -639,6 +643,7 @@ impl From<TransactionExecutionError> for TraceBlockTransactionsError {
                 transaction_index,
                 error
             )),
+            DeadlineExceeded => Self::DeadlineExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -50,6 +50,7 @@ pub async fn trace_transaction(
         Unsupported(pathfinder_common::transaction::Transaction),
     }
 
+    let deadline = std::time::Instant::now() + context.config.execution_deadlines.trace;
     let span = tracing::Span::current();
     let local =
         util::task::spawn_blocking(move |_| -> Result<LocalExecution, TraceTransactionError> {
@@ -128,7 +129,8 @@ pub async fn trace_transaction(
                 context.contract_addresses.eth_l2_token_address,
                 context.contract_addresses.strk_l2_token_address,
                 context.native_class_cache,
-            );
+            )
+            .with_deadline(deadline);
 
             let executor_transactions = transactions
                 .iter()
@@ -200,6 +202,7 @@ pub enum TraceTransactionError {
     Custom(anyhow::Error),
     TxnHashNotFound,
     NoTraceAvailable(TraceError),
+    DeadlineExceeded,
 }
 
 impl From<ExecutionStateError> for TraceTransactionError {
@@ -224,6 +227,7 @@ impl From<TransactionExecutionError> for TraceTransactionError {
                 transaction_index,
                 error
             )),
+            DeadlineExceeded => Self::DeadlineExceeded,
             Internal(e) => Self::Internal(e),
             Custom(e) => Self::Custom(e),
         }
@@ -242,6 +246,7 @@ impl From<super::trace_block_transactions::TraceBlockTransactionsError> for Trac
         match e {
             Internal(e) => Self::Internal(e),
             BlockNotFound => Self::Custom(anyhow::anyhow!("Block not found")),
+            DeadlineExceeded => Self::DeadlineExceeded,
             Custom(e) => Self::Custom(e),
         }
     }
@@ -254,6 +259,7 @@ impl From<TraceTransactionError> for ApplicationError {
             TraceTransactionError::NoTraceAvailable(status) => {
                 ApplicationError::NoTraceAvailable(status)
             }
+            TraceTransactionError::DeadlineExceeded => ApplicationError::ExecutionDeadlineExceeded,
             TraceTransactionError::Internal(e) => ApplicationError::Internal(e),
             TraceTransactionError::Custom(e) => ApplicationError::Custom(e),
         }
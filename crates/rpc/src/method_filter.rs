@@ -0,0 +1,113 @@
+//! Disabling of JSON-RPC methods per endpoint.
+//!
+//! Methods are selected with patterns, which are either method names or
+//! contain `*` wildcards matching any sequence of characters, e.g.
+//! `starknet_add*Transaction` or `starknet_subscribe*`. Disabled methods are
+//! not registered at all, so calling them fails with a `Method not found`
+//! error.
+
+/// Selects the methods enabled on an endpoint.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MethodFilter {
+    /// If not empty, only methods matching one of these patterns are enabled.
+    pub allow: Vec<String>,
+    /// Methods matching one of these patterns are disabled, even if they are
+    /// allowed.
+    pub deny: Vec<String>,
+}
+
+impl MethodFilter {
+    pub fn is_enabled(&self, method_name: &str) -> bool {
+        let allowed = self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|pattern| matches(pattern, method_name));
+        let denied = self
+            .deny
+            .iter()
+            .any(|pattern| matches(pattern, method_name));
+
+        allowed && !denied
+    }
+}
+
+/// The method filters of each RPC version. The default endpoint uses the
+/// filter of its version.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MethodFilters {
+    pub v06: MethodFilter,
+    pub v07: MethodFilter,
+    pub v08: MethodFilter,
+    pub pathfinder: MethodFilter,
+}
+
+fn matches(pattern: &str, method_name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = method_name.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts = parts.collect::<Vec<_>>();
+    // Without wildcards the pattern has to match the whole name.
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_matching() {
+        assert!(matches("starknet_call", "starknet_call"));
+        assert!(!matches("starknet_call", "starknet_callx"));
+        assert!(!matches("starknet_call", "starknet_cal"));
+
+        assert!(matches("*", "starknet_call"));
+        assert!(matches("starknet_trace*", "starknet_traceTransaction"));
+        assert!(matches(
+            "starknet_trace*",
+            "starknet_traceBlockTransactions"
+        ));
+        assert!(!matches("starknet_trace*", "starknet_call"));
+
+        assert!(matches(
+            "starknet_add*Transaction",
+            "starknet_addInvokeTransaction"
+        ));
+        assert!(!matches(
+            "starknet_add*Transaction",
+            "starknet_addInvokeTransactionX"
+        ));
+        assert!(matches("*_get*", "pathfinder_getProof"));
+        // Wildcard parts must not overlap.
+        assert!(!matches("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn deny_overrides_allow() {
+        let filter = MethodFilter {
+            allow: vec!["starknet_get*".to_owned(), "starknet_call".to_owned()],
+            deny: vec!["starknet_getEvents".to_owned()],
+        };
+
+        assert!(filter.is_enabled("starknet_getNonce"));
+        assert!(filter.is_enabled("starknet_call"));
+        assert!(!filter.is_enabled("starknet_getEvents"));
+        assert!(!filter.is_enabled("starknet_addInvokeTransaction"));
+
+        assert!(MethodFilter::default().is_enabled("starknet_addInvokeTransaction"));
+    }
+}
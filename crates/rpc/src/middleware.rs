@@ -1,3 +1,4 @@
+pub mod auth;
 pub mod cors;
 pub(crate) mod request_id;
 pub(crate) mod tracing;
@@ -0,0 +1,400 @@
+//! Authentication of requests to selected routes.
+//!
+//! Callers authenticate either with a static API key, presented in the
+//! `X-API-Key` header or as an `Authorization: Bearer <key>` header, or with an
+//! HS256 JSON Web Token presented as `Authorization: Bearer <token>`. As with
+//! the Ethereum engine API, tokens must carry an `iat` claim close to the
+//! current time, which limits the damage a leaked token can do.
+//!
+//! Routes are protected per RPC version: protecting one path of a version also
+//! protects the other paths serving it, e.g. `/ws/rpc/v0_8` along with
+//! `/rpc/v0_8`, or `/` and `/ws` along with the path of the default version.
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use hmac::{Hmac, Mac};
+
+use crate::rate_limit::API_KEY_HEADER;
+use crate::RpcVersion;
+
+/// How far a token's `iat` claim may be from the current time.
+const MAX_TOKEN_AGE: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Default)]
+pub struct AuthConfig {
+    /// Requests to these paths must be authenticated. Other paths are open.
+    pub routes: HashSet<String>,
+    pub api_keys: HashSet<String>,
+    /// The secret JSON Web Tokens are signed with.
+    pub jwt_secret: Option<Vec<u8>>,
+}
+
+#[derive(Clone)]
+pub struct Auth {
+    config: Arc<AuthConfig>,
+    /// The RPC versions served on the protected routes.
+    protected_versions: HashSet<RpcVersion>,
+    default_version: RpcVersion,
+}
+
+impl Auth {
+    pub fn new(config: AuthConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            protected_versions: Default::default(),
+            default_version: Default::default(),
+        }
+    }
+
+    /// Resolves the protected routes to the RPC versions served on them.
+    ///
+    /// Fails if a protected route doesn't serve any RPC version.
+    pub(crate) fn with_default_version(self, default_version: RpcVersion) -> anyhow::Result<Self> {
+        let protected_versions = self
+            .config
+            .routes
+            .iter()
+            .map(|route| {
+                RpcVersion::from_path(route, default_version)
+                    .with_context(|| format!("Authenticated route {route} is not an RPC path"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            protected_versions,
+            default_version,
+            ..self
+        })
+    }
+
+    fn is_protected(&self, path: &str) -> bool {
+        RpcVersion::from_path(path, self.default_version)
+            .is_some_and(|version| self.protected_versions.contains(&version))
+    }
+
+    fn is_authenticated(&self, headers: &http::HeaderMap) -> bool {
+        let api_key = headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok());
+        if api_key.is_some_and(|key| self.is_api_key(key)) {
+            return true;
+        }
+
+        let Some(bearer) = headers
+            .get(http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return false;
+        };
+
+        self.is_api_key(bearer)
+            || self
+                .config
+                .jwt_secret
+                .as_ref()
+                .is_some_and(|secret| verify_jwt(bearer, secret, SystemTime::now()).is_ok())
+    }
+
+    fn is_api_key(&self, key: &str) -> bool {
+        self.config
+            .api_keys
+            .iter()
+            .any(|api_key| constant_time_eq(api_key.as_bytes(), key.as_bytes()))
+    }
+}
+
+/// Rejects unauthenticated requests to protected routes with `401
+/// Unauthorized`.
+pub async fn authenticate(State(auth): State<Auth>, request: Request, next: Next) -> Response {
+    if auth.is_protected(request.uri().path()) && !auth.is_authenticated(request.headers()) {
+        metrics::increment_counter!("rpc_unauthorized_requests_total");
+        return http::StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Compares without short-circuiting, so that response times don't leak how
+/// much of a secret was guessed correctly.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+enum JwtError {
+    #[error("Malformed token")]
+    Malformed,
+    #[error("Unsupported algorithm")]
+    UnsupportedAlgorithm,
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Token issued too far from the current time")]
+    Stale,
+    #[error("Token expired")]
+    Expired,
+}
+
+#[derive(serde::Deserialize)]
+struct JwtHeader {
+    alg: String,
+}
+
+#[derive(serde::Deserialize)]
+struct JwtClaims {
+    iat: u64,
+    exp: Option<u64>,
+}
+
+fn verify_jwt(token: &str, secret: &[u8], now: SystemTime) -> Result<(), JwtError> {
+    let mut parts = token.split('.');
+    let (Some(header), Some(claims), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(JwtError::Malformed);
+    };
+
+    let decode = |part: &str| {
+        base64::decode_config(part, base64::URL_SAFE_NO_PAD).map_err(|_| JwtError::Malformed)
+    };
+
+    let jwt_header: JwtHeader =
+        serde_json::from_slice(&decode(header)?).map_err(|_| JwtError::Malformed)?;
+    if jwt_header.alg != "HS256" {
+        return Err(JwtError::UnsupportedAlgorithm);
+    }
+
+    let mut mac =
+        Hmac::<sha2::Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(header.as_bytes());
+    mac.update(b".");
+    mac.update(claims.as_bytes());
+    mac.verify_slice(&decode(signature)?)
+        .map_err(|_| JwtError::InvalidSignature)?;
+
+    let claims: JwtClaims =
+        serde_json::from_slice(&decode(claims)?).map_err(|_| JwtError::Malformed)?;
+    let now = now
+        .duration_since(UNIX_EPOCH)
+        .expect("Current time is after the epoch")
+        .as_secs();
+    if now.abs_diff(claims.iat) > MAX_TOKEN_AGE.as_secs() {
+        return Err(JwtError::Stale);
+    }
+    if claims.exp.is_some_and(|exp| exp <= now) {
+        return Err(JwtError::Expired);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::context::RpcContext;
+    use crate::{RpcServer, RpcVersion};
+
+    const SECRET: &[u8] = b"secret";
+
+    fn token(header: serde_json::Value, claims: serde_json::Value, secret: &[u8]) -> String {
+        let encode = |value: serde_json::Value| {
+            base64::encode_config(value.to_string(), base64::URL_SAFE_NO_PAD)
+        };
+        let signing_input = format!("{}.{}", encode(header), encode(claims));
+
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret).unwrap();
+        mac.update(signing_input.as_bytes());
+        let signature = mac.finalize().into_bytes();
+
+        format!(
+            "{signing_input}.{}",
+            base64::encode_config(signature, base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn jwt_verification() {
+        let header = json!({"alg": "HS256", "typ": "JWT"});
+        let now = SystemTime::now();
+
+        let valid = token(header.clone(), json!({"iat": self::now()}), SECRET);
+        assert_eq!(verify_jwt(&valid, SECRET, now), Ok(()));
+        assert_eq!(
+            verify_jwt(&valid, b"other", now),
+            Err(JwtError::InvalidSignature)
+        );
+        assert_eq!(
+            verify_jwt(&valid, SECRET, now + Duration::from_secs(120)),
+            Err(JwtError::Stale)
+        );
+
+        let expired = token(
+            header.clone(),
+            json!({"iat": self::now(), "exp": self::now() - 1}),
+            SECRET,
+        );
+        assert_eq!(verify_jwt(&expired, SECRET, now), Err(JwtError::Expired));
+
+        let unsigned = token(json!({"alg": "none"}), json!({"iat": self::now()}), SECRET);
+        assert_eq!(
+            verify_jwt(&unsigned, SECRET, now),
+            Err(JwtError::UnsupportedAlgorithm)
+        );
+
+        assert_eq!(
+            verify_jwt("not.a.token", SECRET, now),
+            Err(JwtError::Malformed)
+        );
+    }
+
+    #[tokio::test]
+    async fn protected_routes() {
+        let auth = Auth::new(AuthConfig {
+            routes: HashSet::from(["/rpc/v0_8".to_owned()]),
+            api_keys: HashSet::from(["key".to_owned()]),
+            jwt_secret: Some(SECRET.to_vec()),
+        });
+        let server = RpcServer::new(
+            "127.0.0.1:0".parse().unwrap(),
+            RpcContext::for_tests(),
+            RpcVersion::V07,
+        )
+        .with_auth(auth);
+        let (_server_handle, address) = server.spawn().await.unwrap();
+
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "starknet_chainId"});
+        let client = reqwest::Client::new();
+        let status = |path: &'static str, header: Option<(&'static str, String)>| {
+            let mut request = client
+                .post(format!("http://{address}{path}"))
+                .json(&request);
+            if let Some((name, value)) = header {
+                request = request.header(name, value);
+            }
+            async move { request.send().await.unwrap().status() }
+        };
+
+        assert_eq!(status("/rpc/v0_7", None).await, reqwest::StatusCode::OK);
+        assert_eq!(
+            status("/rpc/v0_8", None).await,
+            reqwest::StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status("/rpc/v0_8", Some(("x-api-key", "wrong".to_owned()))).await,
+            reqwest::StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status("/rpc/v0_8", Some(("x-api-key", "key".to_owned()))).await,
+            reqwest::StatusCode::OK
+        );
+        assert_eq!(
+            status(
+                "/rpc/v0_8",
+                Some(("authorization", "Bearer key".to_owned()))
+            )
+            .await,
+            reqwest::StatusCode::OK
+        );
+
+        let jwt = token(
+            json!({"alg": "HS256", "typ": "JWT"}),
+            json!({"iat": now()}),
+            SECRET,
+        );
+        assert_eq!(
+            status(
+                "/rpc/v0_8",
+                Some(("authorization", format!("Bearer {jwt}")))
+            )
+            .await,
+            reqwest::StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn aliases_of_protected_routes() {
+        let auth = Auth::new(AuthConfig {
+            routes: HashSet::from(["/rpc/v0_8".to_owned(), "/rpc/pathfinder/v0.1".to_owned()]),
+            api_keys: HashSet::from(["key".to_owned()]),
+            jwt_secret: None,
+        });
+        let (_, rx_pending) = tokio::sync::watch::channel(Default::default());
+        let context =
+            RpcContext::for_tests().with_websockets(crate::context::WebsocketContext::new(
+                std::num::NonZeroUsize::new(10).unwrap(),
+                std::num::NonZeroUsize::new(10).unwrap(),
+                rx_pending,
+            ));
+        let server = RpcServer::new("127.0.0.1:0".parse().unwrap(), context, RpcVersion::V08)
+            .with_auth(auth);
+        let (_server_handle, address) = server.spawn().await.unwrap();
+
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "starknet_chainId"});
+        let client = reqwest::Client::new();
+        let status = |path: &'static str, api_key: Option<&'static str>| {
+            let mut request = client
+                .post(format!("http://{address}{path}"))
+                .json(&request);
+            if let Some(api_key) = api_key {
+                request = request.header("x-api-key", api_key);
+            }
+            async move { request.send().await.unwrap().status() }
+        };
+
+        for path in [
+            "/",
+            "/ws",
+            "/rpc/v0_8",
+            "/ws/rpc/v0_8",
+            "/rpc/pathfinder/v0.1",
+            "/rpc/pathfinder/v0_1",
+            "/ws/rpc/pathfinder/v0_1",
+        ] {
+            assert_eq!(
+                status(path, None).await,
+                reqwest::StatusCode::UNAUTHORIZED,
+                "{path}"
+            );
+            assert_ne!(
+                status(path, Some("key")).await,
+                reqwest::StatusCode::UNAUTHORIZED,
+                "{path}"
+            );
+        }
+
+        for path in ["/rpc/v0_6", "/rpc/v0_7"] {
+            assert_eq!(status(path, None).await, reqwest::StatusCode::OK, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_protected_route() {
+        let auth = Auth::new(AuthConfig {
+            routes: HashSet::from(["/rpc/v0_9".to_owned()]),
+            api_keys: HashSet::from(["key".to_owned()]),
+            jwt_secret: None,
+        });
+        let server = RpcServer::new(
+            "127.0.0.1:0".parse().unwrap(),
+            RpcContext::for_tests(),
+            RpcVersion::V08,
+        )
+        .with_auth(auth);
+
+        assert!(server.spawn().await.is_err());
+    }
+}
@@ -0,0 +1,354 @@
+//! Per-client rate limiting of JSON-RPC method calls.
+//!
+//! Every client gets a token bucket. Each method call takes the method's cost
+//! in tokens from the caller's bucket, and calls are rejected with a
+//! `Limit exceeded` error while the bucket doesn't hold enough tokens. Starting
+//! a WebSocket subscription costs tokens like a method call. Clients presenting
+//! one of the configured API keys share a bucket per key, all other clients are
+//! identified by their IP address. IPv6 clients are identified by the /64
+//! prefix of their address, as hosts are commonly assigned a whole /64.
+//!
+//! New buckets start half full, so that clients can't regain a full bucket by
+//! getting their bucket dropped in favour of other clients.
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv6Addr};
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use cached::{Cached, SizedCache};
+
+/// The header through which clients present their API key.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// The number of clients whose buckets are tracked. The buckets of the least
+/// recently seen clients are dropped first.
+const MAX_CLIENTS: usize = 10_000;
+
+/// The cost of a method call whose method doesn't have an explicit cost.
+pub const DEFAULT_METHOD_COST: NonZeroU32 = NonZeroU32::MIN;
+
+/// The default cost of a method call, which is relative to the work required to
+/// serve it.
+pub(crate) fn default_method_cost(method_name: &str) -> NonZeroU32 {
+    let cost = match method_name {
+        "starknet_traceBlockTransactions" => 100,
+        "starknet_simulateTransactions" | "starknet_traceTransaction" => 20,
+        "starknet_estimateFee" | "starknet_estimateMessageFee" => 10,
+        "starknet_getEvents" | "pathfinder_getProof" | "pathfinder_getClassProof" => 10,
+        "starknet_subscribeEvents" => 10,
+        "starknet_call" => 5,
+        "starknet_addInvokeTransaction"
+        | "starknet_addDeclareTransaction"
+        | "starknet_addDeployAccountTransaction" => 5,
+        _ => return DEFAULT_METHOD_COST,
+    };
+
+    NonZeroU32::new(cost).expect("Costs are non-zero")
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BucketConfig {
+    /// The maximum number of tokens, i.e. the largest burst allowed.
+    pub capacity: NonZeroU32,
+    /// The number of tokens added per second.
+    pub refill_rate: NonZeroU32,
+}
+
+#[derive(Clone, Debug)]
+pub struct RateLimitConfig {
+    /// Limits clients identified by IP address.
+    pub ip: BucketConfig,
+    /// Limits clients identified by one of the configured API keys.
+    pub api_key: BucketConfig,
+    pub api_keys: HashSet<String>,
+}
+
+/// A rate limited client.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Client {
+    Ip(IpAddr),
+    ApiKey(Arc<str>),
+}
+
+impl Client {
+    /// The client class, used as a metrics label.
+    fn class(&self) -> &'static str {
+        match self {
+            Client::Ip(_) => "ip",
+            Client::ApiKey(_) => "api_key",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimiter(Arc<Inner>);
+
+struct Inner {
+    config: RateLimitConfig,
+    buckets: Mutex<SizedCache<Client, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self(Arc::new(Inner {
+            config,
+            buckets: Mutex::new(SizedCache::with_size(MAX_CLIENTS)),
+        }))
+    }
+
+    /// Identifies the client sending a request. Returns [None] if the client
+    /// can't be identified, in which case its requests are not limited.
+    pub(crate) fn client(&self, ip: Option<IpAddr>, headers: &http::HeaderMap) -> Option<Client> {
+        let api_key = headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|key| self.0.config.api_keys.contains(*key));
+
+        match api_key {
+            Some(key) => Some(Client::ApiKey(key.into())),
+            None => ip.map(|ip| Client::Ip(network(ip))),
+        }
+    }
+
+    /// Takes `cost` tokens from the client's bucket. Returns `false` if the
+    /// bucket doesn't hold enough tokens, in which case none are taken.
+    pub(crate) fn try_acquire(
+        &self,
+        client: &Client,
+        cost: NonZeroU32,
+        method_name: &'static str,
+    ) -> bool {
+        let config = self.0.bucket_config(client);
+        // Calls costing more than the capacity would never be allowed otherwise.
+        let cost = cost.min(config.capacity);
+        let now = Instant::now();
+
+        let acquired = self
+            .0
+            .buckets
+            .lock()
+            .unwrap()
+            .cache_get_or_set_with(client.clone(), || Bucket::new(config, now))
+            .refill(config, now)
+            .try_take(cost);
+
+        let class = client.class();
+        if acquired {
+            metrics::counter!("rpc_rate_limit_cost_total", u64::from(cost.get()), "class" => class);
+        } else {
+            metrics::increment_counter!("rpc_rate_limited_requests_total", "class" => class, "method" => method_name);
+        }
+
+        acquired
+    }
+}
+
+/// The network identifying a client with the address `ip`.
+fn network(ip: IpAddr) -> IpAddr {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => IpAddr::V4(ip),
+        IpAddr::V6(ip) => IpAddr::V6(Ipv6Addr::from(u128::from(ip) & !u128::from(u64::MAX))),
+    }
+}
+
+impl Inner {
+    fn bucket_config(&self, client: &Client) -> BucketConfig {
+        match client {
+            Client::Ip(_) => self.config.ip,
+            Client::ApiKey(_) => self.config.api_key,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    /// A half full bucket.
+    fn new(config: BucketConfig, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(config.capacity.get()) / 2.0,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, config: BucketConfig, now: Instant) -> &mut Self {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * f64::from(config.refill_rate.get()))
+            .min(config.capacity.get().into());
+        self.refilled_at = now;
+        self
+    }
+
+    fn try_take(&mut self, cost: NonZeroU32) -> bool {
+        let cost = f64::from(cost.get());
+        if self.tokens < cost {
+            return false;
+        }
+
+        self.tokens -= cost;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            ip: BucketConfig {
+                capacity: NonZeroU32::new(10).unwrap(),
+                refill_rate: NonZeroU32::new(1).unwrap(),
+            },
+            api_key: BucketConfig {
+                capacity: NonZeroU32::new(100).unwrap(),
+                refill_rate: NonZeroU32::new(10).unwrap(),
+            },
+            api_keys: HashSet::from(["key".to_owned()]),
+        })
+    }
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    /// Fills the client's bucket, as if it hadn't made any calls in a while.
+    fn fill(limiter: &RateLimiter, client: &Client) {
+        let config = limiter.0.bucket_config(client);
+        let bucket = Bucket {
+            tokens: config.capacity.get().into(),
+            refilled_at: Instant::now(),
+        };
+        limiter
+            .0
+            .buckets
+            .lock()
+            .unwrap()
+            .cache_set(client.clone(), bucket);
+    }
+
+    #[test]
+    fn clients_are_identified_by_configured_api_keys() {
+        let limiter = limiter();
+        let mut headers = http::HeaderMap::new();
+
+        assert_eq!(limiter.client(None, &headers), None);
+        assert_eq!(limiter.client(Some(IP), &headers), Some(Client::Ip(IP)));
+
+        headers.insert(API_KEY_HEADER, "unknown".parse().unwrap());
+        assert_eq!(limiter.client(Some(IP), &headers), Some(Client::Ip(IP)));
+
+        headers.insert(API_KEY_HEADER, "key".parse().unwrap());
+        assert_eq!(
+            limiter.client(Some(IP), &headers),
+            Some(Client::ApiKey("key".into()))
+        );
+    }
+
+    #[test]
+    fn ipv6_clients_are_identified_by_their_network() {
+        let limiter = limiter();
+        let headers = http::HeaderMap::new();
+        let network = Client::Ip("2001:db8:1:2::".parse().unwrap());
+
+        for ip in ["2001:db8:1:2::1", "2001:db8:1:2:ffff:ffff:ffff:ffff"] {
+            let ip = ip.parse().unwrap();
+            assert_eq!(limiter.client(Some(ip), &headers), Some(network.clone()));
+        }
+        let other = "2001:db8:1:3::1".parse().unwrap();
+        assert_ne!(limiter.client(Some(other), &headers), Some(network));
+
+        // IPv4 clients connecting over IPv6 are identified by their IPv4 address.
+        let mapped = IpAddr::V6(Ipv4Addr::new(192, 0, 2, 1).to_ipv6_mapped());
+        assert_eq!(
+            limiter.client(Some(mapped), &headers),
+            Some(Client::Ip(Ipv4Addr::new(192, 0, 2, 1).into()))
+        );
+    }
+
+    #[test]
+    fn calls_are_limited_by_cost() {
+        let limiter = limiter();
+        let client = Client::Ip(IP);
+        fill(&limiter, &client);
+        let cost = NonZeroU32::new(4).unwrap();
+
+        assert!(limiter.try_acquire(&client, cost, "method"));
+        assert!(limiter.try_acquire(&client, cost, "method"));
+        assert!(!limiter.try_acquire(&client, cost, "method"));
+        // The remaining tokens are still available for cheaper calls.
+        assert!(limiter.try_acquire(&client, NonZeroU32::new(2).unwrap(), "method"));
+
+        // Other clients have their own buckets.
+        let other = Client::ApiKey("key".into());
+        assert!(limiter.try_acquire(&other, cost, "method"));
+    }
+
+    #[test]
+    fn new_buckets_are_half_full() {
+        let limiter = limiter();
+        let client = Client::Ip(IP);
+
+        assert!(!limiter.try_acquire(&client, NonZeroU32::new(6).unwrap(), "method"));
+        assert!(limiter.try_acquire(&client, NonZeroU32::new(5).unwrap(), "method"));
+    }
+
+    #[test]
+    fn calls_costing_more_than_the_capacity_need_a_full_bucket() {
+        let limiter = limiter();
+        let client = Client::Ip(IP);
+        let cost = NonZeroU32::new(1000).unwrap();
+
+        assert!(!limiter.try_acquire(&client, cost, "method"));
+        fill(&limiter, &client);
+        assert!(limiter.try_acquire(&client, cost, "method"));
+        assert!(!limiter.try_acquire(&client, DEFAULT_METHOD_COST, "method"));
+    }
+
+    #[test]
+    fn least_recently_seen_clients_are_forgotten() {
+        let limiter = limiter();
+        let client = Client::Ip(IP);
+        let capacity = NonZeroU32::new(10).unwrap();
+
+        fill(&limiter, &client);
+        assert!(limiter.try_acquire(&client, capacity, "method"));
+        assert!(!limiter.try_acquire(&client, DEFAULT_METHOD_COST, "method"));
+
+        for i in 0..MAX_CLIENTS {
+            let other = Client::ApiKey(i.to_string().into());
+            assert!(limiter.try_acquire(&other, DEFAULT_METHOD_COST, "method"));
+        }
+
+        // The client starts over with a half full bucket.
+        assert!(!limiter.try_acquire(&client, capacity, "method"));
+        assert!(limiter.try_acquire(&client, NonZeroU32::new(5).unwrap(), "method"));
+    }
+
+    #[test]
+    fn buckets_refill_over_time() {
+        let config = BucketConfig {
+            capacity: NonZeroU32::new(10).unwrap(),
+            refill_rate: NonZeroU32::new(2).unwrap(),
+        };
+        let start = Instant::now();
+        let mut bucket = Bucket::new(config, start);
+
+        assert!(bucket.try_take(NonZeroU32::new(5).unwrap()));
+        assert!(!bucket.try_take(DEFAULT_METHOD_COST));
+
+        bucket.refill(config, start + Duration::from_secs(2));
+        assert!(bucket.try_take(NonZeroU32::new(4).unwrap()));
+        assert!(!bucket.try_take(DEFAULT_METHOD_COST));
+
+        // Buckets don't fill up beyond their capacity.
+        bucket.refill(config, start + Duration::from_secs(60));
+        assert!(bucket.try_take(NonZeroU32::new(10).unwrap()));
+        assert!(!bucket.try_take(DEFAULT_METHOD_COST));
+    }
+}
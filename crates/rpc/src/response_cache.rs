@@ -0,0 +1,435 @@
+//! Caching of JSON-RPC responses which only change on reorgs.
+//!
+//! Queries of blocks selected by hash or number, and of receipts of
+//! transactions which are included in a block, have the same answer until the
+//! block is reorged away or accepted on L1, which changes its finality status.
+//! The cache is therefore cleared on every reorg and L1 update. Only
+//! successful responses are cached.
+use std::collections::{BTreeMap, HashMap};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::jsonrpc::{Notifications, Reorg};
+use crate::RpcVersion;
+
+/// Methods whose response is immutable if they select a block by hash or
+/// number.
+const BLOCK_METHODS: &[&str] = &[
+    "starknet_getBlockWithTxHashes",
+    "starknet_getBlockWithTxs",
+    "starknet_getBlockWithReceipts",
+    "starknet_getStateUpdate",
+    "starknet_getBlockTransactionCount",
+    "starknet_getTransactionByBlockIdAndIndex",
+    "starknet_getClass",
+    "starknet_getClassAt",
+    "starknet_getClassHashAt",
+    "starknet_getNonce",
+    "starknet_getStorageAt",
+];
+
+/// Methods whose response is immutable once the transaction is included in a
+/// block.
+const TRANSACTION_METHODS: &[&str] = &["starknet_getTransactionReceipt"];
+
+/// The key of a cacheable method call.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    version: RpcVersion,
+    method: &'static str,
+    /// The params serialized with sorted object keys and without whitespace,
+    /// so that equivalent requests share an entry.
+    params: String,
+}
+
+/// Identifies the contents of the cache between two clears. Responses computed
+/// while the cache was cleared may already be outdated and aren't inserted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Generation(u64);
+
+#[derive(Clone)]
+pub struct ResponseCache(Arc<Mutex<Inner>>);
+
+struct Inner {
+    responses: HashMap<CacheKey, Entry>,
+    /// The keys of [Inner::responses] by the tick they were last used at, from
+    /// the least to the most recently used.
+    recency: BTreeMap<u64, CacheKey>,
+    tick: u64,
+    /// The approximate size of all cached responses in bytes.
+    size: usize,
+    max_size: usize,
+    generation: Generation,
+    reorgs: broadcast::Receiver<Arc<Reorg>>,
+    l1_updates: broadcast::Receiver<pathfinder_common::BlockNumber>,
+}
+
+struct Entry {
+    response: Value,
+    size: usize,
+    last_used: u64,
+}
+
+impl ResponseCache {
+    /// Creates a cache which keeps responses up to a total of `max_size` bytes.
+    pub fn new(max_size: NonZeroUsize, notifications: &Notifications) -> Self {
+        Self(Arc::new(Mutex::new(Inner {
+            responses: Default::default(),
+            recency: Default::default(),
+            tick: 0,
+            size: 0,
+            max_size: max_size.get(),
+            generation: Generation(0),
+            reorgs: notifications.reorgs.subscribe(),
+            l1_updates: notifications.l1_updates.subscribe(),
+        })))
+    }
+
+    /// Returns the cache key of a method call, or [None] if its response may
+    /// change other than by a reorg or L1 update.
+    pub(crate) fn key(
+        version: RpcVersion,
+        method: &'static str,
+        params: Option<&serde_json::value::RawValue>,
+    ) -> Option<CacheKey> {
+        let is_block_method = BLOCK_METHODS.contains(&method);
+        if !is_block_method && !TRANSACTION_METHODS.contains(&method) {
+            return None;
+        }
+
+        let params: Value = match params {
+            Some(params) => serde_json::from_str(params.get()).ok()?,
+            None => Value::Null,
+        };
+        if is_block_method && !selects_block_by_hash_or_number(&params) {
+            return None;
+        }
+
+        let mut canonical = String::new();
+        canonicalize(&params, &mut canonical);
+
+        Some(CacheKey {
+            version,
+            method,
+            params: canonical,
+        })
+    }
+
+    /// The current generation of the cache, which has to be captured before
+    /// computing a response to be inserted.
+    pub(crate) fn generation(&self) -> Generation {
+        let mut inner = self.0.lock().unwrap();
+        inner.invalidate_on_notifications();
+        inner.generation
+    }
+
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<Value> {
+        let mut inner = self.0.lock().unwrap();
+        inner.invalidate_on_notifications();
+
+        let response = inner.get(key);
+        match response {
+            Some(_) => {
+                metrics::increment_counter!("rpc_response_cache_hits_total", "method" => key.method)
+            }
+            None => {
+                metrics::increment_counter!("rpc_response_cache_misses_total", "method" => key.method)
+            }
+        }
+
+        response
+    }
+
+    /// Inserts a response computed in `generation`, unless the cache was
+    /// cleared since.
+    pub(crate) fn insert(&self, key: CacheKey, response: &Value, generation: Generation) {
+        if TRANSACTION_METHODS.contains(&key.method) && !is_included_in_block(response) {
+            return;
+        }
+
+        let mut inner = self.0.lock().unwrap();
+        inner.invalidate_on_notifications();
+        if inner.generation != generation {
+            return;
+        }
+        inner.insert(key, response.clone());
+    }
+}
+
+impl Inner {
+    fn get(&mut self, key: &CacheKey) -> Option<Value> {
+        self.tick += 1;
+        let entry = self.responses.get_mut(key)?;
+
+        self.recency.remove(&entry.last_used);
+        entry.last_used = self.tick;
+        self.recency.insert(entry.last_used, key.clone());
+
+        Some(entry.response.clone())
+    }
+
+    /// Inserts the response, evicting the least recently used responses until
+    /// the cache fits within its maximum size.
+    fn insert(&mut self, key: CacheKey, response: Value) {
+        let size = key.params.len() + response.to_string().len();
+        if size > self.max_size {
+            return;
+        }
+
+        self.remove(&key);
+        while self.size + size > self.max_size {
+            let Some((_, lru)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.responses.remove(&lru) {
+                self.size -= entry.size;
+            }
+        }
+
+        self.tick += 1;
+        self.size += size;
+        self.recency.insert(self.tick, key.clone());
+        self.responses.insert(
+            key,
+            Entry {
+                response,
+                size,
+                last_used: self.tick,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.responses.remove(key) {
+            self.recency.remove(&entry.last_used);
+            self.size -= entry.size;
+        }
+    }
+
+    /// Clears the cache if there was a reorg or L1 update since the last
+    /// access.
+    fn invalidate_on_notifications(&mut self) {
+        let reorged = drain(&mut self.reorgs);
+        let l1_updated = drain(&mut self.l1_updates);
+
+        if reorged || l1_updated {
+            tracing::trace!(%reorged, %l1_updated, "Clearing response cache");
+            self.responses.clear();
+            self.recency.clear();
+            self.size = 0;
+            self.generation.0 += 1;
+        }
+    }
+}
+
+/// Receives all pending messages. Returns `true` if there were any, including
+/// ones that were missed because the receiver lagged behind.
+fn drain<T: Clone>(receiver: &mut broadcast::Receiver<T>) -> bool {
+    let mut received = false;
+    loop {
+        match receiver.try_recv() {
+            Ok(_) | Err(broadcast::error::TryRecvError::Lagged(_)) => received = true,
+            Err(broadcast::error::TryRecvError::Empty | broadcast::error::TryRecvError::Closed) => {
+                return received
+            }
+        }
+    }
+}
+
+/// Whether the params select a block by hash or number and don't contain a
+/// block tag such as `latest` or `pending`.
+fn selects_block_by_hash_or_number(params: &Value) -> bool {
+    let values: Vec<&Value> = match params {
+        Value::Array(values) => values.iter().collect(),
+        Value::Object(values) => values.values().collect(),
+        _ => return false,
+    };
+
+    let is_tag = |value: &Value| matches!(value.as_str(), Some("latest" | "pending"));
+    let is_pinned = |value: &Value| {
+        value.as_object().is_some_and(|block_id| {
+            block_id.contains_key("block_hash") || block_id.contains_key("block_number")
+        })
+    };
+
+    !values.iter().copied().any(is_tag) && values.iter().copied().any(is_pinned)
+}
+
+/// Receipts of pending transactions don't have a block hash.
+fn is_included_in_block(response: &Value) -> bool {
+    response.get("block_hash").is_some()
+}
+
+/// Serializes the value with object keys in sorted order.
+fn canonicalize(value: &Value, out: &mut String) {
+    match value {
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                canonicalize(value, out);
+            }
+            out.push(']');
+        }
+        Value::Object(values) => {
+            let mut entries = values.iter().collect::<Vec<_>>();
+            entries.sort_unstable_by_key(|(key, _)| *key);
+
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                canonicalize(value, out);
+            }
+            out.push('}');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::{BlockHash, BlockNumber};
+    use serde_json::json;
+    use serde_json::value::RawValue;
+
+    use super::*;
+
+    fn key(method: &'static str, params: Value) -> Option<CacheKey> {
+        let params = RawValue::from_string(params.to_string()).unwrap();
+        ResponseCache::key(RpcVersion::V08, method, Some(&params))
+    }
+
+    #[test]
+    fn only_pinned_blocks_are_cacheable() {
+        let method = "starknet_getBlockWithTxs";
+
+        assert!(key(method, json!([{"block_number": 1}])).is_some());
+        assert!(key(method, json!({"block_id": {"block_hash": "0x1"}})).is_some());
+        assert!(key(method, json!(["latest"])).is_none());
+        assert!(key(method, json!({"block_id": "pending"})).is_none());
+        assert!(key("starknet_blockNumber", json!([])).is_none());
+        assert!(key(
+            "starknet_getStorageAt",
+            json!(["0x1", "0x2", {"block_number": 1}])
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn equivalent_params_share_a_key() {
+        let method = "starknet_getClass";
+
+        assert_eq!(
+            key(
+                method,
+                json!({"block_id": {"block_number": 1}, "class_hash": "0x1"})
+            ),
+            key(
+                method,
+                json!({"class_hash": "0x1", "block_id": {"block_number": 1}})
+            ),
+        );
+        assert_ne!(
+            key(method, json!([{"block_number": 1}, "0x1"])),
+            key(method, json!([{"block_number": 2}, "0x1"])),
+        );
+    }
+
+    #[test]
+    fn pending_receipts_are_not_cached() {
+        let cache = ResponseCache::new(NonZeroUsize::new(1000).unwrap(), &Notifications::default());
+        let method = "starknet_getTransactionReceipt";
+
+        let pending = key(method, json!(["0x1"])).unwrap();
+        cache.insert(
+            pending.clone(),
+            &json!({"transaction_hash": "0x1"}),
+            cache.generation(),
+        );
+        assert_eq!(cache.get(&pending), None);
+
+        let included = key(method, json!(["0x2"])).unwrap();
+        let receipt = json!({"transaction_hash": "0x2", "block_hash": "0x3"});
+        cache.insert(included.clone(), &receipt, cache.generation());
+        assert_eq!(cache.get(&included), Some(receipt));
+    }
+
+    #[test]
+    fn cleared_on_reorgs_and_l1_updates() {
+        let notifications = Notifications::default();
+        let cache = ResponseCache::new(NonZeroUsize::new(1000).unwrap(), &notifications);
+        let key = key("starknet_getBlockWithTxs", json!([{"block_number": 1}])).unwrap();
+        let response = json!({"block_number": 1});
+
+        cache.insert(key.clone(), &response, cache.generation());
+        assert_eq!(cache.get(&key), Some(response.clone()));
+
+        notifications
+            .reorgs
+            .send(Arc::new(Reorg {
+                first_block_number: BlockNumber::GENESIS,
+                first_block_hash: BlockHash::ZERO,
+                last_block_number: BlockNumber::GENESIS,
+                last_block_hash: BlockHash::ZERO,
+            }))
+            .unwrap();
+        assert_eq!(cache.get(&key), None);
+
+        cache.insert(key.clone(), &response, cache.generation());
+        notifications.l1_updates.send(BlockNumber::GENESIS).unwrap();
+        assert_eq!(cache.get(&key), None);
+    }
+
+    #[test]
+    fn responses_computed_across_a_clear_are_not_inserted() {
+        let notifications = Notifications::default();
+        let cache = ResponseCache::new(NonZeroUsize::new(1000).unwrap(), &notifications);
+        let key = key("starknet_getBlockWithTxs", json!([{"block_number": 1}])).unwrap();
+
+        let generation = cache.generation();
+        notifications.l1_updates.send(BlockNumber::GENESIS).unwrap();
+        cache.insert(key.clone(), &json!({"block_number": 1}), generation);
+
+        assert_eq!(cache.get(&key), None);
+    }
+
+    #[test]
+    fn least_recently_used_responses_are_evicted_by_size() {
+        let response = json!({"block_number": 1});
+        // Each entry takes the size of its params and its response.
+        let entry_size = r#"[{"block_number":1}]"#.len() + response.to_string().len();
+        let cache = ResponseCache::new(
+            NonZeroUsize::new(2 * entry_size).unwrap(),
+            &Notifications::default(),
+        );
+        let method = "starknet_getBlockWithTxs";
+        let first = key(method, json!([{"block_number": 1}])).unwrap();
+        let second = key(method, json!([{"block_number": 2}])).unwrap();
+        let third = key(method, json!([{"block_number": 3}])).unwrap();
+
+        cache.insert(first.clone(), &response, cache.generation());
+        cache.insert(second.clone(), &response, cache.generation());
+        // Makes the second response the least recently used one.
+        assert!(cache.get(&first).is_some());
+        cache.insert(third.clone(), &response, cache.generation());
+
+        assert!(cache.get(&first).is_some());
+        assert_eq!(cache.get(&second), None);
+        assert!(cache.get(&third).is_some());
+
+        let too_large = key(method, json!([{"block_number": 4}])).unwrap();
+        let large_response = json!({"transactions": vec!["0x1"; 100]});
+        cache.insert(too_large.clone(), &large_response, cache.generation());
+        assert_eq!(cache.get(&too_large), None);
+        assert!(cache.get(&first).is_some());
+    }
+}
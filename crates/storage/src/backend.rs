@@ -0,0 +1,586 @@
+//! Backend-agnostic access to the core blockchain data.
+//!
+//! [StorageBackend] describes reading and writing block headers, state
+//! updates, transactions, events and merkle tries. The SQLite
+//! [Transaction](crate::Transaction) is the production implementation, while
+//! [InMemoryStorage] keeps everything in plain collections and is intended for
+//! tests and tooling.
+//!
+//! Functionality that is specific to the SQLite schema (pruning, bloom filters,
+//! class definitions, ...) remains on [Transaction](crate::Transaction) only.
+use pathfinder_common::event::Event;
+use pathfinder_common::prelude::*;
+use pathfinder_common::receipt::Receipt;
+use pathfinder_common::transaction::Transaction as StarknetTransaction;
+
+use crate::{BlockId, RootIndexUpdate, StoredNode, Transaction, TrieStorageIndex, TrieUpdate};
+
+mod memory;
+
+pub use memory::InMemoryStorage;
+
+/// The merkle tries maintained by the storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trie {
+    Class,
+    Contract,
+    Storage,
+}
+
+/// Read and write access to the core blockchain data, independent of the
+/// underlying storage engine.
+///
+/// Semantics follow the SQLite implementation, e.g. state queries at a block
+/// return the latest value set at or before that block.
+pub trait StorageBackend {
+    fn insert_block_header(&self, header: &BlockHeader) -> anyhow::Result<()>;
+
+    fn block_header(&self, block: BlockId) -> anyhow::Result<Option<BlockHeader>>;
+
+    fn block_id(&self, block: BlockId) -> anyhow::Result<Option<(BlockNumber, BlockHash)>>;
+
+    fn insert_state_update(
+        &self,
+        block_number: BlockNumber,
+        state_update: &StateUpdate,
+    ) -> anyhow::Result<()>;
+
+    /// The block hash and state commitments are taken from the block headers.
+    fn state_update(&self, block: BlockId) -> anyhow::Result<Option<StateUpdate>>;
+
+    fn storage_value(
+        &self,
+        block: BlockId,
+        contract_address: ContractAddress,
+        key: StorageAddress,
+    ) -> anyhow::Result<Option<StorageValue>>;
+
+    fn contract_nonce(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> anyhow::Result<Option<ContractNonce>>;
+
+    fn contract_class_hash(
+        &self,
+        block: BlockId,
+        contract_address: ContractAddress,
+    ) -> anyhow::Result<Option<ClassHash>>;
+
+    /// Inserts the transaction, receipt and event data of a block.
+    fn insert_transaction_data(
+        &self,
+        block_number: BlockNumber,
+        transactions: &[(StarknetTransaction, Receipt)],
+        events: Option<&[Vec<Event>]>,
+    ) -> anyhow::Result<()>;
+
+    fn transaction_with_receipt(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> anyhow::Result<Option<(StarknetTransaction, Receipt, Vec<Event>, BlockNumber)>>;
+
+    fn transactions_with_receipts_for_block(
+        &self,
+        block: BlockId,
+    ) -> anyhow::Result<Option<Vec<(StarknetTransaction, Receipt)>>>;
+
+    /// Returns `None` if the block or its events are not stored.
+    fn events_for_block(
+        &self,
+        block: BlockId,
+    ) -> anyhow::Result<Option<Vec<(TransactionHash, Vec<Event>)>>>;
+
+    fn insert_trie(
+        &self,
+        trie: Trie,
+        update: &TrieUpdate,
+        block_number: BlockNumber,
+    ) -> anyhow::Result<RootIndexUpdate>;
+
+    fn trie_node(&self, trie: Trie, index: TrieStorageIndex) -> anyhow::Result<Option<StoredNode>>;
+
+    fn insert_class_root(
+        &self,
+        block_number: BlockNumber,
+        update: RootIndexUpdate,
+    ) -> anyhow::Result<()>;
+
+    fn class_root_index(
+        &self,
+        block_number: BlockNumber,
+    ) -> anyhow::Result<Option<TrieStorageIndex>>;
+
+    fn insert_storage_root(
+        &self,
+        block_number: BlockNumber,
+        update: RootIndexUpdate,
+    ) -> anyhow::Result<()>;
+
+    fn storage_root_index(
+        &self,
+        block_number: BlockNumber,
+    ) -> anyhow::Result<Option<TrieStorageIndex>>;
+
+    fn insert_contract_root(
+        &self,
+        block_number: BlockNumber,
+        contract: ContractAddress,
+        update: RootIndexUpdate,
+    ) -> anyhow::Result<()>;
+
+    fn contract_root_index(
+        &self,
+        block_number: BlockNumber,
+        contract: ContractAddress,
+    ) -> anyhow::Result<Option<TrieStorageIndex>>;
+}
+
+// The SQLite implementation simply forwards to the inherent methods.
+impl StorageBackend for Transaction<'_> {
+    fn insert_block_header(&self, header: &BlockHeader) -> anyhow::Result<()> {
+        Transaction::insert_block_header(self, header)
+    }
+
+    fn block_header(&self, block: BlockId) -> anyhow::Result<Option<BlockHeader>> {
+        Transaction::block_header(self, block)
+    }
+
+    fn block_id(&self, block: BlockId) -> anyhow::Result<Option<(BlockNumber, BlockHash)>> {
+        Transaction::block_id(self, block)
+    }
+
+    fn insert_state_update(
+        &self,
+        block_number: BlockNumber,
+        state_update: &StateUpdate,
+    ) -> anyhow::Result<()> {
+        Transaction::insert_state_update(self, block_number, state_update)
+    }
+
+    fn state_update(&self, block: BlockId) -> anyhow::Result<Option<StateUpdate>> {
+        Transaction::state_update(self, block)
+    }
+
+    fn storage_value(
+        &self,
+        block: BlockId,
+        contract_address: ContractAddress,
+        key: StorageAddress,
+    ) -> anyhow::Result<Option<StorageValue>> {
+        Transaction::storage_value(self, block, contract_address, key)
+    }
+
+    fn contract_nonce(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> anyhow::Result<Option<ContractNonce>> {
+        Transaction::contract_nonce(self, contract_address, block)
+    }
+
+    fn contract_class_hash(
+        &self,
+        block: BlockId,
+        contract_address: ContractAddress,
+    ) -> anyhow::Result<Option<ClassHash>> {
+        Transaction::contract_class_hash(self, block, contract_address)
+    }
+
+    fn insert_transaction_data(
+        &self,
+        block_number: BlockNumber,
+        transactions: &[(StarknetTransaction, Receipt)],
+        events: Option<&[Vec<Event>]>,
+    ) -> anyhow::Result<()> {
+        Transaction::insert_transaction_data(self, block_number, transactions, events)
+    }
+
+    fn transaction_with_receipt(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> anyhow::Result<Option<(StarknetTransaction, Receipt, Vec<Event>, BlockNumber)>> {
+        Transaction::transaction_with_receipt(self, transaction_hash)
+    }
+
+    fn transactions_with_receipts_for_block(
+        &self,
+        block: BlockId,
+    ) -> anyhow::Result<Option<Vec<(StarknetTransaction, Receipt)>>> {
+        Transaction::transactions_with_receipts_for_block(self, block)
+    }
+
+    fn events_for_block(
+        &self,
+        block: BlockId,
+    ) -> anyhow::Result<Option<Vec<(TransactionHash, Vec<Event>)>>> {
+        Transaction::events_for_block(self, block)
+    }
+
+    fn insert_trie(
+        &self,
+        trie: Trie,
+        update: &TrieUpdate,
+        block_number: BlockNumber,
+    ) -> anyhow::Result<RootIndexUpdate> {
+        match trie {
+            Trie::Class => self.insert_class_trie(update, block_number),
+            Trie::Contract => self.insert_contract_trie(update, block_number),
+            Trie::Storage => self.insert_storage_trie(update, block_number),
+        }
+    }
+
+    fn trie_node(&self, trie: Trie, index: TrieStorageIndex) -> anyhow::Result<Option<StoredNode>> {
+        match trie {
+            Trie::Class => self.class_trie_node(index),
+            Trie::Contract => self.contract_trie_node(index),
+            Trie::Storage => self.storage_trie_node(index),
+        }
+    }
+
+    fn insert_class_root(
+        &self,
+        block_number: BlockNumber,
+        update: RootIndexUpdate,
+    ) -> anyhow::Result<()> {
+        Transaction::insert_class_root(self, block_number, update)
+    }
+
+    fn class_root_index(
+        &self,
+        block_number: BlockNumber,
+    ) -> anyhow::Result<Option<TrieStorageIndex>> {
+        Transaction::class_root_index(self, block_number)
+    }
+
+    fn insert_storage_root(
+        &self,
+        block_number: BlockNumber,
+        update: RootIndexUpdate,
+    ) -> anyhow::Result<()> {
+        Transaction::insert_storage_root(self, block_number, update)
+    }
+
+    fn storage_root_index(
+        &self,
+        block_number: BlockNumber,
+    ) -> anyhow::Result<Option<TrieStorageIndex>> {
+        Transaction::storage_root_index(self, block_number)
+    }
+
+    fn insert_contract_root(
+        &self,
+        block_number: BlockNumber,
+        contract: ContractAddress,
+        update: RootIndexUpdate,
+    ) -> anyhow::Result<()> {
+        Transaction::insert_contract_root(self, block_number, contract, update)
+    }
+
+    fn contract_root_index(
+        &self,
+        block_number: BlockNumber,
+        contract: ContractAddress,
+    ) -> anyhow::Result<Option<TrieStorageIndex>> {
+        Transaction::contract_root_index(self, block_number, contract)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::prelude::*;
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_crypto::Felt;
+
+    use super::*;
+    use crate::{Node, NodeRef, StorageBuilder};
+
+    /// Exercises the backend with the same data and expectations, so that all
+    /// implementations are held to the same semantics.
+    fn exercise(backend: &impl StorageBackend) {
+        let genesis = BlockHeader::builder()
+            .number(BlockNumber::GENESIS)
+            .state_commitment(state_commitment!("0x100"))
+            .finalize_with_hash(block_hash!("0xa"));
+        let block1 = genesis
+            .child_builder()
+            .state_commitment(state_commitment!("0x101"))
+            .finalize_with_hash(block_hash!("0xb"));
+
+        let contract = contract_address!("0x1234");
+        let key = storage_address!("0x1");
+
+        backend.insert_block_header(&genesis).unwrap();
+        backend.insert_block_header(&block1).unwrap();
+
+        backend
+            .insert_state_update(
+                genesis.number,
+                &StateUpdate::default()
+                    .with_deployed_contract(contract, class_hash!("0xc1"))
+                    .with_storage_update(contract, key, storage_value!("0x5"))
+                    .with_contract_nonce(contract, contract_nonce!("0x1")),
+            )
+            .unwrap();
+        backend
+            .insert_state_update(
+                block1.number,
+                &StateUpdate::default()
+                    .with_replaced_class(contract, class_hash!("0xc2"))
+                    .with_storage_update(contract, key, storage_value!("0x6"))
+                    .with_declared_cairo_class(class_hash!("0xc3"))
+                    .with_declared_sierra_class(sierra_hash!("0xc4"), casm_hash!("0xc5"))
+                    // Redeclarations are not part of the state update.
+                    .with_declared_cairo_class(class_hash!("0xc1")),
+            )
+            .unwrap();
+
+        assert_eq!(
+            backend.block_header(BlockId::Latest).unwrap(),
+            Some(block1.clone())
+        );
+        assert_eq!(
+            backend.block_header(block_hash!("0xa").into()).unwrap(),
+            Some(genesis.clone())
+        );
+        assert_eq!(
+            backend
+                .block_id(BlockNumber::new_or_panic(1).into())
+                .unwrap(),
+            Some((block1.number, block1.hash))
+        );
+        assert_eq!(
+            backend
+                .block_header(BlockNumber::new_or_panic(2).into())
+                .unwrap(),
+            None
+        );
+
+        // The class deployed without a declaration is declared implicitly.
+        let state_update = backend.state_update(genesis.hash.into()).unwrap().unwrap();
+        assert_eq!(
+            state_update,
+            StateUpdate::default()
+                .with_block_hash(genesis.hash)
+                .with_state_commitment(genesis.state_commitment)
+                .with_deployed_contract(contract, class_hash!("0xc1"))
+                .with_storage_update(contract, key, storage_value!("0x5"))
+                .with_contract_nonce(contract, contract_nonce!("0x1"))
+                .with_declared_cairo_class(class_hash!("0xc1"))
+        );
+
+        let state_update = backend.state_update(block1.hash.into()).unwrap().unwrap();
+        assert_eq!(
+            state_update,
+            StateUpdate::default()
+                .with_block_hash(block1.hash)
+                .with_state_commitment(block1.state_commitment)
+                .with_parent_state_commitment(genesis.state_commitment)
+                .with_replaced_class(contract, class_hash!("0xc2"))
+                .with_storage_update(contract, key, storage_value!("0x6"))
+                .with_declared_cairo_class(class_hash!("0xc3"))
+                .with_declared_sierra_class(sierra_hash!("0xc4"), casm_hash!("0xc5"))
+        );
+
+        assert_eq!(
+            backend
+                .storage_value(genesis.number.into(), contract, key)
+                .unwrap(),
+            Some(storage_value!("0x5"))
+        );
+        assert_eq!(
+            backend
+                .storage_value(BlockId::Latest, contract, key)
+                .unwrap(),
+            Some(storage_value!("0x6"))
+        );
+        assert_eq!(
+            backend.contract_nonce(contract, BlockId::Latest).unwrap(),
+            Some(contract_nonce!("0x1"))
+        );
+        assert_eq!(
+            backend
+                .contract_class_hash(genesis.hash.into(), contract)
+                .unwrap(),
+            Some(class_hash!("0xc1"))
+        );
+        assert_eq!(
+            backend
+                .contract_class_hash(BlockId::Latest, contract)
+                .unwrap(),
+            Some(class_hash!("0xc2"))
+        );
+
+        let transaction = StarknetTransaction {
+            hash: transaction_hash!("0x7"),
+            variant: Default::default(),
+        };
+        let receipt = Receipt {
+            transaction_hash: transaction.hash,
+            ..Default::default()
+        };
+        let events = vec![Event {
+            from_address: contract,
+            data: vec![],
+            keys: vec![event_key!("0x8")],
+        }];
+        backend
+            .insert_transaction_data(
+                block1.number,
+                &[(transaction.clone(), receipt.clone())],
+                Some(&[events.clone()]),
+            )
+            .unwrap();
+
+        assert_eq!(
+            backend.transaction_with_receipt(transaction.hash).unwrap(),
+            Some((
+                transaction.clone(),
+                receipt.clone(),
+                events.clone(),
+                block1.number
+            ))
+        );
+        assert_eq!(
+            backend
+                .transactions_with_receipts_for_block(BlockId::Latest)
+                .unwrap(),
+            Some(vec![(transaction.clone(), receipt)])
+        );
+        assert_eq!(
+            backend.events_for_block(block1.hash.into()).unwrap(),
+            Some(vec![(transaction.hash, events)])
+        );
+        assert_eq!(
+            backend
+                .transaction_with_receipt(transaction_hash!("0x99"))
+                .unwrap(),
+            None
+        );
+
+        // A root with two leaves.
+        let update = TrieUpdate {
+            nodes_added: vec![
+                (Felt::from_u64(1), Node::LeafBinary),
+                (
+                    Felt::from_u64(2),
+                    Node::LeafEdge {
+                        path: bitvec![u8, Msb0; 1; 5],
+                    },
+                ),
+                (
+                    Felt::from_u64(3),
+                    Node::Binary {
+                        left: NodeRef::Index(0),
+                        right: NodeRef::Index(1),
+                    },
+                ),
+            ],
+            nodes_removed: vec![],
+            root_commitment: Felt::from_u64(3),
+        };
+
+        let RootIndexUpdate::Updated(root) = backend
+            .insert_trie(Trie::Storage, &update, block1.number)
+            .unwrap()
+        else {
+            panic!("Expected the root to be updated");
+        };
+        backend
+            .insert_storage_root(block1.number, RootIndexUpdate::Updated(root))
+            .unwrap();
+
+        assert_eq!(
+            backend.storage_root_index(block1.number).unwrap(),
+            Some(root)
+        );
+        assert_eq!(backend.storage_root_index(genesis.number).unwrap(), None);
+
+        let Some(StoredNode::Binary { left, right }) =
+            backend.trie_node(Trie::Storage, root).unwrap()
+        else {
+            panic!("Expected a binary root node");
+        };
+        assert_eq!(
+            backend.trie_node(Trie::Storage, left).unwrap(),
+            Some(StoredNode::LeafBinary)
+        );
+        assert_eq!(
+            backend.trie_node(Trie::Storage, right).unwrap(),
+            Some(StoredNode::LeafEdge {
+                path: bitvec![u8, Msb0; 1; 5]
+            })
+        );
+        assert_eq!(backend.trie_node(Trie::Class, root).unwrap(), None);
+
+        backend
+            .insert_contract_root(genesis.number, contract, RootIndexUpdate::Updated(root))
+            .unwrap();
+        backend
+            .insert_contract_root(block1.number, contract, RootIndexUpdate::TrieEmpty)
+            .unwrap();
+        assert_eq!(
+            backend
+                .contract_root_index(genesis.number, contract)
+                .unwrap(),
+            Some(root)
+        );
+        assert_eq!(
+            backend
+                .contract_root_index(block1.number, contract)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn sqlite() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        exercise(&tx);
+    }
+
+    #[test]
+    fn in_memory() {
+        exercise(&InMemoryStorage::default());
+    }
+
+    #[test]
+    fn in_memory_matches_sqlite_for_generated_blocks() {
+        let blocks = crate::fake::generate::n_blocks(10);
+
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let sqlite = db.transaction().unwrap();
+        crate::fake::fill_backend(&sqlite, &blocks);
+
+        let memory = InMemoryStorage::default();
+        crate::fake::fill_backend(&memory, &blocks);
+
+        for block in &blocks {
+            let block_id = BlockId::from(block.header.header.number);
+            assert_eq!(
+                memory.block_header(block_id).unwrap(),
+                sqlite.block_header(block_id).unwrap()
+            );
+            assert_eq!(
+                memory.state_update(block_id).unwrap(),
+                sqlite.state_update(block_id).unwrap()
+            );
+            assert_eq!(
+                memory
+                    .transactions_with_receipts_for_block(block_id)
+                    .unwrap(),
+                sqlite
+                    .transactions_with_receipts_for_block(block_id)
+                    .unwrap()
+            );
+            assert_eq!(
+                memory.events_for_block(block_id).unwrap(),
+                sqlite.events_for_block(block_id).unwrap()
+            );
+        }
+    }
+}
@@ -0,0 +1,486 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::Context;
+use pathfinder_common::event::Event;
+use pathfinder_common::prelude::*;
+use pathfinder_common::receipt::Receipt;
+use pathfinder_common::state_update::{ContractClassUpdate, ContractUpdate};
+use pathfinder_common::transaction::Transaction as StarknetTransaction;
+use pathfinder_crypto::Felt;
+
+use super::{StorageBackend, Trie};
+use crate::{BlockId, Node, NodeRef, RootIndexUpdate, StoredNode, TrieStorageIndex, TrieUpdate};
+
+/// A [StorageBackend] which keeps all data in memory.
+///
+/// Tries are never pruned.
+#[derive(Default)]
+pub struct InMemoryStorage(Mutex<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    headers: BTreeMap<BlockNumber, BlockHeader>,
+    block_numbers: HashMap<BlockHash, BlockNumber>,
+    state_updates: BTreeMap<BlockNumber, StateUpdate>,
+    /// The block at which each class was first declared.
+    declared_at: HashMap<ClassHash, BlockNumber>,
+    transactions: BTreeMap<BlockNumber, BlockTransactions>,
+    transaction_index: HashMap<TransactionHash, (BlockNumber, usize)>,
+    tries: HashMap<Trie, TrieNodes>,
+    class_roots: BTreeMap<BlockNumber, Option<TrieStorageIndex>>,
+    storage_roots: BTreeMap<BlockNumber, Option<TrieStorageIndex>>,
+    contract_roots: HashMap<ContractAddress, BTreeMap<BlockNumber, Option<TrieStorageIndex>>>,
+}
+
+struct BlockTransactions {
+    transactions: Vec<(StarknetTransaction, Receipt)>,
+    events: Option<Vec<Vec<Event>>>,
+}
+
+#[derive(Default)]
+struct TrieNodes {
+    nodes: HashMap<TrieStorageIndex, (Felt, StoredNode)>,
+    next_index: u64,
+}
+
+impl InMemoryStorage {
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        // A panic while holding the lock cannot leave the collections in an unusable
+        // state, so poisoning is ignored.
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Inner {
+    fn block_number(&self, block: BlockId) -> Option<BlockNumber> {
+        match block {
+            BlockId::Latest => self.headers.keys().next_back().copied(),
+            BlockId::Number(number) => self.headers.contains_key(&number).then_some(number),
+            BlockId::Hash(hash) => self.block_numbers.get(&hash).copied(),
+        }
+    }
+
+    /// Like [Inner::block_number] but for state queries, which treat
+    /// [BlockId::Latest] as "all of the state" even when no headers are stored.
+    fn state_block_number(&self, block: BlockId) -> Option<BlockNumber> {
+        match block {
+            BlockId::Latest => Some(BlockNumber::MAX),
+            BlockId::Number(number) => Some(number),
+            BlockId::Hash(hash) => self.block_numbers.get(&hash).copied(),
+        }
+    }
+
+    /// Iterates over the contract updates of blocks up to and including
+    /// `block`, most recent first.
+    fn contract_updates_until(
+        &self,
+        block: BlockId,
+        contract_address: ContractAddress,
+    ) -> impl Iterator<Item = &ContractUpdate> {
+        let block_number = self.state_block_number(block);
+        block_number
+            .into_iter()
+            .flat_map(move |number| self.state_updates.range(..=number).rev())
+            .filter_map(move |(_, state_update)| {
+                state_update.contract_updates.get(&contract_address)
+            })
+    }
+}
+
+/// Returns the root index set at or before `block_number`.
+fn root_index_at(
+    roots: &BTreeMap<BlockNumber, Option<TrieStorageIndex>>,
+    block_number: BlockNumber,
+) -> Option<TrieStorageIndex> {
+    roots
+        .range(..=block_number)
+        .next_back()
+        .and_then(|(_, index)| *index)
+}
+
+fn insert_root(
+    roots: &mut BTreeMap<BlockNumber, Option<TrieStorageIndex>>,
+    block_number: BlockNumber,
+    update: RootIndexUpdate,
+) {
+    let index = match update {
+        RootIndexUpdate::Unchanged => return,
+        RootIndexUpdate::Updated(index) => Some(index),
+        RootIndexUpdate::TrieEmpty => None,
+    };
+    roots.insert(block_number, index);
+}
+
+impl StorageBackend for InMemoryStorage {
+    fn insert_block_header(&self, header: &BlockHeader) -> anyhow::Result<()> {
+        let mut inner = self.inner();
+        anyhow::ensure!(
+            !inner.headers.contains_key(&header.number),
+            "Block header {} already exists",
+            header.number
+        );
+        inner.block_numbers.insert(header.hash, header.number);
+        inner.headers.insert(header.number, header.clone());
+        Ok(())
+    }
+
+    fn block_header(&self, block: BlockId) -> anyhow::Result<Option<BlockHeader>> {
+        let inner = self.inner();
+        Ok(inner
+            .block_number(block)
+            .and_then(|number| inner.headers.get(&number))
+            .cloned())
+    }
+
+    fn block_id(&self, block: BlockId) -> anyhow::Result<Option<(BlockNumber, BlockHash)>> {
+        Ok(self
+            .block_header(block)?
+            .map(|header| (header.number, header.hash)))
+    }
+
+    fn insert_state_update(
+        &self,
+        block_number: BlockNumber,
+        state_update: &StateUpdate,
+    ) -> anyhow::Result<()> {
+        let mut inner = self.inner();
+        let mut state_update = state_update.clone();
+
+        // Like the SQLite implementation, only the first declaration of a class is
+        // part of a state update. Cairo 0 classes deployed without being declared
+        // are implicitly declared by their deployment.
+        let declared_at = &mut inner.declared_at;
+        let mut first_declaration =
+            |class: ClassHash| *declared_at.entry(class).or_insert(block_number) == block_number;
+        state_update
+            .declared_sierra_classes
+            .retain(|sierra, _| first_declaration(ClassHash(sierra.0)));
+        state_update
+            .declared_cairo_classes
+            .retain(|class| first_declaration(*class));
+        let implicitly_declared = state_update
+            .contract_updates
+            .values()
+            .filter_map(|update| match update.class {
+                Some(ContractClassUpdate::Deploy(class)) => Some(class),
+                _ => None,
+            })
+            .filter(|class| !declared_at.contains_key(class))
+            .collect::<Vec<_>>();
+        for class in implicitly_declared {
+            declared_at.insert(class, block_number);
+            state_update.declared_cairo_classes.insert(class);
+        }
+
+        inner.state_updates.insert(block_number, state_update);
+        Ok(())
+    }
+
+    fn state_update(&self, block: BlockId) -> anyhow::Result<Option<StateUpdate>> {
+        let inner = self.inner();
+        let Some(header) = inner
+            .block_number(block)
+            .and_then(|number| inner.headers.get(&number))
+        else {
+            return Ok(None);
+        };
+
+        // The genesis block does not have a parent.
+        let parent_state_commitment = header
+            .number
+            .parent()
+            .and_then(|parent| inner.headers.get(&parent))
+            .map(|parent| parent.state_commitment)
+            .unwrap_or_default();
+
+        let state_update = inner
+            .state_updates
+            .get(&header.number)
+            .cloned()
+            .unwrap_or_default()
+            .with_block_hash(header.hash)
+            .with_state_commitment(header.state_commitment)
+            .with_parent_state_commitment(parent_state_commitment);
+
+        Ok(Some(state_update))
+    }
+
+    fn storage_value(
+        &self,
+        block: BlockId,
+        contract_address: ContractAddress,
+        key: StorageAddress,
+    ) -> anyhow::Result<Option<StorageValue>> {
+        let inner = self.inner();
+        let Some(block_number) = inner.state_block_number(block) else {
+            return Ok(None);
+        };
+
+        Ok(inner
+            .state_updates
+            .range(..=block_number)
+            .rev()
+            .find_map(|(_, state_update)| {
+                state_update
+                    .contract_updates
+                    .get(&contract_address)
+                    .and_then(|update| update.storage.get(&key))
+                    .or_else(|| {
+                        state_update
+                            .system_contract_updates
+                            .get(&contract_address)
+                            .and_then(|update| update.storage.get(&key))
+                    })
+                    .copied()
+            }))
+    }
+
+    fn contract_nonce(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> anyhow::Result<Option<ContractNonce>> {
+        Ok(self
+            .inner()
+            .contract_updates_until(block, contract_address)
+            .find_map(|update| update.nonce))
+    }
+
+    fn contract_class_hash(
+        &self,
+        block: BlockId,
+        contract_address: ContractAddress,
+    ) -> anyhow::Result<Option<ClassHash>> {
+        Ok(self
+            .inner()
+            .contract_updates_until(block, contract_address)
+            .find_map(|update| update.class.map(|class| class.class_hash())))
+    }
+
+    fn insert_transaction_data(
+        &self,
+        block_number: BlockNumber,
+        transactions: &[(StarknetTransaction, Receipt)],
+        events: Option<&[Vec<Event>]>,
+    ) -> anyhow::Result<()> {
+        let mut inner = self.inner();
+        for (idx, (transaction, _)) in transactions.iter().enumerate() {
+            inner
+                .transaction_index
+                .insert(transaction.hash, (block_number, idx));
+        }
+        inner.transactions.insert(
+            block_number,
+            BlockTransactions {
+                transactions: transactions.to_vec(),
+                events: events.map(<[_]>::to_vec),
+            },
+        );
+        Ok(())
+    }
+
+    fn transaction_with_receipt(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> anyhow::Result<Option<(StarknetTransaction, Receipt, Vec<Event>, BlockNumber)>> {
+        let inner = self.inner();
+        let Some((block_number, idx)) = inner.transaction_index.get(&transaction_hash).copied()
+        else {
+            return Ok(None);
+        };
+
+        let block = inner
+            .transactions
+            .get(&block_number)
+            .context("Transaction data missing")?;
+        let (transaction, receipt) = block
+            .transactions
+            .get(idx)
+            .cloned()
+            .context("Transaction missing")?;
+        let events = block
+            .events
+            .as_ref()
+            .and_then(|events| events.get(idx))
+            .cloned()
+            .context("Events missing")?;
+
+        Ok(Some((transaction, receipt, events, block_number)))
+    }
+
+    fn transactions_with_receipts_for_block(
+        &self,
+        block: BlockId,
+    ) -> anyhow::Result<Option<Vec<(StarknetTransaction, Receipt)>>> {
+        let inner = self.inner();
+        let Some(block_number) = inner.block_number(block) else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            inner
+                .transactions
+                .get(&block_number)
+                .map(|block| block.transactions.clone())
+                .unwrap_or_default(),
+        ))
+    }
+
+    fn events_for_block(
+        &self,
+        block: BlockId,
+    ) -> anyhow::Result<Option<Vec<(TransactionHash, Vec<Event>)>>> {
+        let inner = self.inner();
+        let Some(block) = inner
+            .block_number(block)
+            .and_then(|number| inner.transactions.get(&number))
+        else {
+            return Ok(None);
+        };
+        let Some(events) = &block.events else {
+            return Ok(None);
+        };
+
+        if events.len() != block.transactions.len() {
+            anyhow::bail!("Event list and transaction list mismatch");
+        }
+
+        Ok(Some(
+            block
+                .transactions
+                .iter()
+                .map(|(transaction, _)| transaction.hash)
+                .zip(events.iter().cloned())
+                .collect(),
+        ))
+    }
+
+    fn insert_trie(
+        &self,
+        trie: Trie,
+        update: &TrieUpdate,
+        _block_number: BlockNumber,
+    ) -> anyhow::Result<RootIndexUpdate> {
+        if update.nodes_added.is_empty() {
+            if !update.nodes_removed.is_empty() && update.root_commitment.is_zero() {
+                return Ok(RootIndexUpdate::TrieEmpty);
+            } else {
+                return Ok(RootIndexUpdate::Unchanged);
+            }
+        }
+
+        // Same traversal as the SQLite implementation: only nodes reachable from the
+        // new root are stored, children before their parents.
+        let mut to_insert = Vec::new();
+        let mut to_process = vec![NodeRef::Index(update.nodes_added.len() - 1)];
+
+        while let Some(node) = to_process.pop() {
+            let NodeRef::Index(idx) = node else {
+                continue;
+            };
+
+            let (_, node) = &update.nodes_added.get(idx).context("Node index missing")?;
+            to_insert.push(idx);
+
+            match node {
+                Node::Binary { left, right } => {
+                    to_process.push(*left);
+                    to_process.push(*right);
+                }
+                Node::Edge { child, .. } => {
+                    to_process.push(*child);
+                }
+                Node::LeafEdge { .. } | Node::LeafBinary => {}
+            }
+        }
+
+        let mut inner = self.inner();
+        let nodes = inner.tries.entry(trie).or_default();
+        let mut indices = HashMap::new();
+
+        for idx in to_insert.into_iter().rev() {
+            let (hash, node) = &update.nodes_added.get(idx).context("Node index missing")?;
+            let node = node.as_stored(&indices)?;
+
+            nodes.next_index += 1;
+            let storage_idx = TrieStorageIndex(nodes.next_index);
+            nodes.nodes.insert(storage_idx, (*hash, node));
+
+            indices.insert(idx, storage_idx);
+        }
+
+        Ok(RootIndexUpdate::Updated(
+            *indices
+                .get(&(update.nodes_added.len() - 1))
+                .expect("Root index must exist as we just inserted it"),
+        ))
+    }
+
+    fn trie_node(&self, trie: Trie, index: TrieStorageIndex) -> anyhow::Result<Option<StoredNode>> {
+        Ok(self
+            .inner()
+            .tries
+            .get(&trie)
+            .and_then(|nodes| nodes.nodes.get(&index))
+            .map(|(_, node)| node.clone()))
+    }
+
+    fn insert_class_root(
+        &self,
+        block_number: BlockNumber,
+        update: RootIndexUpdate,
+    ) -> anyhow::Result<()> {
+        insert_root(&mut self.inner().class_roots, block_number, update);
+        Ok(())
+    }
+
+    fn class_root_index(
+        &self,
+        block_number: BlockNumber,
+    ) -> anyhow::Result<Option<TrieStorageIndex>> {
+        Ok(root_index_at(&self.inner().class_roots, block_number))
+    }
+
+    fn insert_storage_root(
+        &self,
+        block_number: BlockNumber,
+        update: RootIndexUpdate,
+    ) -> anyhow::Result<()> {
+        insert_root(&mut self.inner().storage_roots, block_number, update);
+        Ok(())
+    }
+
+    fn storage_root_index(
+        &self,
+        block_number: BlockNumber,
+    ) -> anyhow::Result<Option<TrieStorageIndex>> {
+        Ok(root_index_at(&self.inner().storage_roots, block_number))
+    }
+
+    fn insert_contract_root(
+        &self,
+        block_number: BlockNumber,
+        contract: ContractAddress,
+        update: RootIndexUpdate,
+    ) -> anyhow::Result<()> {
+        insert_root(
+            self.inner().contract_roots.entry(contract).or_default(),
+            block_number,
+            update,
+        );
+        Ok(())
+    }
+
+    fn contract_root_index(
+        &self,
+        block_number: BlockNumber,
+        contract: ContractAddress,
+    ) -> anyhow::Result<Option<TrieStorageIndex>> {
+        Ok(self
+            .inner()
+            .contract_roots
+            .get(&contract)
+            .and_then(|roots| root_index_at(roots, block_number)))
+    }
+}
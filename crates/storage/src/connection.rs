@@ -4,10 +4,12 @@ mod block;
 mod class;
 mod ethereum;
 pub mod event;
+mod message;
 pub mod pruning;
 mod reference;
 mod signature;
 mod state_update;
+mod trace;
 pub(crate) mod transaction;
 mod trie;
 
@@ -19,6 +21,7 @@ pub use event::{
     PageOfEvents,
     PAGE_SIZE_LIMIT as EVENT_PAGE_SIZE_LIMIT,
 };
+pub use message::L1ToL2Message;
 use pathfinder_common::event::Event;
 use pathfinder_common::receipt::Receipt;
 use pathfinder_common::transaction::Transaction as StarknetTransaction;
@@ -120,6 +120,9 @@ impl Transaction<'_> {
             )
             .context("Deleting transactions")?;
 
+        self.delete_block_traces(block)
+            .context("Deleting block traces")?;
+
         self.inner()
             .execute(
                 "DELETE FROM canonical_blocks WHERE number = ?",
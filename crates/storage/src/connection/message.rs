@@ -0,0 +1,164 @@
+//! Index of messages sent from L1 to L2.
+//!
+//! Messages are only indexed once their L1 block is finalized, so the index is
+//! not affected by L1 reorgs. Each message is mapped to the hash of the L1
+//! handler transaction which consumes it on L2, whether or not that
+//! transaction has been included in a block yet.
+use anyhow::Context;
+use pathfinder_common::{L1BlockNumber, L1TransactionHash, TransactionHash};
+use primitive_types::H256;
+
+use super::Transaction;
+use crate::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L1ToL2Message {
+    pub message_hash: H256,
+    pub l1_block_number: L1BlockNumber,
+    pub l1_transaction_hash: L1TransactionHash,
+    pub l2_transaction_hash: TransactionHash,
+}
+
+impl Transaction<'_> {
+    /// Stores the messages sent in L1 blocks up to and including `head`, and
+    /// records `head` as the last indexed L1 block.
+    pub fn insert_l1_to_l2_messages(
+        &self,
+        head: L1BlockNumber,
+        messages: &[L1ToL2Message],
+    ) -> anyhow::Result<()> {
+        let mut stmt = self
+            .inner()
+            .prepare_cached(
+                r"
+                INSERT OR REPLACE INTO l1_to_l2_messages (
+                    message_hash,
+                    l1_block_number,
+                    l1_transaction_hash,
+                    l2_transaction_hash
+                ) VALUES (?, ?, ?, ?)
+                ",
+            )
+            .context("Preparing insert statement")?;
+
+        for message in messages {
+            stmt.execute(params![
+                &message.message_hash.as_bytes(),
+                &message.l1_block_number,
+                &message.l1_transaction_hash.as_bytes(),
+                &message.l2_transaction_hash,
+            ])
+            .context("Inserting L1 to L2 message")?;
+        }
+
+        self.inner()
+            .execute(
+                "INSERT OR REPLACE INTO storage_options (option, value) VALUES \
+                 ('l1_to_l2_messages_head', ?)",
+                params![&head],
+            )
+            .context("Updating L1 to L2 message index head")?;
+
+        Ok(())
+    }
+
+    /// Returns the last L1 block whose messages are indexed.
+    pub fn l1_to_l2_messages_head(&self) -> anyhow::Result<Option<L1BlockNumber>> {
+        self.inner()
+            .query_row(
+                "SELECT value FROM storage_options WHERE option = 'l1_to_l2_messages_head'",
+                [],
+                |row| row.get_i64(0),
+            )
+            .optional()
+            .context("Querying L1 to L2 message index head")?
+            .map(|head| {
+                let head = u64::try_from(head).context("Invalid L1 block number")?;
+                Ok(L1BlockNumber::new_or_panic(head))
+            })
+            .transpose()
+    }
+
+    /// Returns the hashes of the L1 handler transactions consuming the
+    /// messages sent by the L1 transaction, in the order they were sent. The
+    /// result is empty if the L1 transaction didn't send any messages or isn't
+    /// indexed.
+    pub fn l1_to_l2_messages(
+        &self,
+        l1_transaction_hash: L1TransactionHash,
+    ) -> anyhow::Result<Vec<TransactionHash>> {
+        let mut stmt = self
+            .inner()
+            .prepare_cached(
+                r"
+                SELECT l2_transaction_hash FROM l1_to_l2_messages
+                WHERE l1_transaction_hash = ?
+                ORDER BY rowid
+                ",
+            )
+            .context("Preparing query")?;
+
+        stmt.query_map(params![&l1_transaction_hash.as_bytes()], |row| {
+            row.get_transaction_hash(0)
+        })
+        .context("Querying L1 to L2 messages")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Iterating over L1 to L2 messages")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+
+    use super::*;
+    use crate::StorageBuilder;
+
+    #[test]
+    fn round_trip() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let l1_transaction_hash = L1TransactionHash::from(H256::repeat_byte(1));
+        assert_eq!(tx.l1_to_l2_messages_head().unwrap(), None);
+        assert!(tx
+            .l1_to_l2_messages(l1_transaction_hash)
+            .unwrap()
+            .is_empty());
+
+        let messages = [
+            L1ToL2Message {
+                message_hash: H256::repeat_byte(2),
+                l1_block_number: L1BlockNumber::new_or_panic(10),
+                l1_transaction_hash,
+                l2_transaction_hash: transaction_hash!("0x2"),
+            },
+            L1ToL2Message {
+                message_hash: H256::repeat_byte(3),
+                l1_block_number: L1BlockNumber::new_or_panic(10),
+                l1_transaction_hash,
+                l2_transaction_hash: transaction_hash!("0x1"),
+            },
+        ];
+        tx.insert_l1_to_l2_messages(L1BlockNumber::new_or_panic(12), &messages)
+            .unwrap();
+
+        assert_eq!(
+            tx.l1_to_l2_messages_head().unwrap(),
+            Some(L1BlockNumber::new_or_panic(12))
+        );
+        assert_eq!(
+            tx.l1_to_l2_messages(l1_transaction_hash).unwrap(),
+            vec![transaction_hash!("0x2"), transaction_hash!("0x1")]
+        );
+
+        // The head advances without any messages too.
+        tx.insert_l1_to_l2_messages(L1BlockNumber::new_or_panic(20), &[])
+            .unwrap();
+        assert_eq!(
+            tx.l1_to_l2_messages_head().unwrap(),
+            Some(L1BlockNumber::new_or_panic(20))
+        );
+    }
+}
@@ -5,6 +5,7 @@
 //! Database tables that are subject to pruning are:
 //! - `transactions`
 //! - `transaction_hashes`
+//! - `block_traces`
 //! - `block_headers`
 //! - `canonical_blocks`
 //! - `block_signatures`
@@ -16,17 +17,22 @@
 //!   same `contract_address_id`, same `storage_address_id` and a higher
 //!   `block_number`)
 //!
-//! The number of blocks kept in the database is configurable but once chosen,
-//! it cannot change until a new database is created. It is also forbidden to
-//! enable pruning on a database that was created with it disabled (and vice
-//! versa). These restrictions are there to make it possible to prune block and
-//! (contract/nonce/storage) update data without breaking reorg handling.
+//! The number of blocks kept in the database is configurable and pruning can be
+//! enabled on a database that was created without it. Normally, a single block
+//! is pruned whenever a new block is added. Reducing the number of blocks kept
+//! (or enabling pruning) leaves a backlog of blocks outside of the history
+//! window, which is pruned by a one-time
+//! [compaction](Transaction::compact_blockchain_history). Increasing the number
+//! of blocks kept does not restore any pruned blocks, the history grows to the
+//! new size as new blocks are added. It is forbidden to disable pruning on a
+//! database that was created with it enabled, since the pruned history cannot
+//! be restored.
 
 use anyhow::Context;
 use pathfinder_common::BlockNumber;
 
 use super::Transaction;
-use crate::prelude::{named_params, params, RowExt};
+use crate::prelude::{named_params, params, OptionalExtension, RowExt};
 use crate::BlockId;
 
 #[derive(Debug, Clone, Copy)]
@@ -76,6 +82,7 @@ impl Transaction<'_> {
         )?;
         transaction_stmt.execute(params![&block])?;
         transaction_hashes_stmt.execute(params![&block])?;
+        self.delete_block_traces(block)?;
 
         Ok(())
     }
@@ -317,6 +324,80 @@ impl Transaction<'_> {
         Ok(())
     }
 
+    /// Prunes up to `max_blocks` blocks of the backlog left behind by reducing
+    /// the number of blocks kept, or by enabling pruning on an archive
+    /// database.
+    ///
+    /// Blocks are pruned in ascending order, exactly as they would have been
+    /// had the current setting been in place from the start. Returns the next
+    /// block to prune, or `None` once the compaction is complete or if none is
+    /// pending.
+    pub fn compact_blockchain_history(
+        &self,
+        max_blocks: u64,
+    ) -> anyhow::Result<Option<BlockNumber>> {
+        let BlockchainHistoryMode::Prune { num_blocks_kept } = self.blockchain_history_mode else {
+            return Ok(None);
+        };
+        let Some(next) = self
+            .inner()
+            .query_row(
+                "SELECT value FROM storage_options WHERE option = 'prune_blockchain_compaction'",
+                [],
+                |row| row.get_block_number(0),
+            )
+            .optional()
+            .context("Querying blockchain compaction progress")?
+        else {
+            return Ok(None);
+        };
+
+        // Blocks after this one are pruned as new blocks are added.
+        let last_block_to_prune = self
+            .block_number(BlockId::Latest)?
+            .and_then(|latest| latest.checked_sub(num_blocks_kept + 1))
+            .filter(|last| *last >= next);
+        let Some(last_block_to_prune) = last_block_to_prune else {
+            self.inner()
+                .execute(
+                    "DELETE FROM storage_options WHERE option = 'prune_blockchain_compaction'",
+                    [],
+                )
+                .context("Removing blockchain compaction progress")?;
+            return Ok(None);
+        };
+
+        let last = std::cmp::min(next + max_blocks.saturating_sub(1), last_block_to_prune);
+        tracing::debug!(from=%next, to=%last, "Compacting blockchain history");
+
+        let mut block = next;
+        while block <= last {
+            self.prune_transaction_data(block)
+                .context("Pruning transaction data")?;
+            self.prune_block_data(block).context("Pruning block data")?;
+            block += 1;
+        }
+
+        if last == last_block_to_prune {
+            self.inner()
+                .execute(
+                    "DELETE FROM storage_options WHERE option = 'prune_blockchain_compaction'",
+                    [],
+                )
+                .context("Removing blockchain compaction progress")?;
+            Ok(None)
+        } else {
+            self.inner()
+                .execute(
+                    "UPDATE storage_options SET value = ? WHERE option = \
+                     'prune_blockchain_compaction'",
+                    params![&block],
+                )
+                .context("Updating blockchain compaction progress")?;
+            Ok(Some(block))
+        }
+    }
+
     /// Checks if a block has been pruned.
     ///
     /// But not really, because there can be blocks that have had *some* of
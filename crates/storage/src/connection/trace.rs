@@ -0,0 +1,155 @@
+//! Persistent store of block transaction traces.
+//!
+//! Traces are stored as opaque serialized blobs, since their types are owned by
+//! the executor. The store is bounded: once it holds more than the requested
+//! number of blocks, the least recently stored ones are evicted. Traces are
+//! also removed together with their block on reorgs and blockchain history
+//! pruning.
+use std::num::NonZeroUsize;
+
+use anyhow::Context;
+use pathfinder_common::{BlockHash, BlockNumber};
+
+use super::Transaction;
+use crate::prelude::*;
+
+impl Transaction<'_> {
+    /// Stores the serialized traces of a block's transactions, replacing any
+    /// previously stored ones. Afterwards at most `max_blocks` blocks are kept
+    /// in the store.
+    pub fn insert_block_traces(
+        &self,
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+        traces: &[u8],
+        max_blocks: NonZeroUsize,
+    ) -> anyhow::Result<()> {
+        let mut compressor = zstd::bulk::Compressor::new(10).context("Creating zstd compressor")?;
+        let traces = compressor
+            .compress(traces)
+            .context("Compressing block traces")?;
+
+        // Replacing a row assigns it a new rowid, which keeps the rowid order
+        // equal to the insertion order.
+        self.inner()
+            .execute(
+                r"
+                INSERT OR REPLACE INTO block_traces (block_hash, block_number, traces)
+                VALUES (?, ?, ?)
+                ",
+                params![&block_hash, &block_number, &traces],
+            )
+            .context("Inserting block traces")?;
+
+        self.inner()
+            .execute(
+                r"
+                DELETE FROM block_traces WHERE rowid IN (
+                    SELECT rowid FROM block_traces ORDER BY rowid DESC LIMIT -1 OFFSET ?
+                )
+                ",
+                params![&max_blocks.get()],
+            )
+            .context("Evicting block traces")?;
+
+        Ok(())
+    }
+
+    /// Returns the serialized traces of a block's transactions, if stored.
+    pub fn block_traces(&self, block_hash: BlockHash) -> anyhow::Result<Option<Vec<u8>>> {
+        let traces = self
+            .inner()
+            .query_row(
+                "SELECT traces FROM block_traces WHERE block_hash = ?",
+                params![&block_hash],
+                |row| row.get_blob(0).map(|x| x.to_vec()),
+            )
+            .optional()
+            .context("Querying block traces")?;
+
+        let Some(traces) = traces else {
+            return Ok(None);
+        };
+        let traces = zstd::decode_all(traces.as_slice()).context("Decompressing block traces")?;
+
+        Ok(Some(traces))
+    }
+
+    pub(super) fn delete_block_traces(&self, block_number: BlockNumber) -> anyhow::Result<()> {
+        self.inner()
+            .execute(
+                "DELETE FROM block_traces WHERE block_number = ?",
+                params![&block_number],
+            )
+            .context("Deleting block traces")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+
+    use super::*;
+    use crate::StorageBuilder;
+
+    #[test]
+    fn round_trip() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let max_blocks = NonZeroUsize::new(10).unwrap();
+
+        assert_eq!(tx.block_traces(block_hash!("0x1")).unwrap(), None);
+
+        tx.insert_block_traces(
+            BlockNumber::GENESIS,
+            block_hash!("0x1"),
+            b"traces",
+            max_blocks,
+        )
+        .unwrap();
+        assert_eq!(
+            tx.block_traces(block_hash!("0x1")).unwrap().unwrap(),
+            b"traces"
+        );
+
+        tx.insert_block_traces(
+            BlockNumber::GENESIS,
+            block_hash!("0x1"),
+            b"other",
+            max_blocks,
+        )
+        .unwrap();
+        assert_eq!(
+            tx.block_traces(block_hash!("0x1")).unwrap().unwrap(),
+            b"other"
+        );
+
+        tx.delete_block_traces(BlockNumber::GENESIS).unwrap();
+        assert_eq!(tx.block_traces(block_hash!("0x1")).unwrap(), None);
+    }
+
+    #[test]
+    fn least_recently_stored_are_evicted() {
+        let storage = StorageBuilder::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let max_blocks = NonZeroUsize::new(2).unwrap();
+
+        let blocks = [
+            (BlockNumber::new_or_panic(5), block_hash!("0x5")),
+            (BlockNumber::new_or_panic(1), block_hash!("0x1")),
+            (BlockNumber::new_or_panic(3), block_hash!("0x3")),
+        ];
+        for (number, hash) in blocks {
+            tx.insert_block_traces(number, hash, b"traces", max_blocks)
+                .unwrap();
+        }
+
+        assert_eq!(tx.block_traces(block_hash!("0x5")).unwrap(), None);
+        assert!(tx.block_traces(block_hash!("0x1")).unwrap().is_some());
+        assert!(tx.block_traces(block_hash!("0x3")).unwrap().is_some());
+    }
+}
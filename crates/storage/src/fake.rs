@@ -22,7 +22,7 @@ use pathfinder_crypto::Felt;
 use rand::seq::IteratorRandom;
 use rand::Rng;
 
-use crate::{Storage, StorageBuilder};
+use crate::{Storage, StorageBackend, StorageBuilder};
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Block {
@@ -105,38 +105,18 @@ pub fn fill(storage: &Storage, blocks: &[Block], update_tries: Option<UpdateTrie
     let db = db.transaction().unwrap();
 
     blocks.iter().for_each(
-        |Block {
+        |block @ Block {
              header,
-             transaction_data,
              state_update,
              cairo_defs,
              sierra_defs,
              ..
          }| {
-            db.insert_block_header(&header.header).unwrap();
+            insert_block(&db, block);
             db.insert_signature(header.header.number, &header.signature)
                 .unwrap();
-            db.insert_transaction_data(
-                header.header.number,
-                &transaction_data
-                    .iter()
-                    .cloned()
-                    .map(|(tx, receipt, ..)| (tx, receipt))
-                    .collect::<Vec<_>>(),
-                Some(
-                    &transaction_data
-                        .iter()
-                        .cloned()
-                        .map(|(_, _, events)| events)
-                        .collect::<Vec<_>>(),
-                ),
-            )
-            .unwrap();
 
             if let Some(state_update) = state_update {
-                db.insert_state_update(header.header.number, state_update)
-                    .unwrap();
-
                 if let Some(update_tries) = &update_tries {
                     update_tries(
                         &db,
@@ -175,6 +155,32 @@ pub fn fill(storage: &Storage, blocks: &[Block], update_tries: Option<UpdateTrie
     db.commit().unwrap();
 }
 
+/// Like [fill] but for any [StorageBackend], which only covers block headers,
+/// transaction data and state updates.
+pub fn fill_backend(backend: &impl StorageBackend, blocks: &[Block]) {
+    for block in blocks {
+        insert_block(backend, block);
+    }
+}
+
+/// Inserts the parts of `block` which are covered by [StorageBackend].
+fn insert_block(backend: &impl StorageBackend, block: &Block) {
+    let number = block.header.header.number;
+    backend.insert_block_header(&block.header.header).unwrap();
+    let (transactions, events): (Vec<_>, Vec<_>) = block
+        .transaction_data
+        .iter()
+        .cloned()
+        .map(|(tx, receipt, events)| ((tx, receipt), events))
+        .unzip();
+    backend
+        .insert_transaction_data(number, &transactions, Some(&events))
+        .unwrap();
+    if let Some(state_update) = &block.state_update {
+        backend.insert_state_update(number, state_update).unwrap();
+    }
+}
+
 /// Create fake blocks and state updates with __limited consistency
 /// guarantees__:
 /// - starknet version: 0.13.2
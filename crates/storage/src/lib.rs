@@ -1,11 +1,15 @@
 //! Local storage.
 //!
-//! Currently this consists of a Sqlite backend implementation.
+//! Currently this consists of a Sqlite backend implementation. The core
+//! blockchain data can also be accessed through the [StorageBackend] trait,
+//! which additionally has an in-memory implementation.
 
 // This is intended for internal use only -- do not make public.
 mod prelude;
 
+mod backend;
 mod bloom;
+pub use backend::{InMemoryStorage, StorageBackend, Trie};
 use bloom::AggregateBloomCache;
 pub use bloom::AGGREGATE_BLOOM_BLOCK_RANGE_LEN;
 use connection::pruning::BlockchainHistoryMode;
@@ -13,6 +17,8 @@ mod connection;
 pub mod fake;
 mod params;
 mod schema;
+mod snapshot;
+pub use snapshot::{restore_snapshot, SnapshotManifest};
 pub mod test_utils;
 
 use std::num::NonZeroU32;
@@ -391,6 +397,52 @@ impl StorageBuilder {
         })
     }
 
+    /// Opens an existing database without migrating it and returns a [storage
+    /// manager](StorageManager).
+    ///
+    /// This is intended for accessing the database of a running node from a
+    /// separate process, so nothing is written to the database. Fails if the
+    /// database does not exist or its schema revision does not match.
+    pub fn open_existing(self) -> anyhow::Result<StorageManager> {
+        let mut open_flags = OpenFlags::default();
+        open_flags.remove(OpenFlags::SQLITE_OPEN_CREATE);
+        let mut connection = rusqlite::Connection::open_with_flags(&self.database_path, open_flags)
+            .context("Opening existing DB")?;
+        setup_connection(&mut connection, self.journal_mode)
+            .context("Setting up database connection")?;
+
+        let current_revision = schema_version(&connection)?;
+        let latest_revision = schema::BASE_SCHEMA_REVISION + schema::migrations().len();
+        anyhow::ensure!(
+            current_revision == latest_revision,
+            "Database schema revision {current_revision} does not match the expected revision \
+             {latest_revision}"
+        );
+
+        let blockchain_history_mode =
+            self.determine_blockchain_history_mode(&mut connection, false)?;
+        let trie_prune_mode = self.determine_trie_prune_mode(&mut connection, false)?;
+
+        let running_event_filter = event::RunningEventFilter::load(&connection.transaction()?)
+            .context("Loading running event filter")?;
+
+        connection
+            .close()
+            .map_err(|(_connection, error)| error)
+            .context("Closing DB")?;
+
+        Ok(StorageManager {
+            database_path: self.database_path,
+            journal_mode: self.journal_mode,
+            event_filter_cache: Arc::new(AggregateBloomCache::with_size(
+                self.event_filter_cache_size,
+            )),
+            running_event_filter: Arc::new(Mutex::new(running_event_filter)),
+            trie_prune_mode,
+            blockchain_history_mode,
+        })
+    }
+
     /// - If there is no explicitly requested configuration, assumes the user
     ///   wants to archive. If this doesn't match the database setting, errors.
     /// - If there's an explicitly requested setting: uses it if matches DB
@@ -453,19 +505,22 @@ impl StorageBuilder {
     /// Determines the blockchain history mode based on the database state and
     /// configuration.
     ///
-    /// - If there is no explicitly requested configuration, assumes the user
-    ///   wants to archive. If this doesn't match the database setting, errors.
-    /// - If there's an explicitly requested setting: uses it if it matches the
-    ///   DB setting, otherwise errors.
-    /// - If the database is new and no configuration is provided, the database
-    ///   is created in archive mode.
-    /// - Once the history size for pruning is set, it cannot be changed.
+    /// - If there is no explicitly requested configuration, keeps the DB
+    ///   setting. If the database is new, it is created in archive mode.
+    /// - If there's an explicitly requested setting: uses it and updates the DB
+    ///   setting accordingly.
+    /// - Pruning can be enabled on an archive database and the number of blocks
+    ///   kept can be changed. Blocks that fall outside of the new history
+    ///   window are pruned by a [one-time
+    ///   compaction](Transaction::compact_blockchain_history).
+    /// - Pruning cannot be disabled once enabled, since the pruned history
+    ///   cannot be restored.
     fn determine_blockchain_history_mode(
         &self,
         connection: &mut rusqlite::Connection,
         is_new_database: bool,
     ) -> anyhow::Result<BlockchainHistoryMode> {
-        let init_num_blocks_kept = connection
+        let init_num_blocks_kept: Option<u64> = connection
             .query_row(
                 "SELECT value FROM storage_options WHERE option = 'prune_blockchain'",
                 [],
@@ -492,27 +547,71 @@ impl StorageBuilder {
                 }
             }
             BlockchainHistoryMode::Prune { num_blocks_kept } => {
-                if !is_new_database {
-                    let Some(init_num_blocks_kept) = init_num_blocks_kept else {
-                        anyhow::bail!(
-                            "Cannot enable blockchain history pruning on a database that was not \
-                             created with it disabled."
+                let schedule_compaction = match init_num_blocks_kept {
+                    _ if is_new_database => {
+                        tracing::info!(
+                            "Created new database with blockchain history pruning enabled."
                         );
-                    };
-                    if num_blocks_kept != init_num_blocks_kept {
-                        anyhow::bail!(
-                            "Cannot enable blockchain history pruning with a different number of \
-                             blocks kept than the database was created with \
-                             ({init_num_blocks_kept})."
+                        false
+                    }
+                    None => {
+                        tracing::info!(
+                            history_kept=%num_blocks_kept,
+                            "Converting archive database to pruned. Blocks outside of the \
+                             history window will be pruned in the background."
+                        );
+                        true
+                    }
+                    Some(init_num_blocks_kept) if num_blocks_kept < init_num_blocks_kept => {
+                        tracing::info!(
+                            history_kept=%num_blocks_kept,
+                            previous_history_kept=%init_num_blocks_kept,
+                            "Reducing blockchain history. Blocks outside of the history window \
+                             will be pruned in the background."
+                        );
+                        true
+                    }
+                    Some(init_num_blocks_kept) if num_blocks_kept > init_num_blocks_kept => {
+                        // Already pruned blocks cannot be restored, the history grows to the
+                        // new size as new blocks are added.
+                        tracing::info!(
+                            history_kept=%num_blocks_kept,
+                            previous_history_kept=%init_num_blocks_kept,
+                            "Increasing blockchain history"
                         );
+                        false
                     }
-                } else {
+                    Some(_) => false,
+                };
+
+                if init_num_blocks_kept != Some(num_blocks_kept) {
                     connection.execute(
-                        "INSERT OR IGNORE INTO storage_options (option, value) VALUES \
+                        "INSERT OR REPLACE INTO storage_options (option, value) VALUES \
                          ('prune_blockchain', ?)",
                         [num_blocks_kept],
                     )?;
-                    tracing::info!("Created new database with blockchain history pruning enabled.");
+                }
+
+                if schedule_compaction {
+                    // Blocks before the previous history window have already been pruned.
+                    let first_block = match init_num_blocks_kept {
+                        Some(init_num_blocks_kept) => connection
+                            .query_row(
+                                "SELECT number FROM canonical_blocks ORDER BY number DESC LIMIT 1",
+                                [],
+                                |row| row.get::<_, u64>(0),
+                            )
+                            .optional()?
+                            .map_or(0, |latest| latest.saturating_sub(init_num_blocks_kept)),
+                        None => 0,
+                    };
+                    // Any progress of a previously scheduled compaction is still valid, since
+                    // it only depends on the block being pruned.
+                    connection.execute(
+                        "INSERT OR IGNORE INTO storage_options (option, value) VALUES \
+                         ('prune_blockchain_compaction', ?)",
+                        [first_block],
+                    )?;
                 }
             }
         }
@@ -816,6 +915,140 @@ mod tests {
         );
     }
 
+    #[test]
+    fn disabling_blockchain_history_pruning_fails() {
+        let (_db_dir, db_path) = rpc_test_db_fixture();
+
+        StorageBuilder::file(db_path.clone())
+            .blockchain_history_mode(Some(BlockchainHistoryMode::Prune { num_blocks_kept: 1 }))
+            .migrate()
+            .unwrap();
+
+        assert_eq!(
+            StorageBuilder::file(db_path)
+                .blockchain_history_mode(Some(BlockchainHistoryMode::Archive))
+                .migrate()
+                .unwrap_err()
+                .to_string(),
+            "Cannot disable blockchain history pruning on a database that was created with it \
+             enabled."
+        );
+    }
+
+    #[test]
+    fn changing_blockchain_history_size() {
+        let (_db_dir, db_path) = rpc_test_db_fixture();
+
+        let num_blocks_kept = |db_path: &PathBuf| {
+            rusqlite::Connection::open(db_path)
+                .unwrap()
+                .query_row(
+                    "SELECT value FROM storage_options WHERE option = 'prune_blockchain'",
+                    [],
+                    |row| row.get::<_, u64>(0),
+                )
+                .unwrap()
+        };
+
+        for n in [10, 2, 5] {
+            StorageBuilder::file(db_path.clone())
+                .blockchain_history_mode(Some(BlockchainHistoryMode::Prune { num_blocks_kept: n }))
+                .migrate()
+                .unwrap();
+            assert_eq!(num_blocks_kept(&db_path), n);
+        }
+
+        // The setting is kept if not specified.
+        StorageBuilder::file(db_path.clone()).migrate().unwrap();
+        assert_eq!(num_blocks_kept(&db_path), 5);
+    }
+
+    #[test]
+    fn reducing_blockchain_history_skips_pruned_blocks() {
+        let (_db_dir, db_path) = rpc_test_db_fixture();
+
+        let compaction_start = |db_path: &PathBuf| {
+            rusqlite::Connection::open(db_path)
+                .unwrap()
+                .query_row(
+                    "SELECT value FROM storage_options WHERE option = \
+                     'prune_blockchain_compaction'",
+                    [],
+                    |row| row.get::<_, u64>(0),
+                )
+                .optional()
+                .unwrap()
+        };
+
+        // Converting an archive database prunes from genesis.
+        StorageBuilder::file(db_path.clone())
+            .blockchain_history_mode(Some(BlockchainHistoryMode::Prune { num_blocks_kept: 1 }))
+            .migrate()
+            .unwrap();
+        assert_eq!(compaction_start(&db_path), Some(0));
+
+        // Pretend the compaction completed.
+        let connection = rusqlite::Connection::open(&db_path).unwrap();
+        connection
+            .execute(
+                "DELETE FROM storage_options WHERE option = 'prune_blockchain_compaction'",
+                [],
+            )
+            .unwrap();
+        let latest: u64 = connection
+            .query_row(
+                "SELECT number FROM canonical_blocks ORDER BY number DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        drop(connection);
+
+        // Blocks outside of the previous history window are already pruned.
+        StorageBuilder::file(db_path.clone())
+            .blockchain_history_mode(Some(BlockchainHistoryMode::Prune { num_blocks_kept: 0 }))
+            .migrate()
+            .unwrap();
+        assert_eq!(compaction_start(&db_path), Some(latest.saturating_sub(1)));
+    }
+
+    #[test]
+    fn compacting_blockchain_history() {
+        let (_db_dir, db_path) = rpc_test_db_fixture();
+
+        let storage = StorageBuilder::file(db_path)
+            .blockchain_history_mode(Some(BlockchainHistoryMode::Prune { num_blocks_kept: 0 }))
+            .migrate()
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let latest = tx.block_number(BlockId::Latest).unwrap().unwrap();
+        let last_pruned = latest.checked_sub(1).unwrap();
+
+        // One block at a time, until the backlog is gone.
+        let mut next = tx.compact_blockchain_history(1).unwrap();
+        while let Some(block) = next {
+            assert!(block <= last_pruned);
+            next = tx.compact_blockchain_history(1).unwrap();
+        }
+
+        for block in 0..=last_pruned.get() {
+            let block = BlockNumber::new_or_panic(block);
+            assert!(tx.block_pruned(block.into()).unwrap());
+            assert!(tx
+                .transactions_for_block(block.into())
+                .unwrap()
+                .is_none_or(|txs| txs.is_empty()));
+        }
+        assert!(tx.block_exists(latest.into()).unwrap());
+
+        // Nothing is left to compact.
+        assert_eq!(tx.compact_blockchain_history(1).unwrap(), None);
+    }
+
     #[test]
     fn running_event_filter_rebuilt_after_shutdown() {
         let n_blocks = 6;
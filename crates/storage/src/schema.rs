@@ -30,6 +30,8 @@ mod revision_0067;
 mod revision_0068;
 mod revision_0069;
 mod revision_0070;
+mod revision_0071;
+mod revision_0072;
 
 pub(crate) use base::base_schema;
 
@@ -68,6 +70,8 @@ pub fn migrations() -> &'static [MigrationFn] {
         revision_0068::migrate,
         revision_0069::migrate,
         revision_0070::migrate,
+        revision_0071::migrate,
+        revision_0072::migrate,
     ]
 }
 
@@ -0,0 +1,24 @@
+use anyhow::Context;
+
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tracing::info!("Creating block_traces table");
+
+    tx.execute(
+        r"
+        CREATE TABLE block_traces (
+            block_hash   BLOB PRIMARY KEY,
+            block_number INTEGER NOT NULL,
+            traces       BLOB NOT NULL
+        )
+        ",
+        [],
+    )
+    .context("Creating block_traces table")?;
+    tx.execute(
+        "CREATE INDEX block_traces_block_number ON block_traces(block_number)",
+        [],
+    )
+    .context("Creating block_traces block_number index")?;
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use anyhow::Context;
+
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tracing::info!("Creating l1_to_l2_messages table");
+
+    tx.execute(
+        r"
+        CREATE TABLE l1_to_l2_messages (
+            message_hash        BLOB PRIMARY KEY,
+            l1_block_number     INTEGER NOT NULL,
+            l1_transaction_hash BLOB NOT NULL,
+            l2_transaction_hash BLOB NOT NULL
+        )
+        ",
+        [],
+    )
+    .context("Creating l1_to_l2_messages table")?;
+    tx.execute(
+        "CREATE INDEX l1_to_l2_messages_l1_transaction_hash ON \
+         l1_to_l2_messages(l1_transaction_hash)",
+        [],
+    )
+    .context("Creating l1_to_l2_messages l1_transaction_hash index")?;
+
+    Ok(())
+}
@@ -0,0 +1,550 @@
+//! Online database snapshots.
+//!
+//! Snapshots are created with SQLite's online backup API, which lets the node
+//! keep syncing and serving requests while the copy is taken. The copy is
+//! compressed with zstd and described by a [SnapshotManifest] stored next to
+//! it.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use pathfinder_common::{BlockHash, BlockNumber};
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::OptionalExtension;
+use sha2::{Digest, Sha256};
+
+use crate::params::RowExt;
+use crate::{BlockId, Connection, JournalMode, Storage, StorageBuilder};
+
+/// Describes a snapshot created by [Storage::snapshot].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SnapshotManifest {
+    /// Name of the compressed database file, relative to the manifest.
+    pub file_name: String,
+    /// Hex encoded SHA-256 digest of the compressed database file.
+    pub sha256: String,
+    /// The latest block contained in the snapshot.
+    pub block_number: BlockNumber,
+    pub block_hash: BlockHash,
+    /// The schema revision of the snapshot database.
+    pub schema_revision: usize,
+}
+
+impl SnapshotManifest {
+    pub const FILE_EXTENSION: &'static str = "manifest.json";
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path).context("Opening snapshot manifest")?;
+        serde_json::from_reader(BufReader::new(file)).context("Parsing snapshot manifest")
+    }
+
+    /// The path of the manifest belonging to the snapshot at `snapshot`.
+    pub fn path_for(snapshot: &Path) -> PathBuf {
+        let file_name = snapshot
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let name = file_name.strip_suffix(".sqlite.zst").unwrap_or(&file_name);
+        snapshot.with_file_name(format!("{name}.{}", Self::FILE_EXTENSION))
+    }
+}
+
+impl Storage {
+    /// Creates a zstd-compressed copy of the database and its
+    /// [manifest](SnapshotManifest) in `directory`.
+    ///
+    /// The copy is consistent as of the latest block at the time the backup
+    /// started. Writes from other connections are not blocked while the
+    /// backup is in progress if the database is in WAL mode.
+    ///
+    /// If `block` is set and older than the latest block of the copy, `revert`
+    /// is called on the copy and is expected to revert it so that `block`
+    /// becomes its latest block. The database itself is never modified.
+    ///
+    /// The files are named `<database name>_<block number>.sqlite.zst` and
+    /// `<database name>_<block number>.manifest.json`.
+    pub fn snapshot<F>(
+        &self,
+        directory: &Path,
+        block: Option<BlockNumber>,
+        revert: F,
+    ) -> anyhow::Result<SnapshotManifest>
+    where
+        F: FnOnce(&mut Connection, BlockNumber) -> anyhow::Result<()>,
+    {
+        let uncompressed =
+            tempfile::NamedTempFile::new_in(directory).context("Creating temporary file")?;
+        let mut destination =
+            rusqlite::Connection::open(uncompressed.path()).context("Opening backup database")?;
+
+        let source = self.0.pool.get().context("Getting database connection")?;
+        backup(&source, &mut destination).context("Backing up database")?;
+        drop(source);
+
+        if let Some(block) = block {
+            let (latest, _) = latest_block(&destination)?;
+            anyhow::ensure!(
+                block <= latest,
+                "Block {block} is newer than the latest block {latest} in the database"
+            );
+
+            if block < latest {
+                destination
+                    .close()
+                    .map_err(|(_connection, error)| error)
+                    .context("Closing backup database")?;
+
+                tracing::info!(from=%latest, to=%block, "Reverting database snapshot");
+                revert_copy(uncompressed.path(), block, revert)?;
+
+                destination = rusqlite::Connection::open(uncompressed.path())
+                    .context("Opening backup database")?;
+                // Reclaim the space of the reverted blocks.
+                destination
+                    .execute_batch("VACUUM")
+                    .context("Vacuuming backup database")?;
+            }
+        }
+
+        // The copy inherits the WAL journal mode of the source, switch back so that
+        // the snapshot is a single self-contained file.
+        destination
+            .pragma_update(None, "journal_mode", "DELETE")
+            .context("Setting journal mode of backup database")?;
+
+        // Read these from the copy so that they're guaranteed to match its content.
+        let schema_revision = crate::schema_version(&destination)?;
+        let (block_number, block_hash) = latest_block(&destination)?;
+
+        destination
+            .close()
+            .map_err(|(_connection, error)| error)
+            .context("Closing backup database")?;
+
+        let database_name = self
+            .path()
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .context("Database path has no file name")?;
+        let name = format!("{database_name}_{block_number}");
+        let file_name = format!("{name}.sqlite.zst");
+
+        tracing::info!(%block_number, %file_name, "Compressing database snapshot");
+
+        let compressed_path = directory.join(&file_name);
+        let mut compressed =
+            tempfile::NamedTempFile::new_in(directory).context("Creating temporary file")?;
+        zstd::stream::copy_encode(
+            BufReader::new(uncompressed.as_file()),
+            compressed.as_file_mut(),
+            zstd::DEFAULT_COMPRESSION_LEVEL,
+        )
+        .context("Compressing database snapshot")?;
+        compressed
+            .persist(&compressed_path)
+            .context("Persisting database snapshot")?;
+
+        let manifest = SnapshotManifest {
+            sha256: sha256(&compressed_path)?,
+            file_name,
+            block_number,
+            block_hash,
+            schema_revision,
+        };
+
+        let manifest_path = directory.join(format!("{name}.{}", SnapshotManifest::FILE_EXTENSION));
+        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+        std::fs::write(&manifest_path, manifest_json).context("Writing snapshot manifest")?;
+
+        Ok(manifest)
+    }
+}
+
+/// Decompresses the snapshot at `snapshot` into a new database file at
+/// `destination`.
+///
+/// Fails if the schema revision of the snapshot cannot be migrated to the
+/// current one. The snapshot's checksum, schema revision and latest block must
+/// also match its [manifest](SnapshotManifest), which is required unless
+/// `allow_missing_manifest` is set.
+///
+/// Nothing is left at `destination` on failure.
+pub fn restore_snapshot(
+    snapshot: &Path,
+    destination: &Path,
+    allow_missing_manifest: bool,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !destination.exists(),
+        "Database {} already exists",
+        destination.display()
+    );
+
+    let manifest_path = SnapshotManifest::path_for(snapshot);
+    let manifest = if manifest_path.exists() {
+        Some(SnapshotManifest::read(&manifest_path)?)
+    } else if allow_missing_manifest {
+        tracing::warn!(manifest=%manifest_path.display(), "Snapshot manifest not found, skipping checksum verification");
+        None
+    } else {
+        anyhow::bail!("Snapshot manifest {} not found", manifest_path.display());
+    };
+
+    if let Some(manifest) = &manifest {
+        let sha256 = sha256(snapshot)?;
+        anyhow::ensure!(
+            sha256 == manifest.sha256,
+            "Snapshot checksum {sha256} does not match the manifest's {}",
+            manifest.sha256
+        );
+    }
+
+    let result = decompress_and_check(snapshot, destination, manifest.as_ref());
+    if result.is_err() {
+        // Best effort, the original error is more relevant.
+        let _ = std::fs::remove_file(destination);
+    }
+    result
+}
+
+fn decompress_and_check(
+    snapshot: &Path,
+    destination: &Path,
+    manifest: Option<&SnapshotManifest>,
+) -> anyhow::Result<()> {
+    let compressed = File::open(snapshot).context("Opening snapshot")?;
+    let decompressed = File::options()
+        .write(true)
+        .create_new(true)
+        .open(destination)
+        .context("Creating database file")?;
+    zstd::stream::copy_decode(BufReader::new(compressed), decompressed)
+        .context("Decompressing snapshot")?;
+
+    let connection = rusqlite::Connection::open_with_flags(
+        destination,
+        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )
+    .context("Opening decompressed snapshot")?;
+
+    let schema_revision = crate::schema_version(&connection)?;
+    let latest_revision = crate::schema::BASE_SCHEMA_REVISION + crate::schema::migrations().len();
+    anyhow::ensure!(
+        schema_revision <= latest_revision,
+        "Snapshot schema revision {schema_revision} is newer than the latest supported revision \
+         {latest_revision}"
+    );
+    anyhow::ensure!(
+        schema_revision >= crate::schema::BASE_SCHEMA_REVISION,
+        "Snapshot schema revision {schema_revision} is too old to be migrated, the oldest \
+         supported revision is {}",
+        crate::schema::BASE_SCHEMA_REVISION
+    );
+
+    let (block_number, block_hash) = latest_block(&connection)?;
+
+    if let Some(manifest) = manifest {
+        anyhow::ensure!(
+            schema_revision == manifest.schema_revision,
+            "Snapshot schema revision {schema_revision} does not match the manifest's {}",
+            manifest.schema_revision
+        );
+        anyhow::ensure!(
+            (block_number, block_hash) == (manifest.block_number, manifest.block_hash),
+            "Latest block {block_number} {block_hash} of the snapshot does not match the \
+             manifest's {} {}",
+            manifest.block_number,
+            manifest.block_hash
+        );
+    }
+
+    connection
+        .close()
+        .map_err(|(_connection, error)| error)
+        .context("Closing decompressed snapshot")?;
+
+    tracing::info!(%block_number, %block_hash, %schema_revision, "Snapshot restored");
+
+    Ok(())
+}
+
+/// Opens the backup at `path` as [Storage] and lets `revert` revert it to
+/// `block`.
+fn revert_copy<F>(path: &Path, block: BlockNumber, revert: F) -> anyhow::Result<()>
+where
+    F: FnOnce(&mut Connection, BlockNumber) -> anyhow::Result<()>,
+{
+    let storage = StorageBuilder::file(path.to_path_buf())
+        .journal_mode(JournalMode::Rollback)
+        .open_existing()
+        .context("Opening backup database")?
+        .create_pool(NonZeroU32::new(1).unwrap())
+        .context("Creating backup database connection pool")?;
+    let mut connection = storage
+        .connection()
+        .context("Creating backup database connection")?;
+
+    revert(&mut connection, block).context("Reverting backup database")?;
+
+    let latest = connection
+        .transaction()?
+        .block_number(BlockId::Latest)
+        .context("Querying latest block of backup database")?;
+    anyhow::ensure!(
+        latest == Some(block),
+        "Backup database was reverted to block {latest:?} instead of {block}"
+    );
+
+    Ok(())
+}
+
+fn latest_block(connection: &rusqlite::Connection) -> anyhow::Result<(BlockNumber, BlockHash)> {
+    connection
+        .query_row(
+            "SELECT number, hash FROM canonical_blocks ORDER BY number DESC LIMIT 1",
+            [],
+            |row| Ok((row.get_block_number(0)?, row.get_block_hash(1)?)),
+        )
+        .optional()
+        .context("Querying latest block")?
+        .context("Database contains no blocks")
+}
+
+/// Copies `source` into `destination` in a single step.
+///
+/// Copying incrementally would restart the backup every time another
+/// connection writes to the source, which could go on indefinitely while the
+/// node is syncing. A single step instead reads from one consistent snapshot
+/// of the source.
+fn backup(
+    source: &rusqlite::Connection,
+    destination: &mut rusqlite::Connection,
+) -> anyhow::Result<()> {
+    let backup = Backup::new(source, destination)?;
+    loop {
+        match backup.step(-1)? {
+            StepResult::Done => return Ok(()),
+            StepResult::Busy | StepResult::Locked => {
+                tracing::debug!("Database busy, retrying backup");
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            other => anyhow::bail!("Unexpected backup step result: {other:?}"),
+        }
+    }
+}
+
+/// Returns the hex encoded SHA-256 digest of the file, as produced by
+/// `sha256sum`.
+pub(crate) fn sha256(path: &Path) -> anyhow::Result<String> {
+    let mut file = File::open(path).context("Opening file for hashing")?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).context("Hashing file")?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use pathfinder_common::BlockHeader;
+
+    use super::*;
+    use crate::fake::{fill, generate};
+    use crate::{JournalMode, StorageBuilder};
+
+    fn storage_with_blocks(directory: &Path) -> (Storage, BlockHeader) {
+        let storage = StorageBuilder::file(directory.join("mainnet.sqlite"))
+            .journal_mode(JournalMode::WAL)
+            .migrate()
+            .unwrap()
+            .create_pool(NonZeroU32::new(2).unwrap())
+            .unwrap();
+        let blocks = generate::n_blocks(5);
+        fill(&storage, &blocks, None);
+        let latest = blocks.last().unwrap().header.header.clone();
+
+        (storage, latest)
+    }
+
+    #[test]
+    fn snapshot_contains_latest_block() {
+        let database_dir = tempfile::tempdir().unwrap();
+        let snapshot_dir = tempfile::tempdir().unwrap();
+        let (storage, expected) = storage_with_blocks(database_dir.path());
+
+        let manifest = storage
+            .snapshot(snapshot_dir.path(), None, no_revert)
+            .unwrap();
+
+        assert_eq!(manifest.block_number, expected.number);
+        assert_eq!(manifest.block_hash, expected.hash);
+        assert_eq!(
+            manifest.file_name,
+            format!("mainnet_{}.sqlite.zst", expected.number)
+        );
+        assert_eq!(
+            manifest.schema_revision,
+            crate::schema::migrations().len() + crate::schema::BASE_SCHEMA_REVISION
+        );
+
+        let compressed_path = snapshot_dir.path().join(&manifest.file_name);
+        assert_eq!(sha256(&compressed_path).unwrap(), manifest.sha256);
+
+        let manifest_path = SnapshotManifest::path_for(&compressed_path);
+        assert_eq!(
+            manifest_path,
+            snapshot_dir
+                .path()
+                .join(format!("mainnet_{}.manifest.json", expected.number))
+        );
+        assert_eq!(SnapshotManifest::read(&manifest_path).unwrap(), manifest);
+
+        // The restored snapshot is a usable database.
+        let restored_path = snapshot_dir.path().join("restored.sqlite");
+        restore_snapshot(&compressed_path, &restored_path, false).unwrap();
+        let restored = StorageBuilder::file(restored_path)
+            .migrate()
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut connection = restored.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        assert_eq!(
+            tx.block_id(BlockId::Latest).unwrap(),
+            Some((expected.number, expected.hash))
+        );
+    }
+
+    fn no_revert(_: &mut Connection, _: BlockNumber) -> anyhow::Result<()> {
+        unreachable!("Snapshot of the latest block must not be reverted")
+    }
+
+    #[test]
+    fn snapshot_at_older_block() {
+        let database_dir = tempfile::tempdir().unwrap();
+        let snapshot_dir = tempfile::tempdir().unwrap();
+        let (storage, latest) = storage_with_blocks(database_dir.path());
+        let target = latest.number - 2;
+
+        let manifest = storage
+            .snapshot(snapshot_dir.path(), Some(target), |connection, block| {
+                let tx = connection.transaction()?;
+                let mut head = latest.number;
+                while head > block {
+                    tx.purge_block(head)?;
+                    head -= 1;
+                }
+                tx.commit()
+            })
+            .unwrap();
+
+        assert_eq!(manifest.block_number, target);
+        assert_eq!(manifest.file_name, format!("mainnet_{target}.sqlite.zst"));
+
+        // The database itself is left untouched.
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        assert_eq!(
+            tx.block_id(BlockId::Latest).unwrap(),
+            Some((latest.number, latest.hash))
+        );
+        let target_hash = tx.block_hash(target.into()).unwrap().unwrap();
+        assert_eq!(manifest.block_hash, target_hash);
+
+        let compressed_path = snapshot_dir.path().join(&manifest.file_name);
+        let restored_path = snapshot_dir.path().join("restored.sqlite");
+        restore_snapshot(&compressed_path, &restored_path, false).unwrap();
+    }
+
+    #[test]
+    fn snapshot_rejects_future_block() {
+        let database_dir = tempfile::tempdir().unwrap();
+        let snapshot_dir = tempfile::tempdir().unwrap();
+        let (storage, latest) = storage_with_blocks(database_dir.path());
+
+        storage
+            .snapshot(snapshot_dir.path(), Some(latest.number + 1), no_revert)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn restore_rejects_checksum_mismatch() {
+        let database_dir = tempfile::tempdir().unwrap();
+        let snapshot_dir = tempfile::tempdir().unwrap();
+        let (storage, _) = storage_with_blocks(database_dir.path());
+
+        let mut manifest = storage
+            .snapshot(snapshot_dir.path(), None, no_revert)
+            .unwrap();
+        let compressed_path = snapshot_dir.path().join(&manifest.file_name);
+        manifest.sha256 = "00".repeat(32);
+        std::fs::write(
+            SnapshotManifest::path_for(&compressed_path),
+            serde_json::to_vec(&manifest).unwrap(),
+        )
+        .unwrap();
+
+        let restored_path = snapshot_dir.path().join("restored.sqlite");
+        restore_snapshot(&compressed_path, &restored_path, false).unwrap_err();
+        assert!(!restored_path.exists());
+    }
+
+    #[test]
+    fn restore_rejects_block_mismatch() {
+        let database_dir = tempfile::tempdir().unwrap();
+        let snapshot_dir = tempfile::tempdir().unwrap();
+        let (storage, _) = storage_with_blocks(database_dir.path());
+
+        let mut manifest = storage
+            .snapshot(snapshot_dir.path(), None, no_revert)
+            .unwrap();
+        let compressed_path = snapshot_dir.path().join(&manifest.file_name);
+        manifest.block_hash = BlockHash::ZERO;
+        std::fs::write(
+            SnapshotManifest::path_for(&compressed_path),
+            serde_json::to_vec(&manifest).unwrap(),
+        )
+        .unwrap();
+
+        let restored_path = snapshot_dir.path().join("restored.sqlite");
+        restore_snapshot(&compressed_path, &restored_path, false).unwrap_err();
+        assert!(!restored_path.exists());
+    }
+
+    #[test]
+    fn restore_requires_manifest() {
+        let database_dir = tempfile::tempdir().unwrap();
+        let snapshot_dir = tempfile::tempdir().unwrap();
+        let (storage, _) = storage_with_blocks(database_dir.path());
+
+        let manifest = storage
+            .snapshot(snapshot_dir.path(), None, no_revert)
+            .unwrap();
+        let compressed_path = snapshot_dir.path().join(&manifest.file_name);
+        std::fs::remove_file(SnapshotManifest::path_for(&compressed_path)).unwrap();
+
+        let restored_path = snapshot_dir.path().join("restored.sqlite");
+        restore_snapshot(&compressed_path, &restored_path, false).unwrap_err();
+        assert!(!restored_path.exists());
+
+        restore_snapshot(&compressed_path, &restored_path, true).unwrap();
+    }
+
+    #[test]
+    fn restore_does_not_overwrite_existing_database() {
+        let database_dir = tempfile::tempdir().unwrap();
+        let snapshot_dir = tempfile::tempdir().unwrap();
+        let (storage, _) = storage_with_blocks(database_dir.path());
+
+        let manifest = storage
+            .snapshot(snapshot_dir.path(), None, no_revert)
+            .unwrap();
+        let compressed_path = snapshot_dir.path().join(&manifest.file_name);
+
+        restore_snapshot(&compressed_path, storage.path(), false).unwrap_err();
+        assert!(storage.path().exists());
+    }
+}